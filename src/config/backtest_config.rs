@@ -0,0 +1,790 @@
+//! 运行时回测配置
+//!
+//! 从TOML/JSON文件加载手续费、回测周期、用户和数据源配置，未填写的字段使用config.rs中的默认值
+use std::collections::{BTreeMap, HashMap};
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::fs;
+use std::path::Path;
+use chrono::{DateTime, Duration, Local, NaiveDateTime, TimeZone};
+use rust_decimal::Decimal;
+use rust_decimal::prelude::FromPrimitive;
+use serde::{Deserialize, Serialize};
+use crate::config::fee::{MAKER_ORDER_FEE, TAKER_ORDER_FEE, TAKER_SLIPPAGE};
+use crate::config::liquidation::LIQUIDATION_FEE;
+use crate::config::user::{INIT_BALANCE_USDT, USER_NAME};
+use crate::data_runtime::asset::margin::{ECrossMarginBalance, EMaintenanceMarginError, EMarginMode, SMaintenanceMarginTable};
+use crate::data_runtime::user::SUserConfig;
+use crate::data_source::data_manager::{SDataManager, SDataManagerBuilder, STradingPairSource};
+use crate::data_source::data_quality::{EDataRepairStrategy, SDataQualityConfig};
+use crate::data_source::db::api::data_api_cache::SDataApiCache;
+use crate::data_source::db::api::data_api_csv::SDataApiCsv;
+use crate::data_source::db::api::data_api_db::SDataApiDb;
+use crate::data_source::db::api::data_api_union::EDataApiUnion;
+use crate::data_source::kline::EKlineInterval;
+use crate::data_source::trading_pair::ETradingPairType;
+use crate::report::chart::EChartFormat;
+use crate::data_source::db::{EDbConfigError, RDBResult, SClickhouseConfig, SDbClickhouse};
+use crate::runner::back_trade::config::{EStalePricePolicy, SBackTradeRunnerConfig};
+use crate::runner::back_trade::fill_model::{EMakerFillModel, SFillModelConfig};
+use crate::runner::back_trade::price_path::EIntraBarPathModel;
+use crate::script::sweep::{ESweepMetric, ESweepRange, RSweepResult, SSweepConfig};
+use crate::script::walk_forward::SWalkForwardConfig;
+
+pub type RBacktestConfigResult<T> = Result<T, EBacktestConfigError>;
+
+/// 回测配置异常
+#[derive(Debug)]
+pub enum EBacktestConfigError {
+    /// 配置文件读取失败(文件路径，错误信息)
+    IoError(String, String),
+    /// 配置文件解析失败(文件路径，错误信息)
+    ParseError(String, String),
+    /// 不支持的配置文件格式(文件路径)
+    UnsupportedFormatError(String),
+    /// 日期格式错误 需要为"%Y-%m-%d %H:%M:%S"
+    DateParseError(String),
+    /// 回测起始日期晚于结束日期
+    DateRangeError(DateTime<Local>, DateTime<Local>),
+    /// CSV数据源缺少数据目录
+    CsvDirNotSetError,
+    /// ClickHouse连接配置异常
+    DbConfigError(EDbConfigError),
+    /// 配置数值无效 需要为有限的非负数 初始USDT资金量需要为正数(字段名，数值)
+    InvalidValueError(String, f64),
+    /// 维持保证金档位配置异常(交易对，错误信息)
+    MaintenanceMarginError(ETradingPairType, EMaintenanceMarginError),
+}
+
+impl Display for EBacktestConfigError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EBacktestConfigError::IoError(path, e) => { write!(f, "配置文件读取失败: {} ({})", path, e) }
+            EBacktestConfigError::ParseError(path, e) => { write!(f, "配置文件解析失败: {} ({})", path, e) }
+            EBacktestConfigError::UnsupportedFormatError(path) => { write!(f, "不支持的配置文件格式(仅支持.toml/.json): {}", path) }
+            EBacktestConfigError::DateParseError(date) => { write!(f, "日期格式错误(需要为%Y-%m-%d %H:%M:%S): {}", date) }
+            EBacktestConfigError::DateRangeError(from, to) => { write!(f, "回测起始日期晚于结束日期: {} ~ {}", from, to) }
+            EBacktestConfigError::CsvDirNotSetError => { write!(f, "CSV数据源缺少数据目录csv_dir") }
+            EBacktestConfigError::DbConfigError(e) => { write!(f, "{}", e) }
+            EBacktestConfigError::InvalidValueError(name, value) => { write!(f, "配置数值无效: {} = {}", name, value) }
+            EBacktestConfigError::MaintenanceMarginError(tp_type, e) => { write!(f, "{:?}: {}", tp_type, e) }
+        }
+    }
+}
+
+impl Error for EBacktestConfigError {}
+
+impl From<EDbConfigError> for EBacktestConfigError {
+    fn from(value: EDbConfigError) -> Self {
+        Self::DbConfigError(value)
+    }
+}
+
+/// 回测周期预设(东八区)
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Default, Serialize, Deserialize)]
+pub enum EPeriodPreset {
+    /// 回测配置1 震荡走势 周期15小时
+    RangingShort,
+    /// 回测配置2 震荡走势 周期2个月
+    RangingTwoMonths,
+    /// 回测配置3 Debug用
+    #[default]
+    Debug,
+    /// 回测配置4 单边上涨 周期6天
+    OneSidedRally,
+    /// 回测配置5 单边下跌 周期4天
+    OneSidedDrop,
+    /// 回测配置6 4年比特币周期
+    FourYearCycle,
+    /// 回测配置7 2019~2025
+    From2019To2025,
+    /// 回测配置8 目标仓位为负数的情况
+    NegativeTargetPosition,
+}
+
+impl EPeriodPreset {
+    pub fn all() -> Vec<Self> {
+        vec![
+            Self::RangingShort,
+            Self::RangingTwoMonths,
+            Self::Debug,
+            Self::OneSidedRally,
+            Self::OneSidedDrop,
+            Self::FourYearCycle,
+            Self::From2019To2025,
+            Self::NegativeTargetPosition,
+        ]
+    }
+
+    /// 回测周期（起始日期，结束日期）
+    pub fn get_period_str(&self) -> (&'static str, &'static str) {
+        match self {
+            Self::RangingShort => { ("2025-01-27 13:40:00", "2025-01-28 04:22:00") }
+            Self::RangingTwoMonths => { ("2024-12-06 03:43:00", "2025-02-04 13:01:00") }
+            Self::Debug => { ("2020-01-01 00:00:00", "2020-01-05 00:00:00") }
+            Self::OneSidedRally => { ("2025-01-13 14:30:00", "2025-01-20 05:18:00") }
+            Self::OneSidedDrop => { ("2024-12-16 12:00:00", "2024-12-20 12:00:00") }
+            Self::FourYearCycle => { ("2018-08-28 00:00:00", "2022-05-07 00:00:00") }
+            Self::From2019To2025 => { ("2019-01-01 00:00:00", "2025-01-01 00:00:00") }
+            Self::NegativeTargetPosition => { ("2021-07-01 00:00:00", "2021-07-05 08:00:00") }
+        }
+    }
+
+    pub fn get_period(&self) -> (DateTime<Local>, DateTime<Local>) {
+        let (date_from, date_to) = self.get_period_str();
+        (parse_date_time(date_from).unwrap(), parse_date_time(date_to).unwrap())
+    }
+}
+
+/// 解析本地时间 格式为"%Y-%m-%d %H:%M:%S"
+pub fn parse_date_time(date: &str) -> RBacktestConfigResult<DateTime<Local>> {
+    let naive = NaiveDateTime::parse_from_str(date, "%Y-%m-%d %H:%M:%S")
+        .map_err(|_| EBacktestConfigError::DateParseError(date.to_string()))?;
+    Local.from_local_datetime(&naive)
+        .single()
+        .ok_or(EBacktestConfigError::DateParseError(date.to_string()))
+}
+
+/// 将配置数值转换为Decimal 非有限值（nan/inf）或负数时返回错误
+fn parse_non_negative(name: &str, value: f64) -> RBacktestConfigResult<Decimal> {
+    match Decimal::from_f64(value) {
+        Some(decimal) if value.is_finite() && value >= 0.0 => { Ok(decimal) }
+        _ => { Err(EBacktestConfigError::InvalidValueError(name.to_string(), value)) }
+    }
+}
+
+/// 手续费配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SFeeSettings {
+    /// 吃单手续费
+    pub taker_order_fee: f64,
+    /// 挂单手续费
+    pub maker_order_fee: f64,
+    /// 吃单滑点
+    pub taker_slippage: f64,
+    /// 强平手续费
+    pub liquidation_fee: f64,
+}
+
+impl Default for SFeeSettings {
+    fn default() -> Self {
+        Self {
+            taker_order_fee: TAKER_ORDER_FEE,
+            maker_order_fee: MAKER_ORDER_FEE,
+            taker_slippage: TAKER_SLIPPAGE,
+            liquidation_fee: LIQUIDATION_FEE,
+        }
+    }
+}
+
+/// 挂单成交模型类型
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Default, Serialize, Deserialize)]
+pub enum EMakerFillModelKind {
+    /// 触价成交
+    #[default]
+    Touch,
+    /// 穿价成交
+    TradeThrough,
+    /// 排队成交
+    Queue,
+}
+
+/// k线内价格路径模型类型
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Default, Serialize, Deserialize)]
+pub enum EIntraBarPathKind {
+    /// 先结算所有买单再结算所有卖单
+    #[default]
+    BuyThenSell,
+    /// OHLC顺序
+    Ohlc,
+    /// 随机布朗桥
+    BrownianBridge,
+}
+
+/// 成交模型配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SFillModelSettings {
+    /// 挂单成交模型
+    pub maker_fill_model: EMakerFillModelKind,
+    /// 排队成交模型中挂单价格处的排队深度（以基础资产计）
+    pub queue_depth: f64,
+    /// 排队成交模型的随机种子
+    pub queue_seed: u64,
+    /// k线内价格路径模型
+    pub intra_bar_path: EIntraBarPathKind,
+    /// 布朗桥路径步数
+    pub path_steps: usize,
+    /// 布朗桥路径的随机种子
+    pub path_seed: u64,
+    /// 单根k线内订单成交量不超过k线成交量的比例 未设置时订单全部成交
+    pub max_volume_ratio: Option<f64>,
+}
+
+impl Default for SFillModelSettings {
+    fn default() -> Self {
+        Self {
+            maker_fill_model: EMakerFillModelKind::default(),
+            queue_depth: 0.0,
+            queue_seed: 0,
+            intra_bar_path: EIntraBarPathKind::default(),
+            path_steps: 60,
+            path_seed: 0,
+            max_volume_ratio: None,
+        }
+    }
+}
+
+impl SFillModelSettings {
+    pub fn get_fill_model_config(&self) -> SFillModelConfig {
+        let maker_fill_model = match self.maker_fill_model {
+            EMakerFillModelKind::Touch => { EMakerFillModel::Touch }
+            EMakerFillModelKind::TradeThrough => { EMakerFillModel::TradeThrough }
+            EMakerFillModelKind::Queue => {
+                EMakerFillModel::Queue {
+                    queue_depth: Decimal::from_f64(self.queue_depth).unwrap_or_default(),
+                    seed: self.queue_seed,
+                }
+            }
+        };
+        let intra_bar_path = match self.intra_bar_path {
+            EIntraBarPathKind::BuyThenSell => { EIntraBarPathModel::BuyThenSell }
+            EIntraBarPathKind::Ohlc => { EIntraBarPathModel::Ohlc }
+            EIntraBarPathKind::BrownianBridge => { EIntraBarPathModel::BrownianBridge { steps: self.path_steps, seed: self.path_seed } }
+        };
+        SFillModelConfig {
+            maker_fill_model,
+            intra_bar_path,
+            max_volume_ratio: self.max_volume_ratio.and_then(Decimal::from_f64),
+        }
+    }
+}
+
+/// 维持保证金档位配置
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SMaintenanceMarginBracketSettings {
+    /// 名义价值下限（计价资产）
+    pub notional_floor: f64,
+    /// 最大杠杆倍数
+    pub max_leverage: f64,
+    /// 维持保证金率
+    pub maintenance_margin_ratio: f64,
+}
+
+/// 回测周期配置
+/// 同时设置date_from和date_to时优先使用自定义日期，否则使用预设周期
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SPeriodSettings {
+    pub preset: EPeriodPreset,
+    pub date_from: Option<String>,
+    pub date_to: Option<String>,
+}
+
+impl SPeriodSettings {
+    pub fn get_period(&self) -> RBacktestConfigResult<(DateTime<Local>, DateTime<Local>)> {
+        let (date_from, date_to) = match (&self.date_from, &self.date_to) {
+            (Some(date_from), Some(date_to)) => { (parse_date_time(date_from)?, parse_date_time(date_to)?) }
+            _ => { self.preset.get_period() }
+        };
+        if date_from > date_to {
+            return Err(EBacktestConfigError::DateRangeError(date_from, date_to));
+        }
+        Ok((date_from, date_to))
+    }
+}
+
+/// 用户配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SUserSettings {
+    pub user_name: String,
+    /// 初始资金量USDT
+    pub init_balance_usdt: f64,
+    /// 初始资金量BTC
+    pub init_balance_btc: f64,
+}
+
+impl Default for SUserSettings {
+    fn default() -> Self {
+        Self {
+            user_name: USER_NAME.to_string(),
+            init_balance_usdt: INIT_BALANCE_USDT,
+            init_balance_btc: 0.0,
+        }
+    }
+}
+
+/// 数据接口类型
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Default, Serialize, Deserialize)]
+pub enum EDataApiKind {
+    #[default]
+    ClickHouse,
+    Csv,
+}
+
+/// 数据质量检查配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SDataQualitySettings {
+    /// k线周期
+    pub kline_interval: EKlineInterval,
+    /// 资金费率结算间隔（小时）
+    pub funding_rate_interval_hours: i64,
+    /// 价格跳变阈值 收盘价相对前一根k线收盘价的变化比例
+    pub max_price_jump_ratio: f64,
+    /// k线修复策略
+    pub repair_strategy: EDataRepairStrategy,
+}
+
+impl Default for SDataQualitySettings {
+    fn default() -> Self {
+        Self {
+            kline_interval: EKlineInterval::default(),
+            funding_rate_interval_hours: 8,
+            max_price_jump_ratio: 0.1,
+            repair_strategy: EDataRepairStrategy::default(),
+        }
+    }
+}
+
+impl SDataQualitySettings {
+    pub fn get_quality_config(&self) -> SDataQualityConfig {
+        SDataQualityConfig {
+            kline_interval: self.kline_interval,
+            funding_rate_interval: Duration::hours(self.funding_rate_interval_hours),
+            max_price_jump_ratio: Decimal::from_f64(self.max_price_jump_ratio).unwrap_or_default(),
+            repair_strategy: self.repair_strategy,
+        }
+    }
+}
+
+/// 数据源配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SDataSourceSettings {
+    pub api: EDataApiKind,
+    /// CSV数据目录（api为Csv时必填）
+    pub csv_dir: Option<String>,
+    /// ClickHouse连接配置 未设置时从环境变量读取
+    pub clickhouse: Option<SClickhouseConfig>,
+    /// 本地二进制缓存目录 设置后按数据源和月份缓存数据 重复回测时从缓存读取
+    pub cache_dir: Option<String>,
+    /// 加载数据前删除当前数据源的所有缓存 数据源的历史数据被修正后使用
+    pub invalidate_cache: bool,
+    /// 需要加载的交易对
+    pub trading_pairs: Vec<STradingPairSource>,
+    /// 数据质量检查
+    pub quality: SDataQualitySettings,
+}
+
+impl Default for SDataSourceSettings {
+    fn default() -> Self {
+        Self {
+            api: EDataApiKind::default(),
+            csv_dir: None,
+            clickhouse: None,
+            cache_dir: None,
+            invalidate_cache: false,
+            trading_pairs: SDataManager::<SDataApiDb>::default_sources(),
+            quality: SDataQualitySettings::default(),
+        }
+    }
+}
+
+impl SDataSourceSettings {
+    /// 构建数据接口
+    pub fn build_data_api(&self) -> RBacktestConfigResult<EDataApiUnion> {
+        match self.api {
+            EDataApiKind::ClickHouse => {
+                let db = match &self.clickhouse {
+                    None => { SDbClickhouse::from_env()? }
+                    Some(clickhouse) => { SDbClickhouse::new(clickhouse) }
+                };
+                let api = SDataApiDb::new(db);
+                match &self.cache_dir {
+                    None => { Ok(EDataApiUnion::from(api)) }
+                    Some(cache_dir) => { Ok(EDataApiUnion::from(SDataApiCache::new(api, cache_dir))) }
+                }
+            }
+            EDataApiKind::Csv => {
+                let api = match &self.csv_dir {
+                    None => { return Err(EBacktestConfigError::CsvDirNotSetError); }
+                    Some(csv_dir) => { SDataApiCsv::new(csv_dir) }
+                };
+                match &self.cache_dir {
+                    None => { Ok(EDataApiUnion::from(api)) }
+                    Some(cache_dir) => { Ok(EDataApiUnion::from(SDataApiCache::new(api, cache_dir))) }
+                }
+            }
+        }
+    }
+
+    /// 构建数据管理器 加载所有配置的交易对
+    pub async fn build_data_manager(&self, date_from: &DateTime<Local>, date_to: &DateTime<Local>) -> RDBResult<SDataManager<EDataApiUnion>> {
+        let data_api = self.build_data_api()?;
+        if self.invalidate_cache {
+            data_api.invalidate_cache()?;
+        }
+        SDataManagerBuilder::new(data_api)
+            .add_trading_pairs(self.trading_pairs.clone())
+            .with_quality_config(self.quality.get_quality_config())
+            .build(date_from, date_to)
+            .await
+    }
+}
+
+/// 参数扫描配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SSweepSettings {
+    /// 参数名及取值范围 取值为列表或{ start, end, step } 未配置的参数使用策略默认值
+    pub parameters: BTreeMap<String, ESweepRange>,
+    /// 结果排序指标
+    pub rank_by: ESweepMetric,
+    /// 输出的最优参数组数
+    pub top_n: usize,
+}
+
+impl Default for SSweepSettings {
+    fn default() -> Self {
+        Self {
+            parameters: BTreeMap::new(),
+            rank_by: ESweepMetric::default(),
+            top_n: 5,
+        }
+    }
+}
+
+impl SSweepSettings {
+    /// 生成参数扫描配置 展开各参数的取值范围
+    pub fn get_sweep_config(&self) -> RSweepResult<SSweepConfig> {
+        let mut parameters = BTreeMap::new();
+        for (name, range) in self.parameters.iter() {
+            parameters.insert(name.clone(), range.get_values(name)?);
+        }
+        Ok(SSweepConfig {
+            parameters,
+            rank_by: self.rank_by,
+            top_n: self.top_n,
+        })
+    }
+}
+
+/// 滚动前推优化配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SWalkForwardSettings {
+    /// 样本内窗口长度（天）
+    pub in_sample_days: i64,
+    /// 样本外窗口长度（天） 同时也是窗口的滚动步长
+    pub out_of_sample_days: i64,
+    /// 为true时样本内窗口固定从回测起始日期开始（扩展窗口），否则为固定长度的滚动窗口
+    pub anchored: bool,
+}
+
+impl Default for SWalkForwardSettings {
+    fn default() -> Self {
+        Self {
+            in_sample_days: 60,
+            out_of_sample_days: 20,
+            anchored: false,
+        }
+    }
+}
+
+impl SWalkForwardSettings {
+    pub fn get_walk_forward_config(&self) -> SWalkForwardConfig {
+        SWalkForwardConfig {
+            in_sample: Duration::days(self.in_sample_days),
+            out_of_sample: Duration::days(self.out_of_sample_days),
+            anchored: self.anchored,
+        }
+    }
+}
+
+/// 回测配置
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SBacktestConfig {
+    pub fee: SFeeSettings,
+    pub fill_model: SFillModelSettings,
+    /// 报价过期策略 交易对缺少k线时的处理方式
+    pub stale_price_policy: EStalePricePolicy,
+    /// 策略周期 策略在该周期的k线收盘时执行 订单仍按1分钟k线撮合
+    pub strategy_interval: EKlineInterval,
+    pub margin_mode: EMarginMode,
+    /// 全仓模式下作为共享保证金的余额
+    pub cross_margin_balance: ECrossMarginBalance,
+    /// 各交易对的维持保证金档位 按名义价值下限升序排列 未配置的交易对使用config.rs中的默认档位
+    pub maintenance_margin: BTreeMap<ETradingPairType, Vec<SMaintenanceMarginBracketSettings>>,
+    pub period: SPeriodSettings,
+    pub user: SUserSettings,
+    pub data_source: SDataSourceSettings,
+    /// 回测结果输出路径 未设置时输出到data/back_trade/{启动时间}.csv
+    pub output_path: Option<String>,
+    /// 回测图表格式 图表与回测结果输出到同一目录
+    pub chart_format: EChartFormat,
+    /// 参数扫描配置 仅在参数扫描及滚动前推优化模式下使用
+    pub sweep: SSweepSettings,
+    /// 滚动前推优化配置 仅在滚动前推优化模式下使用
+    pub walk_forward: SWalkForwardSettings,
+}
+
+impl SBacktestConfig {
+    /// 从文件加载配置 根据扩展名选择TOML或JSON格式
+    pub fn load(path: impl AsRef<Path>) -> RBacktestConfigResult<Self> {
+        let path = path.as_ref();
+        let path_str = path.display().to_string();
+        let content = fs::read_to_string(path).map_err(|e| EBacktestConfigError::IoError(path_str.clone(), e.to_string()))?;
+        let config = match path.extension().and_then(|ext| ext.to_str()) {
+            Some("toml") => { Self::from_toml_str(&content).map_err(|e| EBacktestConfigError::ParseError(path_str, e.to_string()))? }
+            Some("json") => { Self::from_json_str(&content).map_err(|e| EBacktestConfigError::ParseError(path_str, e.to_string()))? }
+            _ => { return Err(EBacktestConfigError::UnsupportedFormatError(path_str)); }
+        };
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// 校验执行器及用户配置 加载配置文件时执行 避免以无效数值构建策略
+    pub fn validate(&self) -> RBacktestConfigResult<()> {
+        self.get_runner_config()?;
+        self.get_user_config()?;
+        Ok(())
+    }
+
+    pub fn from_toml_str(content: &str) -> Result<Self, toml::de::Error> {
+        toml::from_str(content)
+    }
+
+    pub fn from_json_str(content: &str) -> Result<Self, serde_json::Error> {
+        serde_json::from_str(content)
+    }
+
+    /// 生成执行器配置
+    pub fn get_runner_config(&self) -> RBacktestConfigResult<SBackTradeRunnerConfig> {
+        let (date_from, date_to) = self.period.get_period()?;
+        Ok(SBackTradeRunnerConfig {
+            taker_order_fee: parse_non_negative("fee.taker_order_fee", self.fee.taker_order_fee)?,
+            maker_order_fee: parse_non_negative("fee.maker_order_fee", self.fee.maker_order_fee)?,
+            taker_slippage: parse_non_negative("fee.taker_slippage", self.fee.taker_slippage)?,
+            fill_model: self.fill_model.get_fill_model_config(),
+            stale_price_policy: self.stale_price_policy,
+            strategy_interval: self.strategy_interval,
+            margin_mode: self.margin_mode,
+            cross_margin_balance: self.cross_margin_balance,
+            maintenance_margin_tables: self.get_maintenance_margin_tables()?,
+            liquidation_fee: parse_non_negative("fee.liquidation_fee", self.fee.liquidation_fee)?,
+            date_from,
+            date_to,
+        })
+    }
+
+    /// 生成各交易对的分层维持保证金表 配置的档位覆盖默认档位
+    pub fn get_maintenance_margin_tables(&self) -> RBacktestConfigResult<HashMap<ETradingPairType, SMaintenanceMarginTable>> {
+        let mut tables = SBackTradeRunnerConfig::default_maintenance_margin_tables();
+        for (tp_type, brackets) in self.maintenance_margin.iter() {
+            let brackets: Vec<(f64, f64, f64)> = brackets
+                .iter()
+                .map(|bracket| (bracket.notional_floor, bracket.max_leverage, bracket.maintenance_margin_ratio))
+                .collect();
+            let table = SMaintenanceMarginTable::from_brackets(&brackets)
+                .map_err(|e| EBacktestConfigError::MaintenanceMarginError(*tp_type, e))?;
+            tables.insert(*tp_type, table);
+        }
+        Ok(tables)
+    }
+
+    /// 生成用户配置
+    /// 初始USDT资金量用于计算策略参数 需要为正数
+    pub fn get_user_config(&self) -> RBacktestConfigResult<SUserConfig> {
+        let init_balance_usdt = parse_non_negative("user.init_balance_usdt", self.user.init_balance_usdt)?;
+        if init_balance_usdt.is_zero() {
+            return Err(EBacktestConfigError::InvalidValueError("user.init_balance_usdt".to_string(), self.user.init_balance_usdt));
+        }
+        Ok(SUserConfig {
+            user_name: self.user.user_name.clone(),
+            init_balance_usdt,
+            init_balance_btc: parse_non_negative("user.init_balance_btc", self.user.init_balance_btc)?,
+        })
+    }
+
+    /// 根据初始仓位 将配置的初始资金（USDT计价）拆分为USDT和BTC
+    pub fn get_user_config_with_position(&self, position: Decimal, price: Decimal) -> RBacktestConfigResult<SUserConfig> {
+        let user_config = self.get_user_config()?;
+        let init_asset_total_usdt = user_config.init_balance_usdt;
+        Ok(SUserConfig {
+            init_balance_btc: user_config.init_balance_btc + init_asset_total_usdt * position / price,
+            init_balance_usdt: init_asset_total_usdt * (Decimal::from(1) - position),
+            ..user_config
+        })
+    }
+
+    /// 回测结果输出路径
+    pub fn get_output_path(&self, script_start_time: &str) -> String {
+        match &self.output_path {
+            None => { format!("data/back_trade/{}.csv", script_start_time) }
+            Some(output_path) => { output_path.clone() }
+        }
+    }
+
+    /// 所有预设周期
+    pub fn period_presets() -> HashMap<EPeriodPreset, (DateTime<Local>, DateTime<Local>)> {
+        EPeriodPreset::all()
+            .into_iter()
+            .map(|preset| (preset, preset.get_period()))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Duration;
+    use rust_decimal::Decimal;
+    use rust_decimal::prelude::FromPrimitive;
+    use crate::config::backtest_config::{parse_date_time, EBacktestConfigError, EDataApiKind, EPeriodPreset, SBacktestConfig, SDataSourceSettings};
+    use crate::data_runtime::asset::margin::{ECrossMarginBalance, EMaintenanceMarginError, EMarginMode, SMaintenanceMarginTable};
+    use crate::data_source::data_quality::EDataRepairStrategy;
+    use crate::data_source::db::api::data_api_union::EDataApiUnion;
+    use crate::data_source::kline::EKlineInterval;
+    use crate::data_source::trading_pair::ETradingPairType;
+    use crate::runner::back_trade::config::{EStalePricePolicy, SBackTradeRunnerConfig};
+    use crate::runner::back_trade::fill_model::EMakerFillModel;
+    use crate::runner::back_trade::price_path::EIntraBarPathModel;
+    use crate::script::sweep::ESweepMetric;
+
+    #[test]
+    pub fn test_default() {
+        // 默认配置与config.rs中的静态配置及默认的回测周期预设一致
+        let config = SBacktestConfig::default();
+        let runner_config = config.get_runner_config().unwrap();
+        let (date_from, date_to) = EPeriodPreset::Debug.get_period();
+        assert_eq!(runner_config.date_from, date_from);
+        assert_eq!(runner_config.date_to, date_to);
+        let default_runner_config = SBackTradeRunnerConfig::default();
+        assert_eq!(default_runner_config.date_from, date_from);
+        assert_eq!(default_runner_config.date_to, date_to);
+        assert_eq!(runner_config.fill_model.max_volume_ratio, None);
+        assert_eq!(runner_config.fill_model.maker_fill_model, EMakerFillModel::Touch);
+        assert_eq!(runner_config.fill_model.intra_bar_path, EIntraBarPathModel::BuyThenSell);
+        assert_eq!(runner_config.stale_price_policy, EStalePricePolicy::KeepLast);
+        assert_eq!(runner_config.strategy_interval, EKlineInterval::Minute1);
+        assert_eq!(runner_config.taker_order_fee, Decimal::from_f64(0.0005).unwrap());
+        assert_eq!(config.get_user_config().unwrap().init_balance_usdt, Decimal::from(100_000));
+        assert_eq!(config.data_source.api, EDataApiKind::ClickHouse);
+        assert_eq!(config.data_source.quality.get_quality_config().repair_strategy, EDataRepairStrategy::ReportOnly);
+        assert_eq!(SBacktestConfig::period_presets().len(), 8);
+        assert_eq!(config.get_output_path("20250101_000000"), "data/back_trade/20250101_000000.csv");
+        let sweep_config = config.sweep.get_sweep_config().unwrap();
+        assert_eq!(sweep_config.rank_by, ESweepMetric::SharpeRatio);
+        assert_eq!(sweep_config.top_n, 5);
+        assert_eq!(sweep_config.get_param_sets().len(), 1);
+        assert_eq!(config.walk_forward.get_walk_forward_config().out_of_sample, Duration::days(20));
+    }
+
+    #[test]
+    pub fn test_example_toml() {
+        let config = SBacktestConfig::from_toml_str(include_str!("../../config/backtest.example.toml")).unwrap();
+        assert_eq!(config.period.preset, EPeriodPreset::FourYearCycle);
+        assert_eq!(config.margin_mode, EMarginMode::Isolated);
+        assert_eq!(config.get_runner_config().unwrap().cross_margin_balance, ECrossMarginBalance::FuturesWallet);
+        // 配置的档位覆盖默认档位 未配置的交易对使用默认档位
+        let maintenance_margin_tables = config.get_maintenance_margin_tables().unwrap();
+        let table = &maintenance_margin_tables[&ETradingPairType::BtcUsdtFuture];
+        assert_eq!(table.get_tiers().len(), 3);
+        assert_eq!(table.get_max_leverage(Decimal::from(600_000)), Decimal::from(50));
+        assert_eq!(maintenance_margin_tables[&ETradingPairType::BtcUsdCmFuture], SMaintenanceMarginTable::default_for(ETradingPairType::BtcUsdCmFuture).unwrap());
+        assert_eq!(config.data_source.trading_pairs.len(), 2);
+        let runner_config = config.get_runner_config().unwrap();
+        assert_eq!(runner_config.date_from, parse_date_time("2018-08-28 00:00:00").unwrap());
+        assert_eq!(runner_config.maker_order_fee, Decimal::from_f64(0.0002).unwrap());
+        let sweep_config = config.sweep.get_sweep_config().unwrap();
+        assert_eq!(sweep_config.parameters["cut_off_price_percentage"], vec![0.01, 0.02, 0.03]);
+        assert_eq!(sweep_config.get_param_sets().len(), 9);
+        let walk_forward_config = config.walk_forward.get_walk_forward_config();
+        assert_eq!(walk_forward_config.in_sample, Duration::days(60));
+        assert!(!walk_forward_config.anchored);
+    }
+
+    #[test]
+    pub fn test_json_partial() {
+        let content = r#"{
+            "fee": { "maker_order_fee": 0.0001 },
+            "fill_model": { "maker_fill_model": "Queue", "queue_depth": 2.5, "queue_seed": 7, "intra_bar_path": "BrownianBridge", "path_steps": 30, "max_volume_ratio": 0.1 },
+            "stale_price_policy": { "SkipLog": { "max_age_minutes": 5 } },
+            "strategy_interval": "Hour1",
+            "period": { "date_from": "2024-01-01 00:00:00", "date_to": "2024-01-02 00:00:00" },
+            "user": { "user_name": "tester" },
+            "data_source": {
+                "api": "Csv",
+                "csv_dir": "data/csv",
+                "trading_pairs": [{ "tp_type": "BtcUsdtFuture", "kline_source": "kline_btc_usdt_future_1m", "funding_rate_source": "funding_rate_btc_usdt_future" }],
+                "quality": { "repair_strategy": "ForwardFill", "max_price_jump_ratio": 0.05 }
+            }
+        }"#;
+        let config = SBacktestConfig::from_json_str(content).unwrap();
+        let runner_config = config.get_runner_config().unwrap();
+        assert_eq!(runner_config.maker_order_fee, Decimal::from_f64(0.0001).unwrap());
+        // 未填写的字段使用默认值
+        assert_eq!(runner_config.taker_order_fee, Decimal::from_f64(0.0005).unwrap());
+        assert_eq!(runner_config.fill_model.max_volume_ratio, Decimal::from_f64(0.1));
+        assert_eq!(runner_config.fill_model.maker_fill_model, EMakerFillModel::Queue { queue_depth: Decimal::from_f64(2.5).unwrap(), seed: 7 });
+        assert_eq!(runner_config.fill_model.intra_bar_path, EIntraBarPathModel::BrownianBridge { steps: 30, seed: 0 });
+        assert_eq!(runner_config.stale_price_policy, EStalePricePolicy::SkipLog { max_age_minutes: 5 });
+        assert_eq!(runner_config.strategy_interval, EKlineInterval::Hour1);
+        assert_eq!(runner_config.date_from, parse_date_time("2024-01-01 00:00:00").unwrap());
+        assert_eq!(config.user.user_name, "tester");
+        assert_eq!(config.data_source.trading_pairs[0].tp_type, ETradingPairType::BtcUsdtFuture);
+        let quality_config = config.data_source.quality.get_quality_config();
+        assert_eq!(quality_config.repair_strategy, EDataRepairStrategy::ForwardFill);
+        assert_eq!(quality_config.max_price_jump_ratio, Decimal::from_f64(0.05).unwrap());
+        assert_eq!(quality_config.funding_rate_interval, chrono::Duration::hours(8));
+        assert!(matches!(config.data_source.build_data_api(), Ok(EDataApiUnion::Csv(_))));
+        let config = SBacktestConfig { data_source: SDataSourceSettings { cache_dir: Some("data/cache".to_string()), ..config.data_source }, ..config };
+        assert!(matches!(config.data_source.build_data_api(), Ok(EDataApiUnion::CachedCsv(_))));
+    }
+
+    #[test]
+    pub fn test_error() {
+        let config = SBacktestConfig::from_toml_str("[period]\ndate_from = \"2024-01-02\"\ndate_to = \"2024-01-01 00:00:00\"\n").unwrap();
+        assert!(matches!(config.get_runner_config(), Err(EBacktestConfigError::DateParseError(_))));
+
+        let config = SBacktestConfig::from_toml_str("[period]\ndate_from = \"2024-01-02 00:00:00\"\ndate_to = \"2024-01-01 00:00:00\"\n").unwrap();
+        assert!(matches!(config.get_runner_config(), Err(EBacktestConfigError::DateRangeError(_, _))));
+
+        let config = SBacktestConfig::from_toml_str("[data_source]\napi = \"Csv\"\n").unwrap();
+        assert!(matches!(config.data_source.build_data_api(), Err(EBacktestConfigError::CsvDirNotSetError)));
+        let config = SBacktestConfig::from_toml_str("[data_source]\napi = \"Csv\"\ncache_dir = \"data/cache\"\n").unwrap();
+        assert!(matches!(config.data_source.build_data_api(), Err(EBacktestConfigError::CsvDirNotSetError)));
+
+        // 配置文件中的ClickHouse连接配置优先于环境变量
+        let config = SBacktestConfig::from_toml_str("[data_source.clickhouse]\nurl = \"http://127.0.0.1:8123\"\nuser = \"default\"\npassword = \"\"\ndatabase = \"btc_quant\"\n").unwrap();
+        assert!(config.data_source.build_data_api().is_ok());
+
+        // 数值为nan/inf或负数时返回错误
+        let config = SBacktestConfig::from_toml_str("[fee]\ntaker_order_fee = nan\n").unwrap();
+        assert!(matches!(config.get_runner_config(), Err(EBacktestConfigError::InvalidValueError(name, _)) if name == "fee.taker_order_fee"));
+        let config = SBacktestConfig::from_toml_str("[fee]\nliquidation_fee = inf\n").unwrap();
+        assert!(matches!(config.validate(), Err(EBacktestConfigError::InvalidValueError(name, _)) if name == "fee.liquidation_fee"));
+        let config = SBacktestConfig::from_toml_str("[fee]\nmaker_order_fee = -0.0002\n").unwrap();
+        assert!(matches!(config.get_runner_config(), Err(EBacktestConfigError::InvalidValueError(name, _)) if name == "fee.maker_order_fee"));
+        let config = SBacktestConfig::from_toml_str("[user]\ninit_balance_btc = -1.0\n").unwrap();
+        assert!(matches!(config.get_user_config(), Err(EBacktestConfigError::InvalidValueError(name, _)) if name == "user.init_balance_btc"));
+        let config = SBacktestConfig::from_toml_str("[user]\ninit_balance_usdt = 0.0\n").unwrap();
+        assert!(matches!(config.validate(), Err(EBacktestConfigError::InvalidValueError(name, _)) if name == "user.init_balance_usdt"));
+
+        // 维持保证金档位为空或未按名义价值下限升序排列
+        let config = SBacktestConfig::from_toml_str("[maintenance_margin]\nBtcUsdtFuture = []\n").unwrap();
+        assert!(matches!(config.get_runner_config(), Err(EBacktestConfigError::MaintenanceMarginError(ETradingPairType::BtcUsdtFuture, EMaintenanceMarginError::EmptyBracketsError))));
+        let content = r#"{ "maintenance_margin": { "BtcUsdCmFuture": [
+            { "notional_floor": 5.0, "max_leverage": 100.0, "maintenance_margin_ratio": 0.005 },
+            { "notional_floor": 0.0, "max_leverage": 125.0, "maintenance_margin_ratio": 0.004 }
+        ] } }"#;
+        let config = SBacktestConfig::from_json_str(content).unwrap();
+        assert!(matches!(config.get_runner_config(), Err(EBacktestConfigError::MaintenanceMarginError(ETradingPairType::BtcUsdCmFuture, EMaintenanceMarginError::UnsortedBracketsError(1)))));
+
+        assert!(SBacktestConfig::from_toml_str("[period]\npreset = \"Unknown\"\n").is_err());
+        assert!(matches!(SBacktestConfig::load("config/backtest.example.yaml"), Err(EBacktestConfigError::IoError(_, _)) | Err(EBacktestConfigError::UnsupportedFormatError(_))));
+    }
+}
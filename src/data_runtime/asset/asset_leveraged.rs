@@ -1,6 +1,7 @@
 use rust_decimal::Decimal;
 use crate::data_runtime::asset::asset::{EAssetError, SAsset};
 use crate::data_runtime::asset::EAssetType;
+use crate::data_runtime::asset::margin::SMaintenanceMarginTable;
 use crate::data_runtime::order::EOrderDirection;
 use crate::data_source::trading_pair::ETradingPairType;
 
@@ -137,6 +138,101 @@ impl SAssetLeveraged {
         -(self.quote_asset.balance + self.margin_asset.balance) / self.base_asset.balance
     }
 
+    /// 获取考虑维持保证金的强平价格
+    ///
+    /// 当 保证金+计价资产+基础资产量*价格 = 维持保证金率*|基础资产量|*价格 时触发强平
+    ///
+    /// 未持仓时返回None
+    pub fn get_maintenance_liquidation_price(&self, maintenance_margin_ratio: Decimal) -> Option<Decimal> {
+        self.solve_liquidation_price(maintenance_margin_ratio, Decimal::from(0), Decimal::from(0))
+    }
+
+    /// 根据分层维持保证金表获取强平价格
+    ///
+    /// extra_margin为全仓模式下共享的额外保证金（逐仓模式为0）
+    ///
+    /// 依次假设强平时名义价值处于每个档位，求解强平价格并校验名义价值是否落在该档位内
+    pub fn get_tiered_liquidation_price(&self, table: &SMaintenanceMarginTable, extra_margin: Decimal) -> Option<Decimal> {
+        for (index, tier) in table.get_tiers().iter().enumerate() {
+            let liquidation_price = match self.solve_liquidation_price(tier.maintenance_margin_ratio, tier.maintenance_amount, extra_margin) {
+                None => { continue; }
+                Some(price) => { price }
+            };
+            let notional = self.base_asset.balance.abs() * liquidation_price;
+            let is_above_floor = index == 0 || notional >= tier.notional_floor;
+            let is_below_cap = match table.get_tiers().get(index + 1) {
+                None => { true }
+                Some(next_tier) => { notional < next_tier.notional_floor }
+            };
+            if is_above_floor && is_below_cap {
+                return Some(liquidation_price.max(Decimal::from(0)));
+            }
+        }
+        None
+    }
+
+    /// 求解 保证金+额外保证金+计价资产+基础资产量*价格 = 维持保证金率*|基础资产量|*价格-速算额
+    fn solve_liquidation_price(&self, maintenance_margin_ratio: Decimal, maintenance_amount: Decimal, extra_margin: Decimal) -> Option<Decimal> {
+        let base_balance = self.base_asset.balance;
+        let denominator = base_balance - maintenance_margin_ratio * base_balance.abs();
+        if base_balance == Decimal::from(0) || denominator == Decimal::from(0) {
+            return None;
+        }
+        Some(-(self.quote_asset.balance + self.margin_asset.balance + extra_margin + maintenance_amount) / denominator)
+    }
+
+    /// 获取当前价格下的维持保证金
+    pub fn get_maintenance_margin(&self, price: Decimal, table: &SMaintenanceMarginTable) -> Decimal {
+        table.get_maintenance_margin(self.base_asset.balance * price)
+    }
+
+    /// 判断k线价格区间内是否触发强平 触发时返回强平价格
+    ///
+    /// 多头在最低价跌破强平价格时触发，空头在最高价突破强平价格时触发
+    pub fn check_liquidation(&self, low_price: Decimal, high_price: Decimal, table: &SMaintenanceMarginTable, extra_margin: Decimal) -> Option<Decimal> {
+        let liquidation_price = self.get_tiered_liquidation_price(table, extra_margin)?;
+        let is_liquidated = if self.base_asset.balance > Decimal::from(0) {
+            low_price <= liquidation_price
+        } else {
+            high_price >= liquidation_price
+        };
+        if is_liquidated { Some(liquidation_price) } else { None }
+    }
+
+    /// 以强平价格强制平仓
+    ///
+    /// 强平手续费 = |基础资产量| * 强平价格 * 强平手续费率，从剩余保证金中扣除（最多扣除至0）
+    ///
+    /// 穿仓损失由保险基金承担，用户最多损失全部保证金
+    ///
+    /// 返回（退还给用户的保证金，强平手续费），平仓后仓位清零
+    pub fn liquidate(&mut self, liquidation_price: Decimal, liquidation_fee_rate: Decimal) -> (SAsset, SAsset) {
+        self.update(liquidation_price);
+        let remaining_margin = self.margin_asset.balance.max(Decimal::from(0));
+        let liquidation_fee = (self.base_asset.balance.abs() * liquidation_price * liquidation_fee_rate).min(remaining_margin);
+        let margin_type = self.margin_asset.as_type;
+        self.base_asset.balance = Decimal::from(0);
+        self.quote_asset.balance = Decimal::from(0);
+        self.margin_asset.balance = Decimal::from(0);
+        (
+            SAsset { as_type: margin_type, balance: remaining_margin - liquidation_fee },
+            SAsset { as_type: margin_type, balance: liquidation_fee },
+        )
+    }
+
+    /// 结算资金费
+    ///
+    /// 资金费 = 基础资产量 * 价格 * 资金费率，从保证金中扣除（为负时加入保证金）
+    ///
+    /// 资金费率为正时多头支付给空头，为负时空头支付给多头
+    ///
+    /// 返回用户支付的资金费（保证金资产类型，负值表示用户收取的资金费）
+    pub fn settle_funding(&mut self, price: Decimal, funding_rate: Decimal) -> SAsset {
+        self.update(price);
+        let funding_fee = self.base_asset.balance * price * funding_rate;
+        self.margin_asset.split_allow_negative(funding_fee)
+    }
+
     /// 补充保证金
     pub fn margin_top_up(&mut self, margin: SAsset) -> RAssetLeveragedResult<()> {
         if self.margin_asset.as_type != margin.as_type {
@@ -224,6 +320,7 @@ mod tests {
     use rust_decimal::prelude::FromPrimitive;
     use crate::data_runtime::asset::asset::SAsset;
     use crate::data_runtime::asset::asset_leveraged::{EAssetLeveragedError, SAssetLeveraged};
+    use crate::data_runtime::asset::margin::SMaintenanceMarginTable;
     use crate::data_runtime::asset::EAssetType;
     use crate::data_runtime::order::EOrderDirection;
     use crate::data_source::trading_pair::ETradingPairType;
@@ -312,6 +409,102 @@ mod tests {
         assert_eq!(asset1.get_leverage(), Decimal::from(2));
     }
 
+    #[test]
+    pub fn test_settle_funding() {
+        // 多头 资金费率为正时支付资金费
+        let mut asset1 = get_test_data1();
+        let funding_fee = asset1.settle_funding(Decimal::from(100_000), Decimal::from_f64(0.0001).unwrap());
+        assert_eq!(funding_fee.as_type, EAssetType::Usdt);
+        assert_eq!(funding_fee.balance, Decimal::from(10));
+        assert_eq!(asset1.margin_asset.balance, Decimal::from(9_990));
+        assert_eq!(asset1.quote_asset.balance, Decimal::from(-100_000));
+
+        // 结算时先按照最新价格调整保证金
+        let funding_fee = asset1.settle_funding(Decimal::from(110_000), Decimal::from_f64(-0.0001).unwrap());
+        assert_eq!(funding_fee.balance, Decimal::from(-11));
+        assert_eq!(asset1.margin_asset.balance, Decimal::from(20_001));
+        assert_eq!(asset1.quote_asset.balance, Decimal::from(-110_000));
+
+        // 空头 资金费率为正时收取资金费
+        let mut asset2 = SAssetLeveraged::new(
+            ETradingPairType::BtcUsdtFuture,
+            Decimal::from(-1),
+            SAsset { as_type: EAssetType::Usdt, balance: Decimal::from(10_000) },
+            Decimal::from(100_000),
+        ).unwrap();
+        let funding_fee = asset2.settle_funding(Decimal::from(100_000), Decimal::from_f64(0.0001).unwrap());
+        assert_eq!(funding_fee.balance, Decimal::from(-10));
+        assert_eq!(asset2.margin_asset.balance, Decimal::from(10_010));
+    }
+
+    #[test]
+    pub fn test_liquidate() {
+        // 多头 价格100_000 杠杆10倍
+        let mut asset1 = get_test_data1();
+        assert_eq!(asset1.get_maintenance_liquidation_price(Decimal::from(0)), Some(Decimal::from(90_000)));
+        let maintenance_margin_ratio = Decimal::from_f64(0.004).unwrap();
+        let liquidation_price = asset1.get_maintenance_liquidation_price(maintenance_margin_ratio).unwrap();
+        // 90_000 / (1-0.004)
+        assert!(liquidation_price > Decimal::from(90_361) && liquidation_price < Decimal::from(90_362));
+        let table = SMaintenanceMarginTable::flat(maintenance_margin_ratio, Decimal::from(125));
+        assert_eq!(asset1.get_tiered_liquidation_price(&table, Decimal::from(0)), Some(liquidation_price));
+        assert!(asset1.check_liquidation(Decimal::from(90_400), Decimal::from(101_000), &table, Decimal::from(0)).is_none());
+        assert_eq!(asset1.check_liquidation(Decimal::from(90_000), Decimal::from(101_000), &table, Decimal::from(0)), Some(liquidation_price));
+
+        let (returned_margin, liquidation_fee) = asset1.liquidate(liquidation_price, Decimal::from_f64(0.001).unwrap());
+        assert_eq!(returned_margin.as_type, EAssetType::Usdt);
+        assert_eq!(liquidation_fee.as_type, EAssetType::Usdt);
+        // 剩余保证金 = 维持保证金 = 0.004 * 强平价格
+        assert_eq!((returned_margin.balance + liquidation_fee.balance).round_dp(8), (liquidation_price * maintenance_margin_ratio).round_dp(8));
+        assert_eq!(liquidation_fee.balance.round_dp(8), (liquidation_price * Decimal::from_f64(0.001).unwrap()).round_dp(8));
+        assert_eq!(asset1.base_asset.balance, Decimal::from(0));
+        assert_eq!(asset1.margin_asset.balance, Decimal::from(0));
+        assert!(asset1.get_maintenance_liquidation_price(maintenance_margin_ratio).is_none());
+
+        // 空头 价格100_000 保证金10_000
+        let mut asset2 = SAssetLeveraged::new(
+            ETradingPairType::BtcUsdtFuture,
+            Decimal::from(-1),
+            SAsset { as_type: EAssetType::Usdt, balance: Decimal::from(10_000) },
+            Decimal::from(100_000),
+        ).unwrap();
+        assert_eq!(asset2.get_maintenance_liquidation_price(Decimal::from(0)), Some(Decimal::from(110_000)));
+        let table = SMaintenanceMarginTable::flat(Decimal::from(0), Decimal::from(125));
+        assert!(asset2.check_liquidation(Decimal::from(50_000), Decimal::from(109_000), &table, Decimal::from(0)).is_none());
+        assert!(asset2.check_liquidation(Decimal::from(50_000), Decimal::from(110_000), &table, Decimal::from(0)).is_some());
+
+        // 穿仓时手续费最多扣除至0
+        let (returned_margin, liquidation_fee) = asset2.liquidate(Decimal::from(120_000), Decimal::from_f64(0.01).unwrap());
+        assert_eq!(returned_margin.balance, Decimal::from(0));
+        assert_eq!(liquidation_fee.balance, Decimal::from(0));
+    }
+
+    #[test]
+    pub fn test_tiered_liquidation_price() {
+        let table = SMaintenanceMarginTable::default_for(ETradingPairType::BtcUsdtFuture).unwrap();
+        // 多头 10BTC 价格100_000 保证金100_000（10倍杠杆） 强平时名义价值处于1%档位
+        let asset = SAssetLeveraged::new(
+            ETradingPairType::BtcUsdtFuture,
+            Decimal::from(10),
+            SAsset { as_type: EAssetType::Usdt, balance: Decimal::from(100_000) },
+            Decimal::from(100_000),
+        ).unwrap();
+        let liquidation_price = asset.get_tiered_liquidation_price(&table, Decimal::from(0)).unwrap();
+        // 强平时 权益 = 维持保证金
+        let equity = asset.get_margin().balance + asset.get_quote().balance + asset.get_base().balance * liquidation_price;
+        let maintenance_margin = asset.get_maintenance_margin(liquidation_price, &table);
+        assert!((equity - maintenance_margin).abs() < Decimal::from_f64(0.000001).unwrap());
+        // (1_000_000 - 100_000 - 2_550) / (10 - 0.1)
+        assert_eq!(liquidation_price.round_dp(4), (Decimal::from(897_450) / Decimal::from_f64(9.9).unwrap()).round_dp(4));
+
+        // 全仓模式 额外保证金使强平价格远离当前价格
+        let cross_liquidation_price = asset.get_tiered_liquidation_price(&table, Decimal::from(200_000)).unwrap();
+        assert!(cross_liquidation_price < liquidation_price);
+        let equity = asset.get_margin().balance + Decimal::from(200_000) + asset.get_quote().balance + asset.get_base().balance * cross_liquidation_price;
+        let maintenance_margin = asset.get_maintenance_margin(cross_liquidation_price, &table);
+        assert!((equity - maintenance_margin).abs() < Decimal::from_f64(0.000001).unwrap());
+    }
+
     #[test]
     pub fn test_get_liquidation_price() {
         let mut asset1 = get_test_data1();
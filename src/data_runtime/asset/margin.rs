@@ -0,0 +1,218 @@
+//! 保证金模式与分层维持保证金
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use rust_decimal::Decimal;
+use rust_decimal::prelude::FromPrimitive;
+use serde::{Deserialize, Serialize};
+use crate::config::margin::{BTC_USD_CM_FUTURE_MAINTENANCE_MARGIN_BRACKETS, BTC_USDT_FUTURE_MAINTENANCE_MARGIN_BRACKETS};
+use crate::data_source::trading_pair::ETradingPairType;
+
+/// 保证金模式
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Default, Serialize, Deserialize)]
+pub enum EMarginMode {
+    /// 逐仓：仓位只使用自身的保证金
+    #[default]
+    Isolated,
+    /// 全仓：同一计价资产的仓位共享用户的可用余额
+    Cross,
+}
+
+/// 全仓模式下作为共享保证金的余额
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Default, Serialize, Deserialize)]
+pub enum ECrossMarginBalance {
+    /// 合约账户：只共享同一保证金资产的其他合约仓位的保证金 现货余额不参与强平
+    #[default]
+    FuturesWallet,
+    /// 统一账户：保证金资产的全部可用余额（U本位合约为现货USDT 币本位合约为持有的全部BTC）均作为共享保证金 强平时一并清算
+    AvailableBalance,
+}
+
+/// 维持保证金档位
+#[derive(Debug, Clone, PartialEq)]
+pub struct SMaintenanceMarginTier {
+    /// 名义价值下限（计价资产）
+    pub notional_floor: Decimal,
+    /// 最大杠杆倍数
+    pub max_leverage: Decimal,
+    /// 维持保证金率
+    pub maintenance_margin_ratio: Decimal,
+    /// 维持保证金速算额
+    pub maintenance_amount: Decimal,
+}
+
+pub type RMaintenanceMarginResult<T> = Result<T, EMaintenanceMarginError>;
+
+/// 维持保证金表异常
+#[derive(Debug, Clone, PartialEq)]
+pub enum EMaintenanceMarginError {
+    /// 档位为空
+    EmptyBracketsError,
+    /// 档位数值非有限值、为负数或最大杠杆倍数不为正数(档位序号 从0开始)
+    InvalidBracketError(usize),
+    /// 档位未按名义价值下限严格升序排列(档位序号 从0开始)
+    UnsortedBracketsError(usize),
+}
+
+impl Display for EMaintenanceMarginError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EMaintenanceMarginError::EmptyBracketsError => { write!(f, "维持保证金档位为空") }
+            EMaintenanceMarginError::InvalidBracketError(index) => { write!(f, "序号为{}的维持保证金档位数值无效", index) }
+            EMaintenanceMarginError::UnsortedBracketsError(index) => { write!(f, "序号为{}的维持保证金档位名义价值下限未大于上一档", index) }
+        }
+    }
+}
+
+impl Error for EMaintenanceMarginError {}
+
+/// 分层维持保证金表
+///
+/// 维持保证金 = 名义价值 * 维持保证金率 - 维持保证金速算额
+#[derive(Debug, Clone, PartialEq)]
+pub struct SMaintenanceMarginTable {
+    /// 按名义价值下限升序排列的档位 由构造函数保证非空
+    tiers: Vec<SMaintenanceMarginTier>,
+}
+
+impl SMaintenanceMarginTable {
+    /// 根据档位（名义价值下限，最大杠杆倍数，维持保证金率）构建
+    /// 速算额按照档位边界处维持保证金连续的原则自动计算
+    pub fn from_brackets(brackets: &[(f64, f64, f64)]) -> RMaintenanceMarginResult<Self> {
+        if brackets.is_empty() {
+            return Err(EMaintenanceMarginError::EmptyBracketsError);
+        }
+        let mut tiers: Vec<SMaintenanceMarginTier> = Vec::new();
+        for (index, (notional_floor, max_leverage, maintenance_margin_ratio)) in brackets.iter().enumerate() {
+            let (notional_floor, max_leverage, maintenance_margin_ratio) = match (
+                Decimal::from_f64(*notional_floor),
+                Decimal::from_f64(*max_leverage),
+                Decimal::from_f64(*maintenance_margin_ratio),
+            ) {
+                (Some(notional_floor), Some(max_leverage), Some(maintenance_margin_ratio))
+                if notional_floor >= Decimal::from(0) && max_leverage > Decimal::from(0) && maintenance_margin_ratio >= Decimal::from(0) => {
+                    (notional_floor, max_leverage, maintenance_margin_ratio)
+                }
+                _ => { return Err(EMaintenanceMarginError::InvalidBracketError(index)); }
+            };
+            let maintenance_amount = match tiers.last() {
+                None => { Decimal::from(0) }
+                Some(prev) => {
+                    if notional_floor <= prev.notional_floor {
+                        return Err(EMaintenanceMarginError::UnsortedBracketsError(index));
+                    }
+                    prev.maintenance_amount + notional_floor * (maintenance_margin_ratio - prev.maintenance_margin_ratio)
+                }
+            };
+            tiers.push(SMaintenanceMarginTier {
+                notional_floor,
+                max_leverage,
+                maintenance_margin_ratio,
+                maintenance_amount,
+            });
+        }
+        Ok(Self { tiers })
+    }
+
+    /// 单一维持保证金率
+    pub fn flat(maintenance_margin_ratio: Decimal, max_leverage: Decimal) -> Self {
+        Self {
+            tiers: vec![SMaintenanceMarginTier {
+                notional_floor: Decimal::from(0),
+                max_leverage,
+                maintenance_margin_ratio,
+                maintenance_amount: Decimal::from(0),
+            }],
+        }
+    }
+
+    /// 交易对默认的维持保证金表
+    pub fn default_for(tp_type: ETradingPairType) -> Option<Self> {
+        match tp_type {
+            ETradingPairType::BtcUsdt => { None }
+            ETradingPairType::BtcUsdtFuture => { Self::from_brackets(&BTC_USDT_FUTURE_MAINTENANCE_MARGIN_BRACKETS).ok() }
+            ETradingPairType::BtcUsdCmFuture => { Self::from_brackets(&BTC_USD_CM_FUTURE_MAINTENANCE_MARGIN_BRACKETS).ok() }
+        }
+    }
+
+    /// 按名义价值下限升序排列的档位
+    pub fn get_tiers(&self) -> &[SMaintenanceMarginTier] {
+        &self.tiers
+    }
+
+    /// 获取名义价值所在的档位 低于第一档下限时使用第一档
+    pub fn get_tier(&self, notional: Decimal) -> &SMaintenanceMarginTier {
+        let notional = notional.abs();
+        self.tiers.iter()
+            .rev()
+            .find(|tier| tier.notional_floor <= notional)
+            .unwrap_or(&self.tiers[0])
+    }
+
+    /// 计算维持保证金
+    pub fn get_maintenance_margin(&self, notional: Decimal) -> Decimal {
+        let tier = self.get_tier(notional);
+        (notional.abs() * tier.maintenance_margin_ratio - tier.maintenance_amount).max(Decimal::from(0))
+    }
+
+    /// 获取名义价值允许的最大杠杆倍数
+    pub fn get_max_leverage(&self, notional: Decimal) -> Decimal {
+        self.get_tier(notional).max_leverage
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rust_decimal::Decimal;
+    use rust_decimal::prelude::FromPrimitive;
+    use crate::data_runtime::asset::margin::{EMaintenanceMarginError, SMaintenanceMarginTable};
+    use crate::data_source::trading_pair::ETradingPairType;
+
+    #[test]
+    pub fn test_from_brackets() {
+        let table = SMaintenanceMarginTable::default_for(ETradingPairType::BtcUsdtFuture).unwrap();
+        // 速算额与Binance公布的数值一致
+        assert_eq!(table.tiers[1].maintenance_amount, Decimal::from(50));
+        assert_eq!(table.tiers[2].maintenance_amount, Decimal::from(2_550));
+        assert_eq!(table.tiers[3].maintenance_amount, Decimal::from(122_550));
+
+        assert_eq!(table.get_max_leverage(Decimal::from(10_000)), Decimal::from(125));
+        assert_eq!(table.get_max_leverage(Decimal::from(-50_000)), Decimal::from(100));
+        assert_eq!(table.get_max_leverage(Decimal::from(1_000_000)), Decimal::from(50));
+
+        assert_eq!(table.get_maintenance_margin(Decimal::from(10_000)), Decimal::from(40));
+        assert_eq!(table.get_maintenance_margin(Decimal::from(100_000)), Decimal::from(450));
+        // 档位边界处维持保证金连续
+        let boundary = Decimal::from(500_000);
+        let below = table.get_maintenance_margin(boundary - Decimal::from_f64(0.01).unwrap());
+        let above = table.get_maintenance_margin(boundary);
+        assert!((above - below).abs() < Decimal::from_f64(0.001).unwrap());
+    }
+
+    #[test]
+    pub fn test_from_brackets_error() {
+        assert_eq!(SMaintenanceMarginTable::from_brackets(&[]), Err(EMaintenanceMarginError::EmptyBracketsError));
+        assert_eq!(
+            SMaintenanceMarginTable::from_brackets(&[(0.0, 125.0, 0.004), (50_000.0, f64::NAN, 0.005)]),
+            Err(EMaintenanceMarginError::InvalidBracketError(1)),
+        );
+        assert_eq!(
+            SMaintenanceMarginTable::from_brackets(&[(0.0, 125.0, f64::INFINITY)]),
+            Err(EMaintenanceMarginError::InvalidBracketError(0)),
+        );
+        assert_eq!(
+            SMaintenanceMarginTable::from_brackets(&[(0.0, 0.0, 0.004)]),
+            Err(EMaintenanceMarginError::InvalidBracketError(0)),
+        );
+        assert_eq!(
+            SMaintenanceMarginTable::from_brackets(&[(0.0, 125.0, 0.004), (50_000.0, 100.0, 0.005), (50_000.0, 50.0, 0.01)]),
+            Err(EMaintenanceMarginError::UnsortedBracketsError(2)),
+        );
+    }
+
+    #[test]
+    pub fn test_flat() {
+        let table = SMaintenanceMarginTable::flat(Decimal::from_f64(0.01).unwrap(), Decimal::from(20));
+        assert_eq!(table.get_maintenance_margin(Decimal::from(100_000_000)), Decimal::from(1_000_000));
+        assert_eq!(table.get_max_leverage(Decimal::from(100_000_000)), Decimal::from(20));
+    }
+}
@@ -3,6 +3,7 @@ pub mod asset_map;
 pub mod asset_map_v3;
 pub mod asset_leveraged;
 pub mod asset_union;
+pub mod margin;
 
 /// 资产类型
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
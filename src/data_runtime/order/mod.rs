@@ -1,3 +1,5 @@
+use rust_decimal::Decimal;
+
 pub mod order;
 pub mod order_manager;
 pub mod trading_pair_order_manager_map;
@@ -19,6 +21,36 @@ pub enum EOrderAction {
     Sell
 }
 
+/// 订单类型
+/// 条件单（止损/止盈/跟踪止损）在价格触及触发条件前不参与撮合
+/// 买入方向：止损在价格上涨至触发价时触发，止盈在价格下跌至触发价时触发；卖出方向反之
+#[derive(Debug, Default, Clone, Copy, Eq, PartialEq)]
+pub enum EOrderType {
+    /// 限价单 价格可成交时（买价不低于/卖价不高于当前价格）按吃单成交 否则挂单
+    #[default]
+    Limit,
+    /// 市价单 以下一根k线的开盘价（含滑点）按吃单成交
+    Market,
+    /// 止损市价单(触发价格) 触发后以触发价格（含滑点）按吃单成交
+    StopMarket(Decimal),
+    /// 止损限价单(触发价格) 触发后按订单价格转为限价单
+    StopLimit(Decimal),
+    /// 止盈市价单(触发价格) 触发后以触发价格（含滑点）按吃单成交
+    TakeProfit(Decimal),
+    /// 跟踪止损单(回调比例) 价格从下单后的最优价格回调超过回调比例时触发 触发后按吃单成交
+    TrailingStop(Decimal),
+}
+
+impl EOrderType {
+    /// 是否为条件单
+    pub fn is_conditional(&self) -> bool {
+        match self {
+            EOrderType::Limit | EOrderType::Market => { false }
+            EOrderType::StopMarket(_) | EOrderType::StopLimit(_) | EOrderType::TakeProfit(_) | EOrderType::TrailingStop(_) => { true }
+        }
+    }
+}
+
 /// 仓位状态
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
 pub enum EOrderPosition {
@@ -8,6 +8,7 @@ use crate::data_runtime::asset::asset_map_v3::SAssetMapV3;
 use crate::data_runtime::asset::asset_union::EAssetUnion;
 use crate::data_runtime::order::EOrderAction;
 use crate::data_runtime::order::order_v3::{EOrderState, SAddOrder, SOrderV3};
+use crate::data_source::kline::SKlineUnitData;
 use crate::data_source::trading_pair::ETradingPairType;
 
 pub type ROrderManagerV3Result<T> = Result<T, EOrderManagerV3Error>;
@@ -18,7 +19,7 @@ pub enum EOrderManagerV3Error {
     /// 找不到Uuid对应的订单
     UuidNotFound(Uuid),
     /// 插入订单失败
-    InsertOrderFail(SOrderV3),
+    InsertOrderFail(Box<SOrderV3>),
     /// BuyOrders/SellOrders订单索引 在某个价格的UuidVec为空
     OrdersUuidVecEmptyError(EOrderAction, Decimal),
     /// BuyOrders/SellOrders订单索引中的Uuid无法在Orders上找到
@@ -42,6 +43,12 @@ pub struct SOrderManagerV3 {
     /// 卖单索引集合 K-价格 V-订单uuid列表
     pub sell_orders: BTreeMap<Decimal, Vec<Uuid>>,
 
+    /// 吃单索引 按提交顺序排列的订单uuid列表（不进入买卖单价格索引）
+    pub taker_orders: Vec<Uuid>,
+
+    /// 条件单索引 等待触发的订单uuid列表（不进入买卖单价格索引）
+    pub conditional_orders: Vec<Uuid>,
+
     /// 累计手续费
     pub total_fee_asset_map: SAssetMap,
 }
@@ -53,6 +60,8 @@ impl SOrderManagerV3 {
             orders: Default::default(),
             buy_orders: Default::default(),
             sell_orders: Default::default(),
+            taker_orders: Default::default(),
+            conditional_orders: Default::default(),
             total_fee_asset_map: Default::default(),
         }
     }
@@ -61,6 +70,16 @@ impl SOrderManagerV3 {
     /// 同时调整索引
     pub fn insert_order(&mut self, order: SOrderV3) -> ROrderManagerV3Result<()> {
         match self.orders.insert(order.get_id(), order.clone()) {
+            None if order.is_conditional_pending() => {
+                // 条件单触发前不进入价格索引
+                self.conditional_orders.push(order.get_id());
+                Ok(())
+            }
+            None if order.is_taker() => {
+                // 吃单不进入价格索引
+                self.taker_orders.push(order.get_id());
+                Ok(())
+            }
             None => {
                 // 插入成功的场景
                 let price_level = match order.get_action() {
@@ -72,7 +91,7 @@ impl SOrderManagerV3 {
             }
             Some(order) => {
                 // 插入失败的场景
-                Err(EOrderManagerV3Error::InsertOrderFail(order))
+                Err(EOrderManagerV3Error::InsertOrderFail(Box::new(order)))
             }
         }
     }
@@ -114,6 +133,14 @@ impl SOrderManagerV3 {
         // 删除订单
         match self.orders.remove(&uuid) {
             None => { None }
+            Some(order) if order.is_conditional_pending() => {
+                self.conditional_orders.retain(|&id| id != uuid);
+                Some(order)
+            }
+            Some(order) if order.is_taker() => {
+                self.taker_orders.retain(|&id| id != uuid);
+                Some(order)
+            }
             Some(order) => {
                 // 调整索引
                 let price_level = match order.get_action() {
@@ -210,6 +237,34 @@ impl SOrderManagerV3 {
         }
     }
 
+    /// 取出所有吃单 按提交顺序返回
+    pub fn pop_taker_orders(&mut self) -> Vec<SOrderV3> {
+        let uuid_list = std::mem::take(&mut self.taker_orders);
+        uuid_list.into_iter().filter_map(|uuid| self.orders.remove(&uuid)).collect()
+    }
+
+    /// 根据k线检查所有条件单 取出已触发的条件单及其触发价格
+    pub fn trigger_orders(&mut self, kline: &SKlineUnitData) -> Vec<(SOrderV3, Decimal)> {
+        let mut triggered_orders: Vec<(SOrderV3, Decimal)> = Vec::new();
+        let orders = &mut self.orders;
+        self.conditional_orders.retain(|uuid| {
+            let trigger_price = match orders.get_mut(uuid) {
+                None => { return false; }
+                Some(order) => { order.check_trigger(kline) }
+            };
+            match trigger_price {
+                None => { true }
+                Some(trigger_price) => {
+                    if let Some(order) = orders.remove(uuid) {
+                        triggered_orders.push((order, trigger_price));
+                    }
+                    false
+                }
+            }
+        });
+        triggered_orders
+    }
+
     /// 统计每种资产的总锁定量
     pub fn calculate_total_assets(&self) -> SAssetMapV3 {
         let mut result = SAssetMapV3::new();
@@ -231,15 +286,17 @@ impl SOrderManagerV3 {
 mod tests {
     use std::str::FromStr;
 
+    use chrono::Local;
     use rust_decimal::Decimal;
     use uuid::Uuid;
 
     use crate::data_runtime::asset::asset::SAsset;
     use crate::data_runtime::asset::asset_union::EAssetUnion;
     use crate::data_runtime::asset::EAssetType;
-    use crate::data_runtime::order::EOrderAction;
+    use crate::data_runtime::order::{EOrderAction, EOrderType};
     use crate::data_runtime::order::order_v3::{SAddOrder, SOrderV3};
     use crate::data_runtime::order::order_manager_v3::SOrderManagerV3;
+    use crate::data_source::kline::SKlineUnitData;
     use crate::data_source::trading_pair::ETradingPairType;
 
     fn get_test_data(action: EOrderAction) -> (SOrderManagerV3, Vec<Uuid>) {
@@ -408,6 +465,74 @@ mod tests {
         }
     }
 
+    #[test]
+    pub fn test_taker_orders() {
+        let mut manager = SOrderManagerV3::new(ETradingPairType::BtcUsdt);
+        let maker = SOrderV3::new_buy_order(ETradingPairType::BtcUsdt, Decimal::from(100), Decimal::from(1));
+        let taker1 = SOrderV3::new_buy_order(ETradingPairType::BtcUsdt, Decimal::from(200), Decimal::from(1))
+            .with_order_type(EOrderType::Market);
+        let mut taker2 = SOrderV3::new_sell_order(ETradingPairType::BtcUsdt, Decimal::from(50), Decimal::from(1));
+        taker2.set_taker(true);
+        let (taker1_id, taker2_id) = (taker1.get_id(), taker2.get_id());
+        manager.insert_order(maker).unwrap();
+        manager.insert_order(taker1).unwrap();
+        manager.insert_order(taker2.clone()).unwrap();
+        assert_eq!(manager.orders.len(), 3);
+        assert_eq!(manager.buy_orders.len(), 1);
+        assert!(manager.sell_orders.is_empty());
+        assert_eq!(manager.taker_orders, vec![taker1_id, taker2_id]);
+
+        // 吃单不影响盘口
+        assert_eq!(manager.peek_highest_buy_order().unwrap().unwrap().get_price(), Decimal::from(100));
+
+        // 撤销吃单
+        assert!(manager.remove_order(taker2_id).is_some());
+        assert_eq!(manager.taker_orders, vec![taker1_id]);
+
+        manager.insert_order(taker2).unwrap();
+        let takers = manager.pop_taker_orders();
+        assert_eq!(takers.iter().map(|order| order.get_id()).collect::<Vec<_>>(), vec![taker1_id, taker2_id]);
+        assert!(manager.taker_orders.is_empty());
+        assert_eq!(manager.orders.len(), 1);
+    }
+
+    #[test]
+    pub fn test_trigger_orders() {
+        let mut manager = SOrderManagerV3::new(ETradingPairType::BtcUsdt);
+        let stop_loss = SOrderV3::new_sell_order(ETradingPairType::BtcUsdt, Decimal::from(100), Decimal::from(1))
+            .with_order_type(EOrderType::StopMarket(Decimal::from(90)));
+        let take_profit = SOrderV3::new_sell_order(ETradingPairType::BtcUsdt, Decimal::from(100), Decimal::from(1))
+            .with_order_type(EOrderType::TakeProfit(Decimal::from(120)));
+        let (stop_loss_id, take_profit_id) = (stop_loss.get_id(), take_profit.get_id());
+        for mut order in [stop_loss, take_profit] {
+            order.submit(SAsset { as_type: EAssetType::Btc, balance: Decimal::from(2) }).unwrap();
+            manager.insert_order(order).unwrap();
+        }
+        assert_eq!(manager.conditional_orders, vec![stop_loss_id, take_profit_id]);
+        assert!(manager.sell_orders.is_empty());
+
+        let open_time = Local::now();
+        let kline = SKlineUnitData {
+            open_time,
+            close_time: open_time,
+            open_price: Decimal::from(95),
+            close_price: Decimal::from(88),
+            high_price: Decimal::from(96),
+            low_price: Decimal::from(85),
+            volume: Decimal::from(0),
+        };
+        let triggered_orders = manager.trigger_orders(&kline);
+        assert_eq!(triggered_orders.len(), 1);
+        assert_eq!(triggered_orders[0].0.get_id(), stop_loss_id);
+        assert_eq!(triggered_orders[0].1, Decimal::from(90));
+        assert_eq!(manager.conditional_orders, vec![take_profit_id]);
+        assert_eq!(manager.orders.len(), 1);
+
+        // 撤销条件单
+        assert!(manager.remove_order(take_profit_id).is_some());
+        assert!(manager.conditional_orders.is_empty());
+    }
+
     #[test]
     pub fn test_buy_pop() {
         let mut manager = SOrderManagerV3::new(ETradingPairType::BtcUsdt);
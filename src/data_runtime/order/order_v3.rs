@@ -2,7 +2,8 @@ use rust_decimal::Decimal;
 use uuid::Uuid;
 use crate::data_runtime::asset::asset::SAsset;
 use crate::data_runtime::asset::EAssetType;
-use crate::data_runtime::order::EOrderAction;
+use crate::data_runtime::order::{EOrderAction, EOrderType};
+use crate::data_source::kline::SKlineUnitData;
 use crate::data_source::trading_pair::ETradingPairType;
 
 /// 订单状态
@@ -15,14 +16,17 @@ pub enum EOrderState {
     // 已提交 未成交
     Unfulfilled,
 
+    // 条件单已触发 未成交
+    Triggered,
+
     // 已成交（并释放锁定资产 由交易对进行资产置换 ）
     Executed,
 
     // 已取消（释放锁定资产）
     Canceled,
 
-    // 未知（todo 部分成交 由交易对进行部分资产置换）
-    Unknown,
+    // 部分成交（成交部分拆分为独立订单结算 剩余部分继续挂单）
+    PartiallyExecuted,
 }
 
 pub type ROrderV3Result<T> = Result<T, EOrderV3Error>;
@@ -41,9 +45,11 @@ pub enum EOrderV3Error {
     /// 提供的Asset的资产量小于所需的资产量 将资产返回
     AssetQuantityNotEnoughError(EAssetType, RequiredQuantityDecimal, QuantityDecimal, SAsset),
     /// 未锁定Asset
-    LockedAssetNotExistError(SOrderV3),
+    LockedAssetNotExistError(Box<SOrderV3>),
     /// 成交了一个已存在fee asset的订单
-    ExecuteOrderWithFeeAssetError(SOrderV3),
+    ExecuteOrderWithFeeAssetError(Box<SOrderV3>),
+    /// 部分成交的数量必须大于0且小于订单数量(部分成交数量，订单数量)
+    PartialQuantityError(RequiredQuantityDecimal, QuantityDecimal),
 }
 
 
@@ -65,6 +71,12 @@ pub struct SOrderV3 {
     state: EOrderState,
     /// 订单操作
     action: EOrderAction,
+    /// 订单类型
+    order_type: EOrderType,
+    /// 是否按吃单成交（市价单和可立即成交的限价单）
+    is_taker: bool,
+    /// 跟踪止损单下单后的最优价格（卖出方向为最高价 买入方向为最低价）
+    trailing_extreme_price: Option<Decimal>,
     /// 挂单价格（汇率=计价资产/基础资产）
     price: Decimal,
     /// 挂单量（基础资产）
@@ -75,6 +87,8 @@ pub struct SOrderV3 {
     /// 当锁定资产量 < 所需量时 杠杆率>1
     /// 当锁定资产量 > 所需量时 杠杆率<1
     locked_asset: Option<SAsset>,
+    /// 条件单触发时需锁定的保证金资产量 条件单在触发前不锁定资产
+    margin_quantity: Decimal,
     /// 已支付的fee资产对象（只有Executed状态的Order能够持有此对象）
     paid_fee_asset: Option<SAsset>,
 }
@@ -101,10 +115,14 @@ impl SOrderV3 {
             tp_type,
             state: Default::default(),
             action,
+            order_type: EOrderType::Limit,
+            is_taker: false,
+            trailing_extreme_price: None,
             price,
             quantity,
             amount: price * quantity,
             locked_asset: None,
+            margin_quantity: Decimal::from(0),
             paid_fee_asset: None,
         }
     }
@@ -117,6 +135,110 @@ impl SOrderV3 {
         Self::new(tp_type, price, quantity, EOrderAction::Sell)
     }
 
+    /// 设置订单类型 市价单总是按吃单成交
+    pub fn with_order_type(mut self, order_type: EOrderType) -> Self {
+        self.order_type = order_type;
+        self.is_taker = order_type == EOrderType::Market;
+        self
+    }
+
+    /// 设置是否按吃单成交
+    pub fn set_taker(&mut self, is_taker: bool) {
+        self.is_taker = is_taker;
+    }
+
+    /// 成交方向是否为买入（杠杆资产做空开仓/平仓时与订单操作相反）
+    fn is_buy_side(&self) -> bool {
+        (self.action == EOrderAction::Buy) == (self.quantity >= Decimal::from(0))
+    }
+
+    /// 以当前价格提交时能否立即成交
+    /// 市价单总是可以成交，限价单买价不低于/卖价不高于当前价格时可以成交，条件单需等待触发
+    pub fn is_marketable(&self, current_price: Decimal) -> bool {
+        match self.order_type {
+            EOrderType::Market => { true }
+            EOrderType::StopMarket(_) | EOrderType::StopLimit(_) | EOrderType::TakeProfit(_) | EOrderType::TrailingStop(_) => { false }
+            EOrderType::Limit => {
+                if self.is_buy_side() {
+                    self.price >= current_price
+                } else {
+                    self.price <= current_price
+                }
+            }
+        }
+    }
+
+    /// 吃单成交价格
+    /// 以参考价格（吃单为开盘价 已触发的条件单为触发价格）加上不利方向的滑点成交
+    /// 限价单（含已触发的止损限价单）的成交价格劣于限价时返回None
+    pub fn get_taker_fill_price(&self, reference_price: Decimal, slippage: Decimal) -> Option<Decimal> {
+        let is_buy_side = self.is_buy_side();
+        let fill_price = if is_buy_side {
+            reference_price * (Decimal::from(1) + slippage)
+        } else {
+            reference_price * (Decimal::from(1) - slippage)
+        };
+        match self.order_type {
+            EOrderType::Market | EOrderType::StopMarket(_) | EOrderType::TakeProfit(_) | EOrderType::TrailingStop(_) => { Some(fill_price) }
+            EOrderType::Limit | EOrderType::StopLimit(_) => {
+                if (is_buy_side && fill_price <= self.price) || (!is_buy_side && fill_price >= self.price) {
+                    Some(fill_price)
+                } else {
+                    None
+                }
+            }
+        }
+    }
+
+    /// 是否为等待触发的条件单
+    pub fn is_conditional_pending(&self) -> bool {
+        self.order_type.is_conditional() && matches!(self.state, EOrderState::Pending | EOrderState::Unfulfilled)
+    }
+
+    /// 根据k线检查条件单是否触发
+    /// 触发时状态变为Triggered 返回触发价格（开盘价已越过触发价格时为开盘价）
+    /// 跟踪止损单未触发时 根据k线更新最优价格
+    pub fn check_trigger(&mut self, kline: &SKlineUnitData) -> Option<Decimal> {
+        if self.state != EOrderState::Unfulfilled {
+            return None;
+        }
+        let is_buy_side = self.is_buy_side();
+        let extreme_price = self.trailing_extreme_price.unwrap_or(self.price);
+        // (触发价格, 是否在价格上涨时触发)
+        let (trigger_price, is_trigger_on_rise) = match self.order_type {
+            EOrderType::Limit | EOrderType::Market => { return None; }
+            EOrderType::StopMarket(trigger_price) | EOrderType::StopLimit(trigger_price) => { (trigger_price, is_buy_side) }
+            EOrderType::TakeProfit(trigger_price) => { (trigger_price, !is_buy_side) }
+            EOrderType::TrailingStop(callback_rate) => {
+                if is_buy_side {
+                    (extreme_price * (Decimal::from(1) + callback_rate), true)
+                } else {
+                    (extreme_price * (Decimal::from(1) - callback_rate), false)
+                }
+            }
+        };
+        let reference_price = match is_trigger_on_rise {
+            true if kline.high_price >= trigger_price => { Some(kline.open_price.max(trigger_price)) }
+            false if kline.low_price <= trigger_price => { Some(kline.open_price.min(trigger_price)) }
+            _ => { None }
+        };
+        match reference_price {
+            None => {
+                if let EOrderType::TrailingStop(_) = self.order_type {
+                    self.trailing_extreme_price = Some(if is_buy_side {
+                        extreme_price.min(kline.low_price)
+                    } else {
+                        extreme_price.max(kline.high_price)
+                    });
+                }
+            }
+            Some(_) => {
+                self.state = EOrderState::Triggered;
+            }
+        }
+        reference_price
+    }
+
     pub fn update(&mut self, update: EOrderUpdate) {
         match update {
             EOrderUpdate::Price(price) => {
@@ -168,19 +290,37 @@ impl SOrderV3 {
         }
     }
 
+    /// 提交条件单 只记录触发时需锁定的保证金资产量
+    /// 同一仓位的止损单和止盈单可以同时挂出 先触发的订单平仓后 后触发的订单因可用资产不足被取消
+    pub fn submit_conditional(&mut self, margin_quantity: Decimal) -> ROrderV3Result<()> {
+        self.state_check(EOrderState::Pending)?;
+        self.margin_quantity = margin_quantity;
+        self.state = EOrderState::Unfulfilled;
+        Ok(())
+    }
+
+    /// 条件单触发后 绑定锁定的保证金资产
+    pub fn lock_triggered(&mut self, asset: SAsset) -> ROrderV3Result<()> {
+        self.state_check(EOrderState::Triggered)?;
+        self.locked_asset = Some(asset);
+        Ok(())
+    }
+
     /// 订单成交
     /// 绑定手续费资产
     /// 返回已锁定的资产
     pub fn execute(&mut self, paid_fee_asset: Option<SAsset>) -> ROrderV3Result<SAsset> {
-        // 状态校验
-        self.state_check(EOrderState::Unfulfilled)?;
+        // 状态校验 已触发的条件单和部分成交的订单可以成交
+        if !matches!(self.state, EOrderState::Triggered | EOrderState::PartiallyExecuted) {
+            self.state_check(EOrderState::Unfulfilled)?;
+        }
         match &self.paid_fee_asset {
             Some(_) => {
-                Err(EOrderV3Error::ExecuteOrderWithFeeAssetError(self.clone()))
+                Err(EOrderV3Error::ExecuteOrderWithFeeAssetError(Box::new(self.clone())))
             }
             None => {
                 match self.locked_asset.take() {
-                    None => { Err(EOrderV3Error::LockedAssetNotExistError(self.clone())) }
+                    None => { Err(EOrderV3Error::LockedAssetNotExistError(Box::new(self.clone()))) }
                     Some(asset) => {
                         self.state = EOrderState::Executed;
                         self.paid_fee_asset = Some(match paid_fee_asset {
@@ -199,6 +339,34 @@ impl SOrderV3 {
         }
     }
 
+    /// 部分成交
+    /// 按数量（绝对值）拆分出成交部分 锁定资产按数量比例拆分 拆分出的订单使用相同的id
+    /// 剩余部分状态变为PartiallyExecuted 继续挂单
+    pub fn split_partial(&mut self, quantity: Decimal) -> ROrderV3Result<Self> {
+        let total_quantity = self.quantity.abs();
+        if quantity <= Decimal::from(0) || quantity >= total_quantity {
+            return Err(EOrderV3Error::PartialQuantityError(quantity, total_quantity));
+        }
+        let locked_asset = match &mut self.locked_asset {
+            None => { return Err(EOrderV3Error::LockedAssetNotExistError(Box::new(self.clone()))); }
+            Some(locked_asset) => { locked_asset }
+        };
+        let ratio = quantity / total_quantity;
+        let partial_locked_balance = locked_asset.balance * ratio;
+        locked_asset.balance -= partial_locked_balance;
+        let partial_locked_asset = SAsset {
+            as_type: locked_asset.as_type,
+            balance: partial_locked_balance,
+        };
+
+        let mut partial_order = self.clone();
+        partial_order.update(EOrderUpdate::Quantity(self.quantity * ratio));
+        partial_order.locked_asset = Some(partial_locked_asset);
+        self.update(EOrderUpdate::Quantity(self.quantity - partial_order.quantity));
+        self.state = EOrderState::PartiallyExecuted;
+        Ok(partial_order)
+    }
+
     /// 订单取消
     /// 释放已锁定的资产
     pub fn cancel(&mut self) -> Option<SAsset> {
@@ -221,6 +389,14 @@ impl SOrderV3 {
         self.action
     }
 
+    pub fn get_order_type(&self) -> EOrderType {
+        self.order_type
+    }
+
+    pub fn is_taker(&self) -> bool {
+        self.is_taker
+    }
+
     pub fn get_price(&self) -> Decimal {
         self.price
     }
@@ -233,6 +409,10 @@ impl SOrderV3 {
         self.amount
     }
 
+    pub fn get_margin_quantity(&self) -> Decimal {
+        self.margin_quantity
+    }
+
     pub fn get_locked_asset(&self) -> &Option<SAsset> {
         &self.locked_asset
     }
@@ -249,10 +429,13 @@ impl SOrderV3 {
 
 #[cfg(test)]
 mod tests {
+    use chrono::Local;
     use rust_decimal::prelude::*;
     use crate::data_runtime::asset::asset::SAsset;
     use crate::data_runtime::asset::EAssetType;
+    use crate::data_runtime::order::EOrderType;
     use crate::data_runtime::order::order_v3::{EOrderV3Error, EOrderState, EOrderUpdate, SOrderV3};
+    use crate::data_source::kline::SKlineUnitData;
     use crate::data_source::trading_pair::ETradingPairType;
 
     fn get_pending_data() -> SOrderV3 {
@@ -305,6 +488,116 @@ mod tests {
         assert_eq!(order.get_state(), EOrderState::Pending);
     }
 
+    #[test]
+    pub fn test_order_type() {
+        let order = get_pending_data();
+        assert_eq!(order.get_order_type(), EOrderType::Limit);
+        assert!(!order.is_taker());
+
+        let mut order = get_pending_data().with_order_type(EOrderType::Market);
+        assert_eq!(order.get_order_type(), EOrderType::Market);
+        assert!(order.is_taker());
+        order.set_taker(false);
+        assert!(!order.is_taker());
+    }
+
+    #[test]
+    pub fn test_taker_fill_price() {
+        let tp_type = ETradingPairType::BtcUsdt;
+        let slippage = Decimal::from_str("0.01").unwrap();
+
+        // 市价单以开盘价加滑点成交
+        let order = SOrderV3::new_buy_order(tp_type, Decimal::from(100), Decimal::from(1)).with_order_type(EOrderType::Market);
+        assert!(order.is_marketable(Decimal::from(200)));
+        assert_eq!(order.get_taker_fill_price(Decimal::from(200), slippage), Some(Decimal::from(202)));
+        let order = SOrderV3::new_sell_order(tp_type, Decimal::from(100), Decimal::from(1)).with_order_type(EOrderType::Market);
+        assert_eq!(order.get_taker_fill_price(Decimal::from(200), slippage), Some(Decimal::from(198)));
+
+        // 限价买单 价格高于当前价格时可立即成交 开盘价越过限价时不成交
+        let order = SOrderV3::new_buy_order(tp_type, Decimal::from(105), Decimal::from(1));
+        assert!(order.is_marketable(Decimal::from(100)));
+        assert!(!order.is_marketable(Decimal::from(110)));
+        assert_eq!(order.get_taker_fill_price(Decimal::from(100), slippage), Some(Decimal::from(101)));
+        assert_eq!(order.get_taker_fill_price(Decimal::from(104), slippage), None);
+
+        // 限价卖单
+        let order = SOrderV3::new_sell_order(tp_type, Decimal::from(95), Decimal::from(1));
+        assert!(order.is_marketable(Decimal::from(100)));
+        assert!(!order.is_marketable(Decimal::from(90)));
+        assert_eq!(order.get_taker_fill_price(Decimal::from(100), slippage), Some(Decimal::from(99)));
+        assert_eq!(order.get_taker_fill_price(Decimal::from(95), slippage), None);
+
+        // 做空开仓（买入负数量）按卖出方向判断
+        let order = SOrderV3::new_buy_order(ETradingPairType::BtcUsdtFuture, Decimal::from(95), Decimal::from(-1));
+        assert!(order.is_marketable(Decimal::from(100)));
+        assert_eq!(order.get_taker_fill_price(Decimal::from(100), slippage), Some(Decimal::from(99)));
+    }
+
+    fn get_kline(open: i64, high: i64, low: i64) -> SKlineUnitData {
+        let open_time = Local::now();
+        SKlineUnitData {
+            open_time,
+            close_time: open_time,
+            open_price: Decimal::from(open),
+            close_price: Decimal::from(open),
+            high_price: Decimal::from(high),
+            low_price: Decimal::from(low),
+            volume: Decimal::from(0),
+        }
+    }
+
+    fn get_conditional_data(quantity: i64, order_type: EOrderType) -> SOrderV3 {
+        let mut order = SOrderV3::new_sell_order(ETradingPairType::BtcUsdtFuture, Decimal::from(100), Decimal::from(quantity))
+            .with_order_type(order_type);
+        order.submit(SAsset { as_type: EAssetType::Usdt, balance: Decimal::from(100) }).unwrap();
+        order
+    }
+
+    #[test]
+    pub fn test_check_trigger_stop() {
+        // 卖出止损 价格跌破触发价时触发
+        let mut order = get_conditional_data(1, EOrderType::StopMarket(Decimal::from(90)));
+        assert!(order.is_conditional_pending());
+        assert!(!order.is_marketable(Decimal::from(80)));
+        assert_eq!(order.check_trigger(&get_kline(100, 110, 91)), None);
+        assert_eq!(order.check_trigger(&get_kline(95, 96, 85)), Some(Decimal::from(90)));
+        assert_eq!(order.get_state(), EOrderState::Triggered);
+        assert!(!order.is_conditional_pending());
+        // 已触发的条件单不再重复触发 可以成交
+        assert_eq!(order.check_trigger(&get_kline(95, 96, 85)), None);
+        assert!(order.execute(None).is_ok());
+
+        // 开盘价跳空越过触发价时 以开盘价触发
+        let mut order = get_conditional_data(1, EOrderType::StopLimit(Decimal::from(90)));
+        assert_eq!(order.check_trigger(&get_kline(80, 85, 75)), Some(Decimal::from(80)));
+        // 止损限价单触发后按限价判断能否成交
+        assert_eq!(order.get_taker_fill_price(Decimal::from(80), Decimal::from(0)), None);
+
+        // 做空止损（平空为卖出负数量）价格涨破触发价时触发
+        let mut order = get_conditional_data(-1, EOrderType::StopMarket(Decimal::from(110)));
+        assert_eq!(order.check_trigger(&get_kline(100, 109, 90)), None);
+        assert_eq!(order.check_trigger(&get_kline(100, 115, 90)), Some(Decimal::from(110)));
+    }
+
+    #[test]
+    pub fn test_check_trigger_take_profit() {
+        // 卖出止盈 价格涨破触发价时触发
+        let mut order = get_conditional_data(1, EOrderType::TakeProfit(Decimal::from(120)));
+        assert_eq!(order.check_trigger(&get_kline(100, 110, 80)), None);
+        assert_eq!(order.check_trigger(&get_kline(110, 125, 105)), Some(Decimal::from(120)));
+        assert_eq!(order.get_taker_fill_price(Decimal::from(120), Decimal::from_str("0.01").unwrap()), Some(Decimal::from_str("118.8").unwrap()));
+    }
+
+    #[test]
+    pub fn test_check_trigger_trailing_stop() {
+        // 卖出跟踪止损 以下单价格100为初始最优价格 回调10%触发
+        let mut order = get_conditional_data(1, EOrderType::TrailingStop(Decimal::from_str("0.1").unwrap()));
+        assert_eq!(order.check_trigger(&get_kline(100, 120, 95)), None);
+        // 最优价格更新为120 触发价格为108
+        assert_eq!(order.check_trigger(&get_kline(115, 118, 109)), None);
+        assert_eq!(order.check_trigger(&get_kline(112, 113, 100)), Some(Decimal::from(108)));
+    }
+
     #[test]
     pub fn test_update() {
         let mut order = get_pending_data();
@@ -395,6 +688,57 @@ mod tests {
         assert_eq!(balance, Decimal::from_str("31.4159260").unwrap());
     }
 
+    #[test]
+    pub fn test_submit_conditional() {
+        // 条件单提交时不锁定资产
+        let mut order = SOrderV3::new_sell_order(ETradingPairType::BtcUsdt, Decimal::from(100), Decimal::from(1))
+            .with_order_type(EOrderType::StopMarket(Decimal::from(90)));
+        order.submit_conditional(Decimal::from(1)).unwrap();
+        assert!(order.is_conditional_pending());
+        assert!(order.get_locked_asset().is_none());
+        assert_eq!(order.get_margin_quantity(), Decimal::from(1));
+        assert!(order.cancel().is_none());
+
+        // 触发前不能锁定资产 触发后锁定资产并成交
+        let mut order = SOrderV3::new_sell_order(ETradingPairType::BtcUsdt, Decimal::from(100), Decimal::from(1))
+            .with_order_type(EOrderType::StopMarket(Decimal::from(90)));
+        order.submit_conditional(Decimal::from(1)).unwrap();
+        let asset = SAsset { as_type: EAssetType::Btc, balance: Decimal::from(1) };
+        assert!(order.lock_triggered(asset.clone()).is_err());
+        assert!(order.check_trigger(&get_kline(95, 96, 85)).is_some());
+        order.lock_triggered(asset).unwrap();
+        assert_eq!(order.execute(None).unwrap().balance, Decimal::from(1));
+    }
+
+    #[test]
+    pub fn test_split_partial() {
+        let mut order = get_unfulfilled_data();
+        assert!(matches!(order.split_partial(Decimal::from(10)), Err(EOrderV3Error::PartialQuantityError(..))));
+        assert!(matches!(order.split_partial(Decimal::from(0)), Err(EOrderV3Error::PartialQuantityError(..))));
+
+        let mut partial_order = order.split_partial(Decimal::from(4)).unwrap();
+        assert_eq!(partial_order.get_id(), order.get_id());
+        assert_eq!(partial_order.get_quantity(), Decimal::from(4));
+        assert_eq!(partial_order.get_amount(), Decimal::from_str("12.5663704").unwrap());
+        assert_eq!(partial_order.get_locked_asset().as_ref().unwrap().balance, Decimal::from_str("12.5663704").unwrap());
+        assert_eq!(order.get_quantity(), Decimal::from(6));
+        assert_eq!(order.get_state(), EOrderState::PartiallyExecuted);
+        assert_eq!(order.get_locked_asset().as_ref().unwrap().balance, Decimal::from_str("18.8495556").unwrap());
+
+        // 成交部分和剩余部分均可成交
+        assert!(partial_order.execute(None).is_ok());
+        assert!(order.execute(None).is_ok());
+
+        // 做空订单按绝对值拆分
+        let mut order = get_pending_data();
+        order.update(EOrderUpdate::Quantity(Decimal::from(-10)));
+        order.submit(SAsset { as_type: EAssetType::Usdt, balance: Decimal::from(10) }).unwrap();
+        let partial_order = order.split_partial(Decimal::from(4)).unwrap();
+        assert_eq!(partial_order.get_quantity(), Decimal::from(-4));
+        assert_eq!(order.get_quantity(), Decimal::from(-6));
+        assert_eq!(partial_order.get_locked_asset().as_ref().unwrap().balance, Decimal::from(4));
+    }
+
     #[test]
     pub fn test_cancel_pending() {
         let mut order = get_unfulfilled_data();
@@ -61,6 +61,9 @@ pub struct SUser<S: TStrategy> {
     /// 可用资产管理器
     pub available_assets: SAssetMapV3,
 
+    /// 累计支付的资金费（负值表示累计收取）
+    pub total_funding_fee: SAssetMap,
+
     /// 策略
     pub strategy: S,
 }
@@ -105,6 +108,7 @@ impl<S: TStrategy> SUser<S> {
             name: config.user_name,
             tp_order_map,
             available_assets,
+            total_funding_fee: SAssetMap::new(),
             strategy,
         }
     }
@@ -147,6 +151,23 @@ impl<S: TStrategy> SUser<S> {
         self.tp_order_map.calculate_total_fees()
     }
 
+    /// 累计用户的总资金费
+    pub fn total_funding_fee(&self) -> SAssetMap {
+        self.total_funding_fee.clone()
+    }
+
+    /// 结算杠杆资产的资金费 并累计到total_funding_fee
+    /// 未持有对应杠杆资产时返回None
+    pub fn settle_funding(&mut self, tp_type: ETradingPairType, price: Decimal, funding_rate: Decimal) -> Option<SAsset> {
+        let asset_leveraged = match self.available_assets.get_mut(tp_type.get_base_currency_type()) {
+            Ok(EAssetUnion::BtcUsdtFuture(asset_leveraged)) | Ok(EAssetUnion::BtcUsdCmFuture(asset_leveraged)) => { asset_leveraged }
+            _ => { return None; }
+        };
+        let funding_fee = asset_leveraged.settle_funding(price, funding_rate);
+        self.total_funding_fee.merge_asset(funding_fee.clone());
+        Some(funding_fee)
+    }
+
     /// 向可用资产插入SAsset
     pub fn merge_available_asset(&mut self, other: EAssetUnion) {
         self.available_assets.merge_asset(other)
@@ -165,6 +186,8 @@ mod tests {
     use rust_decimal::Decimal;
 
     use crate::data_runtime::asset::asset::SAsset;
+    use crate::data_runtime::asset::asset_leveraged::SAssetLeveraged;
+    use crate::data_runtime::asset::asset_union::EAssetUnion;
     use crate::data_runtime::asset::EAssetType;
     use crate::data_runtime::order::EOrderAction;
     use crate::data_runtime::order::order_v3::SAddOrder;
@@ -266,4 +289,26 @@ mod tests {
         assert!(btc.is_ok());
         assert_eq!(btc.unwrap().get_balance(), Decimal::from(0));
     }
-}
\ No newline at end of file
+
+    #[test]
+    pub fn test_settle_funding() {
+        let mut user = SUser::new(SUserConfig::default(), SStrategyMkTest::default());
+        // 未持有U本位合约时不结算
+        assert!(user.settle_funding(ETradingPairType::BtcUsdtFuture, Decimal::from(10_000), Decimal::from_str("0.0001").unwrap()).is_none());
+
+        user.merge_available_asset(EAssetUnion::BtcUsdtFuture(SAssetLeveraged::new(
+            ETradingPairType::BtcUsdtFuture,
+            Decimal::from(2),
+            SAsset { as_type: EAssetType::Usdt, balance: Decimal::from(2_000) },
+            Decimal::from(10_000),
+        ).unwrap()));
+        let funding_fee = user.settle_funding(ETradingPairType::BtcUsdtFuture, Decimal::from(10_000), Decimal::from_str("0.0001").unwrap()).unwrap();
+        assert_eq!(funding_fee.balance, Decimal::from(2));
+        user.settle_funding(ETradingPairType::BtcUsdtFuture, Decimal::from(10_000), Decimal::from_str("-0.0003").unwrap());
+        assert_eq!(user.total_funding_fee().get(&EAssetType::Usdt).unwrap().balance, Decimal::from(-4));
+        match user.available_assets.get(&EAssetType::BtcUsdtFuture).unwrap() {
+            EAssetUnion::BtcUsdtFuture(asset_leveraged) => { assert_eq!(asset_leveraged.get_margin().balance, Decimal::from(2_004)) }
+            _ => { panic!() }
+        }
+    }
+}
@@ -1,85 +1,244 @@
 //! 数据管理器
 use std::collections::HashMap;
+use std::sync::Arc;
 
 use chrono::{DateTime, Local};
 use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
 
 use crate::{
     data_source::{
         db::{
             api::data_api_db::SDataApiDb,
             api::TDataApi,
-            SDbClickhouse
+            RDBResult,
         },
+        data_quality::{SDataQualityConfig, SDataQualityReport},
         funding_rate::SFundingRateData,
         trading_pair::{
             ETradingPairType,
-            trading_pair::STradingPair,
-            trading_pair_map::{RTradingPairManagerResult, STradingPairMap}
+            trading_pair::STradingPairView,
+            trading_pair_map::{RTradingPairManagerResult, STradingPairMap, STradingPairMapView}
         }
     }
 };
-use crate::data_source::db::dao::binance_kline_dao::tables::{BTC_MARGINED_FUTURE_BTC_1M_TABLE_NAME, BTC_USDT_1M_TABLE_NAME};
+use crate::data_source::db::dao::binance_funding_rate_dao::tables::{BTC_MARGINED_FUTURE_BTC_FUNDING_RATE_TABLE_NAME, BTC_USDT_FUTURE_FUNDING_RATE_TABLE_NAME};
+use crate::data_source::db::dao::binance_kline_dao::tables::{BTC_MARGINED_FUTURE_BTC_1M_TABLE_NAME, BTC_USDT_1M_TABLE_NAME, BTC_USDT_FUTURE_1M_TABLE_NAME};
 
 /// 数据管理器
+/// 加载完成后的数据只读 通过window生成共享同一份数据的时间窗口数据管理器（如多线程回测的各个分区）
 #[derive(Debug)]
 pub struct SDataManager<A: TDataApi> {
     /// 数据接口
-    pub data_api: A,
+    pub data_api: Arc<A>,
     /// 交易对管理器
-    pub trading_pair_map: STradingPairMap,
+    pub trading_pair_map: STradingPairMapView,
+    /// 数据质量检查结果 各时间窗口数据管理器共享
+    pub quality_report: Arc<SDataQualityReport>,
+}
+
+/// 交易对数据源配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct STradingPairSource {
+    /// 交易对类型
+    pub tp_type: ETradingPairType,
+    /// k线数据源（数据库表名称或文件名称）
+    pub kline_source: String,
+    /// 资金费率数据源 None表示不加载资金费率
+    pub funding_rate_source: Option<String>,
+}
+
+impl STradingPairSource {
+    pub fn new(tp_type: ETradingPairType, kline_source: &str, funding_rate_source: Option<&str>) -> Self {
+        Self {
+            tp_type,
+            kline_source: kline_source.to_string(),
+            funding_rate_source: funding_rate_source.map(|source| source.to_string()),
+        }
+    }
+}
+
+/// 数据管理器构建器
+/// 注入任意数据接口（ClickHouse、本地文件、合成数据），并配置需要加载的交易对
+#[derive(Debug)]
+pub struct SDataManagerBuilder<A: TDataApi> {
+    /// 数据接口
+    pub data_api: A,
+    /// 交易对数据源配置
+    pub sources: Vec<STradingPairSource>,
+    /// 数据质量检查配置 加载完成后自动检查
+    pub quality_config: SDataQualityConfig,
+}
+
+impl<A: TDataApi> SDataManagerBuilder<A> {
+    pub fn new(data_api: A) -> Self {
+        Self { data_api, sources: Vec::new(), quality_config: SDataQualityConfig::default() }
+    }
+
+    /// 设置数据质量检查配置
+    pub fn with_quality_config(mut self, quality_config: SDataQualityConfig) -> Self {
+        self.quality_config = quality_config;
+        self
+    }
+
+    /// 添加交易对数据源
+    pub fn add_trading_pair(mut self, tp_type: ETradingPairType, kline_source: &str, funding_rate_source: Option<&str>) -> Self {
+        self.sources.push(STradingPairSource::new(tp_type, kline_source, funding_rate_source));
+        self
+    }
+
+    /// 批量添加交易对数据源
+    pub fn add_trading_pairs(mut self, sources: Vec<STradingPairSource>) -> Self {
+        self.sources.extend(sources);
+        self
+    }
+
+    /// 从数据接口加载所有交易对的数据 并进行数据质量检查
+    pub async fn build(self, date_from: &DateTime<Local>, date_to: &DateTime<Local>) -> RDBResult<SDataManager<A>> {
+        let Self { data_api, sources, quality_config } = self;
+        let mut trading_pair_map = STradingPairMap::new();
+        for source in sources {
+            let kline = data_api.get_kline(&source.kline_source, date_from, date_to).await?;
+            let funding_rate: Option<SFundingRateData> = match &source.funding_rate_source {
+                None => { None }
+                Some(funding_rate_source) => { Some(data_api.get_funding_rate(funding_rate_source, date_from, date_to).await?) }
+            };
+            trading_pair_map.add_trading_pair(source.tp_type, kline, funding_rate);
+        }
+        let quality_report = quality_config.check(&mut trading_pair_map);
+        quality_report.log();
+        Ok(SDataManager { quality_report: Arc::new(quality_report), ..SDataManager::new(data_api, trading_pair_map) })
+    }
 }
 
 impl SDataManager<SDataApiDb> {
-    pub async fn build(date_from: &DateTime<Local>, date_to: &DateTime<Local>) -> Self {
-        let db = SDbClickhouse::new();
-        let data_api = SDataApiDb::new(db);
-
-        let mut trading_pair_manager = STradingPairMap::new();
-        // 插入trading_pair配置 todo 插入更多配置
-        
-        // 现货配置
-        let btc_usdt_kline = data_api.get_kline(BTC_USDT_1M_TABLE_NAME, date_from, date_to).await.unwrap();
-        let btc_usdt_funding_rate: Option<SFundingRateData> = None; // todo 插入资金费率
-        trading_pair_manager.add_trading_pair(ETradingPairType::BtcUsdt, btc_usdt_kline, btc_usdt_funding_rate);
-        
-        // 币本位合约配置
-        let btc_margined_future_btc_kline = data_api.get_kline(BTC_MARGINED_FUTURE_BTC_1M_TABLE_NAME, date_from, date_to).await.unwrap();
-        let btc_margined_future_btc_funding_rate: Option<SFundingRateData> = None; // todo 插入资金费率
-        trading_pair_manager.add_trading_pair(ETradingPairType::BtcUsdCmFuture, btc_margined_future_btc_kline, btc_margined_future_btc_funding_rate);
-
-        // todo U本位合约配置
-        
-        Self { data_api, trading_pair_map: trading_pair_manager }
+    /// 默认的交易对数据源配置
+    pub fn default_sources() -> Vec<STradingPairSource> {
+        vec![
+            // 现货配置
+            STradingPairSource::new(ETradingPairType::BtcUsdt, BTC_USDT_1M_TABLE_NAME, None),
+            // U本位合约配置
+            STradingPairSource::new(ETradingPairType::BtcUsdtFuture, BTC_USDT_FUTURE_1M_TABLE_NAME, Some(BTC_USDT_FUTURE_FUNDING_RATE_TABLE_NAME)),
+            // 币本位合约配置
+            STradingPairSource::new(ETradingPairType::BtcUsdCmFuture, BTC_MARGINED_FUTURE_BTC_1M_TABLE_NAME, Some(BTC_MARGINED_FUTURE_BTC_FUNDING_RATE_TABLE_NAME)),
+        ]
+    }
+
+    /// 使用环境变量中的ClickHouse连接配置及默认的交易对数据源加载数据 缺少连接配置或加载失败时返回异常
+    pub async fn build(date_from: &DateTime<Local>, date_to: &DateTime<Local>) -> RDBResult<Self> {
+        SDataManagerBuilder::new(SDataApiDb::from_env()?)
+            .add_trading_pairs(Self::default_sources())
+            .build(date_from, date_to)
+            .await
     }
 }
 
 impl<A: TDataApi> SDataManager<A> {
-    /// 获取所有交易对
-    pub fn get_trading_pairs(&self) -> &HashMap<ETradingPairType, STradingPair> {
-        &self.trading_pair_map.inner
+    /// 使用已加载的交易对数据构建（如合成数据）
+    pub fn new(data_api: A, trading_pair_map: STradingPairMap) -> Self {
+        Self {
+            data_api: Arc::new(data_api),
+            trading_pair_map: STradingPairMapView::from(trading_pair_map),
+            quality_report: Arc::new(SDataQualityReport::default()),
+        }
     }
 
-    /// 获取特定交易对
-    pub fn get_mut_trading_pairs(&mut self) -> &mut HashMap<ETradingPairType, STradingPair> {
-        &mut self.trading_pair_map.inner
+    /// 共享同一份数据和数据接口的时间窗口数据管理器 只能访问[date_from, date_to]内的k线和资金费率
+    pub fn window(&self, date_from: DateTime<Local>, date_to: DateTime<Local>) -> Self {
+        Self {
+            data_api: Arc::clone(&self.data_api),
+            trading_pair_map: self.trading_pair_map.with_window(date_from, date_to),
+            quality_report: Arc::clone(&self.quality_report),
+        }
     }
 
-    /// 获取所有交易对
-    pub fn get_trading_pair(&self, tp_type: ETradingPairType) -> RTradingPairManagerResult<&STradingPair> {
-        self.trading_pair_map.get(tp_type)
+    /// 创建数据管理器构建器
+    pub fn builder(data_api: A) -> SDataManagerBuilder<A> {
+        SDataManagerBuilder::new(data_api)
     }
 
-    /// 获取特定交易对
-    pub fn get_mut_trading_pair(&mut self, tp_type: ETradingPairType) -> RTradingPairManagerResult<&mut STradingPair> {
-        self.trading_pair_map.get_mut(tp_type)
+    /// 获取所有交易对的时间窗口视图
+    pub fn get_trading_pairs(&self) -> HashMap<ETradingPairType, STradingPairView<'_>> {
+        self.trading_pair_map.iter().map(|trading_pair| (trading_pair.get_tp_type(), trading_pair)).collect()
     }
 
-    /// 获取特定K线的收盘时间
+    /// 获取特定交易对的时间窗口视图
+    pub fn get_trading_pair(&self, tp_type: ETradingPairType) -> RTradingPairManagerResult<STradingPairView<'_>> {
+        self.trading_pair_map.get(tp_type)
+    }
+
+    /// 获取特定K线的收盘时间 时间窗口外返回None
     pub fn get_close_price(&self, tp_type: ETradingPairType, date_time: &DateTime<Local>) -> RTradingPairManagerResult<Option<Decimal>> {
-        match self.get_trading_pair(tp_type)?.get_kline(date_time) {
+        match self.trading_pair_map.get_kline(tp_type, date_time)? {
             None => {Ok(None)}
             Some(kline) => {Ok(Some(kline.close_price))}
         }
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::sync::Arc;
+    use chrono::{Local, TimeZone};
+    use rust_decimal::Decimal;
+    use crate::data_source::data_manager::SDataManager;
+    use crate::data_source::db::api::data_api_csv::SDataApiCsv;
+    use crate::data_source::trading_pair::ETradingPairType;
+
+    /// 2020-01-01 00:00:00 UTC
+    const BASE_TIMESTAMP: i64 = 1_577_836_800;
+
+    #[tokio::test]
+    pub async fn test_build_with_csv() {
+        let dir = std::env::temp_dir().join(format!("data_manager_{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        let mut kline = String::new();
+        for offset in 0..5 {
+            let open_time = BASE_TIMESTAMP + offset * 60;
+            kline.push_str(&format!("{},7000,7010,6990,{},1,{},7000,1,0.5,3500,0\n", open_time, 7000 + offset, open_time + 59));
+        }
+        fs::write(dir.join("kline_btc_usdt_1m.csv"), &kline).unwrap();
+        fs::write(dir.join("kline_btc_usdt_future_1m.csv"), &kline).unwrap();
+        fs::write(dir.join("funding_rate_btc_usdt_future.csv"), format!("{},8,0.0001\n", BASE_TIMESTAMP)).unwrap();
+
+        let from = Local.timestamp_opt(BASE_TIMESTAMP, 0).unwrap();
+        let to = Local.timestamp_opt(BASE_TIMESTAMP + 10 * 60, 0).unwrap();
+        let data_manager = SDataManager::builder(SDataApiCsv::new(&dir))
+            .add_trading_pair(ETradingPairType::BtcUsdt, "kline_btc_usdt_1m", None)
+            .add_trading_pair(ETradingPairType::BtcUsdtFuture, "kline_btc_usdt_future_1m", Some("funding_rate_btc_usdt_future"))
+            .build(&from, &to)
+            .await
+            .unwrap();
+
+        assert_eq!(data_manager.get_trading_pairs().len(), 2);
+        assert!(data_manager.get_trading_pair(ETradingPairType::BtcUsdCmFuture).is_err());
+        let last = Local.timestamp_opt(BASE_TIMESTAMP + 4 * 60, 0).unwrap();
+        assert_eq!(data_manager.get_close_price(ETradingPairType::BtcUsdtFuture, &last).unwrap(), Some(Decimal::from(7004)));
+        let future = data_manager.get_trading_pair(ETradingPairType::BtcUsdtFuture).unwrap();
+        assert!(future.get_funding_rate(&from).is_some());
+        assert!(!data_manager.get_trading_pair(ETradingPairType::BtcUsdt).unwrap().has_funding_rate());
+        assert!(data_manager.quality_report.is_clean());
+
+        // 时间窗口数据管理器共享同一份数据 只能访问窗口内的数据
+        let window = data_manager.window(from + chrono::Duration::minutes(1), from + chrono::Duration::minutes(2));
+        assert!(window.trading_pair_map.is_shared_with(&data_manager.trading_pair_map));
+        assert!(Arc::ptr_eq(&window.quality_report, &data_manager.quality_report));
+        assert_eq!(window.get_trading_pairs().len(), 2);
+        assert_eq!(window.get_close_price(ETradingPairType::BtcUsdtFuture, &last).unwrap(), None);
+        assert!(window.get_close_price(ETradingPairType::BtcUsdtFuture, &(from + chrono::Duration::minutes(1))).unwrap().is_some());
+        let window_future = window.get_trading_pair(ETradingPairType::BtcUsdtFuture).unwrap();
+        assert_eq!(window_future.iter_kline().count(), 2);
+        assert!(window_future.get_funding_rate(&from).is_none());
+        assert_eq!(window_future.range_funding_rate(from, last).unwrap().count(), 0);
+        assert!(window_future.next_kline(&(from + chrono::Duration::minutes(2))).is_none());
+
+        // 数据源缺失时返回错误
+        let result = SDataManager::builder(SDataApiCsv::new(&dir))
+            .add_trading_pair(ETradingPairType::BtcUsdCmFuture, "kline_btc_margined_future_btc_1m", None)
+            .build(&from, &to)
+            .await;
+        assert!(result.is_err());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
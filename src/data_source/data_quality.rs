@@ -0,0 +1,448 @@
+//! 数据质量检查 检查k线与资金费率数据的缺失、重复、异常值及多交易对的对齐情况
+//! 按修复策略修复k线数据：异常k线视为缺失 删除后按策略填充
+use std::collections::{BTreeSet, HashMap};
+use chrono::{DateTime, Duration, Local};
+use log::{debug, warn};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use crate::data_source::funding_rate::SFundingRateData;
+use crate::data_source::kline::{EKlineInterval, SKlineData, SKlineUnitData};
+use crate::data_source::trading_pair::ETradingPairType;
+use crate::data_source::trading_pair::trading_pair_map::STradingPairMap;
+
+/// 数据质量问题
+#[derive(Debug, Clone, PartialEq)]
+pub enum EDataQualityIssue {
+    /// k线缺失(交易对，第一根缺失k线的开盘时间，缺失数量)
+    KlineGap(ETradingPairType, DateTime<Local>, i64),
+    /// k线时间戳重复 后插入的数据覆盖了先插入的数据
+    DuplicateKline(ETradingPairType, DateTime<Local>),
+    /// 收盘时间早于开盘时间或不晚于前一根k线的收盘时间
+    NonMonotonicCloseTime(ETradingPairType, DateTime<Local>),
+    /// OHLC不一致 最高价低于开盘价/收盘价 或最低价高于开盘价/收盘价
+    OhlcInconsistent(ETradingPairType, DateTime<Local>),
+    /// 成交量为0
+    ZeroVolume(ETradingPairType, DateTime<Local>),
+    /// 价格跳变(交易对，时间，收盘价相对前一根k线收盘价的变化比例)
+    PriceJump(ETradingPairType, DateTime<Local>, Decimal),
+    /// 资金费率时间戳重复
+    DuplicateFundingRate(ETradingPairType, DateTime<Local>),
+    /// 资金费率缺失(交易对，缺失前的结算时间，缺失后的结算时间)
+    FundingRateGap(ETradingPairType, DateTime<Local>, DateTime<Local>),
+    /// 多交易对未对齐(交易对，首根k线时间，最后一根k线时间) 数据范围与其他交易对不一致
+    Misaligned(ETradingPairType, DateTime<Local>, DateTime<Local>),
+}
+
+impl EDataQualityIssue {
+    pub fn get_tp_type(&self) -> ETradingPairType {
+        match self {
+            EDataQualityIssue::KlineGap(tp_type, _, _)
+            | EDataQualityIssue::DuplicateKline(tp_type, _)
+            | EDataQualityIssue::NonMonotonicCloseTime(tp_type, _)
+            | EDataQualityIssue::OhlcInconsistent(tp_type, _)
+            | EDataQualityIssue::ZeroVolume(tp_type, _)
+            | EDataQualityIssue::PriceJump(tp_type, _, _)
+            | EDataQualityIssue::DuplicateFundingRate(tp_type, _)
+            | EDataQualityIssue::FundingRateGap(tp_type, _, _)
+            | EDataQualityIssue::Misaligned(tp_type, _, _) => { *tp_type }
+        }
+    }
+
+    /// 问题类型名称 用于汇总日志
+    pub fn get_name(&self) -> &'static str {
+        match self {
+            EDataQualityIssue::KlineGap(..) => { "KlineGap" }
+            EDataQualityIssue::DuplicateKline(..) => { "DuplicateKline" }
+            EDataQualityIssue::NonMonotonicCloseTime(..) => { "NonMonotonicCloseTime" }
+            EDataQualityIssue::OhlcInconsistent(..) => { "OhlcInconsistent" }
+            EDataQualityIssue::ZeroVolume(..) => { "ZeroVolume" }
+            EDataQualityIssue::PriceJump(..) => { "PriceJump" }
+            EDataQualityIssue::DuplicateFundingRate(..) => { "DuplicateFundingRate" }
+            EDataQualityIssue::FundingRateGap(..) => { "FundingRateGap" }
+            EDataQualityIssue::Misaligned(..) => { "Misaligned" }
+        }
+    }
+}
+
+/// k线修复策略
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Default, Serialize, Deserialize)]
+pub enum EDataRepairStrategy {
+    /// 仅报告问题 不修复
+    #[default]
+    ReportOnly,
+    /// 删除异常k线 缺失k线保持缺失
+    Drop,
+    /// 删除异常k线 以前一根k线的收盘价填充缺失k线（成交量为0）
+    ForwardFill,
+    /// 删除异常k线 在前一根k线收盘价与后一根k线开盘价之间线性插值填充缺失k线（成交量为0）
+    Interpolate,
+}
+
+/// 数据质量检查配置
+#[derive(Debug, Clone)]
+pub struct SDataQualityConfig {
+    /// k线周期 用于检查和填充缺失k线
+    pub kline_interval: EKlineInterval,
+    /// 资金费率结算间隔
+    pub funding_rate_interval: Duration,
+    /// 价格跳变阈值 收盘价相对前一根k线收盘价的变化比例
+    pub max_price_jump_ratio: Decimal,
+    /// k线修复策略
+    pub repair_strategy: EDataRepairStrategy,
+}
+
+impl Default for SDataQualityConfig {
+    fn default() -> Self {
+        Self {
+            kline_interval: EKlineInterval::default(),
+            funding_rate_interval: Duration::hours(8),
+            max_price_jump_ratio: Decimal::new(1, 1),
+            repair_strategy: EDataRepairStrategy::default(),
+        }
+    }
+}
+
+/// 数据质量检查结果
+#[derive(Debug, Clone, Default)]
+pub struct SDataQualityReport {
+    /// 修复前检查到的问题
+    pub issues: Vec<EDataQualityIssue>,
+    /// 删除的异常k线数量
+    pub dropped_count: usize,
+    /// 填充的k线数量
+    pub filled_count: usize,
+}
+
+impl SDataQualityReport {
+    pub fn is_clean(&self) -> bool {
+        self.issues.is_empty()
+    }
+
+    /// 按交易对和问题类型汇总问题数量
+    pub fn summary(&self) -> HashMap<(ETradingPairType, &'static str), usize> {
+        let mut summary = HashMap::new();
+        for issue in self.issues.iter() {
+            *summary.entry((issue.get_tp_type(), issue.get_name())).or_insert(0) += 1;
+        }
+        summary
+    }
+
+    /// 输出汇总日志 问题明细以debug级别输出
+    pub fn log(&self) {
+        for ((tp_type, name), count) in self.summary() {
+            warn!("数据质量问题 - 交易对: {:?}\t类型:{}\t数量:{}", tp_type, name, count);
+        }
+        for issue in self.issues.iter() {
+            debug!("数据质量问题: {:?}", issue);
+        }
+        if self.dropped_count > 0 || self.filled_count > 0 {
+            warn!("数据修复 - 删除k线:{}\t填充k线:{}", self.dropped_count, self.filled_count);
+        }
+    }
+}
+
+impl SDataQualityConfig {
+    /// 检查所有交易对的数据 并按修复策略修复k线数据
+    pub fn check(&self, trading_pair_map: &mut STradingPairMap) -> SDataQualityReport {
+        let mut report = SDataQualityReport::default();
+        for (tp_type, trading_pair) in trading_pair_map.inner.iter() {
+            report.issues.extend(self.check_kline(*tp_type, &trading_pair.kline_data));
+            if let Some(funding_rate) = &trading_pair.funding_rate {
+                report.issues.extend(self.check_funding_rate(*tp_type, funding_rate));
+            }
+        }
+        report.issues.extend(self.check_alignment(trading_pair_map));
+
+        if self.repair_strategy != EDataRepairStrategy::ReportOnly {
+            for (tp_type, trading_pair) in trading_pair_map.inner.iter_mut() {
+                let invalid_times: BTreeSet<DateTime<Local>> = report.issues
+                    .iter()
+                    .filter_map(|issue| {
+                        match issue {
+                            EDataQualityIssue::NonMonotonicCloseTime(issue_tp_type, time)
+                            | EDataQualityIssue::OhlcInconsistent(issue_tp_type, time) if issue_tp_type == tp_type => { Some(*time) }
+                            _ => { None }
+                        }
+                    })
+                    .chain(self.get_spike_times(&trading_pair.kline_data))
+                    .collect();
+                let (dropped_count, filled_count) = self.repair_kline(&mut trading_pair.kline_data, &invalid_times);
+                report.dropped_count += dropped_count;
+                report.filled_count += filled_count;
+            }
+        }
+        report
+    }
+
+    /// 检查单个交易对的k线数据
+    pub fn check_kline(&self, tp_type: ETradingPairType, kline_data: &SKlineData) -> Vec<EDataQualityIssue> {
+        let interval = self.kline_interval.get_duration();
+        let mut issues: Vec<EDataQualityIssue> = kline_data.duplicate_times
+            .iter()
+            .map(|time| EDataQualityIssue::DuplicateKline(tp_type, *time))
+            .collect();
+        let mut prev: Option<&SKlineUnitData> = None;
+        for (time, kline) in kline_data.iter() {
+            if kline.high_price < kline.open_price.max(kline.close_price) || kline.low_price > kline.open_price.min(kline.close_price) {
+                issues.push(EDataQualityIssue::OhlcInconsistent(tp_type, *time));
+            }
+            if kline.volume <= Decimal::from(0) {
+                issues.push(EDataQualityIssue::ZeroVolume(tp_type, *time));
+            }
+            let is_close_time_decreased = prev.is_some_and(|prev| kline.close_time <= prev.close_time);
+            if kline.close_time < kline.open_time || is_close_time_decreased {
+                issues.push(EDataQualityIssue::NonMonotonicCloseTime(tp_type, *time));
+            }
+            if let Some(prev) = prev {
+                let missing_count = (kline.open_time - prev.open_time).num_seconds() / interval.num_seconds() - 1;
+                if missing_count > 0 {
+                    issues.push(EDataQualityIssue::KlineGap(tp_type, prev.open_time + interval, missing_count));
+                }
+                if let Some(jump_ratio) = self.get_jump_ratio(prev, kline) {
+                    issues.push(EDataQualityIssue::PriceJump(tp_type, *time, jump_ratio));
+                }
+            }
+            prev = Some(kline);
+        }
+        issues
+    }
+
+    /// 检查单个交易对的资金费率数据
+    pub fn check_funding_rate(&self, tp_type: ETradingPairType, funding_rate: &SFundingRateData) -> Vec<EDataQualityIssue> {
+        let mut issues: Vec<EDataQualityIssue> = funding_rate.duplicate_times
+            .iter()
+            .map(|time| EDataQualityIssue::DuplicateFundingRate(tp_type, *time))
+            .collect();
+        let times: Vec<&DateTime<Local>> = funding_rate.iter().map(|(time, _)| time).collect();
+        for window in times.windows(2) {
+            if *window[1] - *window[0] > self.funding_rate_interval {
+                issues.push(EDataQualityIssue::FundingRateGap(tp_type, *window[0], *window[1]));
+            }
+        }
+        issues
+    }
+
+    /// 检查多交易对的数据范围是否一致 首根或最后一根k线时间与其他交易对不同时视为未对齐
+    pub fn check_alignment(&self, trading_pair_map: &STradingPairMap) -> Vec<EDataQualityIssue> {
+        let ranges: Vec<(ETradingPairType, DateTime<Local>, DateTime<Local>)> = trading_pair_map.inner
+            .iter()
+            .filter_map(|(tp_type, trading_pair)| {
+                let first_time = *trading_pair.kline_data.data.first_key_value()?.0;
+                let last_time = *trading_pair.kline_data.data.last_key_value()?.0;
+                Some((*tp_type, first_time, last_time))
+            })
+            .collect();
+        let (Some(first_time), Some(last_time)) = (
+            ranges.iter().map(|(_, first_time, _)| *first_time).min(),
+            ranges.iter().map(|(_, _, last_time)| *last_time).max(),
+        ) else {
+            return Vec::new();
+        };
+        ranges
+            .into_iter()
+            .filter(|(_, tp_first_time, tp_last_time)| *tp_first_time != first_time || *tp_last_time != last_time)
+            .map(|(tp_type, tp_first_time, tp_last_time)| EDataQualityIssue::Misaligned(tp_type, tp_first_time, tp_last_time))
+            .collect()
+    }
+
+    /// 收盘价相对前一根k线收盘价的变化比例 超过阈值时返回
+    fn get_jump_ratio(&self, prev: &SKlineUnitData, kline: &SKlineUnitData) -> Option<Decimal> {
+        if prev.close_price <= Decimal::from(0) {
+            return None;
+        }
+        let jump_ratio = (kline.close_price - prev.close_price).abs() / prev.close_price;
+        if jump_ratio > self.max_price_jump_ratio { Some(jump_ratio) } else { None }
+    }
+
+    /// 单根k线的价格尖刺 相对前后k线均发生跳变 而前后k线之间未发生跳变
+    fn get_spike_times(&self, kline_data: &SKlineData) -> Vec<DateTime<Local>> {
+        let klines: Vec<&SKlineUnitData> = kline_data.data.values().collect();
+        klines
+            .windows(3)
+            .filter(|window| {
+                self.get_jump_ratio(window[0], window[1]).is_some()
+                    && self.get_jump_ratio(window[1], window[2]).is_some()
+                    && self.get_jump_ratio(window[0], window[2]).is_none()
+            })
+            .map(|window| window[1].open_time)
+            .collect()
+    }
+
+    /// 删除异常k线 并按修复策略填充缺失k线 返回(删除数量，填充数量)
+    pub fn repair_kline(&self, kline_data: &mut SKlineData, invalid_times: &BTreeSet<DateTime<Local>>) -> (usize, usize) {
+        let dropped_count = invalid_times
+            .iter()
+            .filter(|time| kline_data.data.remove(time).is_some())
+            .count();
+        if !matches!(self.repair_strategy, EDataRepairStrategy::ForwardFill | EDataRepairStrategy::Interpolate) {
+            return (dropped_count, 0);
+        }
+
+        let interval = self.kline_interval.get_duration();
+        let klines: Vec<SKlineUnitData> = kline_data.data.values().copied().collect();
+        let mut filled_count = 0;
+        for window in klines.windows(2) {
+            let (prev, next) = (&window[0], &window[1]);
+            let steps = (next.open_time - prev.open_time).num_seconds() / interval.num_seconds();
+            for step in 1..steps {
+                let price = match self.repair_strategy {
+                    EDataRepairStrategy::Interpolate => {
+                        prev.close_price + (next.open_price - prev.close_price) * Decimal::from(step) / Decimal::from(steps)
+                    }
+                    _ => { prev.close_price }
+                };
+                let open_time = prev.open_time + interval * step as i32;
+                kline_data.insert_unit(SKlineUnitData {
+                    open_time,
+                    close_time: open_time + (prev.close_time - prev.open_time),
+                    open_price: price,
+                    close_price: price,
+                    high_price: price,
+                    low_price: price,
+                    volume: Decimal::from(0),
+                });
+                filled_count += 1;
+            }
+        }
+        (dropped_count, filled_count)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+    use chrono::{Duration, Local, TimeZone};
+    use rust_decimal::Decimal;
+    use crate::data_source::data_quality::{EDataQualityIssue, EDataRepairStrategy, SDataQualityConfig};
+    use crate::data_source::funding_rate::SFundingRateData;
+    use crate::data_source::kline::SKlineData;
+    use crate::data_source::trading_pair::ETradingPairType;
+    use crate::data_source::trading_pair::trading_pair_map::STradingPairMap;
+
+    const TP_TYPE: ETradingPairType = ETradingPairType::BtcUsdt;
+
+    /// 10根1分钟k线 收盘价依次为100~109 缺少第3、4分钟 第6分钟为价格尖刺 第8分钟OHLC不一致 第9分钟成交量为0
+    fn get_test_data() -> SKlineData {
+        let start = Local.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+        let mut data = SKlineData::new();
+        for minute in (0..10).filter(|minute| *minute != 3 && *minute != 4) {
+            let open_time = start + Duration::minutes(minute);
+            let price = match minute {
+                6 => { Decimal::from(200) }
+                _ => { Decimal::from(100 + minute) }
+            };
+            let high_price = match minute {
+                8 => { price - Decimal::from(1) }
+                _ => { price + Decimal::from(1) }
+            };
+            let volume = match minute {
+                9 => { Decimal::from(0) }
+                _ => { Decimal::from(1) }
+            };
+            data.insert(open_time, open_time + Duration::seconds(59), price, price, high_price, price - Decimal::from(1), volume);
+        }
+        data
+    }
+
+    #[test]
+    pub fn test_check_kline() {
+        let start = Local.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+        let mut data = get_test_data();
+        // 重复插入第0分钟
+        data.insert(start, start + Duration::seconds(59), Decimal::from(100), Decimal::from(100), Decimal::from(101), Decimal::from(99), Decimal::from(1));
+
+        let config = SDataQualityConfig::default();
+        let issues = config.check_kline(TP_TYPE, &data);
+        assert!(issues.contains(&EDataQualityIssue::DuplicateKline(TP_TYPE, start)));
+        assert!(issues.contains(&EDataQualityIssue::KlineGap(TP_TYPE, start + Duration::minutes(3), 2)));
+        assert!(issues.contains(&EDataQualityIssue::OhlcInconsistent(TP_TYPE, start + Duration::minutes(8))));
+        assert!(issues.contains(&EDataQualityIssue::ZeroVolume(TP_TYPE, start + Duration::minutes(9))));
+        let jump_count = issues.iter().filter(|issue| matches!(issue, EDataQualityIssue::PriceJump(..))).count();
+        assert_eq!(jump_count, 2);
+        assert!(!issues.iter().any(|issue| matches!(issue, EDataQualityIssue::NonMonotonicCloseTime(..))));
+
+        // 收盘时间早于开盘时间
+        let time = start + Duration::minutes(20);
+        data.insert(time, time - Duration::minutes(30), Decimal::from(109), Decimal::from(109), Decimal::from(110), Decimal::from(108), Decimal::from(1));
+        let issues = config.check_kline(TP_TYPE, &data);
+        assert!(issues.contains(&EDataQualityIssue::NonMonotonicCloseTime(TP_TYPE, time)));
+    }
+
+    #[test]
+    pub fn test_check_funding_rate_and_alignment() {
+        let start = Local.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+        let mut funding_rate = SFundingRateData::new();
+        for hour in [0, 8, 24, 24] {
+            funding_rate.insert(&(start + Duration::hours(hour)), Decimal::new(1, 4));
+        }
+        let config = SDataQualityConfig::default();
+        let issues = config.check_funding_rate(ETradingPairType::BtcUsdtFuture, &funding_rate);
+        assert_eq!(issues, vec![
+            EDataQualityIssue::DuplicateFundingRate(ETradingPairType::BtcUsdtFuture, start + Duration::hours(24)),
+            EDataQualityIssue::FundingRateGap(ETradingPairType::BtcUsdtFuture, start + Duration::hours(8), start + Duration::hours(24)),
+        ]);
+
+        // 币本位合约缺少最后一根k线
+        let mut trading_pair_map = STradingPairMap::new();
+        trading_pair_map.add_trading_pair(TP_TYPE, get_test_data(), None);
+        let mut kline_data = get_test_data();
+        kline_data.data.pop_last();
+        trading_pair_map.add_trading_pair(ETradingPairType::BtcUsdCmFuture, kline_data, None);
+        let issues = config.check_alignment(&trading_pair_map);
+        assert_eq!(issues, vec![
+            EDataQualityIssue::Misaligned(ETradingPairType::BtcUsdCmFuture, start, start + Duration::minutes(8)),
+        ]);
+    }
+
+    #[test]
+    pub fn test_repair() {
+        let start = Local.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+        let config = |repair_strategy| SDataQualityConfig { repair_strategy, ..SDataQualityConfig::default() };
+        let get_trading_pair_map = || {
+            let mut trading_pair_map = STradingPairMap::new();
+            trading_pair_map.add_trading_pair(TP_TYPE, get_test_data(), None);
+            trading_pair_map
+        };
+
+        // 仅报告时不修改数据
+        let mut trading_pair_map = get_trading_pair_map();
+        let report = config(EDataRepairStrategy::ReportOnly).check(&mut trading_pair_map);
+        assert!(!report.is_clean());
+        assert_eq!((report.dropped_count, report.filled_count), (0, 0));
+        assert_eq!(report.summary()[&(TP_TYPE, "PriceJump")], 2);
+        assert_eq!(trading_pair_map.get(TP_TYPE).unwrap().kline_data.iter().count(), 8);
+
+        // 删除价格尖刺和OHLC不一致的k线
+        let mut trading_pair_map = get_trading_pair_map();
+        let report = config(EDataRepairStrategy::Drop).check(&mut trading_pair_map);
+        assert_eq!((report.dropped_count, report.filled_count), (2, 0));
+        let kline_data = &trading_pair_map.get(TP_TYPE).unwrap().kline_data;
+        assert!(kline_data.get(&(start + Duration::minutes(6))).is_none());
+        assert!(kline_data.get(&(start + Duration::minutes(8))).is_none());
+
+        // 前向填充 修复后的数据只剩成交量为0的问题
+        let mut trading_pair_map = get_trading_pair_map();
+        let report = config(EDataRepairStrategy::ForwardFill).check(&mut trading_pair_map);
+        assert_eq!((report.dropped_count, report.filled_count), (2, 4));
+        let kline_data = &trading_pair_map.get(TP_TYPE).unwrap().kline_data;
+        assert_eq!(kline_data.iter().count(), 10);
+        assert_eq!(kline_data.get(&(start + Duration::minutes(4))).unwrap().close_price, Decimal::from(102));
+        assert_eq!(kline_data.get(&(start + Duration::minutes(6))).unwrap().close_price, Decimal::from(105));
+        let report = config(EDataRepairStrategy::ReportOnly).check(&mut trading_pair_map);
+        assert!(report.issues.iter().all(|issue| matches!(issue, EDataQualityIssue::ZeroVolume(..))));
+
+        // 线性插值
+        let mut trading_pair_map = get_trading_pair_map();
+        let report = config(EDataRepairStrategy::Interpolate).check(&mut trading_pair_map);
+        assert_eq!((report.dropped_count, report.filled_count), (2, 4));
+        let kline_data = &trading_pair_map.get(TP_TYPE).unwrap().kline_data;
+        assert_eq!(kline_data.get(&(start + Duration::minutes(3))).unwrap().close_price, Decimal::from(103));
+        assert_eq!(kline_data.get(&(start + Duration::minutes(6))).unwrap().close_price, Decimal::from(106));
+
+        // 修复不影响未指定的时间
+        let mut kline_data = get_test_data();
+        let (dropped_count, filled_count) = config(EDataRepairStrategy::Drop).repair_kline(&mut kline_data, &BTreeSet::new());
+        assert_eq!((dropped_count, filled_count), (0, 0));
+        assert_eq!(kline_data.iter().count(), 8);
+    }
+}
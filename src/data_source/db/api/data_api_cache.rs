@@ -0,0 +1,493 @@
+//! 本地二进制数据缓存
+//! 包装任意数据接口，按数据源和UTC自然月将k线和资金费率缓存为列式二进制文件，读取时通过内存映射加载。
+//!
+//! 缓存规则：
+//!     1）文件路径为`{cache_dir}/{source}/{table_name}/{YYYY-MM}.{kline|funding_rate}.bin` source为数据源标识的CRC32（8位十六进制）
+//!     2）首次加载时从内部数据接口读取整月数据并写入缓存 只缓存结束超过CACHE_GRACE_PERIOD_HOURS小时的月份
+//!     3）文件头记录格式版本和行数，数据区使用CRC32校验；校验失败或版本不一致时删除缓存并重新读取
+//!     4）写入缓存失败时只记录警告 直接使用内部数据接口读取的数据
+//!
+//! 文件格式（小端序）：
+//!     文件头32字节：魔数(4) 版本(4) 数据类型(4) 行数(8) CRC32(4) 保留(8)
+//!     数据区按列存储：先存储所有i64列（时间戳），再存储所有Decimal列（每个值16字节）
+use std::fmt::{Debug, Display, Formatter};
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use chrono::{DateTime, Datelike, Duration, Local, TimeZone, Utc};
+use log::warn;
+use memmap2::Mmap;
+use rust_decimal::Decimal;
+use crate::data_source::db::api::TDataApi;
+use crate::data_source::db::RDBResult;
+use crate::data_source::funding_rate::{SFundingRateData, SFundingRateUnitData};
+use crate::data_source::kline::{SKlineData, SKlineUnitData};
+
+/// 缓存文件魔数
+const CACHE_MAGIC: &[u8; 4] = b"MPBC";
+/// 缓存格式版本 格式变化时递增 旧版本缓存自动失效
+const CACHE_VERSION: u32 = 1;
+/// 文件头长度
+const HEADER_LEN: usize = 32;
+/// 月份结束后到允许缓存的等待时间（小时） 数据源可能延迟补齐月末数据
+const CACHE_GRACE_PERIOD_HOURS: i64 = 48;
+
+/// 缓存数据类型
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+enum ECacheKind {
+    /// k线 i64列：开盘时间(秒)、收盘时间(毫秒) Decimal列：开盘价、收盘价、最高价、最低价、成交量
+    Kline,
+    /// 资金费率 i64列：结算时间(秒) Decimal列：资金费率
+    FundingRate,
+}
+
+impl ECacheKind {
+    fn get_id(&self) -> u32 {
+        match self {
+            ECacheKind::Kline => { 1 }
+            ECacheKind::FundingRate => { 2 }
+        }
+    }
+
+    fn get_extension(&self) -> &'static str {
+        match self {
+            ECacheKind::Kline => { "kline.bin" }
+            ECacheKind::FundingRate => { "funding_rate.bin" }
+        }
+    }
+
+    /// (i64列数，Decimal列数)
+    fn get_column_count(&self) -> (usize, usize) {
+        match self {
+            ECacheKind::Kline => { (2, 5) }
+            ECacheKind::FundingRate => { (1, 1) }
+        }
+    }
+
+    /// 单行数据长度
+    fn get_row_len(&self) -> usize {
+        let (i64_count, decimal_count) = self.get_column_count();
+        i64_count * 8 + decimal_count * 16
+    }
+}
+
+/// 缓存异常
+#[derive(Debug)]
+pub enum EDataApiCacheError {
+    /// 文件头不完整或魔数不一致
+    MagicMismatchError(PathBuf),
+    /// 缓存格式版本不一致(文件路径，文件中的版本)
+    VersionMismatchError(PathBuf, u32),
+    /// 数据类型不一致(文件路径，文件中的数据类型)
+    KindMismatchError(PathBuf, u32),
+    /// 文件长度与行数不一致(文件路径，期望长度，实际长度)
+    LengthMismatchError(PathBuf, usize, usize),
+    /// 数据区校验失败
+    ChecksumMismatchError(PathBuf),
+}
+
+impl Display for EDataApiCacheError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+impl std::error::Error for EDataApiCacheError {}
+
+/// 内存映射的缓存文件
+struct SCacheFile {
+    mmap: Mmap,
+    kind: ECacheKind,
+    rows: usize,
+}
+
+impl SCacheFile {
+    /// 打开缓存文件并校验完整性
+    fn open(path: &Path, kind: ECacheKind) -> RDBResult<Self> {
+        let file = File::open(path)?;
+        // 缓存文件只由本模块通过临时文件原子替换写入 映射期间不会被修改
+        let mmap = unsafe { Mmap::map(&file)? };
+        if mmap.len() < HEADER_LEN || &mmap[0..4] != CACHE_MAGIC {
+            return Err(Box::new(EDataApiCacheError::MagicMismatchError(path.to_path_buf())));
+        }
+        let version = u32::from_le_bytes(mmap[4..8].try_into()?);
+        if version != CACHE_VERSION {
+            return Err(Box::new(EDataApiCacheError::VersionMismatchError(path.to_path_buf(), version)));
+        }
+        let kind_id = u32::from_le_bytes(mmap[8..12].try_into()?);
+        if kind_id != kind.get_id() {
+            return Err(Box::new(EDataApiCacheError::KindMismatchError(path.to_path_buf(), kind_id)));
+        }
+        // 行数来自文件内容 计算长度时溢出视为长度不一致
+        let rows = usize::try_from(u64::from_le_bytes(mmap[12..20].try_into()?)).unwrap_or(usize::MAX);
+        let expected_len = rows.checked_mul(kind.get_row_len()).and_then(|len| len.checked_add(HEADER_LEN));
+        if expected_len != Some(mmap.len()) {
+            return Err(Box::new(EDataApiCacheError::LengthMismatchError(path.to_path_buf(), expected_len.unwrap_or(usize::MAX), mmap.len())));
+        }
+        let checksum = u32::from_le_bytes(mmap[20..24].try_into()?);
+        if crc32fast::hash(&mmap[HEADER_LEN..]) != checksum {
+            return Err(Box::new(EDataApiCacheError::ChecksumMismatchError(path.to_path_buf())));
+        }
+        Ok(Self { mmap, kind, rows })
+    }
+
+    fn get_i64(&self, column: usize, row: usize) -> i64 {
+        let offset = HEADER_LEN + column * self.rows * 8 + row * 8;
+        i64::from_le_bytes(self.mmap[offset..offset + 8].try_into().unwrap())
+    }
+
+    fn get_decimal(&self, column: usize, row: usize) -> Decimal {
+        let (i64_count, _) = self.kind.get_column_count();
+        let offset = HEADER_LEN + i64_count * self.rows * 8 + column * self.rows * 16 + row * 16;
+        Decimal::deserialize(self.mmap[offset..offset + 16].try_into().unwrap())
+    }
+
+    /// 按列写入缓存文件 先写入临时文件再重命名 避免多线程同时加载时读到不完整的文件
+    fn write(path: &Path, kind: ECacheKind, i64_columns: &[Vec<i64>], decimal_columns: &[Vec<Decimal>]) -> RDBResult<()> {
+        let rows = i64_columns.first().map_or(0, |column| column.len());
+        let mut body: Vec<u8> = Vec::with_capacity(rows * kind.get_row_len());
+        for column in i64_columns {
+            body.extend(column.iter().flat_map(|value| value.to_le_bytes()));
+        }
+        for column in decimal_columns {
+            body.extend(column.iter().flat_map(|value| value.serialize()));
+        }
+
+        let mut content: Vec<u8> = Vec::with_capacity(HEADER_LEN + body.len());
+        content.extend_from_slice(CACHE_MAGIC);
+        content.extend_from_slice(&CACHE_VERSION.to_le_bytes());
+        content.extend_from_slice(&kind.get_id().to_le_bytes());
+        content.extend_from_slice(&(rows as u64).to_le_bytes());
+        content.extend_from_slice(&crc32fast::hash(&body).to_le_bytes());
+        content.extend_from_slice(&[0u8; 8]);
+        content.extend_from_slice(&body);
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let tmp_path = path.with_extension(format!("tmp.{}", uuid::Uuid::new_v4()));
+        fs::write(&tmp_path, content)?;
+        fs::rename(&tmp_path, path)?;
+        Ok(())
+    }
+}
+
+/// 带本地二进制缓存的数据接口
+pub struct SDataApiCache<A: TDataApi> {
+    /// 内部数据接口 缓存缺失时从该接口读取
+    pub inner: A,
+    /// 缓存目录
+    pub cache_dir: PathBuf,
+}
+
+impl<A: TDataApi> SDataApiCache<A> {
+    pub fn new(inner: A, cache_dir: impl Into<PathBuf>) -> Self {
+        Self { inner, cache_dir: cache_dir.into() }
+    }
+
+    /// 删除当前数据源中一个数据表的所有缓存
+    pub fn invalidate(&self, table_name: &str) -> RDBResult<()> {
+        let dir_path = self.get_source_dir().join(table_name);
+        if dir_path.is_dir() {
+            fs::remove_dir_all(dir_path)?;
+        }
+        Ok(())
+    }
+
+    /// 删除当前数据源的所有缓存
+    pub fn invalidate_all(&self) -> RDBResult<()> {
+        let dir_path = self.get_source_dir();
+        if dir_path.is_dir() {
+            fs::remove_dir_all(dir_path)?;
+        }
+        Ok(())
+    }
+
+    /// 当前数据源的缓存目录 不同数据源的同名数据表互不影响
+    fn get_source_dir(&self) -> PathBuf {
+        self.cache_dir.join(format!("{:08x}", crc32fast::hash(self.inner.get_source_id().as_bytes())))
+    }
+
+    fn get_cache_path(&self, table_name: &str, month_start: &DateTime<Utc>, kind: ECacheKind) -> PathBuf {
+        self.get_source_dir()
+            .join(table_name)
+            .join(format!("{}.{}", month_start.format("%Y-%m"), kind.get_extension()))
+    }
+
+    /// 月份结束超过等待时间后才允许缓存
+    fn is_month_cacheable(month_end: &DateTime<Utc>) -> bool {
+        *month_end + Duration::hours(CACHE_GRACE_PERIOD_HOURS) <= Utc::now()
+    }
+
+    /// 打开可缓存月份的缓存 缓存不存在或月份不可缓存时返回None 校验失败时删除缓存
+    fn open_cache(path: &Path, kind: ECacheKind, month_end: &DateTime<Utc>) -> Option<SCacheFile> {
+        if !Self::is_month_cacheable(month_end) || !path.is_file() {
+            return None;
+        }
+        match SCacheFile::open(path, kind) {
+            Ok(cache_file) => { Some(cache_file) }
+            Err(e) => {
+                warn!("缓存校验失败 重新加载: {}", e);
+                if let Err(e) = fs::remove_file(path) {
+                    warn!("删除缓存失败: {:?}\t{}", path, e);
+                }
+                None
+            }
+        }
+    }
+
+    /// 写入并重新打开缓存 月份不可缓存时返回None 写入失败时记录警告并返回None
+    fn write_cache(path: &Path, kind: ECacheKind, month_end: &DateTime<Utc>, i64_columns: &[Vec<i64>], decimal_columns: &[Vec<Decimal>]) -> Option<SCacheFile> {
+        if !Self::is_month_cacheable(month_end) {
+            return None;
+        }
+        match SCacheFile::write(path, kind, i64_columns, decimal_columns).and_then(|_| SCacheFile::open(path, kind)) {
+            Ok(cache_file) => { Some(cache_file) }
+            Err(e) => {
+                warn!("写入缓存失败 直接使用原始数据: {:?}\t{}", path, e);
+                None
+            }
+        }
+    }
+
+    fn write_kline_cache(path: &Path, month_end: &DateTime<Utc>, month_data: &SKlineData) -> Option<SCacheFile> {
+        let klines: Vec<&SKlineUnitData> = month_data.iter().map(|(_, unit_data)| unit_data).collect();
+        Self::write_cache(
+            path,
+            ECacheKind::Kline,
+            month_end,
+            &[
+                klines.iter().map(|kline| kline.open_time.timestamp()).collect(),
+                klines.iter().map(|kline| kline.close_time.timestamp_millis()).collect(),
+            ],
+            &[
+                klines.iter().map(|kline| kline.open_price).collect(),
+                klines.iter().map(|kline| kline.close_price).collect(),
+                klines.iter().map(|kline| kline.high_price).collect(),
+                klines.iter().map(|kline| kline.low_price).collect(),
+                klines.iter().map(|kline| kline.volume).collect(),
+            ],
+        )
+    }
+
+    fn write_funding_rate_cache(path: &Path, month_end: &DateTime<Utc>, month_data: &SFundingRateData) -> Option<SCacheFile> {
+        Self::write_cache(
+            path,
+            ECacheKind::FundingRate,
+            month_end,
+            &[month_data.iter().map(|(time, _)| time.timestamp()).collect()],
+            &[month_data.iter().map(|(_, unit_data)| unit_data.funding_rate).collect()],
+        )
+    }
+
+    /// 时间范围覆盖的所有UTC自然月(月初，下月初)
+    fn get_months(from: &DateTime<Local>, to: &DateTime<Local>) -> Vec<(DateTime<Utc>, DateTime<Utc>)> {
+        let from = from.with_timezone(&Utc);
+        let mut months = Vec::new();
+        let mut month_start = Utc.with_ymd_and_hms(from.year(), from.month(), 1, 0, 0, 0).unwrap();
+        while month_start <= *to {
+            let month_end = match month_start.month() {
+                12 => { Utc.with_ymd_and_hms(month_start.year() + 1, 1, 1, 0, 0, 0).unwrap() }
+                month => { Utc.with_ymd_and_hms(month_start.year(), month + 1, 1, 0, 0, 0).unwrap() }
+            };
+            months.push((month_start, month_end));
+            month_start = month_end;
+        }
+        months
+    }
+}
+
+impl<A: TDataApi + Sync> TDataApi for SDataApiCache<A> {
+    async fn get_kline(&self, table_name: &str, from: &DateTime<Local>, to: &DateTime<Local>) -> RDBResult<SKlineData> {
+        let kind = ECacheKind::Kline;
+        let mut result = SKlineData::new();
+        for (month_start, month_end) in Self::get_months(from, to) {
+            let path = self.get_cache_path(table_name, &month_start, kind);
+            let cache_file = match Self::open_cache(&path, kind, &month_end) {
+                Some(cache_file) => { cache_file }
+                None => {
+                    // 从内部数据接口读取整月数据 内部接口的时间范围包含结束时间
+                    let month_from = month_start.with_timezone(&Local);
+                    let month_to = month_end.with_timezone(&Local) - chrono::Duration::seconds(1);
+                    let month_data = self.inner.get_kline(table_name, &month_from, &month_to).await?;
+                    // 不可缓存的月份或写入缓存失败时直接使用读取的数据
+                    match Self::write_kline_cache(&path, &month_end, &month_data) {
+                        Some(cache_file) => { cache_file }
+                        None => {
+                            for (_, unit_data) in month_data.range(*from, *to) {
+                                result.insert_unit(*unit_data);
+                            }
+                            continue;
+                        }
+                    }
+                }
+            };
+            for row in 0..cache_file.rows {
+                let open_time = Local.timestamp_opt(cache_file.get_i64(0, row), 0).unwrap();
+                if open_time < *from || open_time > *to {
+                    continue;
+                }
+                result.insert_unit(SKlineUnitData {
+                    open_time,
+                    close_time: Local.timestamp_millis_opt(cache_file.get_i64(1, row)).unwrap(),
+                    open_price: cache_file.get_decimal(0, row),
+                    close_price: cache_file.get_decimal(1, row),
+                    high_price: cache_file.get_decimal(2, row),
+                    low_price: cache_file.get_decimal(3, row),
+                    volume: cache_file.get_decimal(4, row),
+                });
+            }
+        }
+        Ok(result)
+    }
+
+    async fn get_funding_rate(&self, table_name: &str, from: &DateTime<Local>, to: &DateTime<Local>) -> RDBResult<SFundingRateData> {
+        let kind = ECacheKind::FundingRate;
+        let mut result = SFundingRateData::new();
+        for (month_start, month_end) in Self::get_months(from, to) {
+            let path = self.get_cache_path(table_name, &month_start, kind);
+            let cache_file = match Self::open_cache(&path, kind, &month_end) {
+                Some(cache_file) => { cache_file }
+                None => {
+                    let month_from = month_start.with_timezone(&Local);
+                    let month_to = month_end.with_timezone(&Local) - chrono::Duration::seconds(1);
+                    let month_data = self.inner.get_funding_rate(table_name, &month_from, &month_to).await?;
+                    match Self::write_funding_rate_cache(&path, &month_end, &month_data) {
+                        Some(cache_file) => { cache_file }
+                        None => {
+                            for (time, unit_data) in month_data.range(*from, *to) {
+                                result.insert(time, unit_data.funding_rate);
+                            }
+                            continue;
+                        }
+                    }
+                }
+            };
+            for row in 0..cache_file.rows {
+                let time = Local.timestamp_opt(cache_file.get_i64(0, row), 0).unwrap();
+                if time < *from || time > *to {
+                    continue;
+                }
+                result.insert_unit(SFundingRateUnitData {
+                    time,
+                    funding_rate: cache_file.get_decimal(0, row),
+                });
+            }
+        }
+        Ok(result)
+    }
+
+    fn get_source_id(&self) -> String {
+        self.inner.get_source_id()
+    }
+}
+
+impl<A: TDataApi + Debug> Debug for SDataApiCache<A> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "SDataApiCache({:?}, {:?})", self.inner, self.cache_dir)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use chrono::{Duration, Local, TimeZone, Utc};
+    use rust_decimal::Decimal;
+    use crate::data_source::db::api::data_api_cache::{ECacheKind, EDataApiCacheError, SCacheFile, SDataApiCache, CACHE_GRACE_PERIOD_HOURS};
+    use crate::data_source::db::api::data_api_csv::SDataApiCsv;
+    use crate::data_source::db::api::TDataApi;
+
+    /// 2020-01-31 23:55:00 UTC
+    const BASE_TIMESTAMP: i64 = 1_580_514_900;
+
+    #[tokio::test]
+    pub async fn test_cache() {
+        let dir = std::env::temp_dir().join(format!("data_api_cache_{}", uuid::Uuid::new_v4()));
+        let csv_dir = dir.join("csv");
+        let cache_dir = dir.join("cache");
+        fs::create_dir_all(&csv_dir).unwrap();
+        // 跨越2020-01和2020-02两个月的10根k线
+        let mut kline = String::new();
+        for offset in 0..10 {
+            let open_time = BASE_TIMESTAMP + offset * 60;
+            kline.push_str(&format!("{},7000.5,7010,6990,{},1.25,{},7000,1,0.5,3500,0\n", open_time, 7000 + offset, open_time + 59));
+        }
+        fs::write(csv_dir.join("kline_btc_usdt_1m.csv"), &kline).unwrap();
+        fs::write(csv_dir.join("funding_rate_btc_usdt_future.csv"), format!("{},8,0.0001\n", BASE_TIMESTAMP + 300)).unwrap();
+
+        let api = SDataApiCache::new(SDataApiCsv::new(&csv_dir), &cache_dir);
+        let from = Local.timestamp_opt(BASE_TIMESTAMP + 60, 0).unwrap();
+        let to = Local.timestamp_opt(BASE_TIMESTAMP + 8 * 60, 0).unwrap();
+        let expected = api.inner.get_kline("kline_btc_usdt_1m", &from, &to).await.unwrap();
+        let data = api.get_kline("kline_btc_usdt_1m", &from, &to).await.unwrap();
+        assert_eq!(data.iter().count(), 8);
+        // 缓存按数据源区分 其他数据源的同名数据表使用不同目录
+        let source_dir = api.get_source_dir();
+        assert_eq!(source_dir.parent(), Some(cache_dir.as_path()));
+        assert_ne!(SDataApiCache::new(SDataApiCsv::new(dir.join("other")), &cache_dir).get_source_dir(), source_dir);
+        assert!(source_dir.join("kline_btc_usdt_1m").join("2020-01.kline.bin").is_file());
+        assert!(source_dir.join("kline_btc_usdt_1m").join("2020-02.kline.bin").is_file());
+
+        // 删除原始数据后从缓存读取 结果与原始数据一致
+        fs::remove_file(csv_dir.join("kline_btc_usdt_1m.csv")).unwrap();
+        let cached = api.get_kline("kline_btc_usdt_1m", &from, &to).await.unwrap();
+        for ((time, kline), (expected_time, expected_kline)) in cached.iter().zip(expected.iter()) {
+            assert_eq!(time, expected_time);
+            assert_eq!(kline.close_time, expected_kline.close_time);
+            assert_eq!(kline.open_price, Decimal::new(70005, 1));
+            assert_eq!(kline.close_price, expected_kline.close_price);
+            assert_eq!(kline.volume, expected_kline.volume);
+        }
+        assert_eq!(cached.iter().count(), 8);
+
+        let funding_rate = api.get_funding_rate("funding_rate_btc_usdt_future", &from, &to).await.unwrap();
+        assert_eq!(funding_rate.get(&Local.timestamp_opt(BASE_TIMESTAMP + 300, 0).unwrap()), Some(&Decimal::new(1, 4)));
+
+        // 缓存损坏时删除缓存并重新读取 原始数据已删除时返回错误
+        let cache_path = source_dir.join("kline_btc_usdt_1m").join("2020-02.kline.bin");
+        let mut content = fs::read(&cache_path).unwrap();
+        let last = content.len() - 1;
+        content[last] ^= 0xff;
+        fs::write(&cache_path, content).unwrap();
+        assert!(api.get_kline("kline_btc_usdt_1m", &from, &to).await.is_err());
+        assert!(!cache_path.exists());
+
+        api.invalidate("kline_btc_usdt_1m").unwrap();
+        assert!(!source_dir.join("kline_btc_usdt_1m").exists());
+        assert!(source_dir.join("funding_rate_btc_usdt_future").exists());
+        api.invalidate_all().unwrap();
+        assert!(!source_dir.exists());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    pub async fn test_cache_fallback() {
+        let dir = std::env::temp_dir().join(format!("data_api_cache_{}", uuid::Uuid::new_v4()));
+        let csv_dir = dir.join("csv");
+        fs::create_dir_all(&csv_dir).unwrap();
+        fs::write(csv_dir.join("funding_rate_btc_usdt_future.csv"), format!("{},8,0.0001\n", BASE_TIMESTAMP + 300)).unwrap();
+        // 缓存目录为普通文件 写入缓存失败时直接使用原始数据
+        let cache_dir = dir.join("cache");
+        fs::write(&cache_dir, "").unwrap();
+        let api = SDataApiCache::new(SDataApiCsv::new(&csv_dir), &cache_dir);
+        let from = Local.timestamp_opt(BASE_TIMESTAMP, 0).unwrap();
+        let to = Local.timestamp_opt(BASE_TIMESTAMP + 600, 0).unwrap();
+        let funding_rate = api.get_funding_rate("funding_rate_btc_usdt_future", &from, &to).await.unwrap();
+        assert_eq!(funding_rate.get(&Local.timestamp_opt(BASE_TIMESTAMP + 300, 0).unwrap()), Some(&Decimal::new(1, 4)));
+
+        // 刚结束的月份在等待时间内不缓存
+        assert!(!SDataApiCache::<SDataApiCsv>::is_month_cacheable(&Utc::now()));
+        assert!(SDataApiCache::<SDataApiCsv>::is_month_cacheable(&(Utc::now() - Duration::hours(CACHE_GRACE_PERIOD_HOURS))));
+
+        // 文件头中的行数导致长度溢出时视为长度不一致
+        let cache_path = dir.join("overflow.kline.bin");
+        SCacheFile::write(&cache_path, ECacheKind::Kline, &[vec![], vec![]], &[vec![], vec![], vec![], vec![], vec![]]).unwrap();
+        let mut content = fs::read(&cache_path).unwrap();
+        content[12..20].copy_from_slice(&u64::MAX.to_le_bytes());
+        fs::write(&cache_path, content).unwrap();
+        let e = SCacheFile::open(&cache_path, ECacheKind::Kline).err().unwrap();
+        assert!(matches!(e.downcast_ref::<EDataApiCacheError>(), Some(EDataApiCacheError::LengthMismatchError(..))));
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,301 @@
+//! 本地CSV数据接口
+//! 读取Binance格式的k线导出文件和资金费率导出文件，用于离线回测和CI环境。
+//!
+//! 文件查找规则（以table_name为数据源名称）：
+//!     1）`{data_dir}/{table_name}.csv` 单文件
+//!     2）`{data_dir}/{table_name}/*.csv` 目录（如Binance按月导出的多个文件），按文件名顺序读取
+use std::fmt::{Debug, Display, Formatter};
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use chrono::{DateTime, Local};
+use rust_decimal::Decimal;
+use rust_decimal::prelude::FromStr;
+use crate::data_source::db::api::TDataApi;
+use crate::data_source::db::dao::binance_kline_dao::SBinanceKlineDao;
+use crate::data_source::db::RDBResult;
+use crate::data_source::funding_rate::SFundingRateData;
+use crate::data_source::kline::{SKlineData, SKlineUnitData};
+
+/// 时间戳大于该值时视为毫秒时间戳（Binance导出文件使用毫秒，ClickHouse表使用秒）
+const MILLISECOND_TIMESTAMP_THRESHOLD: i64 = 100_000_000_000;
+
+/// CSV数据接口异常
+#[derive(Debug)]
+pub enum EDataApiCsvError {
+    /// 找不到数据源对应的文件或目录
+    SourceNotFoundError(PathBuf),
+    /// 行数据列数不足(文件路径，行号，实际列数)
+    ColumnNotEnoughError(PathBuf, u64, usize),
+    /// 字段解析失败(文件路径，行号，字段内容)
+    FieldParseError(PathBuf, u64, String),
+}
+
+impl Display for EDataApiCsvError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+impl std::error::Error for EDataApiCsvError {}
+
+/// 本地CSV数据接口
+pub struct SDataApiCsv {
+    /// 数据文件所在目录
+    pub data_dir: PathBuf,
+}
+
+impl SDataApiCsv {
+    pub fn new(data_dir: impl Into<PathBuf>) -> Self {
+        Self { data_dir: data_dir.into() }
+    }
+
+    /// 获取数据源对应的文件列表
+    fn get_source_files(&self, table_name: &str) -> RDBResult<Vec<PathBuf>> {
+        let file_path = self.data_dir.join(format!("{}.csv", table_name));
+        if file_path.is_file() {
+            return Ok(vec![file_path]);
+        }
+        let dir_path = self.data_dir.join(table_name);
+        if dir_path.is_dir() {
+            let mut files: Vec<PathBuf> = std::fs::read_dir(&dir_path)?
+                .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                .filter(|path| path.is_file() && path.extension().is_some_and(|ext| ext == "csv"))
+                .collect();
+            files.sort();
+            return Ok(files);
+        }
+        Err(Box::new(EDataApiCsvError::SourceNotFoundError(file_path)))
+    }
+
+    /// 读取单个k线文件 并按照时间范围过滤
+    fn read_kline_file(path: &Path, from: i64, to: i64, result: &mut SKlineData) -> RDBResult<()> {
+        let mut reader = csv::ReaderBuilder::new()
+            .has_headers(false)
+            .flexible(true)
+            .from_reader(File::open(path)?);
+        for record in reader.records() {
+            let record = record?;
+            let line = record.position().map_or(0, |p| p.line());
+            // 跳过表头（首列不是时间戳）
+            let open_time = match record.get(0).map(|s| s.trim().parse::<i64>()) {
+                Some(Ok(open_time)) => { normalize_timestamp(open_time) }
+                _ => { continue; }
+            };
+            if open_time < from || open_time > to {
+                continue;
+            }
+            if record.len() < 11 {
+                return Err(Box::new(EDataApiCsvError::ColumnNotEnoughError(path.to_path_buf(), line, record.len())));
+            }
+            let parse_f64 = |index: usize| -> RDBResult<f64> {
+                let field = record.get(index).unwrap_or_default().trim();
+                field.parse::<f64>()
+                    .map_err(|_| Box::new(EDataApiCsvError::FieldParseError(path.to_path_buf(), line, field.to_string())).into())
+            };
+            let close_time = parse_f64(6)? as i64;
+            let dao = SBinanceKlineDao {
+                open_time: open_time as i32,
+                open_price: parse_f64(1)?,
+                high_price: parse_f64(2)?,
+                low_price: parse_f64(3)?,
+                close_price: parse_f64(4)?,
+                volume: parse_f64(5)?,
+                close_time: normalize_timestamp(close_time) as i32,
+                quote_asset_volume: parse_f64(7)?,
+                num_of_trades: parse_f64(8)?,
+                taker_buy_base_volume: parse_f64(9)?,
+                taker_buy_quote_volume: parse_f64(10)?,
+            };
+            let unit_data: SKlineUnitData = dao.into();
+            result.insert_unit(unit_data);
+        }
+        Ok(())
+    }
+
+    /// 读取单个资金费率文件 并按照时间范围过滤
+    /// 首列为资金费率结算时间 末列为资金费率
+    /// 兼容Binance导出格式（calc_time,funding_interval_hours,last_funding_rate）
+    fn read_funding_rate_file(path: &Path, from: i64, to: i64, result: &mut SFundingRateData) -> RDBResult<()> {
+        let mut reader = csv::ReaderBuilder::new()
+            .has_headers(false)
+            .flexible(true)
+            .from_reader(File::open(path)?);
+        for record in reader.records() {
+            let record = record?;
+            let line = record.position().map_or(0, |p| p.line());
+            // 跳过表头（首列不是时间戳）
+            let time = match record.get(0).map(|s| s.trim().parse::<i64>()) {
+                Some(Ok(time)) => { normalize_timestamp(time) }
+                _ => { continue; }
+            };
+            if time < from || time > to {
+                continue;
+            }
+            if record.len() < 2 {
+                return Err(Box::new(EDataApiCsvError::ColumnNotEnoughError(path.to_path_buf(), line, record.len())));
+            }
+            let field = record.get(record.len() - 1).unwrap_or_default().trim();
+            let funding_rate = Decimal::from_str(field)
+                .or_else(|_| Decimal::from_scientific(field))
+                .map_err(|_| Box::new(EDataApiCsvError::FieldParseError(path.to_path_buf(), line, field.to_string())))?;
+            let time = match DateTime::from_timestamp(time, 0) {
+                None => { return Err(Box::new(EDataApiCsvError::FieldParseError(path.to_path_buf(), line, time.to_string()))); }
+                Some(time) => { time.with_timezone(&Local) }
+            };
+            result.insert(&time, funding_rate);
+        }
+        Ok(())
+    }
+}
+
+/// 将毫秒时间戳统一转换为秒时间戳
+fn normalize_timestamp(timestamp: i64) -> i64 {
+    if timestamp.abs() >= MILLISECOND_TIMESTAMP_THRESHOLD {
+        timestamp / 1000
+    } else {
+        timestamp
+    }
+}
+
+impl TDataApi for SDataApiCsv {
+    async fn get_kline(&self, table_name: &str, from: &DateTime<Local>, to: &DateTime<Local>) -> RDBResult<SKlineData> {
+        let mut result = SKlineData::new();
+        for path in self.get_source_files(table_name)? {
+            Self::read_kline_file(&path, from.timestamp(), to.timestamp(), &mut result)?;
+        }
+        Ok(result)
+    }
+
+    async fn get_funding_rate(&self, table_name: &str, from: &DateTime<Local>, to: &DateTime<Local>) -> RDBResult<SFundingRateData> {
+        let mut result = SFundingRateData::new();
+        for path in self.get_source_files(table_name)? {
+            Self::read_funding_rate_file(&path, from.timestamp(), to.timestamp(), &mut result)?;
+        }
+        Ok(result)
+    }
+
+    /// 使用数据目录的绝对路径 相对路径与绝对路径指向同一目录时标识一致
+    fn get_source_id(&self) -> String {
+        let data_dir = fs::canonicalize(&self.data_dir).unwrap_or_else(|_| self.data_dir.clone());
+        format!("csv:{}", data_dir.display())
+    }
+}
+
+impl Debug for SDataApiCsv {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "SDataApiCsv({:?})", self.data_dir)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::PathBuf;
+    use chrono::{Local, TimeZone};
+    use rust_decimal::Decimal;
+    use rust_decimal::prelude::FromStr;
+    use crate::data_source::db::api::data_api_csv::SDataApiCsv;
+    use crate::data_source::db::api::TDataApi;
+
+    /// 2020-01-01 00:00:00 UTC
+    const BASE_TIMESTAMP: i64 = 1_577_836_800;
+
+    fn get_test_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("data_api_csv_{}_{}", name, uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn kline_line(offset_minutes: i64, millisecond: bool) -> String {
+        let open_time = BASE_TIMESTAMP + offset_minutes * 60;
+        let close_time = open_time + 59;
+        let (open_time, close_time) = if millisecond {
+            (open_time * 1000, close_time * 1000 + 999)
+        } else {
+            (open_time, close_time)
+        };
+        let price = 7000 + offset_minutes;
+        format!("{},{},{},{},{},1.5,{},10500.0,42,0.7,4900.0,0\n", open_time, price, price + 5, price - 5, price + 1, close_time)
+    }
+
+    #[tokio::test]
+    pub async fn test_kline_single_file() {
+        let dir = get_test_dir("kline");
+        let mut content = String::from("open_time,open,high,low,close,volume,close_time,quote_volume,count,taker_buy_volume,taker_buy_quote_volume,ignore\n");
+        for offset in 0..10 {
+            content.push_str(&kline_line(offset, true));
+        }
+        fs::write(dir.join("kline_btc_usdt_1m.csv"), content).unwrap();
+
+        let api = SDataApiCsv::new(&dir);
+        let from = Local.timestamp_opt(BASE_TIMESTAMP + 2 * 60, 0).unwrap();
+        let to = Local.timestamp_opt(BASE_TIMESTAMP + 5 * 60, 0).unwrap();
+        let data = api.get_kline("kline_btc_usdt_1m", &from, &to).await.unwrap();
+
+        let klines: Vec<_> = data.iter().map(|(_, item)| *item).collect();
+        assert_eq!(klines.len(), 4);
+        assert_eq!(klines[0].open_time, from);
+        assert_eq!(klines[0].open_price, Decimal::from(7002));
+        assert_eq!(klines[0].high_price, Decimal::from(7007));
+        assert_eq!(klines[0].low_price, Decimal::from(6997));
+        assert_eq!(klines[0].close_price, Decimal::from(7003));
+        assert_eq!(klines[0].volume, Decimal::from_str("1.5").unwrap());
+        assert_eq!(klines[3].open_time, to);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    pub async fn test_kline_directory() {
+        let dir = get_test_dir("kline_dir");
+        let source_dir = dir.join("kline_btc_margined_future_btc_1m");
+        fs::create_dir_all(&source_dir).unwrap();
+        let content1: String = (0..3).map(|offset| kline_line(offset, false)).collect();
+        let content2: String = (3..6).map(|offset| kline_line(offset, false)).collect();
+        fs::write(source_dir.join("2020-01-a.csv"), content1).unwrap();
+        fs::write(source_dir.join("2020-01-b.csv"), content2).unwrap();
+        fs::write(source_dir.join("readme.txt"), "not a csv").unwrap();
+
+        let api = SDataApiCsv::new(&dir);
+        let from = Local.timestamp_opt(BASE_TIMESTAMP, 0).unwrap();
+        let to = Local.timestamp_opt(BASE_TIMESTAMP + 60 * 60, 0).unwrap();
+        let data = api.get_kline("kline_btc_margined_future_btc_1m", &from, &to).await.unwrap();
+        assert_eq!(data.iter().count(), 6);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    pub async fn test_source_not_found() {
+        let dir = get_test_dir("not_found");
+        let api = SDataApiCsv::new(&dir);
+        let from = Local.timestamp_opt(BASE_TIMESTAMP, 0).unwrap();
+        let to = Local.timestamp_opt(BASE_TIMESTAMP + 60, 0).unwrap();
+        assert!(api.get_kline("kline_btc_usdt_1m", &from, &to).await.is_err());
+        assert!(api.get_funding_rate("funding_rate_btc_usdt_future", &from, &to).await.is_err());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    pub async fn test_funding_rate() {
+        let dir = get_test_dir("funding_rate");
+        let mut content = String::from("calc_time,funding_interval_hours,last_funding_rate\n");
+        for offset in 0..4 {
+            let time = (BASE_TIMESTAMP + offset * 8 * 3600) * 1000 + 5;
+            content.push_str(&format!("{},8,0.000{}\n", time, offset + 1));
+        }
+        fs::write(dir.join("funding_rate_btc_usdt_future.csv"), content).unwrap();
+
+        let api = SDataApiCsv::new(&dir);
+        let from = Local.timestamp_opt(BASE_TIMESTAMP + 8 * 3600, 0).unwrap();
+        let to = Local.timestamp_opt(BASE_TIMESTAMP + 24 * 3600, 0).unwrap();
+        let data = api.get_funding_rate("funding_rate_btc_usdt_future", &from, &to).await.unwrap();
+
+        assert_eq!(data.iter().count(), 3);
+        assert_eq!(data.get(&from).unwrap().clone(), Decimal::from_str("0.0002").unwrap());
+        assert_eq!(data.get(&to).unwrap().clone(), Decimal::from_str("0.0004").unwrap());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
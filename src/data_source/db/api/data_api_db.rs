@@ -1,8 +1,9 @@
 use std::fmt::{Debug, Formatter};
 use chrono::{DateTime, Local};
 use crate::data_source::db::api::TDataApi;
+use crate::data_source::db::dao::binance_funding_rate_dao::SBinanceFundingRateDao;
 use crate::data_source::db::dao::binance_kline_dao::SBinanceKlineDao;
-use crate::data_source::db::{RDBResult, SDbClickhouse};
+use crate::data_source::db::{EDbConfigError, RDBResult, SDbClickhouse};
 use crate::data_source::funding_rate::SFundingRateData;
 use crate::data_source::kline::SKlineData;
 
@@ -16,9 +17,10 @@ impl SDataApiDb {
     }
 }
 
-impl Default for SDataApiDb {
-    fn default() -> Self {
-        Self::new(SDbClickhouse::new())
+impl SDataApiDb {
+    /// 使用环境变量中的ClickHouse连接配置
+    pub fn from_env() -> Result<Self, EDbConfigError> {
+        Ok(Self::new(SDbClickhouse::from_env()?))
     }
 }
 
@@ -28,8 +30,13 @@ impl TDataApi for SDataApiDb {
         Ok(result)
     }
 
-    async fn get_funding_rate(&self, _table_name: &str, _from: &DateTime<Local>, _to: &DateTime<Local>) -> RDBResult<SFundingRateData> {
-        todo!()
+    async fn get_funding_rate(&self, table_name: &str, from: &DateTime<Local>, to: &DateTime<Local>) -> RDBResult<SFundingRateData> {
+        let result: SFundingRateData = SBinanceFundingRateDao::select_range(table_name, &self.db, from, to).await?.into();
+        Ok(result)
+    }
+
+    fn get_source_id(&self) -> String {
+        self.db.source_id.clone()
     }
 }
 
@@ -50,7 +57,13 @@ mod tests {
 
     #[tokio::test]
     pub async fn test_kline() {
-        let db = SDbClickhouse::new();
+        let db = match SDbClickhouse::from_env() {
+            Ok(db) => { db }
+            Err(e) => {
+                println!("Error: {}", e);
+                return;
+            }
+        };
         let now = Local::now();
         let from = now - chrono::Duration::hours(24 * 200);
         let to = now - chrono::Duration::hours(24 * 200) + chrono::Duration::minutes(100);
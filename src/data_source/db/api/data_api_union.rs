@@ -0,0 +1,85 @@
+use chrono::{DateTime, Local};
+use crate::data_source::db::api::data_api_cache::SDataApiCache;
+use crate::data_source::db::api::data_api_csv::SDataApiCsv;
+use crate::data_source::db::api::data_api_db::SDataApiDb;
+use crate::data_source::db::api::TDataApi;
+use crate::data_source::db::RDBResult;
+use crate::data_source::funding_rate::SFundingRateData;
+use crate::data_source::kline::SKlineData;
+
+/// 运行时可选择的数据接口
+#[derive(Debug)]
+pub enum EDataApiUnion {
+    /// ClickHouse数据库
+    Db(Box<SDataApiDb>),
+    /// 本地CSV文件
+    Csv(SDataApiCsv),
+    /// 带本地二进制缓存的ClickHouse数据库
+    CachedDb(Box<SDataApiCache<SDataApiDb>>),
+    /// 带本地二进制缓存的本地CSV文件
+    CachedCsv(SDataApiCache<SDataApiCsv>),
+}
+
+impl From<SDataApiDb> for EDataApiUnion {
+    fn from(value: SDataApiDb) -> Self {
+        Self::Db(Box::new(value))
+    }
+}
+
+impl From<SDataApiCsv> for EDataApiUnion {
+    fn from(value: SDataApiCsv) -> Self {
+        Self::Csv(value)
+    }
+}
+
+impl From<SDataApiCache<SDataApiDb>> for EDataApiUnion {
+    fn from(value: SDataApiCache<SDataApiDb>) -> Self {
+        Self::CachedDb(Box::new(value))
+    }
+}
+
+impl From<SDataApiCache<SDataApiCsv>> for EDataApiUnion {
+    fn from(value: SDataApiCache<SDataApiCsv>) -> Self {
+        Self::CachedCsv(value)
+    }
+}
+
+impl TDataApi for EDataApiUnion {
+    async fn get_kline(&self, table_name: &str, from: &DateTime<Local>, to: &DateTime<Local>) -> RDBResult<SKlineData> {
+        match self {
+            EDataApiUnion::Db(api) => { api.get_kline(table_name, from, to).await }
+            EDataApiUnion::Csv(api) => { api.get_kline(table_name, from, to).await }
+            EDataApiUnion::CachedDb(api) => { api.get_kline(table_name, from, to).await }
+            EDataApiUnion::CachedCsv(api) => { api.get_kline(table_name, from, to).await }
+        }
+    }
+
+    async fn get_funding_rate(&self, table_name: &str, from: &DateTime<Local>, to: &DateTime<Local>) -> RDBResult<SFundingRateData> {
+        match self {
+            EDataApiUnion::Db(api) => { api.get_funding_rate(table_name, from, to).await }
+            EDataApiUnion::Csv(api) => { api.get_funding_rate(table_name, from, to).await }
+            EDataApiUnion::CachedDb(api) => { api.get_funding_rate(table_name, from, to).await }
+            EDataApiUnion::CachedCsv(api) => { api.get_funding_rate(table_name, from, to).await }
+        }
+    }
+
+    fn get_source_id(&self) -> String {
+        match self {
+            EDataApiUnion::Db(api) => { api.get_source_id() }
+            EDataApiUnion::Csv(api) => { api.get_source_id() }
+            EDataApiUnion::CachedDb(api) => { api.get_source_id() }
+            EDataApiUnion::CachedCsv(api) => { api.get_source_id() }
+        }
+    }
+}
+
+impl EDataApiUnion {
+    /// 删除当前数据源的所有本地缓存 未启用缓存时不做处理
+    pub fn invalidate_cache(&self) -> RDBResult<()> {
+        match self {
+            EDataApiUnion::Db(_) | EDataApiUnion::Csv(_) => { Ok(()) }
+            EDataApiUnion::CachedDb(api) => { api.invalidate_all() }
+            EDataApiUnion::CachedCsv(api) => { api.invalidate_all() }
+        }
+    }
+}
@@ -1,17 +1,22 @@
 pub mod data_api_db;
 pub mod data_api_csv;
+pub mod data_api_union;
+pub mod data_api_cache;
 
 use std::future::Future;
 use chrono::{DateTime, Local};
 use crate::data_source::db::api::data_api_db::SDataApiDb;
-use crate::data_source::db::{RDBResult, SDbClickhouse};
+use crate::data_source::db::RDBResult;
 use crate::data_source::funding_rate::SFundingRateData;
 use crate::data_source::kline::SKlineData;
 
 pub trait TDataApi {
     fn get_kline(&self, table_name: &str, from: &DateTime<Local>, to: &DateTime<Local>) -> impl Future<Output=RDBResult<SKlineData>> + Send;
     fn get_funding_rate(&self, table_name: &str, from: &DateTime<Local>, to: &DateTime<Local>) -> impl Future<Output=RDBResult<SFundingRateData>> + Send;
-    fn default() -> Box<impl TDataApi> {
-        Box::new(SDataApiDb::new(SDbClickhouse::new()))
+    /// 数据源标识 用于区分不同数据源的缓存
+    fn get_source_id(&self) -> String;
+    /// 默认数据接口 环境变量中缺少ClickHouse连接配置时返回异常
+    fn default() -> RDBResult<Box<impl TDataApi>> {
+        Ok(Box::new(SDataApiDb::from_env()?))
     }
 }
\ No newline at end of file
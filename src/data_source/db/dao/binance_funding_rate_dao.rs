@@ -0,0 +1,100 @@
+use chrono::{DateTime, Local};
+use clickhouse::Row;
+use rust_decimal::Decimal;
+use rust_decimal::prelude::FromPrimitive;
+use serde::Deserialize;
+use crate::data_source::db::{RDBResult, SDbClickhouse};
+use crate::data_source::funding_rate::SFundingRateData;
+
+/// 从Binance获取的资金费率数据结构类型
+/// 包括U本位合约和币本位合约
+#[derive(Debug, Row, Deserialize)] // 使结构体支持 ClickHouse 读取
+pub struct SBinanceFundingRateDao {
+    /// 资金费率结算时间（秒级时间戳）
+    pub funding_time: i32,
+    /// 资金费率-正值表示多头支付给空头，负值表示空头支付给多头。
+    pub funding_rate: f64,
+}
+
+pub mod tables {
+    pub static BTC_USDT_FUTURE_FUNDING_RATE_TABLE_NAME:&str = "funding_rate_btc_usdt_future";
+    pub static BTC_MARGINED_FUTURE_BTC_FUNDING_RATE_TABLE_NAME:&str = "funding_rate_btc_margined_future_btc";
+}
+
+impl SBinanceFundingRateDao {
+    pub async fn select_range(table_name:&str, db: &SDbClickhouse, from: &DateTime<Local>, to: &DateTime<Local>) -> RDBResult<Vec<SBinanceFundingRateDao>> {
+        let client = db.get_client();
+        let query = format!("\
+            SELECT \
+                funding_time, funding_rate \
+            FROM '{}' \
+            WHERE funding_time BETWEEN '{}' AND '{}'\
+        ", table_name, from.timestamp(), to.timestamp());
+
+        let data_vec: Vec<Self> = client.query(query.as_str()).fetch_all().await?;
+
+        Ok(data_vec)
+    }
+}
+
+impl From<Vec<SBinanceFundingRateDao>> for SFundingRateData {
+    fn from(data: Vec<SBinanceFundingRateDao>) -> Self {
+        let mut result = SFundingRateData::new();
+        for item in data {
+            let time = DateTime::from_timestamp(item.funding_time as i64, 0).unwrap().with_timezone(&Local);
+            result.insert(&time, Decimal::from_f64(item.funding_rate).unwrap());
+        }
+        result
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, Local, TimeZone};
+    use rust_decimal::Decimal;
+    use rust_decimal::prelude::FromStr;
+    use crate::data_source::db::dao::binance_funding_rate_dao::SBinanceFundingRateDao;
+    use crate::data_source::db::dao::binance_funding_rate_dao::tables::BTC_USDT_FUTURE_FUNDING_RATE_TABLE_NAME;
+    use crate::data_source::db::SDbClickhouse;
+    use crate::data_source::funding_rate::SFundingRateData;
+
+    #[tokio::test]
+    pub async fn test_select() {
+        let db = match SDbClickhouse::from_env() {
+            Ok(db) => { db }
+            Err(e) => {
+                println!("Error: {}", e);
+                return;
+            }
+        };
+        let from = Local.with_ymd_and_hms(2020, 1, 27, 0, 0, 0).unwrap();
+        let to = Local.with_ymd_and_hms(2020, 2, 27, 0, 0, 0).unwrap();
+        let data = SBinanceFundingRateDao::select_range(BTC_USDT_FUTURE_FUNDING_RATE_TABLE_NAME, &db, &from, &to).await;
+
+        match data {
+            Ok(data) => {
+                for item in data {
+                    println!("{:?}", item)
+                }
+            }
+            Err(e) => { println!("Error: {:?}", e) }
+        }
+    }
+
+    #[test]
+    pub fn test_into() {
+        // 2020-01-01 00:00:00 UTC 起每8小时结算一次
+        let base_time = 1_577_836_800;
+        let data: Vec<SBinanceFundingRateDao> = (0..3)
+            .map(|i| SBinanceFundingRateDao { funding_time: base_time + i * 8 * 3600, funding_rate: 0.0001 * (i + 1) as f64 })
+            .collect();
+
+        let funding_rate: SFundingRateData = data.into();
+        let from = Local.timestamp_opt(base_time as i64, 0).unwrap();
+        assert_eq!(funding_rate.iter().count(), 3);
+        assert_eq!(*funding_rate.get(&from).unwrap(), Decimal::from_str("0.0001").unwrap());
+        assert_eq!(*funding_rate.get(&(from + Duration::hours(16))).unwrap(), Decimal::from_str("0.0003").unwrap());
+        assert!(funding_rate.get(&(from + Duration::hours(1))).is_none());
+    }
+}
@@ -25,6 +25,7 @@ pub struct SBinanceKlineDao {
 
 pub mod tables {
     pub static BTC_USDT_1M_TABLE_NAME:&str = "kline_btc_usdt_1m";
+    pub static BTC_USDT_FUTURE_1M_TABLE_NAME:&str = "kline_btc_usdt_future_1m";
     pub static BTC_MARGINED_FUTURE_BTC_1M_TABLE_NAME:&str = "kline_btc_margined_future_btc_1m";
 }
 
@@ -82,7 +83,13 @@ mod tests {
 
     #[tokio::test]
     pub async fn test_select() {
-        let db = SDbClickhouse::new();
+        let db = match SDbClickhouse::from_env() {
+            Ok(db) => { db }
+            Err(e) => {
+                println!("Error: {}", e);
+                return;
+            }
+        };
         let now = Local::now();
         let from = Local.from_local_datetime(&NaiveDateTime::new(NaiveDate::from_ymd_opt(2020, 1, 27).expect("无效的日期"), NaiveTime::from_hms_opt(0, 0, 0).expect("无效的时间"))).single().expect("无法转换为本地时间");
         let to = Local.from_local_datetime(&NaiveDateTime::new(NaiveDate::from_ymd_opt(2020, 2, 27).expect("无效的日期"), NaiveTime::from_hms_opt(0, 0, 0).expect("无效的时间"))).single().expect("无法转换为本地时间");
@@ -101,7 +108,7 @@ mod tests {
 
     #[tokio::test]
     pub async fn test_into() -> RDBResult<()> {
-        let db = SDbClickhouse::new();
+        let db = SDbClickhouse::from_env()?;
         let now = Local::now();
         let from = Local.from_local_datetime(&NaiveDateTime::new(NaiveDate::from_ymd_opt(2020, 1, 27).expect("无效的日期"), NaiveTime::from_hms_opt(0, 0, 0).expect("无效的时间"))).single().expect("无法转换为本地时间");
         let to = Local.from_local_datetime(&NaiveDateTime::new(NaiveDate::from_ymd_opt(2020, 2, 27).expect("无效的日期"), NaiveTime::from_hms_opt(0, 0, 0).expect("无效的时间"))).single().expect("无法转换为本地时间");
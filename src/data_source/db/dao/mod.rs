@@ -1 +1,2 @@
-pub mod binance_kline_dao;
\ No newline at end of file
+pub mod binance_kline_dao;
+pub mod binance_funding_rate_dao;
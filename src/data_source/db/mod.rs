@@ -1,28 +1,127 @@
+use std::error::Error;
+use std::fmt::{Debug, Display, Formatter};
 use clickhouse::Client;
+use serde::{Deserialize, Serialize};
 
 pub mod dao;
 pub mod api;
 
 pub type RDBResult<T> = Result<T, Box<dyn std::error::Error>>;
 
+/// ClickHouse连接配置的环境变量名
+pub mod env_keys {
+    pub static CLICKHOUSE_URL: &str = "CLICKHOUSE_URL";
+    pub static CLICKHOUSE_USER: &str = "CLICKHOUSE_USER";
+    pub static CLICKHOUSE_PASSWORD: &str = "CLICKHOUSE_PASSWORD";
+    pub static CLICKHOUSE_DATABASE: &str = "CLICKHOUSE_DATABASE";
+}
+
+/// 数据库配置异常
+#[derive(Debug)]
+pub enum EDbConfigError {
+    /// 环境变量未设置(环境变量名)
+    EnvVarNotSetError(&'static str),
+}
+
+impl Display for EDbConfigError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EDbConfigError::EnvVarNotSetError(key) => { write!(f, "ClickHouse连接配置缺失: 环境变量{}未设置(可在.env中配置)", key) }
+        }
+    }
+}
+
+impl Error for EDbConfigError {}
+
+/// ClickHouse连接配置
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+pub struct SClickhouseConfig {
+    /// 地址 如http://127.0.0.1:8123
+    pub url: String,
+    pub user: String,
+    pub password: String,
+    pub database: String,
+}
+
+impl SClickhouseConfig {
+    /// 从环境变量读取 run.rs中已通过dotenv加载.env
+    pub fn from_env() -> Result<Self, EDbConfigError> {
+        Self::from_vars(|key| std::env::var(key).ok())
+    }
+
+    /// 通过变量读取函数构建 任一变量缺失时返回异常
+    pub fn from_vars(get_var: impl Fn(&str) -> Option<String>) -> Result<Self, EDbConfigError> {
+        let get = |key: &'static str| get_var(key).ok_or(EDbConfigError::EnvVarNotSetError(key));
+        Ok(Self {
+            url: get(env_keys::CLICKHOUSE_URL)?,
+            user: get(env_keys::CLICKHOUSE_USER)?,
+            password: get(env_keys::CLICKHOUSE_PASSWORD)?,
+            database: get(env_keys::CLICKHOUSE_DATABASE)?,
+        })
+    }
+}
+
+impl Debug for SClickhouseConfig {
+    /// 不输出密码
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SClickhouseConfig")
+            .field("url", &self.url)
+            .field("user", &self.user)
+            .field("password", &"***")
+            .field("database", &self.database)
+            .finish()
+    }
+}
+
 pub struct SDbClickhouse {
     pub client: Client,
+    /// 数据源标识（地址/数据库）
+    pub source_id: String,
 }
 
 impl SDbClickhouse {
-    pub fn new() -> Self {
-        // todo 参数化设置
+    pub fn new(config: &SClickhouseConfig) -> Self {
         let client = Client::default()
-            .with_url("http://192.168.3.64:8123") // 修改为你的 ClickHouse 地址
-            .with_user("gx")
-            .with_password("1234566")
-            .with_database("btc_quant"); // 选择数据库
+            .with_url(&config.url)
+            .with_user(&config.user)
+            .with_password(&config.password)
+            .with_database(&config.database);
         Self {
-            client
+            client,
+            source_id: format!("clickhouse:{}/{}", config.url, config.database),
         }
     }
 
+    /// 使用环境变量中的连接配置
+    pub fn from_env() -> Result<Self, EDbConfigError> {
+        Ok(Self::new(&SClickhouseConfig::from_env()?))
+    }
+
     pub fn get_client(&self) -> &Client {
         &self.client
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use crate::data_source::db::{env_keys, EDbConfigError, SClickhouseConfig};
+
+    #[test]
+    pub fn test_from_vars() {
+        let mut vars = HashMap::from([
+            (env_keys::CLICKHOUSE_URL, "http://127.0.0.1:8123"),
+            (env_keys::CLICKHOUSE_USER, "default"),
+            (env_keys::CLICKHOUSE_PASSWORD, "secret"),
+            (env_keys::CLICKHOUSE_DATABASE, "btc_quant"),
+        ]);
+        let config = SClickhouseConfig::from_vars(|key| vars.get(key).map(|value| value.to_string())).unwrap();
+        assert_eq!(config.url, "http://127.0.0.1:8123");
+        assert_eq!(config.database, "btc_quant");
+        // Debug输出不包含密码
+        assert!(!format!("{:?}", config).contains("secret"));
+
+        vars.remove(env_keys::CLICKHOUSE_PASSWORD);
+        let result = SClickhouseConfig::from_vars(|key| vars.get(key).map(|value| value.to_string()));
+        assert!(matches!(result, Err(EDbConfigError::EnvVarNotSetError(key)) if key == env_keys::CLICKHOUSE_PASSWORD));
+    }
+}
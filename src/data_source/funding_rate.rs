@@ -16,20 +16,22 @@ pub struct SFundingRateUnitData {
 #[derive(Debug)]
 pub struct SFundingRateData {
     pub data: BTreeMap<DateTime<Local>, SFundingRateUnitData>,
+    /// 插入时被覆盖的重复时间戳 用于数据质量检查
+    pub duplicate_times: Vec<DateTime<Local>>,
 }
 
 impl SFundingRateData {
     pub fn new() -> Self {
         SFundingRateData {
             data: Default::default(),
+            duplicate_times: Vec::new(),
         }
     }
 
     /// 插入新的数据点
     pub fn insert(&mut self, time: &DateTime<Local>, funding_rate: Decimal) {
         let time = utils::date_time::normalize_to_minute(time);
-        self.data.insert(
-            time,
+        self.insert_unit(
             SFundingRateUnitData {
                 time,
                 funding_rate,
@@ -39,10 +41,13 @@ impl SFundingRateData {
 
     /// 插入新的数据点
     pub fn insert_unit(&mut self, unit_data: SFundingRateUnitData) {
-        self.data.insert(
-            unit_data.time,
+        let time = unit_data.time;
+        if self.data.insert(
+            time,
             unit_data,
-        );
+        ).is_some() {
+            self.duplicate_times.push(time);
+        }
     }
 
     /// 获取特定时刻的资金费率
@@ -1,9 +1,50 @@
 //! K线数据
 use std::collections::BTreeMap;
-use chrono::{DateTime, Local};
+use std::ops::Bound::{Excluded, Unbounded};
+use chrono::{DateTime, Duration, Local, TimeZone};
 use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
 use crate::utils;
 
+/// K线周期
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Default, Serialize, Deserialize)]
+pub enum EKlineInterval {
+    /// 1分钟
+    #[default]
+    Minute1,
+    /// 5分钟
+    Minute5,
+    /// 15分钟
+    Minute15,
+    /// 1小时
+    Hour1,
+    /// 4小时
+    Hour4,
+    /// 1天
+    Day1,
+}
+
+impl EKlineInterval {
+    /// 周期时长
+    pub fn get_duration(&self) -> Duration {
+        match self {
+            EKlineInterval::Minute1 => { Duration::minutes(1) }
+            EKlineInterval::Minute5 => { Duration::minutes(5) }
+            EKlineInterval::Minute15 => { Duration::minutes(15) }
+            EKlineInterval::Hour1 => { Duration::hours(1) }
+            EKlineInterval::Hour4 => { Duration::hours(4) }
+            EKlineInterval::Day1 => { Duration::days(1) }
+        }
+    }
+
+    /// 时刻所在周期的开盘时间 按UTC时间对齐（与交易所一致 日线开盘时间为东八区08:00）
+    pub fn get_open_time(&self, time: &DateTime<Local>) -> DateTime<Local> {
+        let seconds = self.get_duration().num_seconds();
+        let timestamp = time.timestamp();
+        Local.timestamp_opt(timestamp - timestamp.rem_euclid(seconds), 0).unwrap()
+    }
+}
+
 /// K线单根数据
 #[derive(Debug, Clone, Copy)]
 pub struct SKlineUnitData {
@@ -23,15 +64,33 @@ pub struct SKlineUnitData {
     pub volume: Decimal,
 }
 
+impl SKlineUnitData {
+    /// 合并时间上紧随其后的k线 开盘价与开盘时间保持不变
+    pub fn merge(&self, next: &SKlineUnitData) -> SKlineUnitData {
+        SKlineUnitData {
+            open_time: self.open_time,
+            close_time: next.close_time,
+            open_price: self.open_price,
+            close_price: next.close_price,
+            high_price: self.high_price.max(next.high_price),
+            low_price: self.low_price.min(next.low_price),
+            volume: self.volume + next.volume,
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct SKlineData {
     pub data: BTreeMap<DateTime<Local>, SKlineUnitData>,
+    /// 插入时被覆盖的重复时间戳 用于数据质量检查
+    pub duplicate_times: Vec<DateTime<Local>>,
 }
 
 impl SKlineData {
     pub fn new() -> Self {
         SKlineData {
             data: Default::default(),
+            duplicate_times: Vec::new(),
         }
     }
     /// 插入新的数据点
@@ -46,8 +105,7 @@ impl SKlineData {
         volume: Decimal,
     ) {
         let open_time = utils::date_time::normalize_to_minute(&open_time);
-        self.data.insert(
-            open_time,
+        self.insert_unit(
             SKlineUnitData {
                 open_time,
                 close_time,
@@ -65,10 +123,12 @@ impl SKlineData {
         &mut self,
         unit_data: SKlineUnitData,
     ) {
-        self.data.insert(
+        if self.data.insert(
             unit_data.open_time,
             unit_data,
-        );
+        ).is_some() {
+            self.duplicate_times.push(unit_data.open_time);
+        }
     }
 
     /// 获取特定时刻的k线数据
@@ -81,6 +141,50 @@ impl SKlineData {
         self.data.range(start..=end)
     }
 
+    /// 获取特定时刻之后的第一根k线数据
+    pub fn next(&self, time: &DateTime<Local>) -> Option<&SKlineUnitData> {
+        self.data.range((Excluded(*time), Unbounded)).next().map(|(_, unit_data)| unit_data)
+    }
+
+    /// 合并周期开盘时间至特定时刻（含）的k线 生成该周期截至特定时刻的k线数据
+    pub fn get_bar(&self, interval: EKlineInterval, time: &DateTime<Local>) -> Option<SKlineUnitData> {
+        self.get_bar_since(interval, &interval.get_open_time(time), time)
+    }
+
+    /// 同get_bar 但只合并start（含）之后的k线 如周期开盘时间早于时间窗口时
+    pub fn get_bar_since(&self, interval: EKlineInterval, start: &DateTime<Local>, time: &DateTime<Local>) -> Option<SKlineUnitData> {
+        let open_time = interval.get_open_time(time);
+        let start = open_time.max(*start);
+        if start > *time {
+            return None;
+        }
+        self.range(start, *time)
+            .map(|(_, unit_data)| unit_data)
+            .fold(None, |bar: Option<SKlineUnitData>, unit_data| {
+                match bar {
+                    None => { Some(SKlineUnitData { open_time, ..*unit_data }) }
+                    Some(bar) => { Some(bar.merge(unit_data)) }
+                }
+            })
+    }
+
+    /// 按周期重采样 生成更大周期的k线数据
+    /// 每根k线与周期内最后一根k线时刻的get_bar结果一致 没有任何k线的周期不生成数据
+    pub fn resample(&self, interval: EKlineInterval) -> SKlineData {
+        let mut resampled = SKlineData::new();
+        let mut cursor = self.data.keys().next().copied();
+        while let Some(time) = cursor {
+            let next_open_time = interval.get_open_time(&time) + interval.get_duration();
+            // 周期内最后一根k线的时刻
+            let last_time = self.data.range(time..next_open_time).next_back().map(|(last_time, _)| *last_time).unwrap_or(time);
+            if let Some(bar) = self.get_bar(interval, &last_time) {
+                resampled.insert_unit(bar);
+            }
+            cursor = self.data.range(next_open_time..).next().map(|(next_time, _)| *next_time);
+        }
+        resampled
+    }
+
     /// 输出迭代器 用于遍历
     pub fn iter(&self) -> impl Iterator<Item=(&DateTime<Local>, &SKlineUnitData)> {
         self.data.iter()
@@ -91,12 +195,12 @@ impl SKlineData {
 #[cfg(test)]
 mod tests {
     use std::ptr;
-    use chrono::Local;
+    use chrono::{Duration, Local, TimeZone};
     use rand::seq::SliceRandom;
     use rand::thread_rng;
     use rust_decimal::Decimal;
 
-    use crate::data_source::kline::{SKlineData, SKlineUnitData};
+    use crate::data_source::kline::{EKlineInterval, SKlineData, SKlineUnitData};
     use crate::utils;
 
     const DATA_NUM: i64 = 9;
@@ -210,4 +314,81 @@ mod tests {
             count += 1;
         }
     }
-}
\ No newline at end of file
+
+    #[test]
+    pub fn test_resample() {
+        // 从日线开盘时间开始 与本地时区无关
+        let start = EKlineInterval::Day1.get_open_time(&Local.with_ymd_and_hms(2024, 1, 1, 12, 0, 0).unwrap());
+        let mut data = SKlineData::new();
+        // 缺少第7分钟的k线
+        for minute in (0..12).filter(|minute| *minute != 7) {
+            let open_time = start + Duration::minutes(minute);
+            let price = Decimal::from(100 + minute);
+            data.insert(open_time, open_time + Duration::minutes(1), price, price + Decimal::from(1), price + Decimal::from(2), price - Decimal::from(2), Decimal::from(1));
+        }
+
+        let resampled = data.resample(EKlineInterval::Minute5);
+        let bars: Vec<&SKlineUnitData> = resampled.iter().map(|(_, bar)| bar).collect();
+        assert_eq!(bars.len(), 3);
+        assert_eq!(bars[0].open_time, start);
+        assert_eq!(bars[0].close_time, start + Duration::minutes(5));
+        assert_eq!(bars[0].open_price, Decimal::from(100));
+        assert_eq!(bars[0].close_price, Decimal::from(105));
+        assert_eq!(bars[0].high_price, Decimal::from(106));
+        assert_eq!(bars[0].low_price, Decimal::from(98));
+        assert_eq!(bars[0].volume, Decimal::from(5));
+        assert_eq!(bars[1].volume, Decimal::from(4));
+        assert_eq!(bars[2].open_time, start + Duration::minutes(10));
+        assert_eq!(bars[2].close_price, Decimal::from(112));
+
+        let resampled = data.resample(EKlineInterval::Day1);
+        assert_eq!(resampled.iter().count(), 1);
+        let bar = resampled.get(&start).unwrap();
+        assert_eq!(bar.open_price, Decimal::from(100));
+        assert_eq!(bar.close_price, Decimal::from(112));
+        assert_eq!(bar.high_price, Decimal::from(113));
+        assert_eq!(bar.low_price, Decimal::from(98));
+        assert_eq!(bar.volume, Decimal::from(11));
+
+        // 周期截至特定时刻的k线与重采样结果一致
+        for interval in [EKlineInterval::Minute5, EKlineInterval::Minute15, EKlineInterval::Hour1, EKlineInterval::Hour4, EKlineInterval::Day1] {
+            for (time, bar) in data.resample(interval).iter() {
+                let last_time = *data.range(*time, *time + interval.get_duration() - Duration::minutes(1)).last().unwrap().0;
+                let expected = data.get_bar(interval, &last_time).unwrap();
+                assert_eq!((bar.open_price, bar.close_price, bar.high_price, bar.low_price, bar.volume),
+                           (expected.open_price, expected.close_price, expected.high_price, expected.low_price, expected.volume));
+            }
+        }
+    }
+
+    #[test]
+    pub fn test_get_bar() {
+        // 从日线开盘时间开始 与本地时区无关
+        let start = EKlineInterval::Day1.get_open_time(&Local.with_ymd_and_hms(2024, 1, 1, 12, 0, 0).unwrap());
+        let mut data = SKlineData::new();
+        // 缺少第7分钟的k线
+        for minute in (0..12).filter(|minute| *minute != 7) {
+            let open_time = start + Duration::minutes(minute);
+            let price = Decimal::from(100 + minute);
+            data.insert(open_time, open_time + Duration::minutes(1), price, price + Decimal::from(1), price + Decimal::from(2), price - Decimal::from(2), Decimal::from(1));
+        }
+
+        let bar = data.get_bar(EKlineInterval::Minute5, &(start + Duration::minutes(4))).unwrap();
+        assert_eq!(bar.open_time, start);
+        assert_eq!(bar.close_time, start + Duration::minutes(5));
+        assert_eq!(bar.open_price, Decimal::from(100));
+        assert_eq!(bar.close_price, Decimal::from(105));
+        assert_eq!(bar.high_price, Decimal::from(106));
+        assert_eq!(bar.low_price, Decimal::from(98));
+        assert_eq!(bar.volume, Decimal::from(5));
+
+        // 周期内缺失的k线不影响合并
+        let bar = data.get_bar(EKlineInterval::Minute5, &(start + Duration::minutes(9))).unwrap();
+        assert_eq!(bar.open_time, start + Duration::minutes(5));
+        assert_eq!(bar.close_price, Decimal::from(110));
+        assert_eq!(bar.volume, Decimal::from(4));
+        assert_eq!(data.get_bar(EKlineInterval::Day1, &(start + Duration::minutes(11))).unwrap().volume, Decimal::from(11));
+        assert_eq!(data.next(&(start + Duration::minutes(6))).unwrap().open_time, start + Duration::minutes(8));
+        assert_eq!(EKlineInterval::Hour4.get_open_time(&(start + Duration::hours(5))), start + Duration::hours(4));
+    }
+}
@@ -4,3 +4,4 @@ pub mod kline;
 pub mod db;
 pub mod trading_pair;
 pub mod data_manager;
+pub mod data_quality;
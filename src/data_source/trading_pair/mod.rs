@@ -1,10 +1,11 @@
+use serde::{Deserialize, Serialize};
 use crate::data_runtime::asset::EAssetType;
 
 pub mod trading_pair;
 pub mod trading_pair_map;
 
 /// 交易对类型
-#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Serialize, Deserialize)]
 pub enum ETradingPairType {
     /// Btc/Usdt
     BtcUsdt,
@@ -2,7 +2,7 @@ use chrono::{DateTime, Local};
 use rust_decimal::Decimal;
 
 use crate::data_source::funding_rate::{SFundingRateData, SFundingRateUnitData};
-use crate::data_source::kline::{SKlineData, SKlineUnitData};
+use crate::data_source::kline::{EKlineInterval, SKlineData, SKlineUnitData};
 use crate::data_source::trading_pair::ETradingPairType;
 use crate::data_runtime::asset::EAssetType;
 
@@ -88,6 +88,98 @@ impl STradingPair {
     // endregion ----- 转发SKlineData函数-----
 }
 
+/// 交易对的只读视图
+/// 设置时间窗口[date_from, date_to]后 k线和资金费率的查询只返回窗口内的数据
+#[derive(Debug, Copy, Clone)]
+pub struct STradingPairView<'a> {
+    inner: &'a STradingPair,
+    window: Option<(DateTime<Local>, DateTime<Local>)>,
+}
+
+impl<'a> STradingPairView<'a> {
+    pub fn new(inner: &'a STradingPair, window: Option<(DateTime<Local>, DateTime<Local>)>) -> Self {
+        Self { inner, window }
+    }
+
+    pub fn get_tp_type(&self) -> ETradingPairType {
+        self.inner.tp_type
+    }
+
+    /// 时刻是否在时间窗口内
+    pub fn contains(&self, time: &DateTime<Local>) -> bool {
+        match &self.window {
+            None => { true }
+            Some((date_from, date_to)) => { time >= date_from && time <= date_to }
+        }
+    }
+
+    /// 将时间范围[start, end]限制在时间窗口内 与时间窗口不相交时返回None
+    fn clamp(&self, start: DateTime<Local>, end: DateTime<Local>) -> Option<(DateTime<Local>, DateTime<Local>)> {
+        let (start, end) = match &self.window {
+            None => { (start, end) }
+            Some((date_from, date_to)) => { (start.max(*date_from), end.min(*date_to)) }
+        };
+        match start <= end {
+            true => { Some((start, end)) }
+            false => { None }
+        }
+    }
+
+    /// 是否有资金费率数据
+    pub fn has_funding_rate(&self) -> bool {
+        self.inner.funding_rate.is_some()
+    }
+
+    pub fn get_funding_rate(&self, time: &DateTime<Local>) -> Option<&'a Decimal> {
+        self.inner.get_funding_rate(time).filter(|_| self.contains(time))
+    }
+
+    pub fn range_funding_rate(&self, start: DateTime<Local>, end: DateTime<Local>) -> Option<impl Iterator<Item=(&'a DateTime<Local>, &'a SFundingRateUnitData)>> {
+        let range = self.clamp(start, end);
+        self.inner.funding_rate.as_ref().map(|data| range.into_iter().flat_map(move |(start, end)| data.range(start, end)))
+    }
+
+    /// 时间窗口内是否有k线
+    pub fn has_kline(&self) -> bool {
+        match &self.window {
+            None => { self.inner.iter_kline().next().is_some() }
+            Some((date_from, date_to)) => { self.range_kline(*date_from, *date_to).next().is_some() }
+        }
+    }
+
+    pub fn get_kline(&self, time: &DateTime<Local>) -> Option<&'a SKlineUnitData> {
+        self.inner.get_kline(time).filter(|_| self.contains(time))
+    }
+
+    pub fn range_kline(&self, start: DateTime<Local>, end: DateTime<Local>) -> impl Iterator<Item=(&'a DateTime<Local>, &'a SKlineUnitData)> {
+        let inner = self.inner;
+        self.clamp(start, end).into_iter().flat_map(move |(start, end)| inner.range_kline(start, end))
+    }
+
+    pub fn iter_kline(&self) -> impl Iterator<Item=(&'a DateTime<Local>, &'a SKlineUnitData)> {
+        let view = *self;
+        self.inner.iter_kline().filter(move |(time, _)| view.contains(time))
+    }
+
+    /// 获取特定时刻之后的第一根k线 超出时间窗口时返回None
+    pub fn next_kline(&self, time: &DateTime<Local>) -> Option<&'a SKlineUnitData> {
+        self.inner.kline_data.next(time).filter(|kline| self.contains(&kline.open_time))
+    }
+
+    /// 合并周期开盘时间至特定时刻（含）的k线 周期内时间窗口之前的k线不参与合并
+    pub fn get_bar(&self, interval: EKlineInterval, time: &DateTime<Local>) -> Option<SKlineUnitData> {
+        match &self.window {
+            None => { self.inner.kline_data.get_bar(interval, time) }
+            Some((date_from, date_to)) => {
+                if time > date_to {
+                    return None;
+                }
+                self.inner.kline_data.get_bar_since(interval, date_from, time)
+            }
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     #[test]
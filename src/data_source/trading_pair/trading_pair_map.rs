@@ -1,12 +1,13 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
 
-use chrono::{DateTime, Local};
+use chrono::{DateTime, Duration, Local};
 use rust_decimal::Decimal;
 
 use crate::data_source::funding_rate::{SFundingRateData, SFundingRateUnitData};
 use crate::data_source::kline::{SKlineData, SKlineUnitData};
 use crate::data_source::trading_pair::ETradingPairType;
-use crate::data_source::trading_pair::trading_pair::STradingPair;
+use crate::data_source::trading_pair::trading_pair::{STradingPair, STradingPairView};
 
 pub type RTradingPairManagerResult<T> = Result<T, ETradingPairManagerError>;
 
@@ -50,6 +51,21 @@ impl STradingPairMap {
         }
     }
 
+    /// 合并所有交易对的k线时间 生成按时间排序的事件序列[start, end)
+    /// 每个时刻对应在该时刻有k线的交易对 交易对缺少k线或k线周期不同时只出现在有k线的时刻
+    pub fn merge_kline_times(&self, start: DateTime<Local>, end: DateTime<Local>) -> BTreeMap<DateTime<Local>, Vec<ETradingPairType>> {
+        let mut kline_times: BTreeMap<DateTime<Local>, Vec<ETradingPairType>> = BTreeMap::new();
+        for (tp_type, trading_pair) in self.inner.iter() {
+            for (time, _) in trading_pair.range_kline(start, end).filter(|(time, _)| **time < end) {
+                kline_times.entry(*time).or_default().push(*tp_type);
+            }
+        }
+        for tp_types in kline_times.values_mut() {
+            tp_types.sort();
+        }
+        kline_times
+    }
+
     // region ----- 转发STradingPair函数-----
     pub fn insert_funding_rate(&mut self, tp_type: ETradingPairType, time: &DateTime<Local>, funding_rate: Decimal) -> RTradingPairManagerResult<()> {
         Ok(self.get_mut(tp_type)?.insert_funding_rate(time, funding_rate))
@@ -99,11 +115,79 @@ impl STradingPairMap {
     // endregion ----- 转发STradingPair函数-----
 }
 
+/// 交易对管理器的只读视图 多个视图通过Arc共享同一份已加载的数据
+/// 设置时间窗口[date_from, date_to]后 所有查询（k线事件、交易对、k线及资金费率）只返回窗口内的数据
+#[derive(Debug, Clone)]
+pub struct STradingPairMapView {
+    inner: Arc<STradingPairMap>,
+    window: Option<(DateTime<Local>, DateTime<Local>)>,
+}
+
+impl From<STradingPairMap> for STradingPairMapView {
+    fn from(value: STradingPairMap) -> Self {
+        Self { inner: Arc::new(value), window: None }
+    }
+}
+
+impl STradingPairMapView {
+    /// 共享同一份数据的时间窗口视图
+    pub fn with_window(&self, date_from: DateTime<Local>, date_to: DateTime<Local>) -> Self {
+        Self { inner: Arc::clone(&self.inner), window: Some((date_from, date_to)) }
+    }
+
+    /// 时刻是否在时间窗口内
+    pub fn contains(&self, time: &DateTime<Local>) -> bool {
+        match &self.window {
+            None => { true }
+            Some((date_from, date_to)) => { time >= date_from && time <= date_to }
+        }
+    }
+
+    /// 是否与另一个视图共享同一份数据
+    pub fn is_shared_with(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.inner, &other.inner)
+    }
+
+    /// 获取交易对的时间窗口视图
+    pub fn get(&self, tp_type: ETradingPairType) -> RTradingPairManagerResult<STradingPairView<'_>> {
+        Ok(STradingPairView::new(self.inner.get(tp_type)?, self.window))
+    }
+
+    /// 所有交易对的时间窗口视图
+    pub fn iter(&self) -> impl Iterator<Item=STradingPairView<'_>> {
+        self.inner.inner.values().map(|trading_pair| STradingPairView::new(trading_pair, self.window))
+    }
+
+    /// 时间窗口内有k线的交易对类型
+    pub fn get_tp_types(&self) -> impl Iterator<Item=ETradingPairType> + '_ {
+        self.iter()
+            .filter(|trading_pair| trading_pair.has_kline())
+            .map(|trading_pair| trading_pair.get_tp_type())
+    }
+
+    /// 合并时间窗口内所有交易对的k线时间[start, end)
+    pub fn merge_kline_times(&self, start: DateTime<Local>, end: DateTime<Local>) -> BTreeMap<DateTime<Local>, Vec<ETradingPairType>> {
+        match &self.window {
+            None => { self.inner.merge_kline_times(start, end) }
+            Some((date_from, date_to)) => {
+                self.inner.merge_kline_times(start.max(*date_from), end.min(*date_to + Duration::nanoseconds(1)))
+            }
+        }
+    }
+
+    /// 获取时间窗口内特定时刻的k线数据
+    pub fn get_kline(&self, tp_type: ETradingPairType, time: &DateTime<Local>) -> RTradingPairManagerResult<Option<&SKlineUnitData>> {
+        Ok(self.get(tp_type)?.get_kline(time))
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::data_source::kline::SKlineData;
+    use chrono::{Duration, Local, TimeZone};
+    use rust_decimal::Decimal;
+    use crate::data_source::kline::{EKlineInterval, SKlineData};
     use crate::data_source::trading_pair::ETradingPairType;
-    use crate::data_source::trading_pair::trading_pair_map::STradingPairMap;
+    use crate::data_source::trading_pair::trading_pair_map::{STradingPairMap, STradingPairMapView};
 
     fn get_test_data() -> STradingPairMap {
         let mut data = STradingPairMap::new();
@@ -124,4 +208,70 @@ mod tests {
         data.add_trading_pair(ETradingPairType::BtcUsdt, SKlineData::new(), None);
         dbg!(&data);
     }
-}
\ No newline at end of file
+
+    #[test]
+    pub fn test_merge_kline_times() {
+        let mut data = get_test_data();
+        let start = Local.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+        let price = Decimal::from(100);
+        // 现货为1分钟k线 缺少第3分钟 币本位合约为5分钟k线
+        for minute in [0, 1, 2, 4, 5, 6, 7, 8, 9, 10] {
+            let time = start + Duration::minutes(minute);
+            data.insert_kline(ETradingPairType::BtcUsdt, time, time + Duration::minutes(1), price, price, price, price, price).unwrap();
+        }
+        for minute in [0, 5, 10] {
+            let time = start + Duration::minutes(minute);
+            data.insert_kline(ETradingPairType::BtcUsdCmFuture, time, time + Duration::minutes(5), price, price, price, price, price).unwrap();
+        }
+
+        let kline_times = data.merge_kline_times(start, start + Duration::minutes(10));
+        assert_eq!(kline_times.len(), 9);
+        assert!(!kline_times.contains_key(&(start + Duration::minutes(3))));
+        assert!(!kline_times.contains_key(&(start + Duration::minutes(10))));
+        assert_eq!(kline_times[&start], vec![ETradingPairType::BtcUsdt, ETradingPairType::BtcUsdCmFuture]);
+        assert_eq!(kline_times[&(start + Duration::minutes(4))], vec![ETradingPairType::BtcUsdt]);
+    }
+
+    #[test]
+    pub fn test_window_view() {
+        let mut data = get_test_data();
+        let start = Local.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+        let price = Decimal::from(100);
+        for minute in 0..10 {
+            let time = start + Duration::minutes(minute);
+            data.insert_kline(ETradingPairType::BtcUsdt, time, time + Duration::minutes(1), price, price, price, price, price).unwrap();
+        }
+
+        let view = STradingPairMapView::from(data);
+        let window = view.with_window(start + Duration::minutes(2), start + Duration::minutes(5));
+        // 视图共享同一份数据
+        assert!(view.is_shared_with(&window));
+        assert_eq!(view.get_tp_types().count(), 1);
+        assert_eq!(window.get_tp_types().collect::<Vec<_>>(), vec![ETradingPairType::BtcUsdt]);
+        assert_eq!(view.with_window(start + Duration::minutes(20), start + Duration::minutes(30)).get_tp_types().count(), 0);
+        assert_eq!(view.merge_kline_times(start, start + Duration::minutes(10)).len(), 10);
+
+        let kline_times = window.merge_kline_times(start, start + Duration::minutes(10));
+        assert_eq!(kline_times.keys().copied().collect::<Vec<_>>(), (2..=5).map(|minute| start + Duration::minutes(minute)).collect::<Vec<_>>());
+        assert_eq!(window.merge_kline_times(start, start + Duration::minutes(4)).len(), 2);
+        assert!(window.get_kline(ETradingPairType::BtcUsdt, &(start + Duration::minutes(5))).unwrap().is_some());
+        assert!(window.get_kline(ETradingPairType::BtcUsdt, &(start + Duration::minutes(6))).unwrap().is_none());
+        assert!(view.get_kline(ETradingPairType::BtcUsdt, &(start + Duration::minutes(6))).unwrap().is_some());
+        assert!(window.get_kline(ETradingPairType::BtcUsdCmFuture, &(start + Duration::minutes(2))).unwrap().is_none());
+
+        // 交易对视图的k线查询同样只返回窗口内的数据
+        let trading_pair = window.get(ETradingPairType::BtcUsdt).unwrap();
+        assert_eq!(trading_pair.iter_kline().count(), 4);
+        assert_eq!(trading_pair.range_kline(start, start + Duration::minutes(3)).count(), 2);
+        assert_eq!(trading_pair.range_kline(start + Duration::minutes(7), start + Duration::minutes(9)).count(), 0);
+        assert_eq!(trading_pair.next_kline(&(start + Duration::minutes(4))).unwrap().open_time, start + Duration::minutes(5));
+        assert!(trading_pair.next_kline(&(start + Duration::minutes(5))).is_none());
+        assert!(view.get(ETradingPairType::BtcUsdt).unwrap().next_kline(&(start + Duration::minutes(5))).is_some());
+        // 周期开盘时间早于时间窗口时 只合并窗口内的k线
+        let bar = trading_pair.get_bar(EKlineInterval::Minute5, &(start + Duration::minutes(4))).unwrap();
+        assert_eq!(bar.open_time, start);
+        assert_eq!(bar.volume, Decimal::from(300));
+        assert!(trading_pair.get_bar(EKlineInterval::Minute5, &(start + Duration::minutes(6))).is_none());
+        assert!(trading_pair.get_bar(EKlineInterval::Minute5, &(start + Duration::minutes(1))).is_none());
+    }
+}
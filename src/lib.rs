@@ -7,3 +7,4 @@ pub mod utils;
 pub mod script;
 pub mod config;
 pub mod data_log;
+pub mod report;
@@ -9,6 +9,7 @@ use crate::{
         trading_pair::ETradingPairType,
     }
 };
+use crate::data_runtime::asset::asset::SAsset;
 use crate::data_runtime::order::order_v3::SOrderV3;
 
 /// Runner处理K线的结果-订单部分
@@ -16,6 +17,28 @@ use crate::data_runtime::order::order_v3::SOrderV3;
 pub enum ERunnerParseOrderResult {
     /// 订单已完成
     OrderExecuted(SOrderV3),
+    /// 订单部分成交(成交部分，剩余未成交数量) 成交部分与原订单id相同 剩余部分继续挂单
+    OrderPartiallyExecuted(SOrderV3, Decimal),
+    /// 杠杆资产被强平
+    Liquidated(SRunnerLiquidation),
+    /// 订单被取消（条件单触发时可用资产不足）
+    OrderCanceled(SOrderV3),
+}
+
+/// 强平信息
+#[derive(Debug, Clone)]
+pub struct SRunnerLiquidation {
+    pub tp_type: ETradingPairType,
+    /// 强平价格
+    pub liquidation_price: Decimal,
+    /// 被强平的基础资产量（正值为多头 负值为空头）
+    pub base_quantity: Decimal,
+    /// 退还给用户的保证金
+    pub returned_margin: SAsset,
+    /// 强平手续费（保证金资产类型）
+    pub liquidation_fee: SAsset,
+    /// 因强平被取消的挂单
+    pub canceled_orders: Vec<SOrderV3>,
 }
 
 /// Runner 处理K线的结果
@@ -30,7 +53,7 @@ pub struct SRunnerParseKlineResult {
 pub mod strategy_order {
     use rust_decimal::Decimal;
     use uuid::Uuid;
-    use crate::data_runtime::order::EOrderAction;
+    use crate::data_runtime::order::{EOrderAction, EOrderType};
     use crate::data_source::trading_pair::ETradingPairType;
 
     /// 添加策略订单
@@ -52,9 +75,21 @@ pub mod strategy_order {
         /// 购买现货时 保证金量=基础货币量*价格
         /// 购买杠杆资产时 只要保证 保证金量>0即可
         pub margin_quantity: Decimal,
+
+        /// 订单类型
+        /// 市价单的price为参考价格 用于计算保证金 实际以下一根k线的开盘价（含滑点）成交
+        /// 条件单（止损/止盈/跟踪止损）触发前不参与撮合 可以通过CancelOrder撤销
+        /// 止损限价单的price为触发后的限价 跟踪止损单的price为计算回调的初始价格
+        pub order_type: EOrderType,
     }
 
     impl SStrategyOrderAdd {
+        /// 设置订单类型
+        pub fn with_order_type(mut self, order_type: EOrderType) -> Self {
+            self.order_type = order_type;
+            self
+        }
+
         pub fn new_long_open(
             id: Option<Uuid>,
             tp_type: ETradingPairType,
@@ -69,6 +104,7 @@ pub mod strategy_order {
                 price,
                 base_quantity,
                 margin_quantity,
+                order_type: EOrderType::Limit,
             }
         }
         pub fn new_long_close(
@@ -85,6 +121,7 @@ pub mod strategy_order {
                 price,
                 base_quantity,
                 margin_quantity,
+                order_type: EOrderType::Limit,
             }
         }
 
@@ -102,6 +139,7 @@ pub mod strategy_order {
                 price,
                 base_quantity: -base_quantity,
                 margin_quantity,
+                order_type: EOrderType::Limit,
             }
         }
 
@@ -119,6 +157,7 @@ pub mod strategy_order {
                 price,
                 base_quantity: -base_quantity,
                 margin_quantity,
+                order_type: EOrderType::Limit,
             }
         }
     }
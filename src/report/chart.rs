@@ -0,0 +1,388 @@
+//! 回测结果图表
+//!
+//! 根据回测日志绘制净值、回撤、仓位和挂单/成交图，输出到回测结果CSV所在目录
+use std::collections::BTreeMap;
+use std::error::Error;
+use chrono::{DateTime, Local};
+use plotters::coord::Shift;
+use plotters::prelude::*;
+use rust_decimal::Decimal;
+use rust_decimal::prelude::ToPrimitive;
+use serde::{Deserialize, Serialize};
+use crate::data_source::trading_pair::ETradingPairType;
+use crate::report::performance::{get_drawdown_curve, get_index_curve, split_segments, SReportSample};
+use crate::runner::logger::data_logger::SDataLogger;
+use crate::runner::SRunnerResult;
+
+pub type RChartResult<T> = Result<T, Box<dyn Error>>;
+
+/// 单条折线的最大点数 超出时等间隔抽样
+const MAX_LINE_POINTS: usize = 2_000;
+/// 图片尺寸
+const CHART_SIZE: (u32, u32) = (1600, 900);
+
+/// 图表格式
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Default, Serialize, Deserialize)]
+pub enum EChartFormat {
+    #[default]
+    Png,
+    Svg,
+}
+
+impl EChartFormat {
+    pub fn get_extension(&self) -> &'static str {
+        match self {
+            EChartFormat::Png => { "png" }
+            EChartFormat::Svg => { "svg" }
+        }
+    }
+}
+
+type TChartPoints = Vec<(DateTime<Local>, f64)>;
+/// 散点序列(图例，颜色，散点)
+type TChartScatter<'a> = (&'a str, RGBColor, &'a [(DateTime<Local>, f64)]);
+
+/// 单条折线
+struct SChartLine<'a> {
+    label: &'a str,
+    color: RGBColor,
+    /// 折线可能由多段组成（如挂单价格在没有挂单时中断）
+    runs: Vec<&'a [(DateTime<Local>, f64)]>,
+}
+
+impl<'a> SChartLine<'a> {
+    fn new(label: &'a str, color: RGBColor, points: &'a [(DateTime<Local>, f64)]) -> Self {
+        Self { label, color, runs: vec![points] }
+    }
+
+    fn from_runs(label: &'a str, color: RGBColor, runs: &'a [TChartPoints]) -> Self {
+        Self { label, color, runs: runs.iter().map(|run| run.as_slice()).collect() }
+    }
+}
+
+/// 回测图表数据
+#[derive(Debug, Clone, Default)]
+pub struct SBacktestChart {
+    /// 策略净值（从1开始）
+    pub equity: TChartPoints,
+    /// 基准净值（持有BTC现货 从1开始）
+    pub benchmark: TChartPoints,
+    /// 策略回撤
+    pub drawdown: TChartPoints,
+    /// 目标仓位
+    pub target_position: TChartPoints,
+    /// 实际仓位
+    pub actual_position: TChartPoints,
+    /// BTC现货价格
+    pub price: TChartPoints,
+    /// BTC现货最高买单价格 没有挂单时中断
+    pub highest_buy_price: Vec<TChartPoints>,
+    /// BTC现货最低卖单价格 没有挂单时中断
+    pub lowest_sell_price: Vec<TChartPoints>,
+    /// 买入成交（成交时刻的BTC现货价格）
+    pub buy_fills: TChartPoints,
+    /// 卖出成交（成交时刻的BTC现货价格）
+    pub sell_fills: TChartPoints,
+}
+
+impl From<&SRunnerResult> for SBacktestChart {
+    fn from(value: &SRunnerResult) -> Self {
+        Self::from_logger(&value.data_logger)
+    }
+}
+
+impl SBacktestChart {
+    pub fn from_logger(data_logger: &SDataLogger) -> Self {
+        let to_f64 = |value: Decimal| value.to_f64().unwrap_or(0.0);
+
+        // 净值及回撤 多线程回测时按分区串联
+        let segments = split_segments(data_logger.user_data.values().map(SReportSample::from).collect());
+        let equity = get_index_curve(&segments
+            .iter()
+            .map(|segment| segment.iter().map(|sample| (sample.time, sample.equity_usdt)).collect())
+            .collect::<Vec<TChartPoints>>());
+        let benchmark = get_index_curve(&segments
+            .iter()
+            .map(|segment| segment.iter().filter_map(|sample| sample.benchmark_price.map(|price| (sample.time, price))).collect())
+            .collect::<Vec<TChartPoints>>());
+        let drawdown = get_drawdown_curve(&equity);
+
+        // 价格 优先使用k线日志
+        let mut price_map: BTreeMap<DateTime<Local>, f64> = data_logger.kline_data
+            .values()
+            .filter_map(|kline| kline.price_btc_usdt.map(|price| (kline.time, to_f64(price))))
+            .collect();
+        if price_map.is_empty() {
+            price_map = data_logger.user_data
+                .values()
+                .filter_map(|user_log| user_log.trading_pair_prices.get(&ETradingPairType::BtcUsdt).map(|price| (user_log.time, to_f64(*price))))
+                .collect();
+        }
+
+        let mut chart = Self {
+            equity,
+            benchmark,
+            drawdown,
+            price: price_map.iter().map(|(time, price)| (*time, *price)).collect(),
+            ..Self::default()
+        };
+
+        let mut highest_buy_price: Vec<(DateTime<Local>, Option<f64>)> = Vec::new();
+        let mut lowest_sell_price: Vec<(DateTime<Local>, Option<f64>)> = Vec::new();
+        for user_log in data_logger.user_data.values() {
+            let time = user_log.time;
+            if let Some(target_position_ratio) = user_log.target_position_ratio {
+                chart.target_position.push((time, to_f64(target_position_ratio)));
+            }
+            chart.actual_position.push((time, to_f64(user_log.get_actual_position_ratio())));
+            highest_buy_price.push((time, user_log.order_info.btc_usdt_highest_buy_price.map(to_f64)));
+            lowest_sell_price.push((time, user_log.order_info.btc_usdt_lowest_sell_price.map(to_f64)));
+            if let Some(price) = price_map.get(&time) {
+                if user_log.transfer_info.executed_buy_order_cnt > 0 {
+                    chart.buy_fills.push((time, *price));
+                }
+                if user_log.transfer_info.executed_sell_order_cnt > 0 {
+                    chart.sell_fills.push((time, *price));
+                }
+            }
+        }
+        chart.highest_buy_price = split_runs(&highest_buy_price);
+        chart.lowest_sell_price = split_runs(&lowest_sell_price);
+        chart
+    }
+
+    /// 绘制所有图表 文件名为输出路径去掉.csv后缀加上图表名称，返回生成的文件路径
+    pub fn render(&self, output_path: &str, format: EChartFormat) -> RChartResult<Vec<String>> {
+        let stem = output_path.strip_suffix(".csv").unwrap_or(output_path);
+        let mut paths = Vec::new();
+
+        let equity = downsample(&self.equity);
+        let benchmark = downsample(&self.benchmark);
+        let mut lines = vec![SChartLine::new("Strategy", BLUE, &equity)];
+        if !benchmark.is_empty() {
+            lines.push(SChartLine::new("BTC buy & hold", RGBColor(255, 140, 0), &benchmark));
+        }
+        paths.push(render_to_file(stem, "equity", format, "Equity curve", "Net value", &lines, &[])?);
+
+        let drawdown: TChartPoints = downsample(&self.drawdown).into_iter().map(|(time, value)| (time, -value)).collect();
+        let lines = vec![SChartLine::new("Drawdown", RED, &drawdown)];
+        paths.push(render_to_file(stem, "drawdown", format, "Drawdown", "Drawdown ratio", &lines, &[])?);
+
+        let target_position = downsample(&self.target_position);
+        let actual_position = downsample(&self.actual_position);
+        let lines = vec![
+            SChartLine::new("Target position", RGBColor(255, 140, 0), &target_position),
+            SChartLine::new("Actual position", BLUE, &actual_position),
+        ];
+        paths.push(render_to_file(stem, "position", format, "Target vs actual position", "Position ratio", &lines, &[])?);
+
+        let price = downsample(&self.price);
+        let highest_buy_price: Vec<TChartPoints> = self.highest_buy_price.iter().map(|run| downsample(run)).collect();
+        let lowest_sell_price: Vec<TChartPoints> = self.lowest_sell_price.iter().map(|run| downsample(run)).collect();
+        let lines = vec![
+            SChartLine::new("BTC/USDT", BLACK, &price),
+            SChartLine::from_runs("Highest buy order", GREEN, &highest_buy_price),
+            SChartLine::from_runs("Lowest sell order", RED, &lowest_sell_price),
+        ];
+        let fills = [("Buy fill", GREEN, self.buy_fills.as_slice()), ("Sell fill", RED, self.sell_fills.as_slice())];
+        paths.push(render_to_file(stem, "orders", format, "Order ladder and fills", "Price", &lines, &fills)?);
+
+        Ok(paths)
+    }
+}
+
+/// 将可能缺失的数据拆分为连续的多段
+fn split_runs(points: &[(DateTime<Local>, Option<f64>)]) -> Vec<TChartPoints> {
+    let mut runs: Vec<TChartPoints> = Vec::new();
+    let mut run: TChartPoints = Vec::new();
+    for (time, value) in points {
+        match value {
+            Some(value) => { run.push((*time, *value)); }
+            None => {
+                if !run.is_empty() {
+                    runs.push(std::mem::take(&mut run));
+                }
+            }
+        }
+    }
+    if !run.is_empty() {
+        runs.push(run);
+    }
+    runs
+}
+
+/// 等间隔抽样 保留最后一个点
+fn downsample(points: &[(DateTime<Local>, f64)]) -> TChartPoints {
+    if points.len() <= MAX_LINE_POINTS {
+        return points.to_vec();
+    }
+    let step = points.len().div_ceil(MAX_LINE_POINTS);
+    let mut result: TChartPoints = points.iter().step_by(step).copied().collect();
+    if let Some(last) = points.last() {
+        if result.last() != Some(last) {
+            result.push(*last);
+        }
+    }
+    result
+}
+
+/// 根据格式选择绘图后端并输出到文件
+fn render_to_file(
+    stem: &str,
+    name: &str,
+    format: EChartFormat,
+    caption: &str,
+    y_desc: &str,
+    lines: &[SChartLine],
+    points: &[TChartScatter],
+) -> RChartResult<String> {
+    let path = format!("{}_{}.{}", stem, name, format.get_extension());
+    match format {
+        EChartFormat::Png => {
+            let root = BitMapBackend::new(&path, CHART_SIZE).into_drawing_area();
+            draw_lines(&root, caption, y_desc, lines, points)?;
+            root.present()?;
+        }
+        EChartFormat::Svg => {
+            let root = SVGBackend::new(&path, CHART_SIZE).into_drawing_area();
+            draw_lines(&root, caption, y_desc, lines, points)?;
+            root.present()?;
+        }
+    }
+    Ok(path)
+}
+
+/// 绘制时间序列折线图及散点
+fn draw_lines<DB: DrawingBackend>(
+    root: &DrawingArea<DB, Shift>,
+    caption: &str,
+    y_desc: &str,
+    lines: &[SChartLine],
+    points: &[TChartScatter],
+) -> RChartResult<()>
+where
+    DB::ErrorType: 'static,
+{
+    root.fill(&WHITE)?;
+
+    let all_points = lines
+        .iter()
+        .flat_map(|line| line.runs.iter().flat_map(|run| run.iter()))
+        .chain(points.iter().flat_map(|(_, _, points)| points.iter()));
+    let mut x_range: Option<(DateTime<Local>, DateTime<Local>)> = None;
+    let mut y_range: Option<(f64, f64)> = None;
+    for (time, value) in all_points {
+        x_range = Some(match x_range {
+            None => { (*time, *time) }
+            Some((from, to)) => { (from.min(*time), to.max(*time)) }
+        });
+        y_range = Some(match y_range {
+            None => { (*value, *value) }
+            Some((min, max)) => { (min.min(*value), max.max(*value)) }
+        });
+    }
+    let (x_from, x_to) = match x_range {
+        // 没有数据时只绘制标题
+        None => {
+            root.titled(caption, ("sans-serif", 28))?;
+            return Ok(());
+        }
+        Some((from, to)) if from == to => { (from, to + chrono::Duration::minutes(1)) }
+        Some(range) => { range }
+    };
+    let (y_min, y_max) = y_range.unwrap();
+    let padding = if y_max > y_min { (y_max - y_min) * 0.05 } else { y_max.abs().max(1.0) * 0.05 };
+
+    let mut chart = ChartBuilder::on(root)
+        .caption(caption, ("sans-serif", 28))
+        .margin(20)
+        .x_label_area_size(50)
+        .y_label_area_size(90)
+        .build_cartesian_2d(x_from..x_to, (y_min - padding)..(y_max + padding))?;
+    chart.configure_mesh()
+        .x_labels(10)
+        .x_label_formatter(&|time: &DateTime<Local>| time.format("%Y-%m-%d %H:%M").to_string())
+        .y_desc(y_desc)
+        .draw()?;
+
+    for line in lines {
+        let color = line.color;
+        for (index, run) in line.runs.iter().enumerate() {
+            let series = chart.draw_series(LineSeries::new(run.iter().copied(), color))?;
+            if index == 0 {
+                series
+                    .label(line.label)
+                    .legend(move |(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], color));
+            }
+        }
+    }
+    for (label, color, points) in points {
+        let color = *color;
+        chart.draw_series(points.iter().map(|point| Circle::new(*point, 4, color.filled())))?
+            .label(*label)
+            .legend(move |(x, y)| Circle::new((x + 10, y), 4, color.filled()));
+    }
+
+    chart.configure_series_labels()
+        .position(SeriesLabelPosition::UpperLeft)
+        .background_style(WHITE.mix(0.8))
+        .border_style(BLACK)
+        .draw()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, Local, TimeZone};
+    use crate::report::chart::{downsample, split_runs, EChartFormat, SBacktestChart, MAX_LINE_POINTS};
+
+    #[test]
+    pub fn test_split_runs() {
+        let time = Local.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+        let points: Vec<_> = [Some(1.0), Some(2.0), None, None, Some(3.0), None, Some(4.0), Some(5.0)]
+            .into_iter()
+            .enumerate()
+            .map(|(i, value)| (time + Duration::minutes(i as i64), value))
+            .collect();
+        let runs = split_runs(&points);
+        assert_eq!(runs.iter().map(|run| run.len()).collect::<Vec<_>>(), vec![2, 1, 2]);
+        assert_eq!(runs[2][1].1, 5.0);
+    }
+
+    #[test]
+    pub fn test_downsample() {
+        let time = Local.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+        let points: Vec<_> = (0..10_001).map(|i| (time + Duration::minutes(i), i as f64)).collect();
+        let result = downsample(&points);
+        assert!(result.len() <= MAX_LINE_POINTS + 1);
+        assert_eq!(result.first(), points.first());
+        assert_eq!(result.last(), points.last());
+    }
+
+    #[test]
+    pub fn test_render() {
+        let time = Local.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+        let line = |f: fn(f64) -> f64| (0..100).map(|i| (time + Duration::minutes(i), f(i as f64))).collect::<Vec<_>>();
+        let chart = SBacktestChart {
+            equity: line(|x| 1.0 + (x / 10.0).sin() * 0.1),
+            benchmark: line(|x| 1.0 + x / 1000.0),
+            drawdown: line(|x| (x / 10.0).cos().abs() * 0.1),
+            target_position: line(|_| 0.5),
+            actual_position: line(|x| 0.5 + (x / 5.0).sin() * 0.05),
+            price: line(|x| 100_000.0 + x * 10.0),
+            highest_buy_price: vec![line(|x| 99_990.0 + x * 10.0)],
+            lowest_sell_price: vec![],
+            buy_fills: vec![(time + Duration::minutes(10), 100_100.0)],
+            sell_fills: vec![],
+        };
+        let output_path = std::env::temp_dir().join("test_backtest_chart.csv");
+        for format in [EChartFormat::Svg, EChartFormat::Png] {
+            let paths = chart.render(output_path.to_str().unwrap(), format).unwrap();
+            assert_eq!(paths.len(), 4);
+            for path in paths {
+                assert!(path.ends_with(format.get_extension()));
+                assert!(std::fs::metadata(&path).unwrap().len() > 0);
+            }
+        }
+    }
+}
@@ -0,0 +1,5 @@
+//! 回测报告 基于SDataLogger计算回测指标
+
+pub mod performance;
+pub mod chart;
+pub mod window;
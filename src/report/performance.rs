@@ -0,0 +1,487 @@
+//! 回测绩效指标
+//!
+//! 多线程回测合并后的日志中，每个日期分区对应一个独立的用户，分区之间只串联收益率，不比较资产量
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::fs::File;
+use chrono::{DateTime, Duration, Local};
+use rust_decimal::Decimal;
+use rust_decimal::prelude::ToPrimitive;
+use serde::Serialize;
+use uuid::Uuid;
+use crate::data_source::trading_pair::ETradingPairType;
+use crate::runner::logger::data_logger::SDataLogger;
+use crate::runner::logger::user_unit::SDataLogUserUnit;
+
+pub type RReportResult<T> = Result<T, EReportError>;
+
+/// 回测报告异常
+#[derive(Debug)]
+pub enum EReportError {
+    /// 日志中没有用户数据
+    EmptyDataError,
+    /// 报告输出失败(文件路径，错误信息)
+    OutputError(String, String),
+}
+
+impl Display for EReportError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EReportError::EmptyDataError => { write!(f, "回测日志中没有用户数据") }
+            EReportError::OutputError(path, e) => { write!(f, "回测报告输出失败: {} ({})", path, e) }
+        }
+    }
+}
+
+impl Error for EReportError {}
+
+/// 每年的秒数 用于年化
+const SECONDS_PER_YEAR: f64 = 365.0 * 24.0 * 3600.0;
+
+/// 计算指标所需的单条采样数据
+#[derive(Debug, Clone)]
+pub struct SReportSample {
+    pub time: DateTime<Local>,
+    /// 所属用户 同一用户的采样构成一个连续分区
+    pub user_id: Uuid,
+    /// 总资产（USDT计价）
+    pub equity_usdt: f64,
+    /// 基准价格（BTC现货价格）
+    pub benchmark_price: Option<f64>,
+    /// 累计手续费（USDT计价）
+    pub total_fee_usdt: f64,
+    /// 累计资金费（USDT计价）
+    pub total_funding_fee_usdt: f64,
+    /// 本次采样期间的成交额（USDT计价）
+    pub traded_usdt: f64,
+    /// 本次采样期间的成交订单数
+    pub executed_order_cnt: i64,
+    /// 本次采样期间的强平次数
+    pub liquidated_cnt: i64,
+    /// 累计已平仓的策略订单对数量
+    pub closed_order_pair_cnt: i64,
+    /// 累计盈利的策略订单对数量
+    pub winning_order_pair_cnt: i64,
+}
+
+impl From<&SDataLogUserUnit> for SReportSample {
+    fn from(value: &SDataLogUserUnit) -> Self {
+        let to_f64 = |value: Decimal| value.to_f64().unwrap_or(0.0);
+        let transfer_info = &value.transfer_info;
+        Self {
+            time: value.time,
+            user_id: value.user_id,
+            equity_usdt: to_f64(value.total_assets_usdt),
+            benchmark_price: value.trading_pair_prices.get(&ETradingPairType::BtcUsdt).map(|price| to_f64(*price)),
+            total_fee_usdt: to_f64(value.total_fee_usdt),
+            total_funding_fee_usdt: to_f64(value.total_funding_fee_usdt),
+            traded_usdt: to_f64(transfer_info.executed_buy_usdt_cnt + transfer_info.executed_sell_usdt_cnt),
+            executed_order_cnt: transfer_info.executed_buy_order_cnt + transfer_info.executed_sell_order_cnt,
+            liquidated_cnt: transfer_info.liquidated_cnt,
+            closed_order_pair_cnt: value.closed_order_pair_cnt,
+            winning_order_pair_cnt: value.winning_order_pair_cnt,
+        }
+    }
+}
+
+#[cfg(test)]
+impl SReportSample {
+    /// 测试用的采样数据 同一用户从from开始每隔step采样一次资产 不含基准价格及交易数据
+    pub fn from_equities(from: DateTime<Local>, step: Duration, equities: &[f64]) -> Vec<Self> {
+        let user_id = Uuid::new_v4();
+        equities
+            .iter()
+            .enumerate()
+            .map(|(i, equity_usdt)| Self {
+                time: from + step * i as i32,
+                user_id,
+                equity_usdt: *equity_usdt,
+                benchmark_price: None,
+                total_fee_usdt: 0.0,
+                total_funding_fee_usdt: 0.0,
+                traded_usdt: 0.0,
+                executed_order_cnt: 0,
+                liquidated_cnt: 0,
+                closed_order_pair_cnt: 0,
+                winning_order_pair_cnt: 0,
+            })
+            .collect()
+    }
+}
+
+/// 单期收益率
+fn get_step_return(prev_value: f64, value: f64) -> f64 {
+    if prev_value != 0.0 { value / prev_value - 1.0 } else { 0.0 }
+}
+
+/// 将各分区的净值串联为从1开始的净值曲线 分区内按收益率累乘，分区之间不计收益
+pub fn get_index_curve(segments: &[Vec<(DateTime<Local>, f64)>]) -> Vec<(DateTime<Local>, f64)> {
+    let mut index_curve: Vec<(DateTime<Local>, f64)> = Vec::new();
+    let mut index = 1.0;
+    for segment in segments {
+        if let Some((time, _)) = segment.first() {
+            index_curve.push((*time, index));
+        }
+        for window in segment.windows(2) {
+            index *= 1.0 + get_step_return(window[0].1, window[1].1);
+            index_curve.push((window[1].0, index));
+        }
+    }
+    index_curve
+}
+
+/// 回撤曲线 数值为相对前高的回撤比例
+pub fn get_drawdown_curve(index_curve: &[(DateTime<Local>, f64)]) -> Vec<(DateTime<Local>, f64)> {
+    let mut peak = f64::MIN;
+    index_curve
+        .iter()
+        .map(|(time, value)| {
+            peak = peak.max(*value);
+            (*time, if peak > 0.0 { 1.0 - value / peak } else { 0.0 })
+        })
+        .collect()
+}
+
+/// 按用户拆分分区 分区按起始时间排序
+pub fn split_segments(mut samples: Vec<SReportSample>) -> Vec<Vec<SReportSample>> {
+    samples.sort_by_key(|sample| sample.time);
+    let mut segment_index: HashMap<Uuid, usize> = HashMap::new();
+    let mut segments: Vec<Vec<SReportSample>> = Vec::new();
+    for sample in samples {
+        let index = *segment_index.entry(sample.user_id).or_insert_with(|| {
+            segments.push(Vec::new());
+            segments.len() - 1
+        });
+        segments[index].push(sample);
+    }
+    segments
+}
+
+/// 收益率序列的统计指标
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct SReturnStats {
+    /// 总收益率
+    pub total_return: f64,
+    /// 年化收益率
+    pub annualized_return: f64,
+    /// 年化波动率
+    pub volatility: f64,
+    /// 最大回撤
+    pub max_drawdown: f64,
+    /// 最长回撤持续时间（天） 从前高到收复前高，未收复时计算至回测结束
+    pub max_drawdown_duration_days: f64,
+    /// 夏普比率（无风险利率为0） 收益率无波动时为None
+    pub sharpe_ratio: Option<f64>,
+    /// 索提诺比率 没有下行波动时为None
+    pub sortino_ratio: Option<f64>,
+    /// 卡玛比率 没有回撤时为None
+    pub calmar_ratio: Option<f64>,
+}
+
+impl SReturnStats {
+    /// 根据分区序列计算 每个分区为按时间排序的(时间，净值)
+    fn from_segments(segments: &[Vec<(DateTime<Local>, f64)>]) -> Self {
+        // 分区内计算单期收益率
+        let mut returns: Vec<f64> = Vec::new();
+        let mut step_seconds: Vec<i64> = Vec::new();
+        let mut total_seconds = 0;
+        for segment in segments {
+            for window in segment.windows(2) {
+                let (prev_time, prev_value) = window[0];
+                let (time, value) = window[1];
+                returns.push(get_step_return(prev_value, value));
+                step_seconds.push((time - prev_time).num_seconds());
+            }
+            if let (Some((from, _)), Some((to, _))) = (segment.first(), segment.last()) {
+                total_seconds += (*to - *from).num_seconds();
+            }
+        }
+        let index_curve = get_index_curve(segments);
+        let index = index_curve.last().map(|(_, value)| *value).unwrap_or(1.0);
+
+        let total_return = index - 1.0;
+        let years = total_seconds as f64 / SECONDS_PER_YEAR;
+        let annualized_return = if years > 0.0 && index > 0.0 { index.powf(1.0 / years) - 1.0 } else { 0.0 };
+
+        // 年化系数 使用采样间隔的中位数估计每年的期数
+        step_seconds.sort();
+        let periods_per_year = match step_seconds.get(step_seconds.len() / 2) {
+            Some(step) if *step > 0 => { SECONDS_PER_YEAR / *step as f64 }
+            _ => { 0.0 }
+        };
+        let count = returns.len() as f64;
+        let mean = if count > 0.0 { returns.iter().sum::<f64>() / count } else { 0.0 };
+        let std = if count > 1.0 {
+            (returns.iter().map(|r| (r - mean).powi(2)).sum::<f64>() / (count - 1.0)).sqrt()
+        } else {
+            0.0
+        };
+        let downside_std = if count > 0.0 {
+            (returns.iter().map(|r| r.min(0.0).powi(2)).sum::<f64>() / count).sqrt()
+        } else {
+            0.0
+        };
+        let annualize = periods_per_year.sqrt();
+
+        // 最大回撤及最长回撤持续时间
+        let mut max_drawdown: f64 = 0.0;
+        let mut max_drawdown_duration = Duration::zero();
+        let mut peak: Option<(DateTime<Local>, f64)> = None;
+        for (time, value) in index_curve.iter() {
+            match peak {
+                Some((peak_time, peak_value)) if *value < peak_value => {
+                    max_drawdown = max_drawdown.max(1.0 - value / peak_value);
+                    max_drawdown_duration = max_drawdown_duration.max(*time - peak_time);
+                }
+                _ => { peak = Some((*time, *value)); }
+            }
+        }
+
+        Self {
+            total_return,
+            annualized_return,
+            volatility: std * annualize,
+            max_drawdown,
+            max_drawdown_duration_days: max_drawdown_duration.num_seconds() as f64 / 86400.0,
+            sharpe_ratio: if std > 0.0 { Some(mean / std * annualize) } else { None },
+            sortino_ratio: if downside_std > 0.0 { Some(mean / downside_std * annualize) } else { None },
+            calmar_ratio: if max_drawdown > 0.0 { Some(annualized_return / max_drawdown) } else { None },
+        }
+    }
+}
+
+/// 回测绩效报告
+#[derive(Debug, Clone, Serialize)]
+pub struct SPerformanceReport {
+    pub date_from: DateTime<Local>,
+    pub date_to: DateTime<Local>,
+    /// 采样数量
+    pub sample_cnt: usize,
+    /// 分区数量（多线程回测的日期分区数）
+    pub segment_cnt: usize,
+    /// 策略收益指标
+    pub strategy: SReturnStats,
+    /// 基准（持有BTC现货）收益指标 日志中没有BTC现货价格时为None
+    pub benchmark: Option<SReturnStats>,
+    /// 超额收益（策略总收益率 - 基准总收益率）
+    pub excess_return: Option<f64>,
+    /// 累计手续费（USDT计价）
+    pub total_fee_usdt: f64,
+    /// 累计资金费（USDT计价）
+    pub total_funding_fee_usdt: f64,
+    /// 手续费损耗（累计手续费 / 初始资产 各分区求和）
+    pub fee_drag: f64,
+    /// 资金费损耗（累计资金费 / 初始资产 各分区求和）
+    pub funding_fee_drag: f64,
+    /// 成交额（USDT计价）
+    pub traded_usdt: f64,
+    /// 换手率（成交额 / 平均资产）
+    pub turnover: f64,
+    /// 年化换手率
+    pub annualized_turnover: f64,
+    /// 成交订单数
+    pub executed_order_cnt: i64,
+    /// 强平次数
+    pub liquidated_cnt: i64,
+    /// 已平仓的策略订单对数量
+    pub closed_order_pair_cnt: i64,
+    /// 盈利的策略订单对数量
+    pub winning_order_pair_cnt: i64,
+    /// 策略订单对胜率 没有已平仓订单对时为None
+    pub win_rate: Option<f64>,
+}
+
+impl SPerformanceReport {
+    pub fn from_logger(data_logger: &SDataLogger) -> RReportResult<Self> {
+        Self::from_samples(data_logger.user_data.values().map(SReportSample::from).collect())
+    }
+
+    pub fn from_samples(mut samples: Vec<SReportSample>) -> RReportResult<Self> {
+        if samples.is_empty() {
+            return Err(EReportError::EmptyDataError);
+        }
+        samples.sort_by_key(|sample| sample.time);
+        let segments = split_segments(samples.clone());
+
+        let equity_segments: Vec<Vec<(DateTime<Local>, f64)>> = segments
+            .iter()
+            .map(|segment| segment.iter().map(|sample| (sample.time, sample.equity_usdt)).collect())
+            .collect();
+        let strategy = SReturnStats::from_segments(&equity_segments);
+
+        let has_benchmark = samples.iter().all(|sample| sample.benchmark_price.is_some());
+        let benchmark = if has_benchmark {
+            let benchmark_segments: Vec<Vec<(DateTime<Local>, f64)>> = segments
+                .iter()
+                .map(|segment| segment.iter().map(|sample| (sample.time, sample.benchmark_price.unwrap())).collect())
+                .collect();
+            Some(SReturnStats::from_segments(&benchmark_segments))
+        } else {
+            None
+        };
+
+        let mut total_fee_usdt = 0.0;
+        let mut total_funding_fee_usdt = 0.0;
+        let mut fee_drag = 0.0;
+        let mut funding_fee_drag = 0.0;
+        let mut closed_order_pair_cnt = 0;
+        let mut winning_order_pair_cnt = 0;
+        for segment in segments.iter() {
+            let (first, last) = (segment.first().unwrap(), segment.last().unwrap());
+            total_fee_usdt += last.total_fee_usdt;
+            total_funding_fee_usdt += last.total_funding_fee_usdt;
+            if first.equity_usdt != 0.0 {
+                fee_drag += last.total_fee_usdt / first.equity_usdt;
+                funding_fee_drag += last.total_funding_fee_usdt / first.equity_usdt;
+            }
+            closed_order_pair_cnt += last.closed_order_pair_cnt;
+            winning_order_pair_cnt += last.winning_order_pair_cnt;
+        }
+
+        let traded_usdt: f64 = samples.iter().map(|sample| sample.traded_usdt).sum();
+        let average_equity = samples.iter().map(|sample| sample.equity_usdt).sum::<f64>() / samples.len() as f64;
+        let turnover = if average_equity != 0.0 { traded_usdt / average_equity } else { 0.0 };
+        let total_seconds: i64 = segments
+            .iter()
+            .map(|segment| (segment.last().unwrap().time - segment.first().unwrap().time).num_seconds())
+            .sum();
+        let annualized_turnover = if total_seconds > 0 { turnover * SECONDS_PER_YEAR / total_seconds as f64 } else { 0.0 };
+
+        Ok(Self {
+            date_from: samples.first().unwrap().time,
+            date_to: samples.last().unwrap().time,
+            sample_cnt: samples.len(),
+            segment_cnt: segments.len(),
+            excess_return: benchmark.as_ref().map(|benchmark| strategy.total_return - benchmark.total_return),
+            strategy,
+            benchmark,
+            total_fee_usdt,
+            total_funding_fee_usdt,
+            fee_drag,
+            funding_fee_drag,
+            traded_usdt,
+            turnover,
+            annualized_turnover,
+            executed_order_cnt: samples.iter().map(|sample| sample.executed_order_cnt).sum(),
+            liquidated_cnt: samples.iter().map(|sample| sample.liquidated_cnt).sum(),
+            closed_order_pair_cnt,
+            winning_order_pair_cnt,
+            win_rate: if closed_order_pair_cnt > 0 { Some(winning_order_pair_cnt as f64 / closed_order_pair_cnt as f64) } else { None },
+        })
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap()
+    }
+
+    /// 将报告以JSON格式输出到指定文件
+    pub fn output_json(&self, path: &str) -> RReportResult<()> {
+        let file = File::create(path).map_err(|e| EReportError::OutputError(path.to_string(), e.to_string()))?;
+        serde_json::to_writer_pretty(file, self).map_err(|e| EReportError::OutputError(path.to_string(), e.to_string()))
+    }
+}
+
+impl Display for SPerformanceReport {
+    /// 可读的报告摘要
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let percent = |value: f64| format!("{:.2}%", value * 100.0);
+        let ratio = |value: Option<f64>| value.map(|value| format!("{:.3}", value)).unwrap_or(String::from("-"));
+        let stats = &self.strategy;
+        writeln!(f, "回测周期: {} ~ {} (采样数 {}, 分区数 {})", self.date_from, self.date_to, self.sample_cnt, self.segment_cnt)?;
+        writeln!(f, "总收益率: {}\t年化收益率: {}\t年化波动率: {}", percent(stats.total_return), percent(stats.annualized_return), percent(stats.volatility))?;
+        writeln!(f, "最大回撤: {}\t最长回撤持续: {:.2}天", percent(stats.max_drawdown), stats.max_drawdown_duration_days)?;
+        writeln!(f, "夏普比率: {}\t索提诺比率: {}\t卡玛比率: {}", ratio(stats.sharpe_ratio), ratio(stats.sortino_ratio), ratio(stats.calmar_ratio))?;
+        if let Some(benchmark) = &self.benchmark {
+            writeln!(f, "基准(持有BTC)总收益率: {}\t最大回撤: {}\t超额收益: {}",
+                     percent(benchmark.total_return), percent(benchmark.max_drawdown), percent(self.excess_return.unwrap_or(0.0)))?;
+        }
+        writeln!(f, "累计手续费: {:.4} USDT (损耗 {})\t累计资金费: {:.4} USDT (损耗 {})",
+                 self.total_fee_usdt, percent(self.fee_drag), self.total_funding_fee_usdt, percent(self.funding_fee_drag))?;
+        writeln!(f, "成交额: {:.4} USDT\t换手率: {:.2}\t年化换手率: {:.2}\t成交订单数: {}\t强平次数: {}",
+                 self.traded_usdt, self.turnover, self.annualized_turnover, self.executed_order_cnt, self.liquidated_cnt)?;
+        write!(f, "策略订单对: 已平仓 {}\t盈利 {}\t胜率: {}",
+               self.closed_order_pair_cnt, self.winning_order_pair_cnt, self.win_rate.map(percent).unwrap_or(String::from("-")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, Local, TimeZone};
+    use uuid::Uuid;
+    use crate::report::performance::{EReportError, SPerformanceReport, SReportSample};
+    use crate::runner::logger::data_logger::SDataLogger;
+
+    fn get_sample(minute: i64, user_id: Uuid, equity_usdt: f64, benchmark_price: f64) -> SReportSample {
+        SReportSample {
+            time: Local.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap() + Duration::minutes(minute),
+            user_id,
+            equity_usdt,
+            benchmark_price: Some(benchmark_price),
+            total_fee_usdt: minute as f64,
+            total_funding_fee_usdt: 0.0,
+            traded_usdt: 100.0,
+            executed_order_cnt: 1,
+            liquidated_cnt: 0,
+            closed_order_pair_cnt: minute,
+            winning_order_pair_cnt: minute / 2,
+        }
+    }
+
+    #[test]
+    pub fn test_single_segment() {
+        let user_id = Uuid::new_v4();
+        let samples = vec![
+            get_sample(0, user_id, 100.0, 10.0),
+            get_sample(1, user_id, 110.0, 11.0),
+            get_sample(2, user_id, 99.0, 12.0),
+            get_sample(3, user_id, 120.0, 12.0),
+        ];
+        let report = SPerformanceReport::from_samples(samples).unwrap();
+        assert_eq!(report.sample_cnt, 4);
+        assert_eq!(report.segment_cnt, 1);
+        assert!((report.strategy.total_return - 0.2).abs() < 1e-9);
+        assert!((report.strategy.max_drawdown - 0.1).abs() < 1e-9);
+        assert!((report.strategy.max_drawdown_duration_days - 1.0 / 1440.0).abs() < 1e-9);
+        assert!(report.strategy.sharpe_ratio.unwrap() > 0.0);
+        assert!(report.strategy.sortino_ratio.is_some());
+
+        let benchmark = report.benchmark.as_ref().unwrap();
+        assert!((benchmark.total_return - 0.2).abs() < 1e-9);
+        assert_eq!(benchmark.max_drawdown, 0.0);
+        assert!(benchmark.calmar_ratio.is_none());
+        assert!(report.excess_return.unwrap().abs() < 1e-9);
+
+        assert!((report.fee_drag - 0.03).abs() < 1e-9);
+        assert!((report.turnover - 400.0 / 107.25).abs() < 1e-9);
+        assert_eq!(report.executed_order_cnt, 4);
+        assert_eq!(report.win_rate, Some(1.0 / 3.0));
+
+        let json: serde_json::Value = serde_json::from_str(&report.to_json()).unwrap();
+        assert_eq!(json["sample_cnt"], 4);
+        assert!(report.to_string().contains("20.00%"));
+    }
+
+    #[test]
+    pub fn test_multi_segment() {
+        // 两个分区分别上涨10%和下跌10%，分区之间的资产量不参与计算
+        let (user_1, user_2) = (Uuid::new_v4(), Uuid::new_v4());
+        let samples = vec![
+            get_sample(10, user_2, 1_000.0, 10.0),
+            get_sample(11, user_2, 900.0, 10.0),
+            get_sample(0, user_1, 100.0, 10.0),
+            get_sample(1, user_1, 110.0, 10.0),
+        ];
+        let report = SPerformanceReport::from_samples(samples).unwrap();
+        assert_eq!(report.segment_cnt, 2);
+        assert!((report.strategy.total_return - (1.1 * 0.9 - 1.0)).abs() < 1e-9);
+        assert!((report.strategy.max_drawdown - 0.1).abs() < 1e-9);
+        assert_eq!(report.closed_order_pair_cnt, 12);
+        assert_eq!(report.winning_order_pair_cnt, 5);
+    }
+
+    #[test]
+    pub fn test_empty() {
+        assert!(matches!(SPerformanceReport::from_logger(&SDataLogger::new()), Err(EReportError::EmptyDataError)));
+    }
+}
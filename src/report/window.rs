@@ -0,0 +1,263 @@
+//! 独立窗口回测报告
+//!
+//! 多线程回测将回测周期拆分为多个日期窗口，每个窗口以全新的用户独立回测（资产、挂单和策略状态不在窗口之间传递），
+//! 因此各窗口的资产量不可直接拼接。本报告按窗口分别计算绩效指标，并将各窗口收益率归一化后统计窗口间的分布
+use std::fs::File;
+use chrono::{DateTime, Local};
+use serde::Serialize;
+use crate::report::performance::{EReportError, RReportResult, SPerformanceReport, SReportSample};
+use crate::runner::logger::data_logger::SDataLogger;
+
+/// 单个窗口的回测报告
+#[derive(Debug, Clone, Serialize)]
+pub struct SWindowReport {
+    /// 窗口序号（从1开始，按起始时间排序）
+    pub index: usize,
+    /// 窗口的回测区间
+    pub date_from: DateTime<Local>,
+    pub date_to: DateTime<Local>,
+    /// 窗口初始总资产（USDT计价）
+    pub initial_equity_usdt: f64,
+    /// 窗口结束总资产（USDT计价）
+    pub final_equity_usdt: f64,
+    /// 窗口绩效指标 收益率相对窗口初始资产计算
+    pub report: SPerformanceReport,
+}
+
+/// 回测失败的窗口
+#[derive(Debug, Clone, Serialize)]
+pub struct SFailedWindow {
+    /// 窗口的回测区间
+    pub date_from: DateTime<Local>,
+    pub date_to: DateTime<Local>,
+    /// 失败原因
+    pub error: String,
+}
+
+/// 各窗口总收益率的分布
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct SWindowReturnDistribution {
+    /// 窗口总收益率的平均值
+    pub mean_return: f64,
+    /// 窗口总收益率的中位数
+    pub median_return: f64,
+    /// 窗口总收益率的标准差
+    pub std_return: f64,
+    /// 最好窗口的总收益率
+    pub best_return: f64,
+    /// 最差窗口的总收益率
+    pub worst_return: f64,
+    /// 正收益窗口的占比
+    pub positive_ratio: f64,
+    /// 各窗口最大回撤中的最大值
+    pub worst_max_drawdown: f64,
+}
+
+impl SWindowReturnDistribution {
+    fn from_windows(windows: &[SWindowReport]) -> Self {
+        if windows.is_empty() {
+            return Self::default();
+        }
+        let mut returns: Vec<f64> = windows.iter().map(|window| window.report.strategy.total_return).collect();
+        returns.sort_by(|a, b| a.total_cmp(b));
+        let count = returns.len() as f64;
+        let mean_return = returns.iter().sum::<f64>() / count;
+        let median_return = if returns.len().is_multiple_of(2) {
+            (returns[returns.len() / 2 - 1] + returns[returns.len() / 2]) / 2.0
+        } else {
+            returns[returns.len() / 2]
+        };
+        let std_return = if returns.len() > 1 {
+            (returns.iter().map(|r| (r - mean_return).powi(2)).sum::<f64>() / (count - 1.0)).sqrt()
+        } else {
+            0.0
+        };
+        Self {
+            mean_return,
+            median_return,
+            std_return,
+            best_return: *returns.last().unwrap(),
+            worst_return: *returns.first().unwrap(),
+            positive_ratio: returns.iter().filter(|r| **r > 0.0).count() as f64 / count,
+            worst_max_drawdown: windows.iter().map(|window| window.report.strategy.max_drawdown).fold(0.0, f64::max),
+        }
+    }
+}
+
+/// 独立窗口模式的回测报告
+#[derive(Debug, Clone, Serialize)]
+pub struct SIndependentWindowsReport {
+    /// 回测模式标记 固定为"independent_windows" 表示各窗口独立回测、状态不连续
+    pub mode: &'static str,
+    /// 各窗口的报告
+    pub windows: Vec<SWindowReport>,
+    /// 窗口收益率分布
+    pub distribution: SWindowReturnDistribution,
+    /// 各窗口收益率串联后的汇总指标（窗口之间不计收益，不代表连续持仓的复利结果）
+    pub chained: SPerformanceReport,
+    /// 回测失败的窗口（不计入窗口报告及收益率分布）
+    pub failed_windows: Vec<SFailedWindow>,
+}
+
+impl SIndependentWindowsReport {
+    pub const MODE: &'static str = "independent_windows";
+
+    /// 根据各窗口的回测区间及日志生成报告
+    pub fn from_loggers(loggers: &[(DateTime<Local>, DateTime<Local>, SDataLogger)]) -> RReportResult<Self> {
+        Self::from_samples(
+            loggers
+                .iter()
+                .map(|(date_from, date_to, data_logger)| {
+                    (*date_from, *date_to, data_logger.user_data.values().map(SReportSample::from).collect())
+                })
+                .collect()
+        )
+    }
+
+    /// 根据各窗口的回测区间及采样数据生成报告 跳过没有采样数据的窗口
+    pub fn from_samples(mut windows: Vec<(DateTime<Local>, DateTime<Local>, Vec<SReportSample>)>) -> RReportResult<Self> {
+        windows.sort_by_key(|(date_from, _, _)| *date_from);
+
+        let mut window_reports: Vec<SWindowReport> = Vec::new();
+        let mut all_samples: Vec<SReportSample> = Vec::new();
+        for (date_from, date_to, mut samples) in windows {
+            if samples.is_empty() {
+                continue;
+            }
+            samples.sort_by_key(|sample| sample.time);
+            window_reports.push(SWindowReport {
+                index: window_reports.len() + 1,
+                date_from,
+                date_to,
+                initial_equity_usdt: samples.first().unwrap().equity_usdt,
+                final_equity_usdt: samples.last().unwrap().equity_usdt,
+                report: SPerformanceReport::from_samples(samples.clone())?,
+            });
+            all_samples.append(&mut samples);
+        }
+
+        let chained = SPerformanceReport::from_samples(all_samples)?;
+        Ok(Self {
+            mode: Self::MODE,
+            distribution: SWindowReturnDistribution::from_windows(&window_reports),
+            windows: window_reports,
+            chained,
+            failed_windows: Vec::new(),
+        })
+    }
+
+    /// 记录回测失败的窗口 按起始时间排序
+    pub fn with_failed_windows(mut self, mut failed_windows: Vec<SFailedWindow>) -> Self {
+        failed_windows.sort_by_key(|window| window.date_from);
+        self.failed_windows = failed_windows;
+        self
+    }
+
+    /// 将报告以JSON格式输出到指定文件
+    pub fn output_json(&self, path: &str) -> RReportResult<()> {
+        let file = File::create(path).map_err(|e| EReportError::OutputError(path.to_string(), e.to_string()))?;
+        serde_json::to_writer_pretty(file, self).map_err(|e| EReportError::OutputError(path.to_string(), e.to_string()))
+    }
+
+    /// 将各窗口的主要指标以CSV格式输出到指定文件
+    pub fn output_csv(&self, path: &str) -> RReportResult<()> {
+        let map_err = |e: csv::Error| EReportError::OutputError(path.to_string(), e.to_string());
+        let mut wtr = csv::Writer::from_path(path).map_err(map_err)?;
+        wtr.write_record([
+            "index", "date_from", "date_to", "initial_equity_usdt", "final_equity_usdt",
+            "total_return", "annualized_return", "max_drawdown", "sharpe_ratio", "benchmark_return", "excess_return",
+        ]).map_err(map_err)?;
+        let option_to_string = |value: Option<f64>| value.map(|value| value.to_string()).unwrap_or_default();
+        for window in self.windows.iter() {
+            let stats = &window.report.strategy;
+            wtr.write_record([
+                window.index.to_string(),
+                window.date_from.to_rfc3339(),
+                window.date_to.to_rfc3339(),
+                window.initial_equity_usdt.to_string(),
+                window.final_equity_usdt.to_string(),
+                stats.total_return.to_string(),
+                stats.annualized_return.to_string(),
+                stats.max_drawdown.to_string(),
+                option_to_string(stats.sharpe_ratio),
+                option_to_string(window.report.benchmark.as_ref().map(|benchmark| benchmark.total_return)),
+                option_to_string(window.report.excess_return),
+            ]).map_err(map_err)?;
+        }
+        wtr.flush().map_err(|e| EReportError::OutputError(path.to_string(), e.to_string()))
+    }
+}
+
+impl std::fmt::Display for SIndependentWindowsReport {
+    /// 可读的报告摘要
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let percent = |value: f64| format!("{:.2}%", value * 100.0);
+        let distribution = &self.distribution;
+        writeln!(f, "[独立窗口模式] 各窗口以全新用户独立回测，资产与策略状态不跨窗口延续，收益率按窗口初始资产归一化")?;
+        for window in self.windows.iter() {
+            let stats = &window.report.strategy;
+            writeln!(f, "窗口{}: {} ~ {}\t收益率: {}\t最大回撤: {}",
+                     window.index, window.date_from, window.date_to, percent(stats.total_return), percent(stats.max_drawdown))?;
+        }
+        writeln!(f, "窗口收益率: 平均 {}\t中位数 {}\t标准差 {}\t最好 {}\t最差 {}\t正收益占比 {}",
+                 percent(distribution.mean_return), percent(distribution.median_return), percent(distribution.std_return),
+                 percent(distribution.best_return), percent(distribution.worst_return), percent(distribution.positive_ratio))?;
+        for window in self.failed_windows.iter() {
+            writeln!(f, "失败窗口: {} ~ {}\t原因: {}", window.date_from, window.date_to, window.error)?;
+        }
+        write!(f, "窗口收益率串联（非连续持仓）:\n{}", self.chained)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{DateTime, Duration, Local, TimeZone};
+    use crate::report::performance::SReportSample;
+    use crate::report::window::{SFailedWindow, SIndependentWindowsReport};
+
+    fn get_samples(from: DateTime<Local>, equities: &[f64]) -> Vec<SReportSample> {
+        SReportSample::from_equities(from, Duration::minutes(1), equities)
+    }
+
+    #[test]
+    pub fn test_independent_windows() {
+        let start = Local.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+        let second = start + Duration::days(1);
+        // 第二个窗口资产量远大于第一个窗口 归一化后只比较收益率
+        let windows = vec![
+            (second, second + Duration::minutes(2), get_samples(second, &[10_000.0, 9_000.0, 9_500.0])),
+            (start, start + Duration::minutes(2), get_samples(start, &[100.0, 110.0, 120.0])),
+            (start + Duration::days(2), start + Duration::days(2), Vec::new()),
+        ];
+        let report = SIndependentWindowsReport::from_samples(windows).unwrap();
+        assert_eq!(report.mode, SIndependentWindowsReport::MODE);
+        assert_eq!(report.windows.len(), 2);
+        assert_eq!(report.windows[0].index, 1);
+        assert_eq!(report.windows[0].date_from, start);
+        assert_eq!(report.windows[0].initial_equity_usdt, 100.0);
+        assert_eq!(report.windows[1].final_equity_usdt, 9_500.0);
+        assert!((report.windows[0].report.strategy.total_return - 0.2).abs() < 1e-9);
+        assert!((report.windows[1].report.strategy.total_return + 0.05).abs() < 1e-9);
+
+        let distribution = &report.distribution;
+        assert!((distribution.mean_return - 0.075).abs() < 1e-9);
+        assert!((distribution.best_return - 0.2).abs() < 1e-9);
+        assert_eq!(distribution.positive_ratio, 0.5);
+        assert!((distribution.worst_max_drawdown - 0.1).abs() < 1e-9);
+        assert_eq!(report.chained.segment_cnt, 2);
+        assert!((report.chained.strategy.total_return - (1.2 * 0.95 - 1.0)).abs() < 1e-9);
+        assert!(report.to_string().contains("独立窗口模式"));
+
+        // 失败窗口只记录 不影响窗口报告
+        let report = report.with_failed_windows(vec![SFailedWindow {
+            date_from: start + Duration::days(3),
+            date_to: start + Duration::days(4),
+            error: "回测数据加载失败".to_string(),
+        }]);
+        assert_eq!(report.windows.len(), 2);
+        assert_eq!(report.failed_windows.len(), 1);
+        assert!(report.to_string().contains("失败窗口"));
+
+        assert!(SIndependentWindowsReport::from_loggers(&[]).is_err());
+    }
+}
@@ -1,8 +1,38 @@
-use chrono::{DateTime, Local};
+use std::collections::HashMap;
+use chrono::{DateTime, Duration, Local};
 use rust_decimal::Decimal;
 use rust_decimal::prelude::FromPrimitive;
-use crate::config::{fee::{MAKER_ORDER_FEE, TAKER_ORDER_FEE}};
-use crate::config::back_trade_period::{config_date_from, config_date_to};
+use serde::{Deserialize, Serialize};
+use crate::config::{fee::{MAKER_ORDER_FEE, TAKER_ORDER_FEE, TAKER_SLIPPAGE}};
+use crate::config::backtest_config::EPeriodPreset;
+use crate::config::liquidation::LIQUIDATION_FEE;
+use crate::data_runtime::asset::margin::{ECrossMarginBalance, EMarginMode, SMaintenanceMarginTable};
+use crate::data_source::kline::EKlineInterval;
+use crate::data_source::trading_pair::ETradingPairType;
+use crate::runner::back_trade::fill_model::SFillModelConfig;
+
+/// 报价过期策略 交易对在某时刻缺少k线时沿用最后一次报价
+/// 策略只在交易对自身的k线收盘时执行 不受其他交易对报价的影响 尚无报价的交易对不参与估值
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Default, Serialize, Deserialize)]
+pub enum EStalePricePolicy {
+    /// 沿用最后一次报价 照常记录日志
+    #[default]
+    KeepLast,
+    /// 存在报价超过最大时长（分钟）未更新的交易对时 不记录该时刻的日志
+    SkipLog { max_age_minutes: i64 },
+}
+
+impl EStalePricePolicy {
+    /// 报价是否过期
+    pub fn is_stale(&self, last_update_time: &DateTime<Local>, current_time: DateTime<Local>) -> bool {
+        match self {
+            EStalePricePolicy::KeepLast => { false }
+            EStalePricePolicy::SkipLog { max_age_minutes } => {
+                current_time - *last_update_time > Duration::minutes(*max_age_minutes)
+            }
+        }
+    }
+}
 
 #[derive(Debug, Clone)]
 pub struct SBackTradeRunnerConfig {
@@ -10,6 +40,22 @@ pub struct SBackTradeRunnerConfig {
     pub taker_order_fee: Decimal,
     ///  挂单手续费
     pub maker_order_fee: Decimal,
+    ///  吃单滑点 吃单以开盘价*(1±滑点)成交
+    pub taker_slippage: Decimal,
+    ///  成交模型
+    pub fill_model: SFillModelConfig,
+    ///  报价过期策略
+    pub stale_price_policy: EStalePricePolicy,
+    ///  策略周期 策略在该周期的k线收盘时执行 订单仍按原始k线撮合
+    pub strategy_interval: EKlineInterval,
+    ///  保证金模式
+    pub margin_mode: EMarginMode,
+    ///  全仓模式下作为共享保证金的余额
+    pub cross_margin_balance: ECrossMarginBalance,
+    ///  各交易对的分层维持保证金表
+    pub maintenance_margin_tables: HashMap<ETradingPairType, SMaintenanceMarginTable>,
+    ///  强平手续费
+    pub liquidation_fee: Decimal,
     ///  回测起始日期
     pub date_from: DateTime<Local>,
     ///  回测结束日期
@@ -17,12 +63,44 @@ pub struct SBackTradeRunnerConfig {
 }
 
 impl Default for SBackTradeRunnerConfig {
+    /// 回测周期取默认的回测周期预设
     fn default() -> Self {
+        let (date_from, date_to) = EPeriodPreset::default().get_period();
         Self {
             taker_order_fee: Decimal::from_f64(TAKER_ORDER_FEE).unwrap(),
             maker_order_fee: Decimal::from_f64(MAKER_ORDER_FEE).unwrap(),
-            date_from: config_date_from(),
-            date_to: config_date_to(),
+            taker_slippage: Decimal::from_f64(TAKER_SLIPPAGE).unwrap(),
+            fill_model: SFillModelConfig::default(),
+            stale_price_policy: EStalePricePolicy::default(),
+            strategy_interval: EKlineInterval::default(),
+            margin_mode: EMarginMode::default(),
+            cross_margin_balance: ECrossMarginBalance::default(),
+            maintenance_margin_tables: Self::default_maintenance_margin_tables(),
+            liquidation_fee: Decimal::from_f64(LIQUIDATION_FEE).unwrap(),
+            date_from,
+            date_to,
+        }
+    }
+}
+
+impl SBackTradeRunnerConfig {
+    /// 成交手续费率 吃单使用吃单手续费 挂单使用挂单手续费
+    pub fn get_order_fee(&self, is_taker: bool) -> Decimal {
+        if is_taker {
+            self.taker_order_fee
+        } else {
+            self.maker_order_fee
         }
     }
-}
\ No newline at end of file
+
+    /// 默认的分层维持保证金表（所有合约交易对）
+    pub fn default_maintenance_margin_tables() -> HashMap<ETradingPairType, SMaintenanceMarginTable> {
+        let mut tables = HashMap::new();
+        for tp_type in [ETradingPairType::BtcUsdtFuture, ETradingPairType::BtcUsdCmFuture] {
+            if let Some(table) = SMaintenanceMarginTable::default_for(tp_type) {
+                tables.insert(tp_type, table);
+            }
+        }
+        tables
+    }
+}
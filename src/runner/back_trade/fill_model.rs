@@ -0,0 +1,244 @@
+//! 成交模型 决定单根k线内挂单是否成交以及能够成交的数量
+use std::hash::{DefaultHasher, Hash, Hasher};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use rust_decimal::Decimal;
+use rust_decimal::prelude::FromPrimitive;
+use crate::data_runtime::order::EOrderAction;
+use crate::data_runtime::order::order_v3::SOrderV3;
+use crate::data_source::kline::SKlineUnitData;
+use crate::runner::back_trade::price_path::EIntraBarPathModel;
+
+/// 挂单成交模型
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum EMakerFillModel {
+    /// 触价成交 k线最低价（最高价）触及挂单价格即成交
+    #[default]
+    Touch,
+    /// 穿价成交 k线价格严格越过挂单价格才成交
+    TradeThrough,
+    /// 排队成交 挂单价格处排在订单前面的挂单量在[0, queue_depth)内随机分布
+    /// 穿过挂单价格的成交量消耗完排在前面的挂单和订单本身后才成交
+    Queue {
+        /// 挂单价格处的排队深度（以基础资产计）
+        queue_depth: Decimal,
+        /// 随机种子 相同种子下回测结果可复现
+        seed: u64,
+    },
+}
+
+impl EMakerFillModel {
+    /// 挂单在k线内是否成交
+    pub fn is_filled(&self, kline: &SKlineUnitData, order: &SOrderV3) -> bool {
+        let price = order.get_price();
+        let (is_touched, is_crossed) = match order.get_action() {
+            EOrderAction::Buy => { (price >= kline.low_price, price > kline.low_price) }
+            EOrderAction::Sell => { (price <= kline.high_price, price < kline.high_price) }
+        };
+        match self {
+            EMakerFillModel::Touch => { is_touched }
+            EMakerFillModel::TradeThrough => { is_crossed }
+            EMakerFillModel::Queue { queue_depth, seed } => {
+                if !is_touched {
+                    return false;
+                }
+                let through_volume = SFillModelConfig::get_volume_through_price(kline, order.get_action(), price);
+                let queue_ahead = *queue_depth * Self::get_queue_position(*seed, kline, order);
+                through_volume > Decimal::from(0) && through_volume >= queue_ahead + order.get_quantity().abs()
+            }
+        }
+    }
+
+    /// 订单在挂单价格处的相对排队位置[0, 1) 由种子、k线时间、订单方向和价格决定
+    /// 同一根k线内相同价格的订单排队位置相同
+    fn get_queue_position(seed: u64, kline: &SKlineUnitData, order: &SOrderV3) -> Decimal {
+        let mut hasher = DefaultHasher::new();
+        (seed, kline.open_time.timestamp_millis(), order.get_action() == EOrderAction::Buy, order.get_price()).hash(&mut hasher);
+        let position: f64 = StdRng::seed_from_u64(hasher.finish()).random();
+        Decimal::from_f64(position).unwrap_or_default()
+    }
+}
+
+/// 成交模型配置
+#[derive(Debug, Clone, Default)]
+pub struct SFillModelConfig {
+    /// 挂单成交模型
+    pub maker_fill_model: EMakerFillModel,
+    /// k线内价格路径模型 决定挂单的成交顺序
+    pub intra_bar_path: EIntraBarPathModel,
+    /// 单根k线内 订单成交量不超过穿过订单价格的k线成交量的比例（以基础资产计）
+    /// None时不限制成交量 订单全部成交
+    pub max_volume_ratio: Option<Decimal>,
+}
+
+impl SFillModelConfig {
+    /// 估算k线中穿过价格的成交量
+    /// 假设成交量在最低价和最高价之间均匀分布 买单取低于价格的部分 卖单取高于价格的部分
+    pub fn get_volume_through_price(kline: &SKlineUnitData, action: EOrderAction, price: Decimal) -> Decimal {
+        let range = kline.high_price - kline.low_price;
+        if range <= Decimal::from(0) {
+            return kline.volume;
+        }
+        let through_range = match action {
+            EOrderAction::Buy => { price - kline.low_price }
+            EOrderAction::Sell => { kline.high_price - price }
+        };
+        kline.volume * (through_range / range).max(Decimal::from(0)).min(Decimal::from(1))
+    }
+
+    /// 挂单在k线内是否成交
+    pub fn is_maker_filled(&self, kline: &SKlineUnitData, order: &SOrderV3) -> bool {
+        self.maker_fill_model.is_filled(kline, order)
+    }
+
+    /// 订单在k线内可成交的总量 None为不限制
+    /// 吃单在任意价格成交 使用k线的全部成交量
+    pub fn get_fill_capacity(&self, kline: &SKlineUnitData, order: &SOrderV3) -> Option<Decimal> {
+        let max_volume_ratio = self.max_volume_ratio?;
+        let volume = if order.is_taker() {
+            kline.volume
+        } else {
+            Self::get_volume_through_price(kline, order.get_action(), order.get_price())
+        };
+        Some(volume * max_volume_ratio)
+    }
+}
+
+/// 单根k线内的成交量统计 按买卖方向分别扣减可成交量
+#[derive(Debug)]
+pub struct SKlineFillCapacity<'a> {
+    fill_model: &'a SFillModelConfig,
+    kline: &'a SKlineUnitData,
+    /// 已成交的买入数量
+    filled_buy_quantity: Decimal,
+    /// 已成交的卖出数量
+    filled_sell_quantity: Decimal,
+}
+
+impl<'a> SKlineFillCapacity<'a> {
+    pub fn new(fill_model: &'a SFillModelConfig, kline: &'a SKlineUnitData) -> Self {
+        Self {
+            fill_model,
+            kline,
+            filled_buy_quantity: Decimal::from(0),
+            filled_sell_quantity: Decimal::from(0),
+        }
+    }
+
+    /// 订单本次剩余的可成交数量（绝对值） None为不限制
+    pub fn get_capacity(&self, order: &SOrderV3) -> Option<Decimal> {
+        let capacity = self.fill_model.get_fill_capacity(self.kline, order)?;
+        let filled_quantity = match order.get_action() {
+            EOrderAction::Buy => { self.filled_buy_quantity }
+            EOrderAction::Sell => { self.filled_sell_quantity }
+        };
+        Some((capacity - filled_quantity).max(Decimal::from(0)))
+    }
+
+    /// 扣减已成交订单的数量
+    pub fn consume(&mut self, order: &SOrderV3) {
+        match order.get_action() {
+            EOrderAction::Buy => { self.filled_buy_quantity += order.get_quantity().abs(); }
+            EOrderAction::Sell => { self.filled_sell_quantity += order.get_quantity().abs(); }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Local;
+    use rust_decimal::Decimal;
+    use crate::data_runtime::order::{EOrderAction, EOrderType};
+    use crate::data_runtime::order::order_v3::SOrderV3;
+    use crate::data_source::kline::SKlineUnitData;
+    use crate::data_source::trading_pair::ETradingPairType;
+    use crate::runner::back_trade::fill_model::{EMakerFillModel, SFillModelConfig, SKlineFillCapacity};
+
+    fn get_kline() -> SKlineUnitData {
+        let open_time = Local::now();
+        SKlineUnitData {
+            open_time,
+            close_time: open_time,
+            open_price: Decimal::from(100),
+            close_price: Decimal::from(100),
+            high_price: Decimal::from(110),
+            low_price: Decimal::from(90),
+            volume: Decimal::from(20),
+        }
+    }
+
+    #[test]
+    pub fn test_volume_through_price() {
+        let kline = get_kline();
+        assert_eq!(SFillModelConfig::get_volume_through_price(&kline, EOrderAction::Buy, Decimal::from(95)), Decimal::from(5));
+        assert_eq!(SFillModelConfig::get_volume_through_price(&kline, EOrderAction::Sell, Decimal::from(95)), Decimal::from(15));
+        assert_eq!(SFillModelConfig::get_volume_through_price(&kline, EOrderAction::Buy, Decimal::from(120)), Decimal::from(20));
+        assert_eq!(SFillModelConfig::get_volume_through_price(&kline, EOrderAction::Sell, Decimal::from(120)), Decimal::from(0));
+    }
+
+    #[test]
+    pub fn test_fill_capacity() {
+        let kline = get_kline();
+        let order = SOrderV3::new_buy_order(ETradingPairType::BtcUsdt, Decimal::from(100), Decimal::from(10));
+
+        // 默认不限制成交量
+        let fill_model = SFillModelConfig::default();
+        assert_eq!(SKlineFillCapacity::new(&fill_model, &kline).get_capacity(&order), None);
+
+        // 穿过100的成交量为10 最多成交10%
+        let fill_model = SFillModelConfig { max_volume_ratio: Some(Decimal::new(1, 1)), ..SFillModelConfig::default() };
+        let mut fill_capacity = SKlineFillCapacity::new(&fill_model, &kline);
+        assert_eq!(fill_capacity.get_capacity(&order), Some(Decimal::from(1)));
+        fill_capacity.consume(&SOrderV3::new_buy_order(ETradingPairType::BtcUsdt, Decimal::from(100), Decimal::new(4, 1)));
+        assert_eq!(fill_capacity.get_capacity(&order), Some(Decimal::new(6, 1)));
+        // 卖出方向单独统计
+        let sell_order = SOrderV3::new_sell_order(ETradingPairType::BtcUsdt, Decimal::from(100), Decimal::from(10));
+        assert_eq!(fill_capacity.get_capacity(&sell_order), Some(Decimal::from(1)));
+
+        // 吃单使用k线的全部成交量
+        let taker_order = order.with_order_type(EOrderType::Market);
+        assert_eq!(fill_capacity.get_capacity(&taker_order), Some(Decimal::new(16, 1)));
+    }
+
+    #[test]
+    pub fn test_maker_fill_model() {
+        let kline = get_kline();
+        let touch_buy_order = SOrderV3::new_buy_order(ETradingPairType::BtcUsdt, Decimal::from(90), Decimal::from(1));
+        let touch_sell_order = SOrderV3::new_sell_order(ETradingPairType::BtcUsdt, Decimal::from(110), Decimal::from(1));
+        let through_buy_order = SOrderV3::new_buy_order(ETradingPairType::BtcUsdt, Decimal::from(95), Decimal::from(1));
+        let miss_buy_order = SOrderV3::new_buy_order(ETradingPairType::BtcUsdt, Decimal::from(89), Decimal::from(1));
+
+        // 触价成交
+        let fill_model = EMakerFillModel::Touch;
+        assert!(fill_model.is_filled(&kline, &touch_buy_order));
+        assert!(fill_model.is_filled(&kline, &touch_sell_order));
+        assert!(fill_model.is_filled(&kline, &through_buy_order));
+        assert!(!fill_model.is_filled(&kline, &miss_buy_order));
+
+        // 穿价成交 仅触及价格时不成交
+        let fill_model = EMakerFillModel::TradeThrough;
+        assert!(!fill_model.is_filled(&kline, &touch_buy_order));
+        assert!(!fill_model.is_filled(&kline, &touch_sell_order));
+        assert!(fill_model.is_filled(&kline, &through_buy_order));
+        assert!(!fill_model.is_filled(&kline, &miss_buy_order));
+
+        // 排队成交 穿过95的成交量为5
+        let fill_model = EMakerFillModel::Queue { queue_depth: Decimal::from(0), seed: 0 };
+        assert!(!fill_model.is_filled(&kline, &touch_buy_order));
+        assert!(fill_model.is_filled(&kline, &through_buy_order));
+        let large_buy_order = SOrderV3::new_buy_order(ETradingPairType::BtcUsdt, Decimal::from(95), Decimal::from(6));
+        assert!(!fill_model.is_filled(&kline, &large_buy_order));
+        // 排在前面的挂单量超过穿价成交量时不成交
+        let fill_model = EMakerFillModel::Queue { queue_depth: Decimal::from(1_000_000), seed: 0 };
+        assert!(!fill_model.is_filled(&kline, &through_buy_order));
+        // 相同种子结果可复现 排队深度越大成交的订单越少
+        let orders: Vec<SOrderV3> = (91..110)
+            .map(|price| SOrderV3::new_buy_order(ETradingPairType::BtcUsdt, Decimal::from(price), Decimal::new(1, 1)))
+            .collect();
+        let count_filled = |fill_model: &EMakerFillModel| orders.iter().filter(|order| fill_model.is_filled(&kline, order)).count();
+        let fill_model = EMakerFillModel::Queue { queue_depth: Decimal::from(10), seed: 42 };
+        assert_eq!(count_filled(&fill_model), count_filled(&fill_model.clone()));
+        assert!(count_filled(&fill_model) <= count_filled(&EMakerFillModel::TradeThrough));
+        assert!(count_filled(&EMakerFillModel::Queue { queue_depth: Decimal::from(20), seed: 42 }) <= count_filled(&fill_model));
+    }
+}
@@ -1,3 +1,5 @@
 pub mod runner;
 pub mod config;
-pub mod runner_leveraged;
+pub mod fill_model;
+pub mod price_path;
+pub mod settlement;
@@ -0,0 +1,148 @@
+//! k线内价格路径模型 决定单根k线内挂单的成交顺序
+//!
+//! 价格路径为k线内依次经过的价格点，每到达一个价格点时结算价格穿过的挂单：
+//! 价格不高于买单价格时买单成交，价格不低于卖单价格时卖单成交
+use std::hash::{DefaultHasher, Hash, Hasher};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use rust_decimal::Decimal;
+use rust_decimal::prelude::{FromPrimitive, ToPrimitive};
+use crate::data_source::kline::SKlineUnitData;
+
+/// k线内价格路径模型
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum EIntraBarPathModel {
+    /// 先结算所有买单再结算所有卖单 路径为最低价→最高价
+    #[default]
+    BuyThenSell,
+    /// OHLC顺序 阳线按开盘价→最低价→最高价→收盘价 阴线按开盘价→最高价→最低价→收盘价
+    Ohlc,
+    /// 随机布朗桥 在开盘价与收盘价之间生成经过最高价和最低价的随机路径
+    BrownianBridge {
+        /// 路径步数（不少于3）
+        steps: usize,
+        /// 随机种子 与k线开盘时间共同决定路径
+        seed: u64,
+    },
+}
+
+impl EIntraBarPathModel {
+    /// 生成k线内的价格路径
+    pub fn get_path(&self, kline: &SKlineUnitData) -> Vec<Decimal> {
+        match self {
+            EIntraBarPathModel::BuyThenSell => { vec![kline.low_price, kline.high_price] }
+            EIntraBarPathModel::Ohlc => { Self::get_ohlc_path(kline) }
+            EIntraBarPathModel::BrownianBridge { steps, seed } => {
+                Self::get_brownian_bridge_path(kline, *steps, *seed).unwrap_or_else(|| Self::get_ohlc_path(kline))
+            }
+        }
+    }
+
+    fn get_ohlc_path(kline: &SKlineUnitData) -> Vec<Decimal> {
+        if kline.close_price >= kline.open_price {
+            vec![kline.open_price, kline.low_price, kline.high_price, kline.close_price]
+        } else {
+            vec![kline.open_price, kline.high_price, kline.low_price, kline.close_price]
+        }
+    }
+
+    /// 布朗桥路径 首尾为开盘价和收盘价，路径中间的最低点和最高点分别修正为最低价和最高价
+    /// 价格无法转换或步数不足时返回None
+    fn get_brownian_bridge_path(kline: &SKlineUnitData, steps: usize, seed: u64) -> Option<Vec<Decimal>> {
+        if steps < 3 || kline.high_price <= kline.low_price {
+            return None;
+        }
+        let open = kline.open_price.to_f64()?;
+        let close = kline.close_price.to_f64()?;
+        let volatility = (kline.high_price - kline.low_price).to_f64()? / (steps as f64).sqrt();
+
+        let mut hasher = DefaultHasher::new();
+        (seed, kline.open_time.timestamp_millis()).hash(&mut hasher);
+        let mut rng = StdRng::seed_from_u64(hasher.finish());
+
+        // 随机游走 再减去线性漂移得到首尾为0的布朗桥
+        let mut walk = vec![0.0; steps + 1];
+        for i in 1..=steps {
+            walk[i] = walk[i - 1] + Self::sample_standard_normal(&mut rng) * volatility;
+        }
+        let mut path: Vec<f64> = (0..=steps)
+            .map(|i| {
+                let t = i as f64 / steps as f64;
+                open + (close - open) * t + walk[i] - walk[steps] * t
+            })
+            .collect();
+
+        // 路径中间的最低点和最高点修正为最低价和最高价
+        let inner = 1..steps;
+        let low_index = inner.clone().min_by(|a, b| path[*a].total_cmp(&path[*b]))?;
+        let high_index = inner.filter(|i| *i != low_index).max_by(|a, b| path[*a].total_cmp(&path[*b]))?;
+        let (low, high) = (kline.low_price.to_f64()?, kline.high_price.to_f64()?);
+        for price in path.iter_mut() {
+            *price = price.clamp(low, high);
+        }
+
+        let mut result: Vec<Decimal> = path.into_iter().map(Decimal::from_f64).collect::<Option<_>>()?;
+        result[0] = kline.open_price;
+        result[steps] = kline.close_price;
+        result[low_index] = kline.low_price;
+        result[high_index] = kline.high_price;
+        Some(result)
+    }
+
+    /// Box-Muller变换生成标准正态分布样本
+    fn sample_standard_normal(rng: &mut StdRng) -> f64 {
+        let u1: f64 = 1.0 - rng.random::<f64>();
+        let u2: f64 = rng.random();
+        (-2.0 * u1.ln()).sqrt() * (2.0 * std::f64::consts::PI * u2).cos()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Local, TimeZone};
+    use rust_decimal::Decimal;
+    use crate::data_source::kline::SKlineUnitData;
+    use crate::runner::back_trade::price_path::EIntraBarPathModel;
+
+    fn get_kline(open_price: i64, close_price: i64) -> SKlineUnitData {
+        let open_time = Local.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+        SKlineUnitData {
+            open_time,
+            close_time: open_time,
+            open_price: Decimal::from(open_price),
+            close_price: Decimal::from(close_price),
+            high_price: Decimal::from(110),
+            low_price: Decimal::from(90),
+            volume: Decimal::from(20),
+        }
+    }
+
+    #[test]
+    pub fn test_ohlc_path() {
+        let path = EIntraBarPathModel::Ohlc.get_path(&get_kline(100, 105));
+        assert_eq!(path, [100, 90, 110, 105].map(Decimal::from).to_vec());
+        let path = EIntraBarPathModel::Ohlc.get_path(&get_kline(100, 95));
+        assert_eq!(path, [100, 110, 90, 95].map(Decimal::from).to_vec());
+        let path = EIntraBarPathModel::BuyThenSell.get_path(&get_kline(100, 95));
+        assert_eq!(path, [90, 110].map(Decimal::from).to_vec());
+    }
+
+    #[test]
+    pub fn test_brownian_bridge_path() {
+        let kline = get_kline(100, 105);
+        let path_model = EIntraBarPathModel::BrownianBridge { steps: 60, seed: 42 };
+        let path = path_model.get_path(&kline);
+        assert_eq!(path.len(), 61);
+        assert_eq!(path.first(), Some(&kline.open_price));
+        assert_eq!(path.last(), Some(&kline.close_price));
+        assert!(path.contains(&kline.low_price));
+        assert!(path.contains(&kline.high_price));
+        assert!(path.iter().all(|price| *price >= kline.low_price && *price <= kline.high_price));
+        // 相同种子结果可复现
+        assert_eq!(path, path_model.get_path(&kline));
+
+        // 步数不足时使用OHLC顺序
+        let path = EIntraBarPathModel::BrownianBridge { steps: 2, seed: 42 }.get_path(&kline);
+        assert_eq!(path, EIntraBarPathModel::Ohlc.get_path(&kline));
+    }
+}
@@ -1,3 +1,5 @@
+//! 回测执行器 现货与合约交易对共用同一套k线驱动、撮合及日志流程
+//! 各品种在锁定资产、手续费和成交后获得资产上的差异由交易对的结算处理器处理
 use std::collections::HashMap;
 use chrono::{DateTime, Local};
 use log::{debug, error, info};
@@ -12,17 +14,21 @@ use crate::{data_runtime::{
 }, data_source::{
     data_manager::SDataManager,
     db::api::TDataApi,
-    kline::SKlineUnitData,
-    trading_pair::ETradingPairType,
-}, protocol::{ERunnerSyncActionResult, ERunnerParseOrderResult, EStrategyAction, SRunnerParseKlineResult}, runner::{
+    kline::{EKlineInterval, SKlineUnitData},
+    trading_pair::{trading_pair::STradingPairView, ETradingPairType},
+}, protocol::{ERunnerSyncActionResult, ERunnerParseOrderResult, EStrategyAction, SRunnerLiquidation, SRunnerParseKlineResult}, runner::{
     back_trade::config::SBackTradeRunnerConfig,
     TRunner,
 }, strategy::TStrategy};
-use crate::config::back_trade_period::SAMPLE_PERIOD;
 use crate::data_runtime::asset::asset_union::EAssetUnion;
-use crate::data_runtime::order::order_v3::SOrderV3;
+use crate::data_runtime::asset::margin::{ECrossMarginBalance, EMarginMode};
+use crate::data_runtime::asset::asset_map_v3::SAssetMapV3;
+use crate::data_runtime::order::order_manager_v3::SOrderManagerV3;
+use crate::data_runtime::order::order_v3::{EOrderUpdate, EOrderV3Error, SOrderV3};
 use crate::data_runtime::user::SUser;
 use crate::protocol::strategy_order::SStrategyOrderAdd;
+use crate::runner::back_trade::fill_model::SKlineFillCapacity;
+use crate::runner::back_trade::settlement::{ESettlementHandlerUnion, TSettlementHandler};
 use crate::runner::logger::data_logger::SDataLogger;
 use crate::runner::logger::kline_unit::SDataLogKlineUnit;
 use crate::runner::logger::transfer_unit::{SDataLogTransferExecutedUnit, SDataLogTransferUnfulfilledUnit, SDataLogTransferUnit};
@@ -39,6 +45,11 @@ pub enum EBackTradeRunnerError {
     AssetLockedNotEnoughError(EAssetType),
     DenominateSupportOnlyBtcAndUsdtError(EAssetType),
     MarginMustBeBtcOrUsdtError(EAssetUnion),
+    /// 超过最大杠杆倍数(交易对，名义价值，保证金，最大杠杆倍数)
+    LeverageExceededError(ETradingPairType, Decimal, Decimal, Decimal),
+    /// 可用资产不足(资产类型，所需量，可用量)
+    AssetAvailableNotEnoughError(EAssetType, Decimal, Decimal),
+    OrderV3Error(EOrderV3Error),
 }
 
 /// 回测执行器
@@ -52,19 +63,25 @@ pub struct SBackTradeRunner<D: TDataApi> {
     pub trading_pair_prices: HashMap<ETradingPairType, Decimal>,
     /// 数据日志
     pub data_logger: SDataLogger,
+    /// 各交易对的结算处理器 未设置时使用交易对默认的结算处理器
+    pub settlement_handlers: HashMap<ETradingPairType, ESettlementHandlerUnion>,
 }
 
 impl<S: TStrategy, D: TDataApi> TRunner<S> for SBackTradeRunner<D> {
     fn run(&mut self, users: &mut Vec<SUser<S>>, debug_config: SDebugConfig) -> SRunnerResult {
-        // 循环遍历k线 根据时间间隔1分钟
-        let mut current_date = self.config.date_from;
-        while current_date < self.config.date_to {
+        // 合并所有交易对的k线 按时间顺序依次处理 每个时刻只处理在该时刻有k线的交易对
+        let kline_events = self.data_manager.trading_pair_map.merge_kline_times(self.config.date_from, self.config.date_to);
+        // 各交易对最后一次更新报价的k线时间
+        let mut price_update_times: HashMap<ETradingPairType, DateTime<Local>> = HashMap::new();
+        // 策略周期k线收盘前累计的订单结果 key-(user_id, tp_type)
+        let mut pending_order_results: HashMap<(Uuid, ETradingPairType), Vec<ERunnerParseOrderResult>> = HashMap::new();
+        for (current_date, tp_types) in kline_events {
             if debug_config.is_info { info!("当前k线时间:\t{}", current_date) };
 
             // 用于记录报价
             let mut trading_pair_klines: HashMap<ETradingPairType, SKlineUnitData> = HashMap::new();
             // let mut trading_pair_prices: HashMap<ETradingPairType, Decimal> = HashMap::new();
-            self.trading_pair_prices.clear();
+            // self.trading_pair_prices.clear(); // 当价格不完整时 使用旧的报价
 
             // 用于记录交易量 key-user_id value-transfer_info
             let mut transfer_info_map: HashMap<Uuid, SDataLogTransferUnit> = HashMap::new();
@@ -73,18 +90,41 @@ impl<S: TStrategy, D: TDataApi> TRunner<S> for SBackTradeRunner<D> {
             let mut highest_buy_order_map: HashMap<(Uuid, ETradingPairType), Option<Decimal>> = HashMap::new();
             let mut lowest_sell_order_map: HashMap<(Uuid, ETradingPairType), Option<Decimal>> = HashMap::new();
 
-            let mut continue_flag = false;
-            // 在单分钟k线内遍历所有交易对
-            for (tp_type, trading_pair) in self.data_manager.trading_pair_map.inner.iter() {
-                // 获取k线数据
-                let option_kline = trading_pair.get_kline(&current_date);
-                if let None = option_kline {
-                    let err = EBackTradeRunnerError::KlineNotFoundError(tp_type.clone(), current_date.clone());
-                    error!("{:?}", err);
-                    continue_flag = true;
-                    continue;
+            // 先以该时刻的k线更新所有交易对的报价
+            for tp_type in tp_types.iter() {
+                match self.data_manager.trading_pair_map.get_kline(*tp_type, &current_date) {
+                    Ok(Some(kline_unit_data)) => {
+                        trading_pair_klines.insert(*tp_type, *kline_unit_data);
+                        self.trading_pair_prices.insert(*tp_type, kline_unit_data.close_price);
+                        price_update_times.insert(*tp_type, current_date);
+                    }
+                    Ok(None) => { error!("{:?}", EBackTradeRunnerError::KlineNotFoundError(*tp_type, current_date)); }
+                    Err(e) => { error!("{:?}", e); }
                 }
-                let kline_unit_data = option_kline.unwrap().clone();
+            }
+            // 存在报价过期的交易对时 不记录该时刻的日志 尚无报价的交易对不参与估值 不影响日志记录
+            let stale_tp_types: Vec<ETradingPairType> = price_update_times
+                .iter()
+                .filter(|(_, last_update_time)| self.config.stale_price_policy.is_stale(last_update_time, current_date))
+                .map(|(tp_type, _)| *tp_type)
+                .collect();
+            let is_log_ready = stale_tp_types.is_empty();
+            if !is_log_ready && debug_config.is_info { info!("报价过期 - 交易对: {:?}\t跳过日志时间:{}", stale_tp_types, current_date); }
+
+            // 遍历该时刻有k线的交易对
+            for tp_type in tp_types.iter() {
+                // 获取k线数据
+                let kline_unit_data = match trading_pair_klines.get(tp_type) {
+                    Some(kline_unit_data) => { *kline_unit_data }
+                    None => { continue; }
+                };
+                let trading_pair = match self.data_manager.trading_pair_map.get(*tp_type) {
+                    Ok(trading_pair) => { trading_pair }
+                    Err(e) => {
+                        error!("{:?}", e);
+                        continue;
+                    }
+                };
                 // 查询当前k线对应的资金费率
                 let funding_rate = trading_pair.get_funding_rate(&current_date).unwrap_or(&Decimal::from(0)).clone();
 
@@ -92,13 +132,18 @@ impl<S: TStrategy, D: TDataApi> TRunner<S> for SBackTradeRunner<D> {
                     info!("K线信息 - 交易对: {:?}\t开盘价:{}\t收盘价:{}\t最高价:{}\t最低价:{}\t资金费率:{:.4?}%", tp_type, kline_unit_data.open_price, kline_unit_data.close_price, kline_unit_data.high_price, kline_unit_data.low_price, funding_rate*Decimal::from(100));
                 }
 
-                // 记录日志
-                trading_pair_klines.insert(tp_type.clone(), kline_unit_data);
-                self.trading_pair_prices.entry(tp_type.clone())
-                    .and_modify(|e| *e = kline_unit_data.close_price)
-                    .or_insert(kline_unit_data.close_price);
+                // 策略周期的k线 未收盘时为None 不执行策略
+                let strategy_kline = self.get_strategy_kline(trading_pair, &kline_unit_data);
+
+                // dbg!(&self.trading_pair_prices);
 
                 for user in users.iter_mut() {
+                    // 资金费结算时刻 以开盘价结算杠杆资产的资金费
+                    if let Some(settle_funding_rate) = trading_pair.get_funding_rate(&current_date) {
+                        if let Some(funding_fee) = user.settle_funding(*tp_type, kline_unit_data.open_price, *settle_funding_rate) {
+                            if debug_config.is_info { info!("资金费结算 - 交易对: {:?}\t资金费率:{:.4?}%\t支付资金费:{:?}", tp_type, settle_funding_rate * Decimal::from(100), funding_fee); }
+                        }
+                    }
                     let (
                         runner_parse_result,
                         highest_buy_price,
@@ -119,33 +164,36 @@ impl<S: TStrategy, D: TDataApi> TRunner<S> for SBackTradeRunner<D> {
                             error!("{:?}", e);
                         }
                         Ok(runner_parse_result) => {
-                            // 将增量数据传输给策略模块，获取策略行为。
-                            // 将策略行为进行排序 cancel order在前 new order在后
                             // 记录transfer info
                             let transfer_info_executed = Self::get_parse_new_kline_transfer_info(&runner_parse_result);
-                            // info!("runner_parse_result.order_result: {:?}", runner_parse_result.order_result);
-                            let strategy_actions = user.get_strategy_result(runner_parse_result, &debug_config);
-                            // info!("strategy_actions:");
-                            // for action in strategy_actions.iter() {
-                            //     info!("action: {:?}", action);
-                            // }
-
-                            // 根据策略行为，调整订单数据。
-                            let parse_action_results = self.sync_strategy_action(
-                                strategy_actions,
-                                tp_type,
-                                user,
-                                &debug_config,
-                            );
-                            // info!("parse_action_results:");
-                            // for action in parse_action_results.iter() {
-                            //     info!("action: {:?}", action);
-                            // }
+                            // 累计订单结果 在策略周期k线收盘时一并传输给策略模块
+                            let order_results = pending_order_results.entry((user.id, *tp_type)).or_default();
+                            order_results.extend(runner_parse_result.order_result);
+                            let parse_action_results = match strategy_kline {
+                                None => { Vec::new() }
+                                Some((new_kline, new_funding_rate)) => {
+                                    let runner_parse_result = SRunnerParseKlineResult {
+                                        tp_type: *tp_type,
+                                        new_kline,
+                                        new_funding_rate,
+                                        order_result: std::mem::take(order_results),
+                                    };
+                                    // 将增量数据传输给策略模块，获取策略行为。
+                                    // 将策略行为进行排序 cancel order在前 new order在后
+                                    let strategy_actions = user.get_strategy_result(runner_parse_result, &debug_config);
+                                    // 根据策略行为，调整订单数据。
+                                    self.sync_strategy_action(
+                                        strategy_actions,
+                                        tp_type,
+                                        user,
+                                        &debug_config,
+                                    )
+                                }
+                            };
                             // 记录transfer info
                             let transfer_info_unfulfilled = Self::get_sync_strategy_action_transfer_info(&parse_action_results);
                             let transfer_info = SDataLogTransferUnit::from(transfer_info_unfulfilled, transfer_info_executed);
                             transfer_info_map.entry(user.id).and_modify(|v| {
-                                v.executed_buy_order_cnt += transfer_info.executed_buy_order_cnt;
                                 v.unfulfilled_buy_order_cnt += transfer_info.unfulfilled_buy_order_cnt;
                                 v.unfulfilled_sell_order_cnt += transfer_info.unfulfilled_sell_order_cnt;
                                 v.executed_buy_order_cnt += transfer_info.executed_buy_order_cnt;
@@ -154,17 +202,20 @@ impl<S: TStrategy, D: TDataApi> TRunner<S> for SBackTradeRunner<D> {
                                 v.unfulfilled_sell_usdt_cnt += transfer_info.unfulfilled_sell_usdt_cnt;
                                 v.executed_buy_usdt_cnt += transfer_info.executed_buy_usdt_cnt;
                                 v.executed_sell_usdt_cnt += transfer_info.executed_sell_usdt_cnt;
+                                v.liquidated_cnt += transfer_info.liquidated_cnt;
                             }).or_insert(transfer_info);
                             // 向策略模块反馈校验、调整结果
-                            user.strategy.verify(tp_type, parse_action_results, &debug_config);
+                            if strategy_kline.is_some() {
+                                user.strategy.verify(tp_type, parse_action_results, &debug_config);
+                            }
                         }
                     }
+                    // 根据最新价格 更新杠杆资产的数据
+                    user.available_assets.update_leveraged(&self.trading_pair_prices);
                 }
             }
 
-            if continue_flag {
-                // 时间递增
-                current_date += SAMPLE_PERIOD;
+            if !is_log_ready {
                 continue;
             }
             // 记录日志
@@ -178,23 +229,35 @@ impl<S: TStrategy, D: TDataApi> TRunner<S> for SBackTradeRunner<D> {
                 }
                 // debug!("transfer_info_map: {:?}", transfer_info_map);
                 // debug!("user.id: {:?}", user.id);
-                let transfer_info = transfer_info_map.get(&user.id).unwrap();
+                // dbg!(&user.tp_order_map);
+
+                // 该时刻所有交易对的k线解析均失败时 没有交易数据 以空的交易数据记录日志
+                let transfer_info = match transfer_info_map.get(&user.id) {
+                    Some(transfer_info) => { transfer_info.clone() }
+                    None => {
+                        error!("用户{:?}在{:?}没有交易数据 以空的交易数据记录日志", user.id, current_date);
+                        SDataLogTransferUnit::default()
+                    }
+                };
                 let target_position_ratio = Some(Decimal::from(user.strategy.get_log_info().target_position_ratio));
-                let user_data = SDataLogUserUnit::new(current_date, user, target_position_ratio, &self.trading_pair_prices, transfer_info);
+                let user_data = SDataLogUserUnit::new(current_date, user, target_position_ratio, &self.trading_pair_prices, &transfer_info);
                 let position_ratio = (user_data.total_assets_usdt - user_data.total_usdt) / user_data.total_assets_usdt * Decimal::from(100);
 
                 if debug_config.is_info {
-                    info!("用户信息:{:?}\t仓位:{:.2?}%\t资产 {:.4?}\t现金 {:.4?}\t累计手续费 {:.4?}\t买单数量:{:?}\t卖单数量:{:?}",
-                    user_data.user_name, position_ratio, user_data.total_assets_usdt, user_data.total_usdt, user_data.total_fee_usdt, buy_order_num, sell_order_num);
+                    info!("用户信息:{:?}\t仓位:{:.2?}%\t总资产 {:.4?}\t资产 {:.4?}\t现金 {:.4?}\t累计手续费 {:.4?}\t累计资金费 {:.4?}\t买单数量:{:?}\t卖单数量:{:?}",
+                        user_data.user_name, 
+                        position_ratio, 
+                        user_data.total_assets_usdt, 
+                        user_data.total_assets_usdt - user_data.total_usdt,
+                        user_data.total_usdt, 
+                        user_data.total_fee_usdt, 
+                        user_data.total_funding_fee_usdt, 
+                        buy_order_num, 
+                        sell_order_num
+                    );
                 }
                 self.data_logger.add_user_data(user_data);
             }
-
-            // 时间递增
-            current_date += SAMPLE_PERIOD;
-            // current_date += Duration::minutes(1);
-
-            // thread::sleep(std::time::Duration::from_secs(2));
         }
         // 回测结束 输出结果
         // self.data_logger.output_user(String::from(format!("data/back_trade/{}.csv", Local::now().format("%Y%m%d_%H%M%S"))));
@@ -225,9 +288,45 @@ impl<D: TDataApi> SBackTradeRunner<D> {
             data_manager,
             trading_pair_prices: Default::default(),
             data_logger: Default::default(),
+            settlement_handlers: Default::default(),
         }
     }
 
+    /// 设置交易对的结算处理器
+    pub fn with_settlement_handler(mut self, tp_type: ETradingPairType, handler: ESettlementHandlerUnion) -> Self {
+        self.settlement_handlers.insert(tp_type, handler);
+        self
+    }
+
+    /// 获取交易对的结算处理器
+    pub fn get_settlement_handler(&self, tp_type: ETradingPairType) -> ESettlementHandlerUnion {
+        self.settlement_handlers
+            .get(&tp_type)
+            .copied()
+            .unwrap_or_else(|| ESettlementHandlerUnion::from(tp_type))
+    }
+
+    /// 策略周期的k线及周期内的资金费率之和 策略周期k线未收盘时返回None
+    /// 下一根k线不属于同一周期、不存在或超出回测区间（时间窗口）时 视为策略周期k线收盘
+    fn get_strategy_kline(&self, trading_pair: STradingPairView, kline_unit_data: &SKlineUnitData) -> Option<(SKlineUnitData, Decimal)> {
+        let interval = self.config.strategy_interval;
+        let time = kline_unit_data.open_time;
+        if interval == EKlineInterval::Minute1 {
+            let funding_rate = trading_pair.get_funding_rate(&time).copied().unwrap_or_default();
+            return Some((*kline_unit_data, funding_rate));
+        }
+        let open_time = interval.get_open_time(&time);
+        if let Some(next_kline) = trading_pair.next_kline(&time) {
+            let is_in_range = next_kline.open_time < self.config.date_to;
+            if is_in_range && interval.get_open_time(&next_kline.open_time) == open_time {
+                return None;
+            }
+        }
+        let funding_rate = trading_pair.range_funding_rate(open_time, time)
+            .map(|funding_rates| funding_rates.map(|(_, unit_data)| unit_data.funding_rate).sum())
+            .unwrap_or_default();
+        Some((trading_pair.get_bar(interval, &time)?, funding_rate))
+    }
 
     /// 处理新的k线和资金费率，更新订单和资产，记录增量处理结果。
     fn parse_new_kline<S: TStrategy>(
@@ -241,9 +340,10 @@ impl<D: TDataApi> SBackTradeRunner<D> {
     {
         // 根据K线 结算订单数据 结算资产数据
         let mut order_results: Vec<ERunnerParseOrderResult> = Vec::new(); // 订单已成交列表
-        let base_asset_type = tp_type.get_base_currency_type();
-        let quote_asset_type = tp_type.get_quote_currency_type();
-        let maker_order_fee = self.config.maker_order_fee;
+        // 优先检查杠杆资产是否触发强平
+        if let Some(liquidation) = self.liquidate_leveraged(tp_type, kline_unit_data, user, debug_config) {
+            order_results.push(ERunnerParseOrderResult::Liquidated(liquidation));
+        }
         let user_asset_manager = &mut user.available_assets;
         let order_manager = user.tp_order_map.get_mut(tp_type).unwrap();
 
@@ -257,105 +357,110 @@ impl<D: TDataApi> SBackTradeRunner<D> {
         };
         if debug_config.is_info { info!("盘口信息 - 交易对: {:?}\t买一价格:{:?}\t卖一价格:{:?}", tp_type, highest_buy_price, lowest_sell_price); }
 
-        // 买单结算 用quote_currency换base_current
-        while let Some(order) = order_manager.peek_highest_buy_order().unwrap() {
-            // 操作方向校验
-            if order.get_action() != EOrderAction::Buy {
-                log::error!("EOrderAction Error: Expected Buy - Actually {:?}", order.get_action());
-            }
-            // 挂单价格大于等于当前k线最低价格，则买单成交。
-            if order.get_price() < kline_unit_data.low_price {
-                break;
-            }
-            let mut order = order_manager.pop_highest_buy_order().unwrap().unwrap();
-            let base_quantity = order.get_quantity();
-            let quote_quantity = order.get_amount();
-            // 计算手续费(计价资产)
-            let fee_quote_asset = EAssetUnion::from(SAsset {
-                as_type: quote_asset_type,
-                balance: quote_quantity * maker_order_fee,
-            });
-            // 计算手续费(USDT计价)
-            let fee_usdt = SAsset {
-                as_type: EAssetType::Usdt,
-                balance: assets_denominate_usdt(&fee_quote_asset, &self.trading_pair_prices),
-            };
-            // 结算资产
-            // 提取订单锁定的计价资产 生成基础资产
-            match order.execute(Some(fee_usdt)) {
-                // consumed_quote_asset会被自动析构 代表订单的锁定资产被消耗
-                Ok(_consumed_quote_asset) => {
-                    // 用户获得基础资产
-                    let obtain_base_asset = EAssetUnion::from(SAsset {
-                        as_type: base_asset_type,
-                        balance: base_quantity - base_quantity * maker_order_fee,
-                    });
-
-                    if debug_config.is_debug {
-                        debug!("结算买单: {:?}\t挂单价:{:?}\t挂单量:{:?}\t手续费:{:?}\t用户获得资产:{:?}\t用户消耗资产:{:?}", order.get_id(),order.get_price(), order.get_quantity(), &fee_quote_asset, &obtain_base_asset, &_consumed_quote_asset);
-                    }
+        // 按成交模型限制单根k线内的成交量 部分成交后剩余的订单在本根k线结算完成后重新挂单
+        let mut fill_capacity = SKlineFillCapacity::new(&self.config.fill_model, kline_unit_data);
+        let mut remaining_orders: Vec<SOrderV3> = Vec::new();
 
-                    user_asset_manager.merge_asset(obtain_base_asset);
-                    order_results.push(ERunnerParseOrderResult::OrderExecuted(order.clone()));
-                    if let Err(e) = order_manager.add_finished_order(order) {
+        // 吃单以开盘价成交 已触发的条件单以触发价格成交（均含滑点）
+        let mut taker_orders: Vec<(SOrderV3, Decimal)> = order_manager.pop_taker_orders()
+            .into_iter()
+            .map(|order| (order, kline_unit_data.open_price))
+            .collect();
+        for (mut order, trigger_price) in order_manager.trigger_orders(kline_unit_data) {
+            if debug_config.is_debug { debug!("条件单触发: {:?}\t订单类型:{:?}\t触发价格:{:?}", order.get_id(), order.get_order_type(), trigger_price); }
+            // 条件单在触发时锁定保证金资产 可用资产不足时（如同一仓位的另一条件单已先行平仓）取消订单
+            if let Err(e) = self.lock_triggered_order(&mut order, user_asset_manager) {
+                if debug_config.is_debug { debug!("条件单取消: {:?}\t{:?}", order.get_id(), e); }
+                order.cancel();
+                order_results.push(ERunnerParseOrderResult::OrderCanceled(order));
+                continue;
+            }
+            taker_orders.push((order, trigger_price));
+        }
+        for (mut order, reference_price) in taker_orders {
+            match order.get_taker_fill_price(reference_price, self.config.taker_slippage) {
+                None => {
+                    // 成交价格已越过限价 转为挂单
+                    order.set_taker(false);
+                    if let Err(e) = order_manager.insert_order(order) {
                         error!("{:?}", e);
                     }
                 }
-                Err(e) => {
-                    error!("{:?}", e);
+                Some(fill_price) => {
+                    order.set_taker(true);
+                    order.update(EOrderUpdate::Price(fill_price));
+                    // 锁定资产不足以按成交价格全部成交时 按锁定资产可成交的数量成交 剩余数量不再成交
+                    if let Some(max_quantity) = self.get_settlement_handler(*tp_type).get_taker_max_quantity(&order) {
+                        if debug_config.is_debug { debug!("锁定资产不足 限制吃单成交量: {:?}\t下单量:{:?}\t成交量:{:?}", order.get_id(), order.get_quantity(), max_quantity); }
+                        order.update(EOrderUpdate::Quantity(max_quantity));
+                    }
+                    let (order_result, remaining_order) = self.settle_order_in_capacity(order, &mut fill_capacity, user_asset_manager, order_manager, debug_config);
+                    order_results.extend(order_result);
+                    remaining_orders.extend(remaining_order);
                 }
             }
         }
 
-        // 卖单结算 用base_current换quote_currency
-        while let Some(order) = order_manager.peek_lowest_sell_order().unwrap() {
-            // 操作方向校验
-            if order.get_action() != EOrderAction::Sell {
-                error!("EOrderAction Error: Expected Sell - Actually {:?}", order.get_action());
-            }
-            // 挂单价格大于等于当前k线最低价格，则买单成交。
-            if order.get_price() > kline_unit_data.high_price {
-                break;
-            }
-            let mut order = order_manager.pop_lowest_sell_order().unwrap().unwrap();
-            let base_quantity = order.get_quantity();
-            // 计算手续费(基础资产)
-            let fee_base_asset = EAssetUnion::from(SAsset {
-                as_type: base_asset_type,
-                balance: base_quantity * maker_order_fee,
-            });
-            // 计算手续费(USDT计价)
-            let fee_usdt = SAsset {
-                as_type: EAssetType::Usdt,
-                balance: assets_denominate_usdt(&fee_base_asset, &self.trading_pair_prices),
-            };
-            let quote_quantity = order.get_amount();
-            // 结算资产
-            // 提取订单锁定的基础资产 生成计价资产
-            match order.execute(Some(fee_usdt)) {
-                // consumed_base_asset会被自动析构 代表订单的锁定资产被消耗
-                Ok(_consumed_base_asset) => {
-                    // 用户获得计价资产
-                    let obtain_quote_asset = EAssetUnion::from(SAsset {
-                        as_type: quote_asset_type,
-                        balance: quote_quantity - quote_quantity * maker_order_fee,
-                    });
-
-                    if debug_config.is_debug {
-                        debug!("结算卖单: {:?}\t挂单价:{:?}\t挂单量:{:?}\t手续费:{:?}\t用户获得资产:{:?}\t用户消耗资产:{:?}", order.get_id(),order.get_price(), order.get_quantity(), &fee_base_asset, &obtain_quote_asset, &_consumed_base_asset);
-                    }
-                    user_asset_manager.merge_asset(obtain_quote_asset);
-                    order_results.push(ERunnerParseOrderResult::OrderExecuted(order.clone()));
-                    if let Err(e) = order_manager.add_finished_order(order) {
-                        error!("{:?}", e);
-                    }
+        // 按k线内价格路径依次结算挂单 每到达一个价格点时结算价格穿过的买单和卖单
+        for price in self.config.fill_model.intra_bar_path.get_path(kline_unit_data) {
+            // 买单结算 用quote_currency换base_current
+            while let Some(order) = order_manager.peek_highest_buy_order().unwrap() {
+                // 操作方向校验
+                if order.get_action() != EOrderAction::Buy {
+                    log::error!("EOrderAction Error: Expected Buy - Actually {:?}", order.get_action());
+                }
+                // 价格未到达买单价格时 价格更低的买单同样不成交
+                if order.get_price() < price {
+                    break;
+                }
+                let order = order_manager.pop_highest_buy_order().unwrap().unwrap();
+                // 按挂单成交模型判断买单是否成交 排队成交模型下价格更低的买单仍可能成交 未成交的买单本根k线结束后重新挂单
+                if !self.config.fill_model.is_maker_filled(kline_unit_data, &order) {
+                    remaining_orders.push(order);
+                    continue;
+                }
+                let (order_result, remaining_order) = self.settle_order_in_capacity(order, &mut fill_capacity, user_asset_manager, order_manager, debug_config);
+                order_results.extend(order_result);
+                // 可成交量已用完 价格更低的买单不再成交
+                if let Some(remaining_order) = remaining_order {
+                    remaining_orders.push(remaining_order);
+                    break;
+                }
+            }
+
+            // 卖单结算 用base_current换quote_currency
+            while let Some(order) = order_manager.peek_lowest_sell_order().unwrap() {
+                // 操作方向校验
+                if order.get_action() != EOrderAction::Sell {
+                    error!("EOrderAction Error: Expected Sell - Actually {:?}", order.get_action());
+                }
+                // 价格未到达卖单价格时 价格更高的卖单同样不成交
+                if order.get_price() > price {
+                    break;
+                }
+                let order = order_manager.pop_lowest_sell_order().unwrap().unwrap();
+                // 按挂单成交模型判断卖单是否成交 排队成交模型下价格更高的卖单仍可能成交 未成交的卖单本根k线结束后重新挂单
+                if !self.config.fill_model.is_maker_filled(kline_unit_data, &order) {
+                    remaining_orders.push(order);
+                    continue;
                 }
-                Err(e) => {
-                    error!("{:?}", e);
+                let (order_result, remaining_order) = self.settle_order_in_capacity(order, &mut fill_capacity, user_asset_manager, order_manager, debug_config);
+                order_results.extend(order_result);
+                // 可成交量已用完 价格更高的卖单不再成交
+                if let Some(remaining_order) = remaining_order {
+                    remaining_orders.push(remaining_order);
+                    break;
                 }
             }
         }
 
+        // 部分成交的剩余订单重新挂单
+        for order in remaining_orders {
+            if let Err(e) = order_manager.insert_order(order) {
+                error!("{:?}", e);
+            }
+        }
+
         // 将k线和订单结算结果 用于反馈给strategy
         let runner_parse_result = SRunnerParseKlineResult {
             tp_type: tp_type.clone(),
@@ -367,6 +472,289 @@ impl<D: TDataApi> SBackTradeRunner<D> {
         (Ok(runner_parse_result), highest_buy_price, lowest_sell_price)
     }
 
+    /// 检查杠杆资产是否触发强平
+    /// 触发时以强平价格强制平仓，扣除强平手续费，并取消该交易对的所有挂单
+    fn liquidate_leveraged<S: TStrategy>(
+        &self,
+        tp_type: &ETradingPairType,
+        kline_unit_data: &SKlineUnitData,
+        user: &mut SUser<S>,
+        debug_config: &SDebugConfig,
+    ) -> Option<SRunnerLiquidation>
+    {
+        let table = self.config.maintenance_margin_tables.get(tp_type)?;
+        let margin_type = tp_type.get_quote_currency_type();
+        let leveraged_type = tp_type.get_base_currency_type();
+        // 全仓模式下 按配置的余额来源计算共享保证金
+        let shared_margin_sources = match self.config.margin_mode {
+            EMarginMode::Isolated => { Vec::new() }
+            EMarginMode::Cross => { self.get_cross_margin_sources(&user.available_assets, leveraged_type, margin_type) }
+        };
+        let extra_margin: Decimal = shared_margin_sources.iter().map(|(_, balance)| *balance).sum();
+        let (liquidation_price, base_quantity) = match user.available_assets.get(&leveraged_type) {
+            Ok(EAssetUnion::BtcUsdtFuture(asset_leveraged)) | Ok(EAssetUnion::BtcUsdCmFuture(asset_leveraged)) => {
+                let liquidation_price = asset_leveraged.check_liquidation(
+                    kline_unit_data.low_price,
+                    kline_unit_data.high_price,
+                    table,
+                    extra_margin,
+                )?;
+                (liquidation_price, asset_leveraged.get_base().balance)
+            }
+            _ => { return None; }
+        };
+        // 全仓模式下 共享保证金一并用于强平清算
+        let mut shared_margins: Vec<SAsset> = Vec::new();
+        for (source_type, balance) in shared_margin_sources {
+            let shared_margin = match user.available_assets.get_mut(source_type) {
+                Ok(EAssetUnion::Usdt(asset)) | Ok(EAssetUnion::Btc(asset)) => { Ok(asset.split_allow_negative(balance)) }
+                Ok(EAssetUnion::BtcUsdtFuture(asset_leveraged)) | Ok(EAssetUnion::BtcUsdCmFuture(asset_leveraged)) => {
+                    asset_leveraged.margin_withdraw(balance).map_err(|e| format!("{:?}", e))
+                }
+                Err(e) => { Err(format!("{:?}", e)) }
+            };
+            match shared_margin {
+                Ok(shared_margin) => { shared_margins.push(shared_margin); }
+                Err(e) => { error!("{}", e); }
+            }
+        }
+        let asset_leveraged = match user.available_assets.get_mut(leveraged_type) {
+            Ok(EAssetUnion::BtcUsdtFuture(asset_leveraged)) | Ok(EAssetUnion::BtcUsdCmFuture(asset_leveraged)) => { asset_leveraged }
+            _ => { return None; }
+        };
+        for shared_margin in shared_margins {
+            if let Err(e) = asset_leveraged.margin_top_up(shared_margin) {
+                error!("{:?}", e);
+            }
+        }
+        let (returned_margin, liquidation_fee) = asset_leveraged.liquidate(liquidation_price, self.config.liquidation_fee);
+        user.available_assets.merge_asset(EAssetUnion::from(returned_margin.clone()));
+
+        // 取消该交易对的所有挂单 释放锁定资产
+        let order_manager = user.tp_order_map.get_mut(tp_type).unwrap();
+        let order_ids: Vec<Uuid> = order_manager.orders.keys().cloned().collect();
+        let mut canceled_orders: Vec<SOrderV3> = Vec::new();
+        match order_manager.remove_orders(order_ids) {
+            Err(e) => { error!("Error: {:?}", e); }
+            Ok(removed_order_vec) => {
+                for mut order in removed_order_vec {
+                    if let Some(asset) = order.cancel() {
+                        user.available_assets.merge_asset(EAssetUnion::from(asset));
+                    }
+                    canceled_orders.push(order);
+                }
+            }
+        }
+
+        // 强平手续费计入累计手续费(USDT计价)
+        let fee_usdt = SAsset {
+            as_type: EAssetType::Usdt,
+            balance: assets_denominate_usdt(&EAssetUnion::from(liquidation_fee.clone()), &self.trading_pair_prices),
+        };
+        order_manager.total_fee_asset_map.merge_asset(fee_usdt);
+
+        if debug_config.is_info {
+            info!("强平 - 交易对: {:?}\t强平价格:{:?}\t仓位:{:?}\t退还保证金:{:?}\t强平手续费:{:?}\t取消挂单数量:{:?}",
+                tp_type, liquidation_price, base_quantity, returned_margin, liquidation_fee, canceled_orders.len());
+        }
+
+        Some(SRunnerLiquidation {
+            tp_type: *tp_type,
+            liquidation_price,
+            base_quantity,
+            returned_margin,
+            liquidation_fee,
+            canceled_orders,
+        })
+    }
+
+    /// 校验订单成交后仓位的杠杆倍数是否超过维持保证金档位允许的最大杠杆
+    ///
+    /// 成交后仓位 = 现有仓位 + 同方向未成交订单 + 新订单 按成交后仓位的名义价值查找档位 多空均校验
+    ///
+    /// 减仓订单（成交后仓位方向与订单方向相反）不校验
+    fn check_leverage(&self, add_order: &SStrategyOrderAdd, user_asset_manager: &SAssetMapV3, order_manager: &SOrderManagerV3) -> RBackTradeRunnerResult<()> {
+        let SStrategyOrderAdd { tp_type, action, price, base_quantity, margin_quantity, .. } = *add_order;
+        let table = match self.config.maintenance_margin_tables.get(&tp_type) {
+            None => { return Ok(()); }
+            Some(table) => { table }
+        };
+        let get_base_delta = |action: EOrderAction, base_quantity: Decimal| -> Decimal {
+            match action {
+                EOrderAction::Buy => { base_quantity.abs() }
+                EOrderAction::Sell => { -base_quantity.abs() }
+            }
+        };
+        let base_delta = get_base_delta(action, base_quantity);
+        let (mut position_base, mut position_margin) = match user_asset_manager.get(&tp_type.get_base_currency_type()) {
+            Ok(EAssetUnion::BtcUsdtFuture(asset_leveraged)) | Ok(EAssetUnion::BtcUsdCmFuture(asset_leveraged)) => {
+                (asset_leveraged.get_base().balance, asset_leveraged.get_margin().balance)
+            }
+            _ => { (Decimal::from(0), Decimal::from(0)) }
+        };
+        for order in order_manager.orders.values().filter(|order| order.get_action() == action) {
+            position_base += get_base_delta(action, order.get_quantity());
+            position_margin += match order.get_locked_asset() {
+                Some(locked_asset) => { locked_asset.balance }
+                None => { order.get_margin_quantity() }
+            };
+        }
+        position_base += base_delta;
+        position_margin += margin_quantity;
+        if position_base * base_delta <= Decimal::from(0) {
+            return Ok(());
+        }
+        let notional = (position_base * price).abs();
+        let max_leverage = table.get_max_leverage(notional);
+        if position_margin <= Decimal::from(0) || notional / position_margin > max_leverage {
+            return Err(EBackTradeRunnerError::LeverageExceededError(tp_type, notional, position_margin, max_leverage));
+        }
+        Ok(())
+    }
+
+    /// 全仓模式下共享保证金的来源（资产类型，可用于强平清算的余额）
+    /// 合约账户只计入同一保证金资产的其他杠杆资产的保证金 统一账户计入保证金资产的全部可用余额
+    fn get_cross_margin_sources(&self, assets: &SAssetMapV3, leveraged_type: EAssetType, margin_type: EAssetType) -> Vec<(EAssetType, Decimal)> {
+        let sources: Vec<(EAssetType, Decimal)> = match self.config.cross_margin_balance {
+            ECrossMarginBalance::AvailableBalance => {
+                match assets.get(&margin_type) {
+                    Ok(asset) => { vec![(margin_type, asset.get_balance())] }
+                    Err(_) => { Vec::new() }
+                }
+            }
+            ECrossMarginBalance::FuturesWallet => {
+                assets
+                    .iter()
+                    .filter(|(as_type, _)| **as_type != leveraged_type)
+                    .filter_map(|(as_type, asset)| match asset {
+                        EAssetUnion::BtcUsdtFuture(asset_leveraged) | EAssetUnion::BtcUsdCmFuture(asset_leveraged)
+                        if asset_leveraged.get_margin().as_type == margin_type => {
+                            Some((*as_type, asset_leveraged.get_margin().balance))
+                        }
+                        _ => { None }
+                    })
+                    .collect()
+            }
+        };
+        sources.into_iter().filter(|(_, balance)| *balance > Decimal::from(0)).collect()
+    }
+
+    /// 条件单触发时 从可用资产中锁定下单时指定的保证金资产
+    fn lock_triggered_order(&self, order: &mut SOrderV3, user_asset_manager: &mut SAssetMapV3) -> RBackTradeRunnerResult<()> {
+        let tp_type = order.get_tp_type();
+        let margin_asset_type = self.get_settlement_handler(tp_type).get_margin_asset_type(tp_type, order.get_action());
+        let margin_quantity = order.get_margin_quantity();
+        let available_balance = match user_asset_manager.get(&margin_asset_type) {
+            Ok(asset) => { asset.get_balance() }
+            Err(_) => { Decimal::from(0) }
+        };
+        if available_balance < margin_quantity {
+            return Err(EBackTradeRunnerError::AssetAvailableNotEnoughError(margin_asset_type, margin_quantity, available_balance));
+        }
+        let asset = match user_asset_manager.split_allow_negative(margin_asset_type, margin_quantity) {
+            Ok(EAssetUnion::Usdt(asset)) | Ok(EAssetUnion::Btc(asset)) => { asset }
+            _ => { return Err(EBackTradeRunnerError::AssetAvailableNotEnoughError(margin_asset_type, margin_quantity, available_balance)); }
+        };
+        if let Err(e) = order.lock_triggered(asset.clone()) {
+            user_asset_manager.merge_asset(EAssetUnion::from(asset));
+            return Err(EBackTradeRunnerError::OrderV3Error(e));
+        }
+        Ok(())
+    }
+
+    /// 在k线剩余可成交量内结算订单
+    /// 可成交量不足时拆分订单 成交部分立即结算 返回(成交结果, 剩余未成交的订单)
+    fn settle_order_in_capacity(
+        &self,
+        order: SOrderV3,
+        fill_capacity: &mut SKlineFillCapacity,
+        user_asset_manager: &mut SAssetMapV3,
+        order_manager: &mut SOrderManagerV3,
+        debug_config: &SDebugConfig,
+    ) -> (Option<ERunnerParseOrderResult>, Option<SOrderV3>)
+    {
+        let (order, remaining_order) = match fill_capacity.get_capacity(&order) {
+            Some(capacity) if capacity <= Decimal::from(0) => { return (None, Some(order)); }
+            Some(capacity) if capacity < order.get_quantity().abs() => {
+                let mut remaining_order = order;
+                match remaining_order.split_partial(capacity) {
+                    Err(e) => {
+                        error!("{:?}", e);
+                        return (None, Some(remaining_order));
+                    }
+                    Ok(partial_order) => { (partial_order, Some(remaining_order)) }
+                }
+            }
+            _ => { (order, None) }
+        };
+        fill_capacity.consume(&order);
+        let executed_order = self.settle_order(order, user_asset_manager, order_manager, debug_config);
+        let order_result = executed_order.map(|executed_order| match &remaining_order {
+            None => { ERunnerParseOrderResult::OrderExecuted(executed_order) }
+            Some(remaining_order) => {
+                if debug_config.is_debug { debug!("部分成交: {:?}\t成交量:{:?}\t剩余量:{:?}", executed_order.get_id(), executed_order.get_quantity(), remaining_order.get_quantity()); }
+                ERunnerParseOrderResult::OrderPartiallyExecuted(executed_order, remaining_order.get_quantity())
+            }
+        });
+        (order_result, remaining_order)
+    }
+
+    /// 结算已成交的订单 由交易对的结算处理器计算手续费及用户获得的资产
+    fn settle_order(
+        &self,
+        mut order: SOrderV3,
+        user_asset_manager: &mut SAssetMapV3,
+        order_manager: &mut SOrderManagerV3,
+        debug_config: &SDebugConfig,
+    ) -> Option<SOrderV3>
+    {
+        let handler = self.get_settlement_handler(order.get_tp_type());
+        let order_fee = self.config.get_order_fee(order.is_taker());
+        // 计算手续费
+        let fee_asset = handler.get_fee_asset(&order, order_fee);
+        // 计算手续费(USDT计价)
+        let fee_usdt = SAsset {
+            as_type: EAssetType::Usdt,
+            balance: assets_denominate_usdt(&fee_asset, &self.trading_pair_prices),
+        };
+        // 结算资产
+        // 提取订单锁定的资产 生成用户获得的资产
+        let consumed_margin_asset = match order.execute(Some(fee_usdt)) {
+            Err(e) => {
+                error!("{:?}", e);
+                return None;
+            }
+            Ok(consumed_margin_asset) => { consumed_margin_asset }
+        };
+        let obtain_assets = match handler.get_obtain_assets(&order, consumed_margin_asset.clone(), order_fee) {
+            Err(e) => {
+                error!("{:?}", e);
+                return None;
+            }
+            Ok(obtain_assets) => { obtain_assets }
+        };
+
+        if debug_config.is_debug {
+            debug!("结算订单: {:?}\t方向:{:?}\t成交价:{:?}\t成交量:{:?}\t手续费:{:?}\t用户获得资产:{:?}\t用户消耗资产:{:?}",
+                order.get_id(),
+                order.get_action(),
+                order.get_price(),
+                order.get_quantity(),
+                &fee_asset,
+                &obtain_assets,
+                &consumed_margin_asset);
+        }
+
+        for obtain_asset in obtain_assets {
+            user_asset_manager.merge_asset(obtain_asset);
+        }
+        let executed_order = order.clone();
+        if let Err(e) = order_manager.add_finished_order(order) {
+            error!("{:?}", e);
+        }
+        Some(executed_order)
+    }
+
     /// 根据策略行为，同步订单数据。
     fn sync_strategy_action<S: TStrategy>(
         &self,
@@ -378,9 +766,7 @@ impl<D: TDataApi> SBackTradeRunner<D> {
     {
         let user_asset_manager = &mut user.available_assets;
         let order_manager = user.tp_order_map.get_mut(tp_type).unwrap();
-        let base_asset_type = tp_type.get_base_currency_type();
-        let quote_asset_type = tp_type.get_quote_currency_type();
-
+        let handler = self.get_settlement_handler(*tp_type);
 
         // 根据策略行为，校验、调整订单数据。
         let mut parse_action_result: Vec<ERunnerSyncActionResult> = Vec::new();
@@ -411,17 +797,14 @@ impl<D: TDataApi> SBackTradeRunner<D> {
                 // 订单执行成功 进行资产结算
                 for mut order in removed_order_vec {
                     if debug_config.is_debug { debug!("取消订单: {:?}", order); }
-                    // 订单成功取消 释放锁定资产
+                    // 订单成功取消 释放锁定资产（未触发的条件单没有锁定资产）
                     if let Some(asset) = order.cancel() {
-                        let user_asset = match order.get_action() {
-                            EOrderAction::Buy => { user_asset_manager.get_mut(quote_asset_type).unwrap() }
-                            EOrderAction::Sell => { user_asset_manager.get_mut(base_asset_type).unwrap() }
-                        };
+                        let user_asset = user_asset_manager.get_mut(handler.get_margin_asset_type(*tp_type, order.get_action())).unwrap();
                         if let Err(e) = user_asset.merge(EAssetUnion::from(asset)) {
                             error!("Error: {:?}", e);
                         }
-                        parse_action_result.push(ERunnerSyncActionResult::OrderCanceled(order));
                     }
+                    parse_action_result.push(ERunnerSyncActionResult::OrderCanceled(order));
                 }
             }
         }
@@ -429,19 +812,53 @@ impl<D: TDataApi> SBackTradeRunner<D> {
         // 处理新增订单 资产结算
         for add_order in add_orders {
             // info!("Start: add_order");
-            // info!("add_order:{:?}", add_order);
-            let mut new_order = SOrderV3::new(
-                tp_type.clone(),
-                add_order.price,
-                add_order.base_quantity,
-                add_order.action,
-            );
-            let user_asset = match add_order.action {
-                EOrderAction::Buy => { user_asset_manager.get_mut(quote_asset_type).unwrap() }
-                EOrderAction::Sell => { user_asset_manager.get_mut(base_asset_type).unwrap() }
+            // if debug_config.is_info { info!("add_order:\t{:?}", add_order); }
+
+            // 开仓（多空）时校验成交后仓位的杠杆倍数是否超过维持保证金档位允许的最大杠杆
+            if let Err(e) = self.check_leverage(&add_order, user_asset_manager, order_manager) {
+                error!("{:?}", e);
+                continue;
+            }
+            let SStrategyOrderAdd {
+                id: _,
+                tp_type,
+                action,
+                price,
+                base_quantity,
+                margin_quantity,
+                order_type,
+            } = add_order;
+            let mut new_order = SOrderV3::new(tp_type, price, base_quantity, action).with_order_type(order_type);
+            // 可立即成交的订单 在下一根k线按吃单成交
+            if let Some(current_price) = self.trading_pair_prices.get(&tp_type) {
+                if new_order.is_marketable(*current_price) {
+                    new_order.set_taker(true);
+                }
+            }
+            // 条件单在触发时才锁定保证金资产
+            if order_type.is_conditional() {
+                match new_order.submit_conditional(margin_quantity) {
+                    Err(e) => { error!("Error: {:?}", e); }
+                    Ok(_) => {
+                        if let Err(e) = order_manager.insert_order(new_order.clone()) {
+                            error!("Error: {:?}", e);
+                        }
+                        parse_action_result.push(ERunnerSyncActionResult::OrderPlaced(new_order, add_order.id));
+                    }
+                }
+                continue;
+            }
+            let margin_asset_type = self.get_settlement_handler(tp_type).get_margin_asset_type(tp_type, action);
+            let user_asset = user_asset_manager.get_mut(margin_asset_type).unwrap();
+            // info!("\nmargin_quantity:\t{:?}", margin_quantity);
+            let split_user_asset = user_asset.split_allow_negative(margin_quantity);
+            // info!("\nsplit_user_asset:\t{:?}", split_user_asset);
+            let locked_margin_asset = match split_user_asset {
+                EAssetUnion::Usdt(_) | EAssetUnion::Btc(_) => { split_user_asset }
+                EAssetUnion::BtcUsdtFuture(leveraged_asset) | EAssetUnion::BtcUsdCmFuture(leveraged_asset) => {
+                    EAssetUnion::from(leveraged_asset.get_margin().clone())
+                }
             };
-            // info!("margin_quantity:{:?}", add_order.margin_quantity);
-            let locked_margin_asset = user_asset.split_allow_negative(add_order.margin_quantity);
             // info!("locked_margin_asset:{:?}", locked_margin_asset);
             match locked_margin_asset {
                 EAssetUnion::Usdt(asset) | EAssetUnion::Btc(asset) => {
@@ -449,7 +866,7 @@ impl<D: TDataApi> SBackTradeRunner<D> {
                         error!("Error: {:?}", e);
                     }
 
-                    if debug_config.is_debug { debug!("新增订单: {:?}", &new_order); }
+                    // if debug_config.is_info { debug!("新增订单: {:?}", &new_order); }
 
                     if let Err(e) = order_manager.insert_order(new_order.clone()) {
                         error!("Error: {:?}", e);
@@ -470,17 +887,23 @@ impl<D: TDataApi> SBackTradeRunner<D> {
     fn get_parse_new_kline_transfer_info(runner_parse_result: &SRunnerParseKlineResult) -> SDataLogTransferExecutedUnit {
         let mut result = SDataLogTransferExecutedUnit::default();
         for order_result in &runner_parse_result.order_result {
-            if let ERunnerParseOrderResult::OrderExecuted(order) = order_result {
-                match order.get_action() {
-                    EOrderAction::Buy => {
-                        result.executed_buy_order_cnt += 1;
-                        result.executed_buy_usdt_cnt += order.get_price() * order.get_quantity();
-                    }
-                    EOrderAction::Sell => {
-                        result.executed_sell_order_cnt += 1;
-                        result.executed_sell_usdt_cnt += order.get_price() * order.get_quantity();
+            match order_result {
+                ERunnerParseOrderResult::OrderExecuted(order) | ERunnerParseOrderResult::OrderPartiallyExecuted(order, _) => {
+                    match order.get_action() {
+                        EOrderAction::Buy => {
+                            result.executed_buy_order_cnt += 1;
+                            result.executed_buy_usdt_cnt += order.get_price() * order.get_quantity();
+                        }
+                        EOrderAction::Sell => {
+                            result.executed_sell_order_cnt += 1;
+                            result.executed_sell_usdt_cnt += order.get_price() * order.get_quantity();
+                        }
                     }
                 }
+                ERunnerParseOrderResult::Liquidated(_) => {
+                    result.liquidated_cnt += 1;
+                }
+                ERunnerParseOrderResult::OrderCanceled(_) => {}
             }
         }
         result
@@ -505,4 +928,507 @@ impl<D: TDataApi> SBackTradeRunner<D> {
         }
         result
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+    use chrono::{DateTime, Duration, Local, TimeZone};
+    use rust_decimal::Decimal;
+    use crate::config::SDebugConfig;
+    use crate::data_runtime::asset::asset::SAsset;
+    use crate::data_runtime::asset::asset_leveraged::SAssetLeveraged;
+    use crate::data_runtime::asset::asset_union::EAssetUnion;
+    use crate::data_runtime::asset::margin::{ECrossMarginBalance, EMarginMode};
+    use crate::data_runtime::asset::EAssetType;
+    use crate::data_runtime::order::{EOrderAction, EOrderType};
+    use crate::data_runtime::order::order_v3::{EOrderState, SOrderV3};
+    use crate::data_runtime::user::{SUser, SUserConfig};
+    use crate::data_source::data_manager::SDataManager;
+    use crate::data_source::db::api::data_api_csv::SDataApiCsv;
+    use crate::data_source::kline::{EKlineInterval, SKlineData, SKlineUnitData};
+    use crate::data_source::trading_pair::ETradingPairType;
+    use crate::data_source::trading_pair::trading_pair_map::STradingPairMap;
+    use crate::protocol::{ERunnerParseOrderResult, EStrategyAction};
+    use crate::protocol::strategy_order::SStrategyOrderAdd;
+    use crate::data_runtime::asset::asset_map_v3::SAssetMapV3;
+    use crate::data_runtime::order::trading_pair_order_manager_map_v3::STradingPairOrderManagerMapV3;
+    use crate::protocol::{ERunnerSyncActionResult, SRunnerParseKlineResult};
+    use crate::runner::TRunner;
+    use crate::runner::back_trade::config::{EStalePricePolicy, SBackTradeRunnerConfig};
+    use crate::strategy::logger::SStrategyLogger;
+    use crate::strategy::TStrategy;
+    use crate::runner::back_trade::fill_model::EMakerFillModel;
+    use crate::runner::back_trade::price_path::EIntraBarPathModel;
+    use crate::runner::back_trade::runner::SBackTradeRunner;
+    use crate::runner::back_trade::settlement::ESettlementHandlerUnion;
+    use crate::strategy::mk_test::SStrategyMkTest;
+
+    fn get_start() -> DateTime<Local> {
+        Local.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap()
+    }
+
+    /// 合成1分钟k线 (开盘价, 收盘价, 最高价, 最低价, 成交量)
+    fn get_klines(prices: &[(i64, i64, i64, i64, i64)]) -> Vec<SKlineUnitData> {
+        prices
+            .iter()
+            .enumerate()
+            .map(|(i, (open_price, close_price, high_price, low_price, volume))| {
+                let open_time = get_start() + Duration::minutes(i as i64);
+                SKlineUnitData {
+                    open_time,
+                    close_time: open_time + Duration::seconds(59),
+                    open_price: Decimal::from(*open_price),
+                    close_price: Decimal::from(*close_price),
+                    high_price: Decimal::from(*high_price),
+                    low_price: Decimal::from(*low_price),
+                    volume: Decimal::from(*volume),
+                }
+            })
+            .collect()
+    }
+
+    /// 使用各交易对的合成k线构建执行器 手续费为0 吃单滑点为1%
+    fn get_runner_with_klines(tp_klines: &[(ETradingPairType, &[SKlineUnitData])]) -> SBackTradeRunner<SDataApiCsv> {
+        let mut trading_pair_map = STradingPairMap::new();
+        let mut date_to = get_start();
+        for (tp_type, klines) in tp_klines {
+            let mut kline_data = SKlineData::new();
+            for kline in klines.iter() {
+                kline_data.insert_unit(*kline);
+                date_to = date_to.max(kline.close_time);
+            }
+            trading_pair_map.add_trading_pair(*tp_type, kline_data, None);
+        }
+        let config = SBackTradeRunnerConfig {
+            taker_order_fee: Decimal::from(0),
+            maker_order_fee: Decimal::from(0),
+            taker_slippage: Decimal::new(1, 2),
+            date_from: get_start(),
+            date_to,
+            ..Default::default()
+        };
+        let data_manager = SDataManager::new(SDataApiCsv::new(std::env::temp_dir()), trading_pair_map);
+        SBackTradeRunner::new(config, data_manager)
+    }
+
+    /// 各交易对使用相同的合成k线 当前报价为100
+    fn get_runner(tp_types: &[ETradingPairType], klines: &[SKlineUnitData]) -> SBackTradeRunner<SDataApiCsv> {
+        let tp_klines: Vec<(ETradingPairType, &[SKlineUnitData])> = tp_types.iter().map(|tp_type| (*tp_type, klines)).collect();
+        let mut runner = get_runner_with_klines(&tp_klines);
+        for tp_type in tp_types {
+            runner.trading_pair_prices.insert(*tp_type, Decimal::from(100));
+        }
+        runner
+    }
+
+    fn get_user() -> SUser<SStrategyMkTest> {
+        let user_config = SUserConfig {
+            user_name: "Satoshi Nakamoto".to_string(),
+            init_balance_usdt: Decimal::from(1000),
+            init_balance_btc: Decimal::from(1),
+        };
+        SUser::new(user_config, SStrategyMkTest::default())
+    }
+
+    fn get_balance(user: &SUser<SStrategyMkTest>, as_type: EAssetType) -> Decimal {
+        user.available_assets.get(&as_type).unwrap().get_balance()
+    }
+
+    fn get_new_order(tp_type: ETradingPairType, action: EOrderAction, price: &str, base_quantity: &str, margin_quantity: &str, order_type: EOrderType) -> EStrategyAction {
+        EStrategyAction::NewOrder(SStrategyOrderAdd {
+            id: None,
+            tp_type,
+            action,
+            price: Decimal::from_str(price).unwrap(),
+            base_quantity: Decimal::from_str(base_quantity).unwrap(),
+            margin_quantity: Decimal::from_str(margin_quantity).unwrap(),
+            order_type,
+        })
+    }
+
+    fn parse_kline(runner: &SBackTradeRunner<SDataApiCsv>, tp_type: ETradingPairType, kline: &SKlineUnitData, user: &mut SUser<SStrategyMkTest>) -> Vec<ERunnerParseOrderResult> {
+        let (runner_parse_result, _, _) = runner.parse_new_kline(&tp_type, kline, Decimal::from(0), user, &SDebugConfig::default());
+        runner_parse_result.unwrap().order_result
+    }
+
+    /// 记录执行时刻的策略
+    #[derive(Debug, Default)]
+    struct SStrategyRecord {
+        run_times: Vec<(ETradingPairType, DateTime<Local>)>,
+        bars: Vec<SKlineUnitData>,
+    }
+
+    impl TStrategy for SStrategyRecord {
+        fn run(&mut self, _tp_order_map: &mut STradingPairOrderManagerMapV3, _available_assets: &mut SAssetMapV3, runner_parse_result: SRunnerParseKlineResult, _debug_config: &SDebugConfig) -> Vec<EStrategyAction> {
+            self.run_times.push((runner_parse_result.tp_type, runner_parse_result.new_kline.open_time));
+            self.bars.push(runner_parse_result.new_kline);
+            Vec::new()
+        }
+
+        fn verify(&mut self, _tp_type: &ETradingPairType, _parse_action_results: Vec<ERunnerSyncActionResult>, _debug_config: &SDebugConfig) {}
+
+        fn get_log_info(&self) -> SStrategyLogger {
+            SStrategyLogger::none()
+        }
+
+        fn get_position(&self, _time: DateTime<Local>) -> Option<Decimal> {
+            None
+        }
+    }
+
+    #[test]
+    pub fn test_price_ready() {
+        let spot_klines = get_klines(&[(100, 100, 101, 99, 10); 5]);
+        // U本位合约从第3根k线开始 缺少第4根k线
+        let future_klines = vec![spot_klines[2], spot_klines[4]];
+        let minutes = |indexes: &[i64]| -> Vec<DateTime<Local>> { indexes.iter().map(|i| get_start() + Duration::minutes(*i)).collect() };
+        for (stale_price_policy, expected_log_minutes) in [
+            (EStalePricePolicy::KeepLast, minutes(&[0, 1, 2, 3, 4])),
+            (EStalePricePolicy::SkipLog { max_age_minutes: 0 }, minutes(&[0, 1, 2, 4])),
+        ] {
+            let mut runner = get_runner_with_klines(&[
+                (ETradingPairType::BtcUsdt, &spot_klines),
+                (ETradingPairType::BtcUsdtFuture, &future_klines),
+            ]);
+            runner.config.stale_price_policy = stale_price_policy;
+            let mut users = vec![SUser::new(SUserConfig::default(), SStrategyRecord::default())];
+            let runner_result = runner.run(&mut users, SDebugConfig::default());
+
+            // 尚无报价的交易对不阻塞日志 只有报价过期时才跳过日志
+            assert!(!runner.trading_pair_prices.contains_key(&ETradingPairType::BtcUsdCmFuture));
+            let log_minutes: Vec<DateTime<Local>> = runner_result.data_logger.kline_data.keys().copied().collect();
+            assert_eq!(log_minutes, expected_log_minutes);
+            // 策略在各交易对自身的k线收盘时执行 不受其他交易对报价及日志策略的影响
+            let get_run_minutes = |tp_type: ETradingPairType| -> Vec<DateTime<Local>> {
+                users[0].strategy.run_times
+                    .iter()
+                    .filter(|(run_tp_type, _)| *run_tp_type == tp_type)
+                    .map(|(_, time)| *time)
+                    .collect()
+            };
+            assert_eq!(get_run_minutes(ETradingPairType::BtcUsdt), minutes(&[0, 1, 2, 3, 4]));
+            assert_eq!(get_run_minutes(ETradingPairType::BtcUsdtFuture), minutes(&[2, 4]));
+        }
+    }
+
+    #[test]
+    pub fn test_strategy_kline_end() {
+        let klines = get_klines(&[(100, 100, 101, 99, 10); 10]);
+        let start = get_start();
+        // 回测结束时间及时间窗口结束时间都在5分钟周期内 周期的最后一根k线收盘时执行策略
+        for (date_to, window_to) in [
+            (start + Duration::minutes(3), None),
+            (start + Duration::minutes(10), Some(start + Duration::minutes(2))),
+        ] {
+            let mut runner = get_runner_with_klines(&[(ETradingPairType::BtcUsdt, &klines)]);
+            runner.config.strategy_interval = EKlineInterval::Minute5;
+            runner.config.date_to = date_to;
+            if let Some(window_to) = window_to {
+                runner.data_manager = runner.data_manager.window(start, window_to);
+            }
+            let mut users = vec![SUser::new(SUserConfig::default(), SStrategyRecord::default())];
+            runner.run(&mut users, SDebugConfig::default());
+            assert_eq!(users[0].strategy.run_times, vec![(ETradingPairType::BtcUsdt, start)]);
+            assert_eq!(users[0].strategy.bars[0].close_price, Decimal::from(100));
+            assert_eq!(users[0].strategy.bars[0].volume, Decimal::from(30));
+        }
+    }
+
+    #[test]
+    pub fn test_taker_fill_next_open() {
+        let tp_type = ETradingPairType::BtcUsdt;
+        let klines = get_klines(&[(100, 102, 103, 99, 10)]);
+        let runner = get_runner(&[tp_type], &klines);
+        let mut user = get_user();
+
+        // 买价高于当前报价 按吃单在下一根k线成交 下单时按限价锁定计价资产
+        let actions = vec![get_new_order(tp_type, EOrderAction::Buy, "110", "1", "110", EOrderType::Limit)];
+        runner.sync_strategy_action(actions, &tp_type, &mut user, &SDebugConfig::default());
+        assert_eq!(get_balance(&user, EAssetType::Usdt), Decimal::from(890));
+
+        // 以开盘价加滑点成交 退还锁定资产与成交金额的差额
+        let order_results = parse_kline(&runner, tp_type, &klines[0], &mut user);
+        assert_eq!(order_results.len(), 1);
+        match &order_results[0] {
+            ERunnerParseOrderResult::OrderExecuted(order) => {
+                assert!(order.is_taker());
+                assert_eq!(order.get_price(), Decimal::from(101));
+            }
+            _ => { panic!() }
+        }
+        assert_eq!(get_balance(&user, EAssetType::Btc), Decimal::from(2));
+        assert_eq!(get_balance(&user, EAssetType::Usdt), Decimal::from(899));
+    }
+
+    #[test]
+    pub fn test_taker_gap_up() {
+        let tp_type = ETradingPairType::BtcUsdt;
+        // 开盘价跳空高于下单时的报价
+        let klines = get_klines(&[(110, 112, 113, 109, 10)]);
+        let runner = get_runner(&[tp_type], &klines);
+        let mut user = get_user();
+
+        // 市价单按当前报价锁定计价资产
+        let actions = vec![get_new_order(tp_type, EOrderAction::Buy, "100", "5", "500", EOrderType::Market)];
+        runner.sync_strategy_action(actions, &tp_type, &mut user, &SDebugConfig::default());
+        assert_eq!(get_balance(&user, EAssetType::Usdt), Decimal::from(500));
+
+        // 成交价格（开盘价加滑点）高于锁定价格 按锁定资产可买入的数量成交 计价资产不为负数
+        let order_results = parse_kline(&runner, tp_type, &klines[0], &mut user);
+        match &order_results[..] {
+            [ERunnerParseOrderResult::OrderExecuted(order)] => {
+                assert_eq!(order.get_price(), Decimal::from_str("111.1").unwrap());
+                assert_eq!(order.get_quantity(), Decimal::from_str("4.50045004").unwrap());
+            }
+            _ => { panic!() }
+        }
+        let usdt_balance = get_balance(&user, EAssetType::Usdt);
+        assert!(usdt_balance >= Decimal::from(500) && usdt_balance < Decimal::from_str("500.00001").unwrap());
+    }
+
+    #[test]
+    pub fn test_conditional_trigger() {
+        let tp_type = ETradingPairType::BtcUsdt;
+        let klines = get_klines(&[(100, 102, 104, 99, 10), (100, 108, 110, 99, 10)]);
+        let runner = get_runner(&[tp_type], &klines);
+        let mut user = get_user();
+
+        let actions = vec![get_new_order(tp_type, EOrderAction::Buy, "105", "1", "120", EOrderType::StopMarket(Decimal::from(105)))];
+        runner.sync_strategy_action(actions, &tp_type, &mut user, &SDebugConfig::default());
+
+        // 最高价未达到触发价格 条件单继续等待
+        assert!(parse_kline(&runner, tp_type, &klines[0], &mut user).is_empty());
+        assert_eq!(user.tp_order_map.get(&tp_type).unwrap().conditional_orders.len(), 1);
+
+        // 触发后以触发价格加滑点按吃单成交
+        let order_results = parse_kline(&runner, tp_type, &klines[1], &mut user);
+        assert_eq!(order_results.len(), 1);
+        match &order_results[0] {
+            ERunnerParseOrderResult::OrderExecuted(order) => {
+                assert!(order.is_taker());
+                assert_eq!(order.get_price(), Decimal::from_str("106.05").unwrap());
+            }
+            _ => { panic!() }
+        }
+        assert!(user.tp_order_map.get(&tp_type).unwrap().conditional_orders.is_empty());
+        assert_eq!(get_balance(&user, EAssetType::Btc), Decimal::from(2));
+        assert_eq!(get_balance(&user, EAssetType::Usdt), Decimal::from_str("893.95").unwrap());
+    }
+
+    #[test]
+    pub fn test_conditional_lock_at_trigger() {
+        let tp_type = ETradingPairType::BtcUsdt;
+        let klines = get_klines(&[(100, 108, 111, 99, 10), (100, 92, 101, 90, 10)]);
+        let runner = get_runner(&[tp_type], &klines);
+        let mut user = get_user();
+
+        // 同一仓位的止损单和止盈单同时挂出 提交时不锁定资产
+        let actions = vec![
+            get_new_order(tp_type, EOrderAction::Sell, "95", "1", "1", EOrderType::StopMarket(Decimal::from(95))),
+            get_new_order(tp_type, EOrderAction::Sell, "110", "1", "1", EOrderType::TakeProfit(Decimal::from(110))),
+        ];
+        let parse_action_results = runner.sync_strategy_action(actions, &tp_type, &mut user, &SDebugConfig::default());
+        assert_eq!(parse_action_results.len(), 2);
+        assert_eq!(get_balance(&user, EAssetType::Btc), Decimal::from(1));
+
+        // 止盈单触发时锁定并卖出全部基础资产
+        let order_results = parse_kline(&runner, tp_type, &klines[0], &mut user);
+        assert!(matches!(&order_results[..], [ERunnerParseOrderResult::OrderExecuted(order)] if order.get_price() == Decimal::from_str("108.9").unwrap()));
+        assert_eq!(get_balance(&user, EAssetType::Btc), Decimal::from(0));
+        assert_eq!(get_balance(&user, EAssetType::Usdt), Decimal::from_str("1108.9").unwrap());
+
+        // 止损单触发时可用资产不足 订单被取消
+        let order_results = parse_kline(&runner, tp_type, &klines[1], &mut user);
+        assert!(matches!(&order_results[..], [ERunnerParseOrderResult::OrderCanceled(order)] if order.get_state() == EOrderState::Canceled));
+        assert!(user.tp_order_map.get(&tp_type).unwrap().orders.is_empty());
+        assert_eq!(get_balance(&user, EAssetType::Btc), Decimal::from(0));
+    }
+
+    #[test]
+    pub fn test_partial_remainder() {
+        let tp_type = ETradingPairType::BtcUsdt;
+        let klines = get_klines(&[(105, 105, 110, 90, 4), (105, 105, 110, 90, 4)]);
+        let mut runner = get_runner(&[tp_type], &klines);
+        // 穿过挂单价格的成交量为2 可成交1
+        runner.config.fill_model.max_volume_ratio = Some(Decimal::new(5, 1));
+        let mut user = get_user();
+
+        let actions = vec![get_new_order(tp_type, EOrderAction::Buy, "100", "2", "200", EOrderType::Limit)];
+        runner.sync_strategy_action(actions, &tp_type, &mut user, &SDebugConfig::default());
+
+        // 部分成交 剩余部分按原价格重新挂单
+        let order_results = parse_kline(&runner, tp_type, &klines[0], &mut user);
+        assert_eq!(order_results.len(), 1);
+        let order_id = match &order_results[0] {
+            ERunnerParseOrderResult::OrderPartiallyExecuted(order, remaining_quantity) => {
+                assert_eq!(order.get_quantity(), Decimal::from(1));
+                assert_eq!(*remaining_quantity, Decimal::from(1));
+                order.get_id()
+            }
+            _ => { panic!() }
+        };
+        let order_manager = user.tp_order_map.get(&tp_type).unwrap();
+        assert_eq!(order_manager.buy_orders.get(&Decimal::from(100)).unwrap(), &vec![order_id]);
+        let remaining_order = order_manager.peek_order(&order_id).unwrap();
+        assert_eq!(remaining_order.get_state(), EOrderState::PartiallyExecuted);
+        assert_eq!(remaining_order.get_quantity(), Decimal::from(1));
+        assert_eq!(get_balance(&user, EAssetType::Btc), Decimal::from(2));
+
+        // 下一根k线剩余部分全部成交
+        let order_results = parse_kline(&runner, tp_type, &klines[1], &mut user);
+        assert!(matches!(&order_results[..], [ERunnerParseOrderResult::OrderExecuted(order)] if order.get_id() == order_id));
+        assert!(user.tp_order_map.get(&tp_type).unwrap().orders.is_empty());
+        assert_eq!(get_balance(&user, EAssetType::Btc), Decimal::from(3));
+        assert_eq!(get_balance(&user, EAssetType::Usdt), Decimal::from(800));
+    }
+
+    #[test]
+    pub fn test_queue_fill_skips_unfilled_order() {
+        let tp_type = ETradingPairType::BtcUsdt;
+        // 穿过100的成交量为10 穿过95的成交量为5
+        let klines = get_klines(&[(105, 105, 110, 90, 20)]);
+        let high_buy_order = SOrderV3::new_buy_order(tp_type, Decimal::from(100), Decimal::from(1));
+        let low_buy_order = SOrderV3::new_buy_order(tp_type, Decimal::from(95), Decimal::from(1));
+        // 找到价格更高的买单排队靠后未成交 而价格更低的买单成交的种子
+        let queue_depth = Decimal::from(10);
+        let fill_model = (0..10_000)
+            .map(|seed| EMakerFillModel::Queue { queue_depth, seed })
+            .find(|fill_model| !fill_model.is_filled(&klines[0], &high_buy_order) && fill_model.is_filled(&klines[0], &low_buy_order))
+            .unwrap();
+
+        let mut runner = get_runner(&[tp_type], &klines);
+        runner.config.fill_model.maker_fill_model = fill_model;
+        let mut user = get_user();
+        let actions = vec![
+            get_new_order(tp_type, EOrderAction::Buy, "100", "1", "100", EOrderType::Limit),
+            get_new_order(tp_type, EOrderAction::Buy, "95", "1", "95", EOrderType::Limit),
+        ];
+        runner.sync_strategy_action(actions, &tp_type, &mut user, &SDebugConfig::default());
+
+        // 价格更高的买单未成交时 价格更低的买单照常成交 未成交的买单继续挂单
+        let order_results = parse_kline(&runner, tp_type, &klines[0], &mut user);
+        assert!(matches!(&order_results[..], [ERunnerParseOrderResult::OrderExecuted(order)] if order.get_price() == Decimal::from(95)));
+        let order_manager = user.tp_order_map.get(&tp_type).unwrap();
+        assert_eq!(order_manager.orders.len(), 1);
+        assert_eq!(order_manager.peek_highest_buy_order().unwrap().unwrap().get_price(), Decimal::from(100));
+        assert_eq!(get_balance(&user, EAssetType::Btc), Decimal::from(2));
+    }
+
+    #[test]
+    pub fn test_intra_bar_path() {
+        let tp_type = ETradingPairType::BtcUsdt;
+        // 阴线 OHLC路径为开盘价→最高价→最低价→收盘价
+        let klines = get_klines(&[(100, 96, 110, 90, 10)]);
+        for (intra_bar_path, expected_actions) in [
+            (EIntraBarPathModel::BuyThenSell, [EOrderAction::Buy, EOrderAction::Sell]),
+            (EIntraBarPathModel::Ohlc, [EOrderAction::Sell, EOrderAction::Buy]),
+        ] {
+            let mut runner = get_runner(&[tp_type], &klines);
+            runner.config.fill_model.intra_bar_path = intra_bar_path;
+            let mut user = get_user();
+            let actions = vec![
+                get_new_order(tp_type, EOrderAction::Buy, "95", "0.5", "47.5", EOrderType::Limit),
+                get_new_order(tp_type, EOrderAction::Sell, "105", "0.5", "0.6", EOrderType::Limit),
+            ];
+            runner.sync_strategy_action(actions, &tp_type, &mut user, &SDebugConfig::default());
+
+            let order_actions: Vec<EOrderAction> = parse_kline(&runner, tp_type, &klines[0], &mut user)
+                .iter()
+                .map(|order_result| match order_result {
+                    ERunnerParseOrderResult::OrderExecuted(order) => { order.get_action() }
+                    _ => { panic!() }
+                })
+                .collect();
+            assert_eq!(order_actions, expected_actions);
+        }
+    }
+
+    #[test]
+    pub fn test_check_leverage() {
+        let tp_type = ETradingPairType::BtcUsdtFuture;
+        let klines = get_klines(&[(10_000, 10_000, 10_001, 9_999, 10)]);
+        let runner = get_runner(&[tp_type], &klines);
+        let get_order_cnt = |user: &SUser<SStrategyMkTest>| user.tp_order_map.get(&tp_type).unwrap().orders.len();
+
+        // 空单同样校验杠杆倍数 名义价值40000的档位最大杠杆为125倍
+        let mut user = get_user();
+        let actions = vec![
+            get_new_order(tp_type, EOrderAction::Sell, "10000", "4", "300", EOrderType::Limit),
+            get_new_order(tp_type, EOrderAction::Sell, "10000", "4", "400", EOrderType::Limit),
+        ];
+        runner.sync_strategy_action(actions, &tp_type, &mut user, &SDebugConfig::default());
+        assert_eq!(get_order_cnt(&user), 1);
+
+        // 单笔订单均未超过最大杠杆 叠加后仓位名义价值80000的档位最大杠杆为100倍
+        let mut user = get_user();
+        let actions = vec![
+            get_new_order(tp_type, EOrderAction::Buy, "10000", "4", "370", EOrderType::Limit),
+            get_new_order(tp_type, EOrderAction::Buy, "10000", "4", "370", EOrderType::Limit),
+        ];
+        runner.sync_strategy_action(actions, &tp_type, &mut user, &SDebugConfig::default());
+        assert_eq!(get_order_cnt(&user), 1);
+
+        // 已有仓位计入成交后仓位 减仓订单不校验
+        assert_eq!(parse_kline(&runner, tp_type, &klines[0], &mut user).len(), 1);
+        let actions = vec![
+            get_new_order(tp_type, EOrderAction::Buy, "10000", "4", "370", EOrderType::Limit),
+            get_new_order(tp_type, EOrderAction::Sell, "10000", "2", "0", EOrderType::Limit),
+        ];
+        runner.sync_strategy_action(actions, &tp_type, &mut user, &SDebugConfig::default());
+        let orders: Vec<EOrderAction> = user.tp_order_map.get(&tp_type).unwrap().orders.values().map(|order| order.get_action()).collect();
+        assert_eq!(orders, vec![EOrderAction::Sell]);
+    }
+
+    #[test]
+    pub fn test_cross_margin_balance() {
+        let tp_type = ETradingPairType::BtcUsdtFuture;
+        let klines = get_klines(&[(100, 50, 100, 10, 10)]);
+        for cross_margin_balance in [ECrossMarginBalance::FuturesWallet, ECrossMarginBalance::AvailableBalance] {
+            let mut runner = get_runner(&[ETradingPairType::BtcUsdt, tp_type], &klines);
+            runner.config.margin_mode = EMarginMode::Cross;
+            runner.config.cross_margin_balance = cross_margin_balance;
+            // 用户同时持有现货（1000USDT 1BTC）和20倍杠杆的U本位合约多头
+            let mut user = get_user();
+            let margin_asset = SAsset { as_type: EAssetType::Usdt, balance: Decimal::from(100) };
+            user.available_assets.merge_asset(EAssetUnion::BtcUsdtFuture(SAssetLeveraged::new(tp_type, Decimal::from(20), margin_asset, Decimal::from(100)).unwrap()));
+
+            parse_kline(&runner, tp_type, &klines[0], &mut user);
+            match user.available_assets.get(&EAssetType::BtcUsdtFuture).unwrap() {
+                EAssetUnion::BtcUsdtFuture(asset_leveraged) => { assert_eq!(asset_leveraged.get_base().balance, Decimal::from(0)) }
+                _ => { panic!() }
+            }
+            // 现货BTC不作为U本位合约的保证金
+            assert_eq!(get_balance(&user, EAssetType::Btc), Decimal::from(1));
+            match cross_margin_balance {
+                // 合约账户模式下现货USDT不参与强平清算
+                ECrossMarginBalance::FuturesWallet => { assert!(get_balance(&user, EAssetType::Usdt) >= Decimal::from(1000)) }
+                // 统一账户模式下现货USDT作为共享保证金一并清算
+                ECrossMarginBalance::AvailableBalance => { assert!(get_balance(&user, EAssetType::Usdt) < Decimal::from(1000)) }
+            }
+        }
+    }
+
+    #[test]
+    pub fn test_settlement_dispatch() {
+        let klines = get_klines(&[(100, 102, 103, 99, 10)]);
+        let runner = get_runner(&[ETradingPairType::BtcUsdt, ETradingPairType::BtcUsdtFuture], &klines);
+        let mut user = get_user();
+        for tp_type in [ETradingPairType::BtcUsdt, ETradingPairType::BtcUsdtFuture] {
+            let actions = vec![get_new_order(tp_type, EOrderAction::Buy, "110", "1", "110", EOrderType::Limit)];
+            runner.sync_strategy_action(actions, &tp_type, &mut user, &SDebugConfig::default());
+            assert_eq!(parse_kline(&runner, tp_type, &klines[0], &mut user).len(), 1);
+        }
+
+        // 现货成交获得基础资产 U本位合约成交获得杠杆资产
+        assert_eq!(get_balance(&user, EAssetType::Btc), Decimal::from(2));
+        match user.available_assets.get(&EAssetType::BtcUsdtFuture).unwrap() {
+            EAssetUnion::BtcUsdtFuture(asset_leveraged) => { assert_eq!(asset_leveraged.get_base().balance, Decimal::from(1)) }
+            _ => { panic!() }
+        }
+
+        // 设置的结算处理器优先于交易对默认的结算处理器
+        assert!(matches!(runner.get_settlement_handler(ETradingPairType::BtcUsdt), ESettlementHandlerUnion::Spot(_)));
+        let runner = runner.with_settlement_handler(ETradingPairType::BtcUsdt, ESettlementHandlerUnion::from(ETradingPairType::BtcUsdtFuture));
+        assert!(matches!(runner.get_settlement_handler(ETradingPairType::BtcUsdt), ESettlementHandlerUnion::UsdtMargined(_)));
+    }
+}
@@ -0,0 +1,300 @@
+//! 交易对结算处理器 处理不同品种在下单锁定资产、成交结算时的差异
+//!
+//! 执行器只负责k线驱动、订单撮合和日志记录，新增品种时实现TSettlementHandler并加入ESettlementHandlerUnion即可
+use rust_decimal::{Decimal, RoundingStrategy};
+use crate::data_runtime::asset::asset::SAsset;
+use crate::data_runtime::asset::asset_leveraged::{RAssetLeveragedResult, SAssetLeveraged};
+use crate::data_runtime::asset::asset_union::EAssetUnion;
+use crate::data_runtime::asset::EAssetType;
+use crate::data_runtime::order::EOrderAction;
+use crate::data_runtime::order::order_v3::SOrderV3;
+use crate::data_source::trading_pair::ETradingPairType;
+
+pub trait TSettlementHandler {
+    /// 下单时锁定的资产类型 撤单时锁定资产退还至同一类型
+    fn get_margin_asset_type(&self, tp_type: ETradingPairType, action: EOrderAction) -> EAssetType;
+
+    /// 成交手续费
+    fn get_fee_asset(&self, order: &SOrderV3, order_fee: Decimal) -> EAssetUnion;
+
+    /// 成交后用户获得的资产 consumed_margin_asset为订单消耗的锁定资产
+    fn get_obtain_assets(&self, order: &SOrderV3, consumed_margin_asset: SAsset, order_fee: Decimal) -> RAssetLeveragedResult<Vec<EAssetUnion>>;
+
+    /// 吃单按成交价格可成交的最大数量 锁定资产不足以全部成交时返回Some 无需限制时返回None
+    fn get_taker_max_quantity(&self, order: &SOrderV3) -> Option<Decimal>;
+}
+
+/// 现货结算 买单用计价资产换基础资产 卖单用基础资产换计价资产
+#[derive(Debug, Copy, Clone, Default)]
+pub struct SSpotSettlement;
+
+impl TSettlementHandler for SSpotSettlement {
+    fn get_margin_asset_type(&self, tp_type: ETradingPairType, action: EOrderAction) -> EAssetType {
+        match action {
+            EOrderAction::Buy => { tp_type.get_quote_currency_type() }
+            EOrderAction::Sell => { tp_type.get_base_currency_type() }
+        }
+    }
+
+    fn get_fee_asset(&self, order: &SOrderV3, order_fee: Decimal) -> EAssetUnion {
+        let tp_type = order.get_tp_type();
+        match order.get_action() {
+            // 买单手续费为计价资产
+            EOrderAction::Buy => {
+                EAssetUnion::from(SAsset {
+                    as_type: tp_type.get_quote_currency_type(),
+                    balance: order.get_amount().abs() * order_fee,
+                })
+            }
+            // 卖单手续费为基础资产
+            EOrderAction::Sell => {
+                EAssetUnion::from(SAsset {
+                    as_type: tp_type.get_base_currency_type(),
+                    balance: order.get_quantity().abs() * order_fee,
+                })
+            }
+        }
+    }
+
+    fn get_obtain_assets(&self, order: &SOrderV3, consumed_margin_asset: SAsset, order_fee: Decimal) -> RAssetLeveragedResult<Vec<EAssetUnion>> {
+        let tp_type = order.get_tp_type();
+        let base_quantity = order.get_quantity();
+        let quote_quantity = order.get_amount();
+        let obtain_assets = match order.get_action() {
+            EOrderAction::Buy => {
+                let mut obtain_assets = vec![EAssetUnion::from(SAsset {
+                    as_type: tp_type.get_base_currency_type(),
+                    balance: base_quantity - base_quantity * order_fee,
+                })];
+                // 吃单按参考价格锁定计价资产 退还锁定资产与成交金额的差额
+                // 成交数量已由get_taker_max_quantity限制在锁定资产范围内 差额不为负数
+                if order.is_taker() {
+                    obtain_assets.push(EAssetUnion::from(SAsset {
+                        as_type: consumed_margin_asset.as_type,
+                        balance: consumed_margin_asset.balance - quote_quantity,
+                    }));
+                }
+                obtain_assets
+            }
+            EOrderAction::Sell => {
+                vec![EAssetUnion::from(SAsset {
+                    as_type: tp_type.get_quote_currency_type(),
+                    balance: quote_quantity - quote_quantity * order_fee,
+                })]
+            }
+        };
+        Ok(obtain_assets)
+    }
+
+    /// 买单成交价格因跳空或滑点高于下单时的参考价格时 按锁定的计价资产可买入的数量成交
+    /// 数量按基础资产精度（8位小数）向下取整 保证成交金额不超过锁定资产
+    fn get_taker_max_quantity(&self, order: &SOrderV3) -> Option<Decimal> {
+        if order.get_action() != EOrderAction::Buy || order.get_price() <= Decimal::from(0) {
+            return None;
+        }
+        let locked_balance = order.get_locked_asset().as_ref()?.balance;
+        if order.get_amount() <= locked_balance {
+            return None;
+        }
+        Some((locked_balance / order.get_price()).round_dp_with_strategy(8, RoundingStrategy::ToZero))
+    }
+}
+
+/// U本位合约结算 以USDT为保证金 成交后获得杠杆资产
+#[derive(Debug, Copy, Clone, Default)]
+pub struct SUsdtMarginedSettlement;
+
+impl TSettlementHandler for SUsdtMarginedSettlement {
+    fn get_margin_asset_type(&self, tp_type: ETradingPairType, _action: EOrderAction) -> EAssetType {
+        tp_type.get_quote_currency_type()
+    }
+
+    fn get_fee_asset(&self, order: &SOrderV3, order_fee: Decimal) -> EAssetUnion {
+        // 手续费为保证金资产 必须为正数（避免做空时支付空的手续费）
+        let balance = match order.get_action() {
+            EOrderAction::Buy => { order.get_amount().abs() * order_fee }
+            EOrderAction::Sell => { order.get_quantity().abs() * order.get_price() * order_fee }
+        };
+        EAssetUnion::from(SAsset {
+            as_type: order.get_tp_type().get_quote_currency_type(),
+            balance,
+        })
+    }
+
+    fn get_obtain_assets(&self, order: &SOrderV3, consumed_margin_asset: SAsset, order_fee: Decimal) -> RAssetLeveragedResult<Vec<EAssetUnion>> {
+        let base_quantity = order.get_quantity();
+        let asset_leveraged = SAssetLeveraged::new(
+            order.get_tp_type(),
+            base_quantity - base_quantity.abs() * order_fee,
+            consumed_margin_asset,
+            order.get_price(),
+        )?;
+        Ok(vec![EAssetUnion::BtcUsdtFuture(asset_leveraged)])
+    }
+
+    /// 合约只锁定保证金 成交价格不影响锁定资产 不限制成交数量
+    fn get_taker_max_quantity(&self, _order: &SOrderV3) -> Option<Decimal> {
+        None
+    }
+}
+
+/// 币本位合约结算 以BTC为保证金 成交后获得杠杆资产
+#[derive(Debug, Copy, Clone, Default)]
+pub struct SCoinMarginedSettlement;
+
+impl TSettlementHandler for SCoinMarginedSettlement {
+    fn get_margin_asset_type(&self, tp_type: ETradingPairType, _action: EOrderAction) -> EAssetType {
+        tp_type.get_quote_currency_type()
+    }
+
+    fn get_fee_asset(&self, order: &SOrderV3, order_fee: Decimal) -> EAssetUnion {
+        // 手续费为保证金资产 必须为正数（避免做空时支付空的手续费）
+        let balance = match order.get_action() {
+            EOrderAction::Buy => { order.get_amount().abs() * order_fee }
+            EOrderAction::Sell => { order.get_quantity().abs() * order_fee }
+        };
+        EAssetUnion::from(SAsset {
+            as_type: order.get_tp_type().get_quote_currency_type(),
+            balance,
+        })
+    }
+
+    fn get_obtain_assets(&self, order: &SOrderV3, consumed_margin_asset: SAsset, order_fee: Decimal) -> RAssetLeveragedResult<Vec<EAssetUnion>> {
+        let base_quantity = order.get_quantity();
+        let asset_leveraged = SAssetLeveraged::new(
+            order.get_tp_type(),
+            base_quantity - base_quantity.abs() * order_fee,
+            consumed_margin_asset,
+            order.get_price(),
+        )?;
+        Ok(vec![EAssetUnion::BtcUsdCmFuture(asset_leveraged)])
+    }
+
+    /// 合约只锁定保证金 成交价格不影响锁定资产 不限制成交数量
+    fn get_taker_max_quantity(&self, _order: &SOrderV3) -> Option<Decimal> {
+        None
+    }
+}
+
+/// 运行时可选择的结算处理器
+#[derive(Debug, Copy, Clone)]
+pub enum ESettlementHandlerUnion {
+    Spot(SSpotSettlement),
+    UsdtMargined(SUsdtMarginedSettlement),
+    CoinMargined(SCoinMarginedSettlement),
+}
+
+impl From<ETradingPairType> for ESettlementHandlerUnion {
+    /// 交易对默认的结算处理器
+    fn from(value: ETradingPairType) -> Self {
+        match value {
+            ETradingPairType::BtcUsdt => { Self::Spot(SSpotSettlement) }
+            ETradingPairType::BtcUsdtFuture => { Self::UsdtMargined(SUsdtMarginedSettlement) }
+            ETradingPairType::BtcUsdCmFuture => { Self::CoinMargined(SCoinMarginedSettlement) }
+        }
+    }
+}
+
+impl TSettlementHandler for ESettlementHandlerUnion {
+    fn get_margin_asset_type(&self, tp_type: ETradingPairType, action: EOrderAction) -> EAssetType {
+        match self {
+            ESettlementHandlerUnion::Spot(handler) => { handler.get_margin_asset_type(tp_type, action) }
+            ESettlementHandlerUnion::UsdtMargined(handler) => { handler.get_margin_asset_type(tp_type, action) }
+            ESettlementHandlerUnion::CoinMargined(handler) => { handler.get_margin_asset_type(tp_type, action) }
+        }
+    }
+
+    fn get_fee_asset(&self, order: &SOrderV3, order_fee: Decimal) -> EAssetUnion {
+        match self {
+            ESettlementHandlerUnion::Spot(handler) => { handler.get_fee_asset(order, order_fee) }
+            ESettlementHandlerUnion::UsdtMargined(handler) => { handler.get_fee_asset(order, order_fee) }
+            ESettlementHandlerUnion::CoinMargined(handler) => { handler.get_fee_asset(order, order_fee) }
+        }
+    }
+
+    fn get_obtain_assets(&self, order: &SOrderV3, consumed_margin_asset: SAsset, order_fee: Decimal) -> RAssetLeveragedResult<Vec<EAssetUnion>> {
+        match self {
+            ESettlementHandlerUnion::Spot(handler) => { handler.get_obtain_assets(order, consumed_margin_asset, order_fee) }
+            ESettlementHandlerUnion::UsdtMargined(handler) => { handler.get_obtain_assets(order, consumed_margin_asset, order_fee) }
+            ESettlementHandlerUnion::CoinMargined(handler) => { handler.get_obtain_assets(order, consumed_margin_asset, order_fee) }
+        }
+    }
+
+    fn get_taker_max_quantity(&self, order: &SOrderV3) -> Option<Decimal> {
+        match self {
+            ESettlementHandlerUnion::Spot(handler) => { handler.get_taker_max_quantity(order) }
+            ESettlementHandlerUnion::UsdtMargined(handler) => { handler.get_taker_max_quantity(order) }
+            ESettlementHandlerUnion::CoinMargined(handler) => { handler.get_taker_max_quantity(order) }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rust_decimal::Decimal;
+    use crate::data_runtime::asset::asset::SAsset;
+    use crate::data_runtime::asset::asset_union::EAssetUnion;
+    use crate::data_runtime::asset::EAssetType;
+    use crate::data_runtime::order::{EOrderAction, EOrderType};
+    use crate::data_runtime::order::order_v3::SOrderV3;
+    use crate::data_source::trading_pair::ETradingPairType;
+    use crate::runner::back_trade::settlement::{ESettlementHandlerUnion, TSettlementHandler};
+
+    fn assert_asset(asset: &EAssetUnion, as_type: EAssetType, balance: Decimal) {
+        assert_eq!(asset.get_asset_type(), as_type);
+        assert_eq!(asset.get_balance(), balance);
+    }
+
+    #[test]
+    pub fn test_spot_settlement() {
+        let handler = ESettlementHandlerUnion::from(ETradingPairType::BtcUsdt);
+        let order_fee = Decimal::new(1, 3);
+        assert_eq!(handler.get_margin_asset_type(ETradingPairType::BtcUsdt, EOrderAction::Buy), EAssetType::Usdt);
+        assert_eq!(handler.get_margin_asset_type(ETradingPairType::BtcUsdt, EOrderAction::Sell), EAssetType::Btc);
+
+        // 买单 手续费为计价资产 获得扣除手续费后的基础资产
+        let buy_order = SOrderV3::new_buy_order(ETradingPairType::BtcUsdt, Decimal::from(100), Decimal::from(2));
+        assert_asset(&handler.get_fee_asset(&buy_order, order_fee), EAssetType::Usdt, Decimal::new(2, 1));
+        let consumed = SAsset { as_type: EAssetType::Usdt, balance: Decimal::from(200) };
+        let obtain_assets = handler.get_obtain_assets(&buy_order, consumed.clone(), order_fee).unwrap();
+        assert_eq!(obtain_assets.len(), 1);
+        assert_asset(&obtain_assets[0], EAssetType::Btc, Decimal::new(1998, 3));
+
+        // 吃单退还锁定资产与成交金额的差额
+        let taker_order = SOrderV3::new_buy_order(ETradingPairType::BtcUsdt, Decimal::from(90), Decimal::from(2)).with_order_type(EOrderType::Market);
+        let obtain_assets = handler.get_obtain_assets(&taker_order, consumed, order_fee).unwrap();
+        assert_asset(&obtain_assets[1], EAssetType::Usdt, Decimal::from(20));
+
+        // 卖单 手续费为基础资产 获得扣除手续费后的计价资产
+        let sell_order = SOrderV3::new_sell_order(ETradingPairType::BtcUsdt, Decimal::from(100), Decimal::from(2));
+        assert_asset(&handler.get_fee_asset(&sell_order, order_fee), EAssetType::Btc, Decimal::new(2, 3));
+        let consumed = SAsset { as_type: EAssetType::Btc, balance: Decimal::from(2) };
+        let obtain_assets = handler.get_obtain_assets(&sell_order, consumed, order_fee).unwrap();
+        assert_asset(&obtain_assets[0], EAssetType::Usdt, Decimal::new(1998, 1));
+    }
+
+    #[test]
+    pub fn test_leveraged_settlement() {
+        let order_fee = Decimal::new(1, 3);
+        for (tp_type, margin_type) in [(ETradingPairType::BtcUsdtFuture, EAssetType::Usdt), (ETradingPairType::BtcUsdCmFuture, EAssetType::Btc)] {
+            let handler = ESettlementHandlerUnion::from(tp_type);
+            // 合约买卖均锁定保证金资产
+            assert_eq!(handler.get_margin_asset_type(tp_type, EOrderAction::Buy), margin_type);
+            assert_eq!(handler.get_margin_asset_type(tp_type, EOrderAction::Sell), margin_type);
+
+            let order = SOrderV3::new_buy_order(tp_type, Decimal::from(100), Decimal::from(2));
+            let fee_asset = handler.get_fee_asset(&order, order_fee);
+            assert_eq!(fee_asset.get_asset_type(), margin_type);
+            assert!(fee_asset.get_balance() > Decimal::from(0));
+
+            let consumed = SAsset { as_type: margin_type, balance: Decimal::from(10) };
+            let obtain_assets = handler.get_obtain_assets(&order, consumed, order_fee).unwrap();
+            assert_eq!(obtain_assets.len(), 1);
+            assert_eq!(obtain_assets[0].get_asset_type(), tp_type.get_base_currency_type());
+
+            // 保证金类型与交易对不一致
+            let consumed = SAsset { as_type: tp_type.get_base_currency_type(), balance: Decimal::from(10) };
+            assert!(handler.get_obtain_assets(&order, consumed, order_fee).is_err());
+        }
+    }
+}
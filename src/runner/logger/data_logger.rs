@@ -109,6 +109,7 @@ impl SDataLogger {
                     trading_pair_prices,
                 ),
                 total_fee_usdt: user_log.total_fee_usdt,
+                total_funding_fee_usdt: user_log.total_funding_fee_usdt,
                 usdt_total,
                 usdt_available: fn_get_asset_balance(&assets_available, &EAssetType::Usdt),
                 usdt_locked: fn_get_asset_balance(&assets_locked, &EAssetType::Usdt),
@@ -144,6 +145,9 @@ impl SDataLogger {
                 unfulfilled_sell_usdt_cnt: user_log.transfer_info.unfulfilled_sell_usdt_cnt,
                 executed_buy_usdt_cnt: user_log.transfer_info.executed_buy_usdt_cnt,
                 executed_sell_usdt_cnt: user_log.transfer_info.executed_sell_usdt_cnt,
+                liquidated_cnt: user_log.transfer_info.liquidated_cnt,
+                closed_order_pair_cnt: user_log.closed_order_pair_cnt,
+                winning_order_pair_cnt: user_log.winning_order_pair_cnt,
             };
             wtr.serialize(merged_output).unwrap();
         }
@@ -198,6 +202,12 @@ struct SMergeOutput {
     pub executed_buy_usdt_cnt: Decimal,
     /// 已成交的卖出资产量（USDT计价）
     pub executed_sell_usdt_cnt: Decimal,
+    /// 强平次数
+    pub liquidated_cnt: i64,
+    /// 累计已平仓的策略订单对数量
+    pub closed_order_pair_cnt: i64,
+    /// 累计盈利的策略订单对数量
+    pub winning_order_pair_cnt: i64,
 
     // -----资产信息-----
     /// 资产总量（USDT计价）
@@ -213,6 +223,8 @@ struct SMergeOutput {
 
     /// 累计手续费（Usdt计价）
     pub total_fee_usdt: Decimal,
+    /// 累计资金费（Usdt计价 负值表示累计收取）
+    pub total_funding_fee_usdt: Decimal,
 
     /// USDT可用量
     pub usdt_available: Decimal,
@@ -19,6 +19,8 @@ pub struct SDataLogTransferUnit {
     pub executed_buy_usdt_cnt: Decimal,
     /// 已成交的卖出资产量（USDT计价）
     pub executed_sell_usdt_cnt: Decimal,
+    /// 强平次数
+    pub liquidated_cnt: i64,
 }
 
 impl SDataLogTransferUnit {
@@ -35,6 +37,7 @@ impl SDataLogTransferUnit {
             unfulfilled_sell_usdt_cnt: unfulfilled.unfulfilled_sell_usdt_cnt,
             executed_buy_usdt_cnt: executed.executed_buy_usdt_cnt,
             executed_sell_usdt_cnt: executed.executed_sell_usdt_cnt,
+            liquidated_cnt: executed.liquidated_cnt,
         }
     }
 }
@@ -63,5 +66,7 @@ pub struct SDataLogTransferExecutedUnit {
     pub executed_buy_usdt_cnt: Decimal,
     /// 已成交的卖出资产量（USDT计价）
     pub executed_sell_usdt_cnt: Decimal,
+    /// 强平次数
+    pub liquidated_cnt: i64,
 }
 
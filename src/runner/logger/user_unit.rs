@@ -54,8 +54,20 @@ pub struct SDataLogUserUnit {
     /// 累计手续费（Usdt计价 以当前时刻的价格计价）
     pub total_fee_usdt: Decimal,
 
+    /// 累计资金费（负值表示累计收取）
+    pub total_funding_fee: SAssetMap,
+
+    /// 累计资金费（Usdt计价 以当前时刻的价格计价）
+    pub total_funding_fee_usdt: Decimal,
+
     /// 目标仓位
     pub target_position_ratio: Option<Decimal>,
+
+    // -----策略订单对-----
+    /// 累计已平仓的策略订单对数量
+    pub closed_order_pair_cnt: i64,
+    /// 累计盈利的策略订单对数量
+    pub winning_order_pair_cnt: i64,
 }
 
 impl SDataLogUserUnit {
@@ -85,6 +97,7 @@ impl SDataLogUserUnit {
             btc_usdt_highest_buy_price,
             btc_usdt_lowest_sell_price,
         };
+        let strategy_log_info = user.strategy.get_log_info();
         // ------
         Self {
             time,
@@ -102,7 +115,11 @@ impl SDataLogUserUnit {
             locked_assets_usdt: assets_map_denominate_usdt(&user.locked_assets(), &trading_pair_prices),
             total_fee: user.total_fee(),
             total_fee_usdt: assets_map_denominate_usdt_old(&user.total_fee(), &trading_pair_prices), // todo 将old函数改为new
+            total_funding_fee: user.total_funding_fee(),
+            total_funding_fee_usdt: assets_map_denominate_usdt_old(&user.total_funding_fee(), trading_pair_prices),
             target_position_ratio,
+            closed_order_pair_cnt: strategy_log_info.closed_order_pair_cnt,
+            winning_order_pair_cnt: strategy_log_info.winning_order_pair_cnt,
         }
     }
 
@@ -1,20 +1,21 @@
 //! 执行器执行脚本 程序主要入口
 
+pub mod registry;
+pub mod cli;
+pub mod sweep;
+pub mod walk_forward;
+
 use std::sync::{Arc, mpsc, Mutex};
 use std::thread;
 use chrono::{DateTime, Duration, Local};
-use log::info;
+use log::{error, info};
 use threadpool::ThreadPool;
 use rust_decimal::Decimal;
 use rust_decimal::prelude::FromPrimitive;
 use tokio::runtime::Runtime;
 use crate::{
-    config::*,
+    config::SDebugConfig,
     data_runtime::user::{SUser, SUserConfig},
-    data_source::{
-        data_manager::SDataManager,
-        db::api::data_api_db::SDataApiDb,
-    },
     runner::{
         back_trade::{
             config::SBackTradeRunnerConfig,
@@ -22,15 +23,18 @@ use crate::{
         },
         TRunner,
     },
-    strategy::TStrategy,
+    strategy::{TStrategy, TStrategyFromConfig},
 };
-use crate::config::back_trade_period::{config_date_from, config_date_to};
-use crate::config::fee::{MAKER_ORDER_FEE, TAKER_ORDER_FEE};
-use crate::config::user::INIT_BALANCE_USDT;
+use crate::config::backtest_config::SBacktestConfig;
+use crate::data_source::data_manager::SDataManager;
+use crate::data_source::db::api::data_api_union::EDataApiUnion;
 use crate::data_source::trading_pair::ETradingPairType;
+use crate::report::chart::{EChartFormat, SBacktestChart};
+use crate::report::performance::SPerformanceReport;
+use crate::report::window::{SFailedWindow, SIndependentWindowsReport};
 use crate::runner::logger::data_logger::SDataLogger;
 use crate::runner::{SRunnerResult, TRunnerGetPrice};
-use crate::runner::back_trade::runner_leveraged::SLeveragedBackTradeRunner;
+use crate::script::registry::{EScriptError, RScriptResult};
 
 pub struct SScript<R, S>
 where
@@ -58,40 +62,25 @@ where
     }
 }
 
-impl<S> SScript<SBackTradeRunner<SDataApiDb>, S>
+impl<S> SScript<SBackTradeRunner<EDataApiUnion>, S>
 where
-    S: TStrategy + Default,
+    S: TStrategy + TStrategyFromConfig,
 {
     /// 回测 单线程计算
-    pub fn back_trader_single_thread_computing() {
+    pub fn back_trader_single_thread_computing(config: &SBacktestConfig) -> RScriptResult<()> {
         println!("启动单线程回测");
         let script_start_time = Local::now().format("%Y%m%d_%H%M%S");
 
         // 配置runner
-        let date_from = config_date_from();
-        let date_to = config_date_to() + Duration::minutes(1);
-        let runner_config = SBackTradeRunnerConfig {
-            taker_order_fee: Decimal::from_f64(TAKER_ORDER_FEE).unwrap(),
-            maker_order_fee: Decimal::from_f64(MAKER_ORDER_FEE).unwrap(),
-            date_from,
-            date_to,
-        };
-        let rt = Runtime::new().unwrap();
-        let data_manager = rt.block_on(SDataManager::build(&runner_config.date_from, &runner_config.date_to));
+        let mut runner_config = config.get_runner_config()?;
+        let date_from = runner_config.date_from;
+        runner_config.date_to += Duration::minutes(1);
+        let data_manager = build_data_manager(config, &runner_config.date_from, &runner_config.date_to)?;
         let runner = SBackTradeRunner::new(runner_config, data_manager);
 
         // 配置 user
-        let strategy = S::default();
-        let position = strategy.get_position(date_from).unwrap();
-        let price = runner.get_price(date_from, ETradingPairType::BtcUsdt).unwrap().close_price;
-        let init_asset_total_usdt = Decimal::from_f64(INIT_BALANCE_USDT).unwrap();
-        let init_balance_btc = init_asset_total_usdt * position / price;
-        let init_balance_usdt = init_asset_total_usdt * (Decimal::from(1) - position);
-        let user_config = SUserConfig {
-            user_name: user::USER_NAME.to_string(),
-            init_balance_usdt,
-            init_balance_btc,
-        };
+        let strategy = S::from_init_balance_usdt(config.user.init_balance_usdt);
+        let user_config = get_user_config_with_strategy_position(config, &runner, &strategy, date_from)?;
         let users = vec![
             SUser::<S>::new(user_config, strategy)
         ];
@@ -102,18 +91,25 @@ where
             users,
             runner,
         }.run(debug_config);
-        result.data_logger.output_user(String::from(format!("data/back_trade/{}.csv", script_start_time)));
+        let output_path = config.get_output_path(&script_start_time.to_string());
+        result.data_logger.output_user(output_path.clone());
+        output_report(&result.data_logger, &output_path, config.chart_format);
+        Ok(())
     }
 
-    /// 回测 多线程计算
-    pub fn back_trader_multi_thread_computing() {
-        info!("启动多线程回测");
+    /// 回测 多线程计算（独立窗口模式）
+    ///
+    /// 回测周期拆分为多个日期窗口，每个窗口按窗口起始日期的仓位以全新用户独立回测，资产、挂单和策略状态不跨窗口延续。
+    /// 各窗口的资产量不可直接拼接 因此不输出合并的回测结果 只输出按窗口归一化的独立窗口报告（后缀为_windows.json及_windows.csv）
+    pub fn back_trader_multi_thread_computing(config: &SBacktestConfig) -> RScriptResult<()> {
+        info!("启动多线程回测（独立窗口模式 各窗口资产与策略状态不连续）");
         let script_start_time = Local::now().format("%Y%m%d_%H%M%S");
         // let strategy = S::default();
 
         // 日期拆分
-        let date_from = config_date_from();
-        let date_to = config_date_to() + Duration::minutes(1);
+        let base_runner_config = config.get_runner_config()?;
+        let date_from = base_runner_config.date_from;
+        let date_to = base_runner_config.date_to + Duration::minutes(1);
         // let date_split_step = Duration::days(20);   // 每个分组的间隔时间（20天）
 
         // 自适应日期分区大小
@@ -145,6 +141,9 @@ where
         let total_tasks = date_split_vec.len();  // 任务的总数
         info!("任务总数：{:?}", total_tasks);
 
+        // 一次性加载完整回测周期的数据 各任务通过时间窗口共享同一份只读数据
+        let data_manager = build_data_manager(config, &date_from, &date_to)?;
+
         // 收集键值对到一个中间容器中
         let tasks: Vec<(DateTime<Local>, DateTime<Local>)> = date_split_vec
             .iter()
@@ -152,48 +151,35 @@ where
             .collect();
 
         // 构造并发任务（Map）
-        let mut count = 0;
-        for (date_from, date_to) in tasks {
-            count += 1;
+        for (index, (date_from, date_to)) in tasks.into_iter().enumerate() {
             let tx = tx.clone();
             let progress = Arc::clone(&progress);
-            let tmp_count = count;
+            let tmp_count = index + 1;
+            let config = config.clone();
+            let base_runner_config = base_runner_config.clone();
+            let data_manager = data_manager.window(date_from, date_to);
             pool.execute(move || {
-                info!("提交任务：N0.{:?}\tfrom-{:?}\tto-{:?}", tmp_count, date_from.clone(), date_to.clone());
+                info!("提交任务：N0.{:?}\tfrom-{:?}\tto-{:?}", tmp_count, date_from, date_to);
 
                 // 配置runner
                 let runner_config = SBackTradeRunnerConfig {
-                    taker_order_fee: Decimal::from_f64(TAKER_ORDER_FEE).unwrap(),
-                    maker_order_fee: Decimal::from_f64(MAKER_ORDER_FEE).unwrap(),
-                    date_from: date_from.clone(),
-                    date_to: date_to.clone(),
+                    date_from,
+                    date_to,
+                    ..base_runner_config
                 };
-                let rt = Runtime::new().unwrap();
-                let data_manager = rt.block_on(SDataManager::build(&runner_config.date_from, &runner_config.date_to));
                 let runner = SBackTradeRunner::new(runner_config, data_manager);
 
-                // 配置 user
-                let strategy = S::default();
-                let position = strategy.get_position(date_from).unwrap();
-                let price = runner.get_price(date_from, ETradingPairType::BtcUsdt).unwrap().close_price;
-                let init_asset_total_usdt = Decimal::from_f64(INIT_BALANCE_USDT).unwrap();
-                let init_balance_btc = init_asset_total_usdt * position / price;
-                let init_balance_usdt = init_asset_total_usdt * (Decimal::from(1) - position);
-                let user_config = SUserConfig {
-                    user_name: user::USER_NAME.to_string(),
-                    init_balance_usdt,
-                    init_balance_btc,
-                };
-
-                // 执行回测
-                let result = SScript {
-                    users: vec![
-                        SUser::<S>::new(user_config, S::default())
-                    ],
-                    runner,
-                }.run(SDebugConfig { is_debug: false, is_info: false });
+                // 配置 user 并执行回测
+                let strategy = S::from_init_balance_usdt(config.user.init_balance_usdt);
+                let result = get_user_config_with_strategy_position(&config, &runner, &strategy, date_from)
+                    .map(|user_config| SScript {
+                        users: vec![
+                            SUser::<S>::new(user_config, strategy)
+                        ],
+                        runner,
+                    }.run(SDebugConfig { is_debug: false, is_info: false }));
                 // 处理回测结果
-                tx.send(result).unwrap();
+                tx.send((date_from, date_to, result)).unwrap();
                 let mut prog = progress.lock().unwrap();
                 *prog += 1;
             });
@@ -212,57 +198,58 @@ where
         });
 
         // 收集并发处理结果（Reduce）
-        // 将结果merge为一个SDataLogger
-        let mut results = SDataLogger::new();
-        for _ in 0..total_tasks {
-            let mut result = rx.recv().unwrap();
-            // println!("result:{:?}", result);
-            results.append(&mut result.data_logger);
+        // 保留各窗口的SDataLogger用于独立窗口报告
+        // 任务异常退出时不再等待其结果
+        // 单个窗口回测失败时记录失败原因并继续收集其余窗口
+        drop(tx);
+        let mut window_loggers = Vec::new();
+        let mut failed_windows = Vec::new();
+        for (date_from, date_to, result) in rx.iter() {
+            match result {
+                Ok(result) => { window_loggers.push((date_from, date_to, result.data_logger)); }
+                Err(e) => {
+                    error!("窗口回测失败：from-{:?}\tto-{:?}\t{}", date_from, date_to, e);
+                    failed_windows.push(SFailedWindow { date_from, date_to, error: e.to_string() });
+                }
+            }
+        }
+        if window_loggers.len() + failed_windows.len() < total_tasks {
+            error!("{}个窗口回测异常退出", total_tasks - window_loggers.len() - failed_windows.len());
         }
 
         info!("已完成！");
 
         // 将结果存储到文件中
-        results.output_user(String::from(format!("data/back_trade/{}.csv", script_start_time)));
+        let output_path = config.get_output_path(&script_start_time.to_string());
+        output_windows_report(&window_loggers, failed_windows, &output_path);
+        Ok(())
     }
 }
-impl<S> SScript<SLeveragedBackTradeRunner<SDataApiDb>, S>
+
+impl<S> SScript<SBackTradeRunner<EDataApiUnion>, S>
 where
-    S: TStrategy + Default,
+    S: TStrategy + TStrategyFromConfig,
 {
-    /// 回测 单线程计算
-    pub fn back_trader_single_thread_computing() {
+    /// 杠杆回测 单线程计算 按币本位合约价格初始化用户
+    pub fn leveraged_single_thread_computing(config: &SBacktestConfig) -> RScriptResult<()> {
         println!("启动单线程回测");
         let script_start_time = Local::now().format("%Y%m%d_%H%M%S");
 
         // 配置runner
-        let date_from = config_date_from();
-        let date_to = config_date_to() + Duration::minutes(1);
-        let runner_config = SBackTradeRunnerConfig {
-            taker_order_fee: Decimal::from_f64(TAKER_ORDER_FEE).unwrap(),
-            maker_order_fee: Decimal::from_f64(MAKER_ORDER_FEE).unwrap(),
-            date_from,
-            date_to,
-        };
-        let rt = Runtime::new().unwrap();
-        let data_manager = rt.block_on(SDataManager::build(&runner_config.date_from, &runner_config.date_to));
-        let runner = SLeveragedBackTradeRunner::new(runner_config, data_manager);
+        let mut runner_config = config.get_runner_config()?;
+        let date_from = runner_config.date_from;
+        runner_config.date_to += Duration::minutes(1);
+        let data_manager = build_data_manager(config, &runner_config.date_from, &runner_config.date_to)?;
+        let runner = SBackTradeRunner::new(runner_config, data_manager);
 
         // 配置 user
-        let strategy = S::default();
+        let strategy = S::from_init_balance_usdt(config.user.init_balance_usdt);
         // let position = strategy.get_position(date_from).unwrap();
         let position = Decimal::from_f64(0.5).unwrap();
         // todo 
         // let price = runner.get_price(date_from, ETradingPairType::BtcUsdt).unwrap().close_price;
-        let price = runner.get_price(date_from, ETradingPairType::BtcUsdCmFuture).unwrap().close_price;
-        let init_asset_total_usdt = Decimal::from_f64(INIT_BALANCE_USDT).unwrap();
-        let init_balance_btc = init_asset_total_usdt * position / price;
-        let init_balance_usdt = init_asset_total_usdt * (Decimal::from(1) - position);
-        let user_config = SUserConfig {
-            user_name: user::USER_NAME.to_string(),
-            init_balance_usdt,
-            init_balance_btc,
-        };
+        let price = get_start_price(&runner, date_from, ETradingPairType::BtcUsdCmFuture)?;
+        let user_config = config.get_user_config_with_position(position, price)?;
         let users = vec![
             SUser::<S>::new(user_config, strategy)
         ];
@@ -273,6 +260,75 @@ where
             users,
             runner,
         }.run(debug_config);
-        result.data_logger.output_user(String::from(format!("data/back_trade/{}.csv", script_start_time)));
+        let output_path = config.get_output_path(&script_start_time.to_string());
+        result.data_logger.output_user(output_path.clone());
+        output_report(&result.data_logger, &output_path, config.chart_format);
+        Ok(())
+    }
+}
+
+/// 加载回测周期的数据
+fn build_data_manager(config: &SBacktestConfig, date_from: &DateTime<Local>, date_to: &DateTime<Local>) -> RScriptResult<SDataManager<EDataApiUnion>> {
+    let rt = Runtime::new().map_err(|e| EScriptError::DataError(e.to_string()))?;
+    rt.block_on(config.data_source.build_data_manager(date_from, date_to)).map_err(|e| EScriptError::DataError(e.to_string()))
+}
+
+/// 获取回测起始时间的收盘价 用于按仓位初始化用户
+fn get_start_price(runner: &SBackTradeRunner<EDataApiUnion>, date_from: DateTime<Local>, tp_type: ETradingPairType) -> RScriptResult<Decimal> {
+    runner
+        .get_price(date_from, tp_type)
+        .map(|kline| kline.close_price)
+        .ok_or(EScriptError::PriceNotFoundError(tp_type, date_from))
+}
+
+/// 按策略在回测起始时间的仓位及现货价格生成用户配置
+fn get_user_config_with_strategy_position<S: TStrategy>(
+    config: &SBacktestConfig,
+    runner: &SBackTradeRunner<EDataApiUnion>,
+    strategy: &S,
+    date_from: DateTime<Local>,
+) -> RScriptResult<SUserConfig> {
+    let position = strategy.get_position(date_from).ok_or(EScriptError::PositionNotFoundError(date_from))?;
+    let price = get_start_price(runner, date_from, ETradingPairType::BtcUsdt)?;
+    config.get_user_config_with_position(position, price).map_err(EScriptError::ConfigError)
+}
+
+/// 计算独立窗口报告 打印摘要并输出JSON及CSV文件（与回测结果同目录 后缀为_windows.json及_windows.csv）
+fn output_windows_report(window_loggers: &[(DateTime<Local>, DateTime<Local>, SDataLogger)], failed_windows: Vec<SFailedWindow>, output_path: &str) {
+    match SIndependentWindowsReport::from_loggers(window_loggers) {
+        Err(e) => { error!("{}", e); }
+        Ok(report) => {
+            let report = report.with_failed_windows(failed_windows);
+            info!("独立窗口报告:\n{}", report);
+            let path_prefix = output_path.strip_suffix(".csv").unwrap_or(output_path);
+            for result in [
+                report.output_json(&format!("{}_windows.json", path_prefix)),
+                report.output_csv(&format!("{}_windows.csv", path_prefix)),
+            ] {
+                match result {
+                    Err(e) => { error!("{}", e); }
+                    Ok(_) => { println!("独立窗口报告写入完成：{}_windows.*", path_prefix); }
+                }
+            }
+        }
     }
-}
\ No newline at end of file
+}
+
+/// 计算回测报告并绘制图表 打印摘要并输出JSON文件和图表（与回测结果同目录 后缀为_report.json及图表名称）
+fn output_report(data_logger: &SDataLogger, output_path: &str, chart_format: EChartFormat) {
+    match SPerformanceReport::from_logger(data_logger) {
+        Err(e) => { error!("{}", e); }
+        Ok(report) => {
+            info!("回测报告:\n{}", report);
+            let report_path = format!("{}_report.json", output_path.strip_suffix(".csv").unwrap_or(output_path));
+            match report.output_json(&report_path) {
+                Err(e) => { error!("{}", e); }
+                Ok(_) => { println!("回测报告写入完成：{}", report_path); }
+            }
+        }
+    }
+    match SBacktestChart::from_logger(data_logger).render(output_path, chart_format) {
+        Err(e) => { error!("回测图表绘制失败: {}", e); }
+        Ok(paths) => { println!("回测图表写入完成：{:?}", paths); }
+    }
+}
@@ -0,0 +1,341 @@
+//! 回测命令行参数
+use clap::{Parser, ValueEnum};
+use crate::config::backtest_config::{EIntraBarPathKind, EMakerFillModelKind, EPeriodPreset, RBacktestConfigResult, SBacktestConfig};
+use crate::data_source::kline::EKlineInterval;
+use crate::report::chart::EChartFormat;
+use crate::script::registry::{ERunMode, ERunnerKind, RScriptResult, SStrategyRegistry};
+use crate::script::sweep::ESweepMetric;
+
+/// 命令行的k线周期 对应数据层的EKlineInterval
+#[derive(Debug, Copy, Clone, Eq, PartialEq, ValueEnum)]
+pub enum EKlineIntervalArg {
+    /// 1分钟
+    Minute1,
+    /// 5分钟
+    Minute5,
+    /// 15分钟
+    Minute15,
+    /// 1小时
+    Hour1,
+    /// 4小时
+    Hour4,
+    /// 1天
+    Day1,
+}
+
+impl From<EKlineIntervalArg> for EKlineInterval {
+    fn from(value: EKlineIntervalArg) -> Self {
+        match value {
+            EKlineIntervalArg::Minute1 => { EKlineInterval::Minute1 }
+            EKlineIntervalArg::Minute5 => { EKlineInterval::Minute5 }
+            EKlineIntervalArg::Minute15 => { EKlineInterval::Minute15 }
+            EKlineIntervalArg::Hour1 => { EKlineInterval::Hour1 }
+            EKlineIntervalArg::Hour4 => { EKlineInterval::Hour4 }
+            EKlineIntervalArg::Day1 => { EKlineInterval::Day1 }
+        }
+    }
+}
+
+/// 命令行的回测周期预设 对应配置层的EPeriodPreset
+#[derive(Debug, Copy, Clone, Eq, PartialEq, ValueEnum)]
+pub enum EPeriodPresetArg {
+    /// 震荡走势 周期15小时
+    RangingShort,
+    /// 震荡走势 周期2个月
+    RangingTwoMonths,
+    /// Debug用
+    Debug,
+    /// 单边上涨 周期6天
+    OneSidedRally,
+    /// 单边下跌 周期4天
+    OneSidedDrop,
+    /// 4年比特币周期
+    FourYearCycle,
+    /// 2019~2025
+    From2019To2025,
+    /// 目标仓位为负数的情况
+    NegativeTargetPosition,
+}
+
+impl From<EPeriodPresetArg> for EPeriodPreset {
+    fn from(value: EPeriodPresetArg) -> Self {
+        match value {
+            EPeriodPresetArg::RangingShort => { EPeriodPreset::RangingShort }
+            EPeriodPresetArg::RangingTwoMonths => { EPeriodPreset::RangingTwoMonths }
+            EPeriodPresetArg::Debug => { EPeriodPreset::Debug }
+            EPeriodPresetArg::OneSidedRally => { EPeriodPreset::OneSidedRally }
+            EPeriodPresetArg::OneSidedDrop => { EPeriodPreset::OneSidedDrop }
+            EPeriodPresetArg::FourYearCycle => { EPeriodPreset::FourYearCycle }
+            EPeriodPresetArg::From2019To2025 => { EPeriodPreset::From2019To2025 }
+            EPeriodPresetArg::NegativeTargetPosition => { EPeriodPreset::NegativeTargetPosition }
+        }
+    }
+}
+
+/// 命令行的图表格式 对应报告层的EChartFormat
+#[derive(Debug, Copy, Clone, Eq, PartialEq, ValueEnum)]
+pub enum EChartFormatArg {
+    /// PNG图片
+    Png,
+    /// SVG矢量图
+    Svg,
+}
+
+impl From<EChartFormatArg> for EChartFormat {
+    fn from(value: EChartFormatArg) -> Self {
+        match value {
+            EChartFormatArg::Png => { EChartFormat::Png }
+            EChartFormatArg::Svg => { EChartFormat::Svg }
+        }
+    }
+}
+
+/// 命令行的挂单成交模型 对应配置层的EMakerFillModelKind
+#[derive(Debug, Copy, Clone, Eq, PartialEq, ValueEnum)]
+pub enum EMakerFillModelArg {
+    /// 触价成交
+    Touch,
+    /// 穿价成交
+    TradeThrough,
+    /// 排队成交
+    Queue,
+}
+
+impl From<EMakerFillModelArg> for EMakerFillModelKind {
+    fn from(value: EMakerFillModelArg) -> Self {
+        match value {
+            EMakerFillModelArg::Touch => { EMakerFillModelKind::Touch }
+            EMakerFillModelArg::TradeThrough => { EMakerFillModelKind::TradeThrough }
+            EMakerFillModelArg::Queue => { EMakerFillModelKind::Queue }
+        }
+    }
+}
+
+/// 命令行的k线内价格路径模型 对应配置层的EIntraBarPathKind
+#[derive(Debug, Copy, Clone, Eq, PartialEq, ValueEnum)]
+pub enum EIntraBarPathArg {
+    /// 先结算所有买单再结算所有卖单
+    BuyThenSell,
+    /// OHLC顺序
+    Ohlc,
+    /// 随机布朗桥
+    BrownianBridge,
+}
+
+impl From<EIntraBarPathArg> for EIntraBarPathKind {
+    fn from(value: EIntraBarPathArg) -> Self {
+        match value {
+            EIntraBarPathArg::BuyThenSell => { EIntraBarPathKind::BuyThenSell }
+            EIntraBarPathArg::Ohlc => { EIntraBarPathKind::Ohlc }
+            EIntraBarPathArg::BrownianBridge => { EIntraBarPathKind::BrownianBridge }
+        }
+    }
+}
+
+/// 命令行的参数扫描排序指标 对应ESweepMetric
+#[derive(Debug, Copy, Clone, Eq, PartialEq, ValueEnum)]
+pub enum ESweepMetricArg {
+    /// 总收益率
+    TotalReturn,
+    /// 年化收益率
+    AnnualizedReturn,
+    /// 夏普比率
+    SharpeRatio,
+    /// 索提诺比率
+    SortinoRatio,
+    /// 卡玛比率
+    CalmarRatio,
+    /// 最大回撤（越小越好）
+    MaxDrawdown,
+    /// 超额收益
+    ExcessReturn,
+}
+
+impl From<ESweepMetricArg> for ESweepMetric {
+    fn from(value: ESweepMetricArg) -> Self {
+        match value {
+            ESweepMetricArg::TotalReturn => { ESweepMetric::TotalReturn }
+            ESweepMetricArg::AnnualizedReturn => { ESweepMetric::AnnualizedReturn }
+            ESweepMetricArg::SharpeRatio => { ESweepMetric::SharpeRatio }
+            ESweepMetricArg::SortinoRatio => { ESweepMetric::SortinoRatio }
+            ESweepMetricArg::CalmarRatio => { ESweepMetric::CalmarRatio }
+            ESweepMetricArg::MaxDrawdown => { ESweepMetric::MaxDrawdown }
+            ESweepMetricArg::ExcessReturn => { ESweepMetric::ExcessReturn }
+        }
+    }
+}
+
+/// 多交易对回测
+#[derive(Debug, Parser)]
+#[command(name = "run")]
+pub struct SCliArgs {
+    /// 配置文件路径(.toml/.json) 未指定时读取环境变量BACKTEST_CONFIG，都未设置时使用默认配置
+    #[arg(short, long)]
+    pub config: Option<String>,
+    /// 执行器
+    #[arg(short, long, value_enum, default_value_t = ERunnerKind::Leveraged)]
+    pub runner: ERunnerKind,
+    /// 策略 如mk1/mk3_2/mk_test_leveraged 使用--list查看所有已注册的策略
+    #[arg(short, long, default_value = "mk_test_leveraged")]
+    pub strategy: String,
+    /// 价格模型 如long_term_trend/sin/step
+    #[arg(short, long)]
+    pub model: Option<String>,
+    /// 运行模式
+    #[arg(long, value_enum, default_value_t = ERunMode::SingleThread)]
+    pub mode: ERunMode,
+    /// 预设回测周期 覆盖配置文件
+    #[arg(short, long, value_enum)]
+    pub preset: Option<EPeriodPresetArg>,
+    /// 回测起始日期 格式为"%Y-%m-%d %H:%M:%S"
+    #[arg(long)]
+    pub from: Option<String>,
+    /// 回测结束日期 格式为"%Y-%m-%d %H:%M:%S"
+    #[arg(long)]
+    pub to: Option<String>,
+    /// 回测结果输出路径
+    #[arg(short, long)]
+    pub output: Option<String>,
+    /// 回测图表格式 覆盖配置文件
+    #[arg(long, value_enum)]
+    pub chart_format: Option<EChartFormatArg>,
+    /// 挂单成交模型 覆盖配置文件
+    #[arg(long, value_enum)]
+    pub maker_fill_model: Option<EMakerFillModelArg>,
+    /// k线内价格路径模型 覆盖配置文件
+    #[arg(long, value_enum)]
+    pub intra_bar_path: Option<EIntraBarPathArg>,
+    /// 策略周期 覆盖配置文件
+    #[arg(long, value_enum)]
+    pub strategy_interval: Option<EKlineIntervalArg>,
+    /// 参数扫描结果排序指标 覆盖配置文件
+    #[arg(long, value_enum)]
+    pub rank_by: Option<ESweepMetricArg>,
+    /// 加载数据前删除当前数据源的所有缓存
+    #[arg(long)]
+    pub invalidate_cache: bool,
+    /// 列出所有已注册的策略
+    #[arg(long)]
+    pub list: bool,
+}
+
+impl SCliArgs {
+    /// 加载配置文件并使用命令行参数覆盖
+    pub fn load_config(&self) -> RBacktestConfigResult<SBacktestConfig> {
+        let config_path = self.config.clone().or_else(|| std::env::var("BACKTEST_CONFIG").ok());
+        let mut config = match config_path {
+            None => { SBacktestConfig::default() }
+            Some(config_path) => { SBacktestConfig::load(config_path)? }
+        };
+
+        if let Some(preset) = self.preset {
+            config.period.preset = preset.into();
+            config.period.date_from = None;
+            config.period.date_to = None;
+        }
+        // 只设置一端日期时 另一端使用当前配置的周期
+        if self.from.is_some() || self.to.is_some() {
+            let (date_from, date_to) = config.period.get_period()?;
+            let date_format = "%Y-%m-%d %H:%M:%S";
+            config.period.date_from = Some(self.from.clone().unwrap_or_else(|| date_from.format(date_format).to_string()));
+            config.period.date_to = Some(self.to.clone().unwrap_or_else(|| date_to.format(date_format).to_string()));
+        }
+        if let Some(output) = &self.output {
+            config.output_path = Some(output.clone());
+        }
+        if let Some(chart_format) = self.chart_format {
+            config.chart_format = chart_format.into();
+        }
+        if let Some(maker_fill_model) = self.maker_fill_model {
+            config.fill_model.maker_fill_model = maker_fill_model.into();
+        }
+        if let Some(intra_bar_path) = self.intra_bar_path {
+            config.fill_model.intra_bar_path = intra_bar_path.into();
+        }
+        if let Some(strategy_interval) = self.strategy_interval {
+            config.strategy_interval = strategy_interval.into();
+        }
+        if let Some(rank_by) = self.rank_by {
+            config.sweep.rank_by = rank_by.into();
+        }
+        if self.invalidate_cache {
+            config.data_source.invalidate_cache = true;
+        }
+        Ok(config)
+    }
+
+    /// 执行命令
+    pub fn run(&self, registry: &SStrategyRegistry) -> RScriptResult<()> {
+        if self.list {
+            for key in registry.keys() {
+                println!("{:?}\t{}\t{}", key.runner, key.strategy, key.price_model.as_deref().unwrap_or("-"));
+            }
+            return Ok(());
+        }
+        let config = self.load_config()?;
+        registry.run(self.runner, &self.strategy, self.model.as_deref(), self.mode, &config)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+    use crate::config::backtest_config::{parse_date_time, EPeriodPreset};
+    use crate::data_source::kline::EKlineInterval;
+    use crate::report::chart::EChartFormat;
+    use crate::runner::back_trade::fill_model::EMakerFillModel;
+    use crate::runner::back_trade::price_path::EIntraBarPathModel;
+    use crate::script::cli::{EPeriodPresetArg, SCliArgs};
+    use crate::script::registry::{ERunMode, ERunnerKind, EScriptError, SStrategyRegistry};
+    use crate::script::sweep::ESweepMetric;
+
+    #[test]
+    pub fn test_parse() {
+        let args = SCliArgs::try_parse_from(["run"]).unwrap();
+        assert_eq!(args.runner, ERunnerKind::Leveraged);
+        assert_eq!(args.strategy, "mk_test_leveraged");
+        assert_eq!(args.mode, ERunMode::SingleThread);
+
+        let args = SCliArgs::try_parse_from([
+            "run", "-r", "back-trade", "-s", "mk3_2", "-m", "sin", "--mode", "multi-thread",
+            "-p", "four-year-cycle", "--to", "2019-01-01 00:00:00", "-o", "data/back_trade/test.csv", "--chart-format", "svg",
+            "--maker-fill-model", "trade-through", "--intra-bar-path", "ohlc", "--strategy-interval", "hour4",
+        ]).unwrap();
+        assert_eq!(args.runner, ERunnerKind::BackTrade);
+        assert_eq!(args.model.as_deref(), Some("sin"));
+        assert_eq!(args.mode, ERunMode::MultiThread);
+        assert_eq!(args.preset, Some(EPeriodPresetArg::FourYearCycle));
+        assert_eq!(EPeriodPreset::from(EPeriodPresetArg::FourYearCycle), EPeriodPreset::FourYearCycle);
+
+        let config = args.load_config().unwrap();
+        let runner_config = config.get_runner_config().unwrap();
+        assert_eq!(runner_config.date_from, parse_date_time("2018-08-28 00:00:00").unwrap());
+        assert_eq!(runner_config.date_to, parse_date_time("2019-01-01 00:00:00").unwrap());
+        assert_eq!(config.get_output_path("20250101_000000"), "data/back_trade/test.csv");
+        assert_eq!(config.chart_format, EChartFormat::Svg);
+        assert_eq!(runner_config.fill_model.maker_fill_model, EMakerFillModel::TradeThrough);
+        assert_eq!(runner_config.fill_model.intra_bar_path, EIntraBarPathModel::Ohlc);
+        assert_eq!(runner_config.strategy_interval, EKlineInterval::Hour4);
+        assert!(!config.data_source.invalidate_cache);
+        let args = SCliArgs::try_parse_from(["run", "--invalidate-cache"]).unwrap();
+        assert!(args.load_config().unwrap().data_source.invalidate_cache);
+
+        assert!(SCliArgs::try_parse_from(["run", "-r", "unknown"]).is_err());
+    }
+
+    #[test]
+    pub fn test_parse_sweep() {
+        let args = SCliArgs::try_parse_from(["run", "-r", "back-trade", "-s", "mk5", "-m", "long_term_trend", "--mode", "sweep", "--rank-by", "calmar-ratio"]).unwrap();
+        assert_eq!(args.mode, ERunMode::Sweep);
+        assert_eq!(args.load_config().unwrap().sweep.rank_by, ESweepMetric::CalmarRatio);
+        let args = SCliArgs::try_parse_from(["run", "-r", "back-trade", "-s", "mk5", "-m", "long_term_trend", "--mode", "walk-forward"]).unwrap();
+        assert_eq!(args.mode, ERunMode::WalkForward);
+    }
+
+    #[test]
+    pub fn test_run_not_found() {
+        let registry = SStrategyRegistry::default();
+        let args = SCliArgs::try_parse_from(["run", "-r", "back-trade", "-s", "mk3", "-m", "long_term_trend"]).unwrap();
+        assert!(matches!(args.run(&registry), Err(EScriptError::StrategyNotFoundError(..))));
+    }
+}
@@ -0,0 +1,321 @@
+//! 回测入口注册表
+//!
+//! 按（执行器，策略，价格模型）登记回测入口，新增策略只需在注册表中登记，无需修改main
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use chrono::{DateTime, Local};
+use clap::ValueEnum;
+use crate::config::backtest_config::{EBacktestConfigError, SBacktestConfig};
+use crate::data_source::db::api::data_api_union::EDataApiUnion;
+use crate::data_source::trading_pair::ETradingPairType;
+use crate::runner::back_trade::runner::SBackTradeRunner;
+use crate::script::SScript;
+use crate::script::sweep::{ESweepError, TSweepStrategy};
+use crate::strategy::mk1::SStrategyMk1;
+use crate::strategy::mk2::SStrategyMk2;
+use crate::strategy::mk3::SStrategyMk3;
+use crate::strategy::mk3_2::SStrategyMk3_2;
+use crate::strategy::mk4::SStrategyMk4;
+use crate::strategy::mk5::SStrategyMk5;
+use crate::strategy::mk_test::SStrategyMkTest;
+use crate::strategy::mk_test_leveraged::SStrategyMkTestLeveraged;
+use crate::strategy::model::price_model_long_term_trend::SPriceModelLongTermTrend;
+use crate::strategy::model::price_model_sin_test::SPriceModelSin;
+use crate::strategy::model::price_model_step_test::SPriceModelStep;
+use crate::strategy::{TStrategy, TStrategyFromConfig};
+
+pub type RScriptResult<T> = Result<T, EScriptError>;
+
+/// 回测脚本异常
+#[derive(Debug)]
+pub enum EScriptError {
+    /// 未注册的策略(执行器，策略，价格模型)
+    StrategyNotFoundError(ERunnerKind, String, Option<String>),
+    /// 策略不支持该运行模式(策略，运行模式)
+    RunModeNotSupportedError(String, ERunMode),
+    /// 配置异常
+    ConfigError(EBacktestConfigError),
+    /// 参数扫描配置异常
+    SweepError(ESweepError),
+    /// 回测数据加载失败(错误信息)
+    DataError(String),
+    /// 回测起始时间缺少交易对价格(交易对，时间)
+    PriceNotFoundError(ETradingPairType, DateTime<Local>),
+    /// 策略在回测起始时间无法给出仓位(时间)
+    PositionNotFoundError(DateTime<Local>),
+}
+
+impl Display for EScriptError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EScriptError::StrategyNotFoundError(runner, strategy, price_model) => {
+                write!(f, "未注册的策略: 执行器={:?} 策略={} 价格模型={}", runner, strategy, price_model.as_deref().unwrap_or("无"))
+            }
+            EScriptError::RunModeNotSupportedError(strategy, run_mode) => { write!(f, "策略{}不支持运行模式{:?}", strategy, run_mode) }
+            EScriptError::ConfigError(e) => { write!(f, "{}", e) }
+            EScriptError::SweepError(e) => { write!(f, "{}", e) }
+            EScriptError::DataError(e) => { write!(f, "回测数据加载失败: {}", e) }
+            EScriptError::PriceNotFoundError(tp_type, time) => { write!(f, "回测起始时间缺少价格: {:?} {}", tp_type, time) }
+            EScriptError::PositionNotFoundError(time) => { write!(f, "策略无法给出回测起始时间的仓位: {}", time) }
+        }
+    }
+}
+
+impl Error for EScriptError {}
+
+impl From<EBacktestConfigError> for EScriptError {
+    fn from(value: EBacktestConfigError) -> Self {
+        Self::ConfigError(value)
+    }
+}
+
+impl From<ESweepError> for EScriptError {
+    fn from(value: ESweepError) -> Self {
+        Self::SweepError(value)
+    }
+}
+
+/// 执行器类型
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, ValueEnum)]
+pub enum ERunnerKind {
+    /// 现货回测 按策略仓位初始化用户
+    BackTrade,
+    /// 杠杆回测 按币本位合约价格初始化用户
+    Leveraged,
+}
+
+/// 运行模式
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Default, ValueEnum)]
+pub enum ERunMode {
+    /// 单线程计算
+    #[default]
+    SingleThread,
+    /// 按日期分区多线程计算 各窗口独立回测 只输出独立窗口报告
+    MultiThread,
+    /// 参数扫描 按配置的参数范围多线程回测
+    Sweep,
+    /// 滚动前推优化 样本内参数扫描并在样本外验证
+    WalkForward,
+}
+
+/// 回测入口函数 配置或数据异常时返回错误
+pub type FScriptEntry = fn(&SBacktestConfig) -> RScriptResult<()>;
+
+/// 注册表键
+#[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct SStrategyKey {
+    pub runner: ERunnerKind,
+    pub strategy: String,
+    /// 价格模型 不依赖价格模型的策略为None
+    pub price_model: Option<String>,
+}
+
+impl SStrategyKey {
+    pub fn new(runner: ERunnerKind, strategy: &str, price_model: Option<&str>) -> Self {
+        Self {
+            runner,
+            strategy: strategy.to_string(),
+            price_model: price_model.map(|price_model| price_model.to_string()),
+        }
+    }
+}
+
+/// 注册的回测入口
+#[derive(Debug, Clone, Copy)]
+pub struct SStrategyEntry {
+    pub single_thread: FScriptEntry,
+    /// 多线程入口 不支持多线程时为None
+    pub multi_thread: Option<FScriptEntry>,
+    /// 参数扫描入口 不支持参数扫描时为None
+    pub sweep: Option<FScriptEntry>,
+    /// 滚动前推优化入口 不支持参数扫描时为None
+    pub walk_forward: Option<FScriptEntry>,
+}
+
+/// 回测入口注册表
+#[derive(Debug, Clone)]
+pub struct SStrategyRegistry {
+    entries: BTreeMap<SStrategyKey, SStrategyEntry>,
+}
+
+impl Default for SStrategyRegistry {
+    /// 注册所有内置策略
+    fn default() -> Self {
+        let mut registry = Self::new();
+        registry
+            .register_back_trade::<SStrategyMkTest>("mk_test", None)
+            .register_back_trade::<SStrategyMk1>("mk1", None)
+            .register_back_trade::<SStrategyMk2>("mk2", None)
+            .register_back_trade::<SStrategyMk3<SPriceModelSin>>("mk3", Some("sin"))
+            .register_back_trade::<SStrategyMk3<SPriceModelStep>>("mk3", Some("step"))
+            .register_back_trade::<SStrategyMk3_2<SPriceModelSin>>("mk3_2", Some("sin"))
+            .register_back_trade::<SStrategyMk3_2<SPriceModelStep>>("mk3_2", Some("step"))
+            .register_back_trade::<SStrategyMk4<SPriceModelLongTermTrend>>("mk4", Some("long_term_trend"))
+            .register_back_trade::<SStrategyMk4<SPriceModelSin>>("mk4", Some("sin"))
+            .register_back_trade::<SStrategyMk4<SPriceModelStep>>("mk4", Some("step"))
+            .register_back_trade_sweep::<SStrategyMk5<SPriceModelLongTermTrend>>("mk5", Some("long_term_trend"))
+            .register_back_trade::<SStrategyMk5<SPriceModelSin>>("mk5", Some("sin"))
+            .register_back_trade::<SStrategyMk5<SPriceModelStep>>("mk5", Some("step"))
+            .register_leveraged::<SStrategyMkTestLeveraged>("mk_test_leveraged", None);
+        registry
+    }
+}
+
+impl SStrategyRegistry {
+    /// 空注册表
+    pub fn new() -> Self {
+        Self {
+            entries: BTreeMap::new(),
+        }
+    }
+
+    /// 登记回测入口 同一个键重复登记时覆盖原入口
+    pub fn register(&mut self, key: SStrategyKey, entry: SStrategyEntry) -> &mut Self {
+        self.entries.insert(key, entry);
+        self
+    }
+
+    /// 登记现货回测策略
+    pub fn register_back_trade<S>(&mut self, strategy: &str, price_model: Option<&str>) -> &mut Self
+    where
+        S: TStrategy + TStrategyFromConfig,
+    {
+        self.register(
+            SStrategyKey::new(ERunnerKind::BackTrade, strategy, price_model),
+            SStrategyEntry {
+                single_thread: SScript::<SBackTradeRunner<EDataApiUnion>, S>::back_trader_single_thread_computing,
+                multi_thread: Some(SScript::<SBackTradeRunner<EDataApiUnion>, S>::back_trader_multi_thread_computing),
+                sweep: None,
+                walk_forward: None,
+            },
+        )
+    }
+
+    /// 登记支持参数扫描的现货回测策略
+    pub fn register_back_trade_sweep<S>(&mut self, strategy: &str, price_model: Option<&str>) -> &mut Self
+    where
+        S: TSweepStrategy + TStrategyFromConfig,
+    {
+        self.register(
+            SStrategyKey::new(ERunnerKind::BackTrade, strategy, price_model),
+            SStrategyEntry {
+                single_thread: SScript::<SBackTradeRunner<EDataApiUnion>, S>::back_trader_single_thread_computing,
+                multi_thread: Some(SScript::<SBackTradeRunner<EDataApiUnion>, S>::back_trader_multi_thread_computing),
+                sweep: Some(SScript::<SBackTradeRunner<EDataApiUnion>, S>::back_trader_parameter_sweep),
+                walk_forward: Some(SScript::<SBackTradeRunner<EDataApiUnion>, S>::back_trader_walk_forward),
+            },
+        )
+    }
+
+    /// 登记杠杆回测策略
+    pub fn register_leveraged<S>(&mut self, strategy: &str, price_model: Option<&str>) -> &mut Self
+    where
+        S: TStrategy + TStrategyFromConfig,
+    {
+        self.register(
+            SStrategyKey::new(ERunnerKind::Leveraged, strategy, price_model),
+            SStrategyEntry {
+                single_thread: SScript::<SBackTradeRunner<EDataApiUnion>, S>::leveraged_single_thread_computing,
+                multi_thread: None,
+                sweep: None,
+                walk_forward: None,
+            },
+        )
+    }
+
+    pub fn get(&self, runner: ERunnerKind, strategy: &str, price_model: Option<&str>) -> RScriptResult<&SStrategyEntry> {
+        self.entries
+            .get(&SStrategyKey::new(runner, strategy, price_model))
+            .ok_or(EScriptError::StrategyNotFoundError(runner, strategy.to_string(), price_model.map(|price_model| price_model.to_string())))
+    }
+
+    /// 所有已登记的键
+    pub fn keys(&self) -> impl Iterator<Item = &SStrategyKey> {
+        self.entries.keys()
+    }
+
+    /// 查找并执行回测
+    pub fn run(&self, runner: ERunnerKind, strategy: &str, price_model: Option<&str>, run_mode: ERunMode, config: &SBacktestConfig) -> RScriptResult<()> {
+        let entry = self.get(runner, strategy, price_model)?;
+        let script_entry = match run_mode {
+            ERunMode::SingleThread => { entry.single_thread }
+            ERunMode::MultiThread => {
+                entry.multi_thread.ok_or(EScriptError::RunModeNotSupportedError(strategy.to_string(), run_mode))?
+            }
+            ERunMode::Sweep => {
+                entry.sweep.ok_or(EScriptError::RunModeNotSupportedError(strategy.to_string(), run_mode))?
+            }
+            ERunMode::WalkForward => {
+                entry.walk_forward.ok_or(EScriptError::RunModeNotSupportedError(strategy.to_string(), run_mode))?
+            }
+        };
+        script_entry(config)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::backtest_config::{EBacktestConfigError, SBacktestConfig};
+    use crate::script::registry::{ERunMode, ERunnerKind, EScriptError, RScriptResult, SStrategyEntry, SStrategyKey, SStrategyRegistry};
+
+    #[test]
+    pub fn test_default() {
+        let registry = SStrategyRegistry::default();
+        assert_eq!(registry.keys().count(), 14);
+        assert!(registry.get(ERunnerKind::BackTrade, "mk3_2", Some("sin")).is_ok());
+        assert!(registry.get(ERunnerKind::BackTrade, "mk4", Some("long_term_trend")).unwrap().multi_thread.is_some());
+        assert!(registry.get(ERunnerKind::Leveraged, "mk_test_leveraged", None).unwrap().multi_thread.is_none());
+        assert!(registry.get(ERunnerKind::BackTrade, "mk5", Some("long_term_trend")).unwrap().sweep.is_some());
+        assert!(registry.get(ERunnerKind::BackTrade, "mk5", Some("long_term_trend")).unwrap().walk_forward.is_some());
+        assert!(registry.get(ERunnerKind::BackTrade, "mk4", Some("long_term_trend")).unwrap().sweep.is_none());
+        assert!(registry.get(ERunnerKind::BackTrade, "mk4", Some("step")).is_ok());
+        assert!(registry.get(ERunnerKind::BackTrade, "mk5", Some("sin")).unwrap().sweep.is_none());
+        // 价格模型不匹配
+        assert!(matches!(registry.get(ERunnerKind::BackTrade, "mk3", Some("long_term_trend")), Err(EScriptError::StrategyNotFoundError(..))));
+        // 执行器不匹配
+        assert!(registry.get(ERunnerKind::Leveraged, "mk1", None).is_err());
+    }
+
+    #[test]
+    pub fn test_register() {
+        fn entry(_config: &SBacktestConfig) -> RScriptResult<()> { Ok(()) }
+        fn failed_entry(_config: &SBacktestConfig) -> RScriptResult<()> { Err(EScriptError::ConfigError(EBacktestConfigError::CsvDirNotSetError)) }
+
+        let mut registry = SStrategyRegistry::new();
+        let key = SStrategyKey::new(ERunnerKind::BackTrade, "custom", None);
+        registry.register(key.clone(), SStrategyEntry { single_thread: entry, multi_thread: None, sweep: None, walk_forward: None });
+        assert_eq!(registry.keys().next(), Some(&key));
+
+        let config = SBacktestConfig::default();
+        assert!(registry.run(ERunnerKind::BackTrade, "custom", None, ERunMode::SingleThread, &config).is_ok());
+        assert!(matches!(
+            registry.run(ERunnerKind::BackTrade, "custom", None, ERunMode::MultiThread, &config),
+            Err(EScriptError::RunModeNotSupportedError(..))
+        ));
+        assert!(matches!(
+            registry.run(ERunnerKind::BackTrade, "custom", None, ERunMode::Sweep, &config),
+            Err(EScriptError::RunModeNotSupportedError(..))
+        ));
+        assert!(matches!(
+            registry.run(ERunnerKind::BackTrade, "custom", None, ERunMode::WalkForward, &config),
+            Err(EScriptError::RunModeNotSupportedError(..))
+        ));
+
+        // 内置入口的配置异常作为错误返回
+        let mut invalid_config = SBacktestConfig::default();
+        invalid_config.period.date_from = Some(String::from("2024-02-01 00:00:00"));
+        invalid_config.period.date_to = Some(String::from("2024-01-01 00:00:00"));
+        assert!(matches!(
+            SStrategyRegistry::default().run(ERunnerKind::BackTrade, "mk1", None, ERunMode::MultiThread, &invalid_config),
+            Err(EScriptError::ConfigError(EBacktestConfigError::DateRangeError(..)))
+        ));
+
+        // 入口执行失败时返回错误
+        registry.register(key, SStrategyEntry { single_thread: failed_entry, multi_thread: None, sweep: None, walk_forward: None });
+        assert!(matches!(
+            registry.run(ERunnerKind::BackTrade, "custom", None, ERunMode::SingleThread, &config),
+            Err(EScriptError::ConfigError(..))
+        ));
+    }
+}
@@ -0,0 +1,500 @@
+//! 策略参数扫描
+//!
+//! 按配置的参数范围生成参数组合（笛卡尔积），在线程池中并行回测整个回测周期，
+//! 输出每组参数一行的结果表及按排序指标选出的最优参数
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::fs::File;
+use std::sync::mpsc;
+use chrono::{DateTime, Duration, Local};
+use log::{error, info};
+use serde::{Deserialize, Serialize};
+use threadpool::ThreadPool;
+use crate::config::SDebugConfig;
+use crate::config::backtest_config::SBacktestConfig;
+use crate::data_runtime::user::SUser;
+use crate::data_source::db::api::data_api_union::EDataApiUnion;
+use crate::report::performance::SPerformanceReport;
+use crate::data_source::data_manager::SDataManager;
+use crate::runner::back_trade::config::SBackTradeRunnerConfig;
+use crate::runner::back_trade::runner::SBackTradeRunner;
+use crate::runner::logger::data_logger::SDataLogger;
+use crate::script::registry::RScriptResult;
+use crate::script::{build_data_manager, get_user_config_with_strategy_position, SScript};
+use crate::strategy::TStrategy;
+
+pub type RSweepResult<T> = Result<T, ESweepError>;
+
+/// 参数扫描异常
+#[derive(Debug)]
+pub enum ESweepError {
+    /// 策略不支持该参数(参数名)
+    UnknownParameterError(String),
+    /// 参数取值非法(参数名，取值)
+    InvalidParameterError(String, f64),
+    /// 参数范围非法(参数名)
+    InvalidRangeError(String),
+    /// 结果输出失败(文件路径，错误信息)
+    OutputError(String, String),
+    /// 回测周期不足一个样本内+样本外窗口，或窗口长度不为正(回测起始日期，回测结束日期)
+    WalkForwardWindowError(DateTime<Local>, DateTime<Local>),
+}
+
+impl Display for ESweepError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ESweepError::UnknownParameterError(name) => { write!(f, "策略不支持参数: {}", name) }
+            ESweepError::InvalidParameterError(name, value) => { write!(f, "参数取值非法: {}={}", name, value) }
+            ESweepError::InvalidRangeError(name) => { write!(f, "参数范围非法(步长需要大于0且start不大于end): {}", name) }
+            ESweepError::OutputError(path, e) => { write!(f, "参数扫描结果输出失败: {} ({})", path, e) }
+            ESweepError::WalkForwardWindowError(from, to) => { write!(f, "回测周期无法划分滚动前推窗口: {} ~ {}", from, to) }
+        }
+    }
+}
+
+impl Error for ESweepError {}
+
+/// 参数取值范围
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum ESweepRange {
+    /// 枚举取值
+    Values(Vec<f64>),
+    /// 等间距取值 包含start和end
+    Range {
+        start: f64,
+        end: f64,
+        step: f64,
+    },
+}
+
+impl ESweepRange {
+    /// 展开为取值列表
+    pub fn get_values(&self, name: &str) -> RSweepResult<Vec<f64>> {
+        match self {
+            ESweepRange::Values(values) => {
+                if values.is_empty() {
+                    return Err(ESweepError::InvalidRangeError(name.to_string()));
+                }
+                Ok(values.clone())
+            }
+            ESweepRange::Range { start, end, step } => {
+                if *step <= 0.0 || start > end {
+                    return Err(ESweepError::InvalidRangeError(name.to_string()));
+                }
+                // 容忍浮点误差 并去除累加产生的尾数
+                let count = ((end - start) / step + 1e-9).floor() as usize;
+                Ok((0..=count).map(|i| ((start + step * i as f64) * 1e12).round() / 1e12).collect())
+            }
+        }
+    }
+}
+
+/// 结果排序指标
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Default, Serialize, Deserialize)]
+pub enum ESweepMetric {
+    /// 总收益率
+    TotalReturn,
+    /// 年化收益率
+    AnnualizedReturn,
+    /// 夏普比率
+    #[default]
+    SharpeRatio,
+    /// 索提诺比率
+    SortinoRatio,
+    /// 卡玛比率
+    CalmarRatio,
+    /// 最大回撤（越小越好）
+    MaxDrawdown,
+    /// 超额收益
+    ExcessReturn,
+}
+
+impl ESweepMetric {
+    /// 排序分数 分数越大越好 指标不存在时为None
+    pub fn get_score(&self, report: &SPerformanceReport) -> Option<f64> {
+        let stats = &report.strategy;
+        match self {
+            ESweepMetric::TotalReturn => { Some(stats.total_return) }
+            ESweepMetric::AnnualizedReturn => { Some(stats.annualized_return) }
+            ESweepMetric::SharpeRatio => { stats.sharpe_ratio }
+            ESweepMetric::SortinoRatio => { stats.sortino_ratio }
+            ESweepMetric::CalmarRatio => { stats.calmar_ratio }
+            ESweepMetric::MaxDrawdown => { Some(-stats.max_drawdown) }
+            ESweepMetric::ExcessReturn => { report.excess_return }
+        }
+    }
+}
+
+/// 参数扫描配置
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SSweepConfig {
+    /// 各参数的取值列表 按参数名排序
+    pub parameters: BTreeMap<String, Vec<f64>>,
+    /// 结果排序指标
+    pub rank_by: ESweepMetric,
+    /// 输出的最优参数组数
+    pub top_n: usize,
+}
+
+impl SSweepConfig {
+    /// 所有参数组合（笛卡尔积） 没有配置参数时只有一组空参数（即策略默认参数）
+    pub fn get_param_sets(&self) -> Vec<BTreeMap<String, f64>> {
+        let mut param_sets = vec![BTreeMap::new()];
+        for (name, values) in self.parameters.iter() {
+            param_sets = param_sets
+                .into_iter()
+                .flat_map(|param_set| {
+                    values.iter().map(move |value| {
+                        let mut param_set = param_set.clone();
+                        param_set.insert(name.clone(), *value);
+                        param_set
+                    })
+                })
+                .collect();
+        }
+        param_sets
+    }
+
+    /// 所有参数组合及对应的策略参数 参数名或取值非法时返回异常
+    /// 未扫描的参数取按初始资金量（USDT计价）生成的默认值
+    pub fn get_params<P: TSweepParams>(&self, init_balance_usdt: f64) -> RSweepResult<Vec<(BTreeMap<String, f64>, P)>> {
+        self.get_param_sets()
+            .into_iter()
+            .map(|param_set| {
+                let params = P::from_param_set(&param_set, init_balance_usdt)?;
+                Ok((param_set, params))
+            })
+            .collect()
+    }
+}
+
+/// 可扫描的策略参数
+pub trait TSweepParams: Default + Clone + Serialize + Send + 'static {
+    /// 按配置的初始资金量（USDT计价）生成默认参数 参数与初始资金量无关时即Default
+    fn from_init_balance_usdt(_init_balance_usdt: f64) -> Self {
+        Self::default()
+    }
+
+    /// 按参数名设置参数
+    fn set(&mut self, name: &str, value: f64) -> RSweepResult<()>;
+
+    /// 校验所有参数的取值及参数之间的约束（如最小值不大于最大值）
+    fn validate(&self) -> RSweepResult<()> {
+        Ok(())
+    }
+
+    /// 在按初始资金量生成的默认参数的基础上设置一组参数 设置完成后校验参数
+    fn from_param_set(param_set: &BTreeMap<String, f64>, init_balance_usdt: f64) -> RSweepResult<Self> {
+        let mut params = Self::from_init_balance_usdt(init_balance_usdt);
+        for (name, value) in param_set.iter() {
+            params.set(name, *value)?;
+        }
+        params.validate()?;
+        Ok(params)
+    }
+}
+
+/// 支持参数扫描的策略
+pub trait TSweepStrategy: TStrategy {
+    type Params: TSweepParams;
+
+    fn from_params(params: &Self::Params) -> Self;
+}
+
+/// 单组参数的回测结果
+#[derive(Debug, Clone, Serialize)]
+pub struct SSweepResult<P: Serialize> {
+    /// 参数组序号（从1开始）
+    pub index: usize,
+    /// 扫描的参数
+    pub param_set: BTreeMap<String, f64>,
+    /// 完整的策略参数
+    pub params: P,
+    /// 排序分数
+    pub score: Option<f64>,
+    /// 绩效报告 回测没有产生数据时为None
+    pub report: Option<SPerformanceReport>,
+}
+
+/// 参数扫描报告
+#[derive(Debug, Clone, Serialize)]
+pub struct SSweepReport<P: Serialize> {
+    pub rank_by: ESweepMetric,
+    /// 按参数组序号排序的结果
+    pub results: Vec<SSweepResult<P>>,
+}
+
+impl<P: Serialize> SSweepReport<P> {
+    pub fn new(rank_by: ESweepMetric, mut results: Vec<SSweepResult<P>>) -> Self {
+        results.sort_by_key(|result| result.index);
+        Self {
+            rank_by,
+            results,
+        }
+    }
+
+    /// 按排序分数从高到低取前top_n组结果 没有分数的结果不参与排序
+    pub fn get_best(&self, top_n: usize) -> Vec<&SSweepResult<P>> {
+        let mut results: Vec<&SSweepResult<P>> = self.results.iter().filter(|result| result.score.is_some()).collect();
+        results.sort_by(|a, b| b.score.unwrap().total_cmp(&a.score.unwrap()));
+        results.truncate(top_n);
+        results
+    }
+
+    /// 将结果表以CSV格式输出到指定文件 每组参数一行
+    pub fn output_csv(&self, path: &str) -> RSweepResult<()> {
+        let map_err = |e: csv::Error| ESweepError::OutputError(path.to_string(), e.to_string());
+        let mut wtr = csv::Writer::from_path(path).map_err(map_err)?;
+        let param_names: Vec<String> = self.results.first().map(|result| result.param_set.keys().cloned().collect()).unwrap_or_default();
+        let mut header: Vec<String> = vec![String::from("index")];
+        header.extend(param_names.iter().cloned());
+        header.extend([
+            "score", "total_return", "annualized_return", "volatility", "max_drawdown", "sharpe_ratio", "sortino_ratio",
+            "calmar_ratio", "excess_return", "total_fee_usdt", "turnover", "executed_order_cnt", "liquidated_cnt", "win_rate",
+        ].map(String::from));
+        wtr.write_record(&header).map_err(map_err)?;
+
+        let option_to_string = |value: Option<f64>| value.map(|value| value.to_string()).unwrap_or_default();
+        for result in self.results.iter() {
+            let mut record: Vec<String> = vec![result.index.to_string()];
+            record.extend(param_names.iter().map(|name| option_to_string(result.param_set.get(name).copied())));
+            record.push(option_to_string(result.score));
+            match &result.report {
+                None => { record.extend(std::iter::repeat_n(String::new(), header.len() - record.len())); }
+                Some(report) => {
+                    let stats = &report.strategy;
+                    record.extend([
+                        stats.total_return.to_string(),
+                        stats.annualized_return.to_string(),
+                        stats.volatility.to_string(),
+                        stats.max_drawdown.to_string(),
+                        option_to_string(stats.sharpe_ratio),
+                        option_to_string(stats.sortino_ratio),
+                        option_to_string(stats.calmar_ratio),
+                        option_to_string(report.excess_return),
+                        report.total_fee_usdt.to_string(),
+                        report.turnover.to_string(),
+                        report.executed_order_cnt.to_string(),
+                        report.liquidated_cnt.to_string(),
+                        option_to_string(report.win_rate),
+                    ]);
+                }
+            }
+            wtr.write_record(&record).map_err(map_err)?;
+        }
+        wtr.flush().map_err(|e| ESweepError::OutputError(path.to_string(), e.to_string()))
+    }
+
+    /// 将最优的top_n组结果以JSON格式输出到指定文件
+    pub fn output_best_json(&self, path: &str, top_n: usize) -> RSweepResult<()> {
+        let file = File::create(path).map_err(|e| ESweepError::OutputError(path.to_string(), e.to_string()))?;
+        serde_json::to_writer_pretty(file, &self.get_best(top_n)).map_err(|e| ESweepError::OutputError(path.to_string(), e.to_string()))
+    }
+}
+
+impl<S> SScript<SBackTradeRunner<EDataApiUnion>, S>
+where
+    S: TSweepStrategy,
+{
+    /// 按给定参数回测runner_config的回测周期 按回测起始日期的仓位初始化用户
+    pub fn back_trader_with_params(
+        config: &SBacktestConfig,
+        runner_config: SBackTradeRunnerConfig,
+        data_manager: SDataManager<EDataApiUnion>,
+        params: &S::Params,
+    ) -> RScriptResult<SDataLogger> {
+        let date_from = runner_config.date_from;
+        let runner = SBackTradeRunner::new(runner_config, data_manager);
+
+        // 配置 user
+        let strategy = S::from_params(params);
+        let user_config = get_user_config_with_strategy_position(config, &runner, &strategy, date_from)?;
+
+        // 执行回测
+        Ok(SScript {
+            users: vec![
+                SUser::<S>::new(user_config, strategy)
+            ],
+            runner,
+        }.run(SDebugConfig { is_debug: false, is_info: false }).data_logger)
+    }
+
+    /// 在线程池中逐组回测参数 每组参数回测runner_config的完整回测周期
+    /// 回测异常退出的参数组不计入结果
+    pub fn sweep_param_sets(
+        config: &SBacktestConfig,
+        rank_by: ESweepMetric,
+        param_sets: Vec<(BTreeMap<String, f64>, S::Params)>,
+        runner_config: &SBackTradeRunnerConfig,
+        data_manager: &SDataManager<EDataApiUnion>,
+    ) -> SSweepReport<S::Params> {
+        let total_tasks = param_sets.len();
+        let running_threads = num_cpus::get().saturating_sub(4).max(1);
+        let pool = ThreadPool::new(running_threads);
+        let (tx, rx) = mpsc::channel();
+        for (i, (param_set, params)) in param_sets.into_iter().enumerate() {
+            let tx = tx.clone();
+            let config = config.clone();
+            let runner_config = runner_config.clone();
+            let data_manager = data_manager.window(runner_config.date_from, runner_config.date_to);
+            pool.execute(move || {
+                info!("提交任务：N0.{:?}\t{:?}", i + 1, param_set);
+                let report = match Self::back_trader_with_params(&config, runner_config, data_manager, &params) {
+                    Ok(data_logger) => { SPerformanceReport::from_logger(&data_logger).ok() }
+                    Err(e) => {
+                        error!("N0.{:?}: {}", i + 1, e);
+                        None
+                    }
+                };
+                tx.send(SSweepResult {
+                    index: i + 1,
+                    param_set,
+                    params,
+                    score: report.as_ref().and_then(|report| rank_by.get_score(report)),
+                    report,
+                }).unwrap();
+            });
+        }
+        // 任务异常退出时不再等待其结果
+        drop(tx);
+
+        let mut results = Vec::new();
+        for result in rx.iter() {
+            results.push(result);
+            info!("当前进度：{:.2}%", results.len() as f64 / total_tasks as f64 * 100.0);
+        }
+        if results.len() < total_tasks {
+            error!("{}组参数回测异常退出", total_tasks - results.len());
+        }
+        SSweepReport::new(rank_by, results)
+    }
+
+    /// 参数扫描 每组参数在线程池中回测整个回测周期
+    /// 结果输出到回测结果路径同目录 后缀为_sweep.csv（结果表）及_sweep_best.json（最优参数）
+    pub fn back_trader_parameter_sweep(config: &SBacktestConfig) -> RScriptResult<()> {
+        info!("启动参数扫描");
+        let script_start_time = Local::now().format("%Y%m%d_%H%M%S");
+        // 参数组合 参数范围、参数名或取值非法时不执行回测
+        let sweep_config = config.sweep.get_sweep_config()?;
+        let param_sets = sweep_config.get_params::<S::Params>(config.user.init_balance_usdt)?;
+        info!("参数组合数：{}", param_sets.len());
+
+        // 一次性加载完整回测周期的数据 各任务共享同一份只读数据
+        let mut runner_config = config.get_runner_config()?;
+        runner_config.date_to += Duration::minutes(1);
+        let data_manager = build_data_manager(config, &runner_config.date_from, &runner_config.date_to)?;
+
+        let report = Self::sweep_param_sets(config, sweep_config.rank_by, param_sets, &runner_config, &data_manager);
+        info!("已完成！");
+
+        let output_path = config.get_output_path(&script_start_time.to_string());
+        let path_prefix = output_path.strip_suffix(".csv").unwrap_or(&output_path);
+        for (i, result) in report.get_best(sweep_config.top_n).iter().enumerate() {
+            info!("最优参数{}: N0.{}\t{:?}={:?}\t{:?}", i + 1, result.index, sweep_config.rank_by, result.score, result.param_set);
+        }
+        let sweep_path = format!("{}_sweep.csv", path_prefix);
+        match report.output_csv(&sweep_path) {
+            Err(e) => { error!("{}", e); }
+            Ok(_) => { println!("参数扫描结果写入完成：{}", sweep_path); }
+        }
+        let best_path = format!("{}_sweep_best.json", path_prefix);
+        match report.output_best_json(&best_path, sweep_config.top_n) {
+            Err(e) => { error!("{}", e); }
+            Ok(_) => { println!("最优参数写入完成：{}", best_path); }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+    use chrono::{Duration, Local, TimeZone};
+    use crate::report::performance::{SPerformanceReport, SReportSample};
+    use crate::script::sweep::{ESweepError, ESweepMetric, ESweepRange, SSweepConfig, SSweepReport, SSweepResult, TSweepParams};
+    use crate::config::trading_pair::btc_usdt::TRADDING_PAIR_USDT_MIN_QUANTITY;
+    use crate::config::user::INIT_BALANCE_USDT;
+    use crate::strategy::mk5::SStrategyMk5Params;
+
+    fn get_report(equities: &[f64]) -> SPerformanceReport {
+        let time = Local.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+        SPerformanceReport::from_samples(SReportSample::from_equities(time, Duration::minutes(1), equities)).unwrap()
+    }
+
+    #[test]
+    pub fn test_param_sets() {
+        let range = ESweepRange::Range { start: 0.01, end: 0.03, step: 0.01 };
+        assert_eq!(range.get_values("a").unwrap(), vec![0.01, 0.02, 0.03]);
+        assert!(matches!(ESweepRange::Range { start: 0.01, end: 0.03, step: 0.0 }.get_values("a"), Err(ESweepError::InvalidRangeError(_))));
+        assert!(ESweepRange::Values(vec![]).get_values("a").is_err());
+
+        let config = SSweepConfig {
+            parameters: BTreeMap::from([
+                (String::from("cut_off_price_percentage"), vec![0.01, 0.02, 0.03]),
+                (String::from("pid_p_parameter"), vec![0.5, 1.0]),
+            ]),
+            ..SSweepConfig::default()
+        };
+        let param_sets = config.get_param_sets();
+        assert_eq!(param_sets.len(), 6);
+        assert_eq!(param_sets[1], BTreeMap::from([(String::from("cut_off_price_percentage"), 0.01), (String::from("pid_p_parameter"), 1.0)]));
+        assert_eq!(SSweepConfig::default().get_param_sets().len(), 1);
+
+        let params = SStrategyMk5Params::from_param_set(&param_sets[1], INIT_BALANCE_USDT).unwrap();
+        assert_eq!(params.cut_off_price_percentage, 0.01);
+        assert_eq!(params.minimum_profit_percentage, SStrategyMk5Params::default().minimum_profit_percentage);
+        // 未扫描的订单最小价格间距按配置的初始资金量计算
+        let params = SStrategyMk5Params::from_param_set(&param_sets[1], 1_000.0).unwrap();
+        assert_eq!(params.delta_price_min_percentage, TRADDING_PAIR_USDT_MIN_QUANTITY / 1_000.0);
+        let unknown = BTreeMap::from([(String::from("unknown"), 1.0)]);
+        assert!(matches!(SStrategyMk5Params::from_param_set(&unknown, INIT_BALANCE_USDT), Err(ESweepError::UnknownParameterError(_))));
+        let invalid = BTreeMap::from([(String::from("pid_i_max_cumulative"), 0.0)]);
+        assert!(matches!(SStrategyMk5Params::from_param_set(&invalid, INIT_BALANCE_USDT), Err(ESweepError::InvalidParameterError(..))));
+        // 取值超出范围、非有限数或违反最小值不大于最大值时返回异常
+        for (name, value) in [
+            ("position_max", 1.5),
+            ("position_min", 0.99),
+            ("cut_off_price_percentage", 1.0),
+            ("max_profit_percentage", 0.0001),
+            ("maker_order_fee_percentage", -0.001),
+            ("open_quantity_percentage", 0.0),
+            ("dead_zone_range_percentage", -0.1),
+            ("pid_p_parameter", f64::NAN),
+            ("pid_i_parameter", f64::INFINITY),
+            ("delta_price_min_percentage", 1e30),
+        ] {
+            let invalid = BTreeMap::from([(String::from(name), value)]);
+            assert!(matches!(SStrategyMk5Params::from_param_set(&invalid, INIT_BALANCE_USDT), Err(ESweepError::InvalidParameterError(invalid_name, _)) if invalid_name == name), "{}", name);
+        }
+        let valid = BTreeMap::from([(String::from("position_min"), 0.5), (String::from("position_max"), 0.5)]);
+        assert!(SStrategyMk5Params::from_param_set(&valid, INIT_BALANCE_USDT).is_ok());
+    }
+
+    #[test]
+    pub fn test_report() {
+        let get_result = |index: usize, equities: &[f64]| {
+            let report = get_report(equities);
+            SSweepResult {
+                index,
+                param_set: BTreeMap::from([(String::from("position_max"), index as f64 / 10.0)]),
+                params: SStrategyMk5Params::default(),
+                score: ESweepMetric::MaxDrawdown.get_score(&report),
+                report: Some(report),
+            }
+        };
+        let no_data = SSweepResult { index: 4, param_set: BTreeMap::new(), params: SStrategyMk5Params::default(), score: None, report: None };
+        let report = SSweepReport::new(ESweepMetric::MaxDrawdown, vec![
+            get_result(3, &[100.0, 80.0, 120.0]),
+            no_data,
+            get_result(1, &[100.0, 90.0, 100.0]),
+            get_result(2, &[100.0, 99.0, 100.0]),
+        ]);
+        assert_eq!(report.results.iter().map(|result| result.index).collect::<Vec<_>>(), vec![1, 2, 3, 4]);
+        // 最大回撤越小越好
+        let best = report.get_best(2);
+        assert_eq!(best.iter().map(|result| result.index).collect::<Vec<_>>(), vec![2, 1]);
+        assert_eq!(report.get_best(10).len(), 3);
+    }
+}
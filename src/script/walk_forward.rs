@@ -0,0 +1,405 @@
+//! 滚动前推（walk-forward）优化
+//!
+//! 将回测周期划分为连续的样本内+样本外窗口：在样本内窗口上做参数扫描选出最优参数，
+//! 再以该参数回测紧随其后的样本外窗口。样本外窗口首尾相接，各窗口以全新用户回测，收益率串联为一条样本外净值曲线，
+//! 并对比样本内与样本外的指标以诊断过拟合
+use std::collections::{BTreeMap, HashSet};
+use std::fmt::{Display, Formatter};
+use std::fs::File;
+use chrono::{DateTime, Duration, Local};
+use log::{error, info};
+use serde::Serialize;
+use crate::config::backtest_config::SBacktestConfig;
+use crate::data_source::db::api::data_api_union::EDataApiUnion;
+use crate::report::performance::SPerformanceReport;
+use crate::report::window::SFailedWindow;
+use crate::runner::back_trade::config::SBackTradeRunnerConfig;
+use crate::runner::back_trade::runner::SBackTradeRunner;
+use crate::runner::logger::data_logger::SDataLogger;
+use crate::script::{build_data_manager, output_report, SScript};
+use crate::script::registry::RScriptResult;
+use crate::script::sweep::{ESweepError, ESweepMetric, RSweepResult, TSweepStrategy};
+
+/// 滚动前推配置
+#[derive(Debug, Clone, PartialEq)]
+pub struct SWalkForwardConfig {
+    /// 样本内窗口长度
+    pub in_sample: Duration,
+    /// 样本外窗口长度 同时也是窗口的滚动步长
+    pub out_of_sample: Duration,
+    /// 为true时样本内窗口固定从回测起始日期开始（扩展窗口），否则为固定长度的滚动窗口
+    pub anchored: bool,
+}
+
+impl SWalkForwardConfig {
+    /// 划分窗口 date_to不包含在回测周期内 最后一个样本外窗口截止到回测结束
+    pub fn get_windows(&self, date_from: DateTime<Local>, date_to: DateTime<Local>) -> RSweepResult<Vec<SWalkForwardWindow>> {
+        if self.in_sample <= Duration::zero() || self.out_of_sample <= Duration::zero() {
+            return Err(ESweepError::WalkForwardWindowError(date_from, date_to));
+        }
+        let mut windows = Vec::new();
+        let mut out_of_sample_from = date_from + self.in_sample;
+        while out_of_sample_from < date_to {
+            let out_of_sample_to = (out_of_sample_from + self.out_of_sample).min(date_to);
+            windows.push(SWalkForwardWindow {
+                index: windows.len() + 1,
+                in_sample_from: if self.anchored { date_from } else { out_of_sample_from - self.in_sample },
+                in_sample_to: out_of_sample_from - Duration::minutes(1),
+                out_of_sample_from,
+                out_of_sample_to: out_of_sample_to - Duration::minutes(1),
+            });
+            out_of_sample_from = out_of_sample_to;
+        }
+        if windows.is_empty() {
+            return Err(ESweepError::WalkForwardWindowError(date_from, date_to));
+        }
+        Ok(windows)
+    }
+}
+
+/// 单个滚动窗口的日期范围（均包含首尾）
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SWalkForwardWindow {
+    /// 窗口序号（从1开始）
+    pub index: usize,
+    pub in_sample_from: DateTime<Local>,
+    pub in_sample_to: DateTime<Local>,
+    pub out_of_sample_from: DateTime<Local>,
+    pub out_of_sample_to: DateTime<Local>,
+}
+
+/// 单个滚动窗口的优化及验证结果
+#[derive(Debug, Clone, Serialize)]
+pub struct SWalkForwardWindowResult<P: Serialize> {
+    pub window: SWalkForwardWindow,
+    /// 样本内最优的扫描参数
+    pub param_set: BTreeMap<String, f64>,
+    /// 样本内最优的完整策略参数
+    pub params: P,
+    /// 样本内排序分数
+    pub in_sample_score: Option<f64>,
+    /// 样本内绩效
+    pub in_sample: SPerformanceReport,
+    /// 样本外排序分数
+    pub out_of_sample_score: Option<f64>,
+    /// 样本外绩效 样本外回测没有产生数据时为None
+    pub out_of_sample: Option<SPerformanceReport>,
+}
+
+/// 过拟合诊断 对比样本内与样本外的表现
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct SOverfittingDiagnostics {
+    /// 样本内年化收益率的平均值
+    pub mean_in_sample_annualized_return: f64,
+    /// 样本外年化收益率的平均值
+    pub mean_out_of_sample_annualized_return: f64,
+    /// 前推效率（样本外平均年化收益率 / 样本内平均年化收益率） 样本内平均年化收益率不为正时为None
+    pub walk_forward_efficiency: Option<f64>,
+    /// 排序分数衰减的平均值（样本内分数 - 样本外分数） 没有可比较的窗口时为None
+    pub mean_score_degradation: Option<f64>,
+    /// 样本外排序分数低于样本内的窗口占比
+    pub degraded_window_ratio: f64,
+    /// 样本外正收益窗口的占比
+    pub out_of_sample_positive_ratio: f64,
+    /// 各窗口选出的不同参数组数 越少说明最优参数越稳定
+    pub distinct_param_set_cnt: usize,
+}
+
+impl SOverfittingDiagnostics {
+    pub fn from_windows<P: Serialize>(windows: &[SWalkForwardWindowResult<P>]) -> Self {
+        if windows.is_empty() {
+            return Self::default();
+        }
+        let mean = |values: &[f64]| if values.is_empty() { 0.0 } else { values.iter().sum::<f64>() / values.len() as f64 };
+        let in_sample_returns: Vec<f64> = windows.iter().map(|result| result.in_sample.strategy.annualized_return).collect();
+        let out_of_sample_returns: Vec<f64> = windows
+            .iter()
+            .map(|result| result.out_of_sample.as_ref().map(|report| report.strategy.annualized_return).unwrap_or(0.0))
+            .collect();
+        let mean_in_sample_annualized_return = mean(&in_sample_returns);
+        let mean_out_of_sample_annualized_return = mean(&out_of_sample_returns);
+
+        let score_degradations: Vec<f64> = windows
+            .iter()
+            .filter_map(|result| Some(result.in_sample_score? - result.out_of_sample_score?))
+            .collect();
+        let distinct_param_sets: HashSet<String> = windows.iter().map(|result| format!("{:?}", result.param_set)).collect();
+        let positive_cnt = windows
+            .iter()
+            .filter(|result| result.out_of_sample.as_ref().is_some_and(|report| report.strategy.total_return > 0.0))
+            .count();
+
+        Self {
+            mean_in_sample_annualized_return,
+            mean_out_of_sample_annualized_return,
+            walk_forward_efficiency: if mean_in_sample_annualized_return > 0.0 {
+                Some(mean_out_of_sample_annualized_return / mean_in_sample_annualized_return)
+            } else {
+                None
+            },
+            mean_score_degradation: if score_degradations.is_empty() { None } else { Some(mean(&score_degradations)) },
+            degraded_window_ratio: if score_degradations.is_empty() {
+                0.0
+            } else {
+                score_degradations.iter().filter(|degradation| **degradation > 0.0).count() as f64 / score_degradations.len() as f64
+            },
+            out_of_sample_positive_ratio: positive_cnt as f64 / windows.len() as f64,
+            distinct_param_set_cnt: distinct_param_sets.len(),
+        }
+    }
+}
+
+/// 滚动前推报告
+#[derive(Debug, Clone, Serialize)]
+pub struct SWalkForwardReport<P: Serialize> {
+    pub rank_by: ESweepMetric,
+    pub windows: Vec<SWalkForwardWindowResult<P>>,
+    pub diagnostics: SOverfittingDiagnostics,
+    /// 样本外窗口收益率串联后的绩效 没有样本外数据时为None
+    pub out_of_sample: Option<SPerformanceReport>,
+    /// 样本内没有可排序的参数组或样本外回测失败的窗口（日期为样本外窗口的日期 不计入诊断）
+    pub failed_windows: Vec<SFailedWindow>,
+}
+
+impl<P: Serialize> SWalkForwardReport<P> {
+    pub fn new(rank_by: ESweepMetric, windows: Vec<SWalkForwardWindowResult<P>>, out_of_sample: Option<SPerformanceReport>) -> Self {
+        Self {
+            rank_by,
+            diagnostics: SOverfittingDiagnostics::from_windows(&windows),
+            windows,
+            out_of_sample,
+            failed_windows: Vec::new(),
+        }
+    }
+
+    /// 记录失败的窗口 按起始时间排序
+    pub fn with_failed_windows(mut self, mut failed_windows: Vec<SFailedWindow>) -> Self {
+        failed_windows.sort_by_key(|window| window.date_from);
+        self.failed_windows = failed_windows;
+        self
+    }
+
+    /// 将报告以JSON格式输出到指定文件
+    pub fn output_json(&self, path: &str) -> RSweepResult<()> {
+        let file = File::create(path).map_err(|e| ESweepError::OutputError(path.to_string(), e.to_string()))?;
+        serde_json::to_writer_pretty(file, self).map_err(|e| ESweepError::OutputError(path.to_string(), e.to_string()))
+    }
+}
+
+impl<P: Serialize> Display for SWalkForwardReport<P> {
+    /// 可读的报告摘要
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let percent = |value: f64| format!("{:.2}%", value * 100.0);
+        let score = |value: Option<f64>| value.map(|value| format!("{:.3}", value)).unwrap_or(String::from("-"));
+        for result in self.windows.iter() {
+            let window = &result.window;
+            writeln!(f, "窗口{}: 样本内 {} ~ {}\t样本外 {} ~ {}\t参数 {:?}",
+                     window.index, window.in_sample_from, window.in_sample_to, window.out_of_sample_from, window.out_of_sample_to, result.param_set)?;
+            writeln!(f, "\t{:?}: 样本内 {}\t样本外 {}\t样本外收益率: {}", self.rank_by, score(result.in_sample_score), score(result.out_of_sample_score),
+                     result.out_of_sample.as_ref().map(|report| percent(report.strategy.total_return)).unwrap_or(String::from("-")))?;
+        }
+        let diagnostics = &self.diagnostics;
+        writeln!(f, "平均年化收益率: 样本内 {}\t样本外 {}\t前推效率: {}\t分数衰减: {}\t衰减窗口占比: {}\t样本外正收益占比: {}\t不同参数组数: {}",
+                 percent(diagnostics.mean_in_sample_annualized_return), percent(diagnostics.mean_out_of_sample_annualized_return),
+                 score(diagnostics.walk_forward_efficiency), score(diagnostics.mean_score_degradation),
+                 percent(diagnostics.degraded_window_ratio), percent(diagnostics.out_of_sample_positive_ratio), diagnostics.distinct_param_set_cnt)?;
+        for window in self.failed_windows.iter() {
+            writeln!(f, "失败窗口: 样本外 {} ~ {}\t原因: {}", window.date_from, window.date_to, window.error)?;
+        }
+        match &self.out_of_sample {
+            None => { write!(f, "没有样本外数据") }
+            Some(report) => { write!(f, "样本外收益率串联绩效（各窗口以全新用户回测）:\n{}", report) }
+        }
+    }
+}
+
+impl<S> SScript<SBackTradeRunner<EDataApiUnion>, S>
+where
+    S: TSweepStrategy,
+{
+    /// 滚动前推优化 参数范围及排序指标使用参数扫描配置
+    ///
+    /// 各样本外窗口以全新用户回测 资产量不可直接拼接 因此各窗口的回测结果分别输出（后缀为_window{窗口序号}.csv）
+    /// 报告及图表按窗口收益率串联计算，滚动前推报告输出到同目录 后缀为_walk_forward.json
+    /// 窗口回测失败时记录失败窗口并继续后续窗口
+    pub fn back_trader_walk_forward(config: &SBacktestConfig) -> RScriptResult<()> {
+        info!("启动滚动前推优化");
+        let script_start_time = Local::now().format("%Y%m%d_%H%M%S");
+        let sweep_config = config.sweep.get_sweep_config()?;
+        let param_sets = sweep_config.get_params::<S::Params>(config.user.init_balance_usdt)?;
+
+        // 窗口划分
+        let mut base_runner_config = config.get_runner_config()?;
+        base_runner_config.date_to += Duration::minutes(1);
+        let windows = config.walk_forward.get_walk_forward_config().get_windows(base_runner_config.date_from, base_runner_config.date_to)?;
+        info!("参数组合数：{}\t窗口数：{}", param_sets.len(), windows.len());
+
+        // 一次性加载完整回测周期的数据 各窗口通过时间窗口共享同一份只读数据
+        let data_manager = build_data_manager(config, &base_runner_config.date_from, &base_runner_config.date_to)?;
+
+        let output_path = config.get_output_path(&script_start_time.to_string());
+        let output_stem = output_path.strip_suffix(".csv").unwrap_or(&output_path).to_string();
+        let mut window_results = Vec::new();
+        let mut failed_windows = Vec::new();
+        let mut out_of_sample_logger = SDataLogger::new();
+        for window in windows {
+            // 样本内参数扫描
+            info!("窗口{}: 样本内优化 {:?} ~ {:?}", window.index, window.in_sample_from, window.in_sample_to);
+            let in_sample_runner_config = SBackTradeRunnerConfig {
+                date_from: window.in_sample_from,
+                date_to: window.in_sample_to,
+                ..base_runner_config.clone()
+            };
+            let sweep_report = Self::sweep_param_sets(config, sweep_config.rank_by, param_sets.clone(), &in_sample_runner_config, &data_manager);
+            let best = match sweep_report.get_best(1).first() {
+                Some(best) => { (*best).clone() }
+                None => {
+                    error!("窗口{}: 样本内没有可排序的参数组 跳过该窗口", window.index);
+                    failed_windows.push(SFailedWindow {
+                        date_from: window.out_of_sample_from,
+                        date_to: window.out_of_sample_to,
+                        error: String::from("样本内没有可排序的参数组"),
+                    });
+                    continue;
+                }
+            };
+
+            // 样本外验证
+            info!("窗口{}: 样本外验证 {:?} ~ {:?}\t{:?}", window.index, window.out_of_sample_from, window.out_of_sample_to, best.param_set);
+            let out_of_sample_runner_config = SBackTradeRunnerConfig {
+                date_from: window.out_of_sample_from,
+                date_to: window.out_of_sample_to,
+                ..base_runner_config.clone()
+            };
+            let result = Self::back_trader_with_params(
+                config,
+                out_of_sample_runner_config,
+                data_manager.window(window.out_of_sample_from, window.out_of_sample_to),
+                &best.params,
+            );
+            let mut data_logger = match result {
+                Ok(data_logger) => { data_logger }
+                Err(e) => {
+                    error!("窗口{}: 样本外回测失败 {}", window.index, e);
+                    failed_windows.push(SFailedWindow {
+                        date_from: window.out_of_sample_from,
+                        date_to: window.out_of_sample_to,
+                        error: e.to_string(),
+                    });
+                    continue;
+                }
+            };
+            let out_of_sample = SPerformanceReport::from_logger(&data_logger).ok();
+            data_logger.output_user(format!("{}_window{}.csv", output_stem, window.index));
+            // 合并后各窗口为不同用户 报告及图表按用户分区串联收益率
+            out_of_sample_logger.append(&mut data_logger);
+
+            window_results.push(SWalkForwardWindowResult {
+                window,
+                param_set: best.param_set,
+                params: best.params,
+                in_sample_score: best.score,
+                // 有分数的结果必然有绩效报告
+                in_sample: best.report.unwrap(),
+                out_of_sample_score: out_of_sample.as_ref().and_then(|report| sweep_config.rank_by.get_score(report)),
+                out_of_sample,
+            });
+        }
+        info!("已完成！");
+
+        let report = SWalkForwardReport::new(sweep_config.rank_by, window_results, SPerformanceReport::from_logger(&out_of_sample_logger).ok())
+            .with_failed_windows(failed_windows);
+        info!("滚动前推报告:\n{}", report);
+
+        // 样本外报告及图表按窗口收益率串联输出
+        output_report(&out_of_sample_logger, &output_path, config.chart_format);
+        let report_path = format!("{}_walk_forward.json", output_stem);
+        match report.output_json(&report_path) {
+            Err(e) => { error!("{}", e); }
+            Ok(_) => { println!("滚动前推报告写入完成：{}", report_path); }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+    use chrono::{Duration, Local, TimeZone};
+    use crate::report::performance::{SPerformanceReport, SReportSample};
+    use crate::report::window::SFailedWindow;
+    use crate::script::sweep::{ESweepError, ESweepMetric};
+    use crate::script::walk_forward::{SOverfittingDiagnostics, SWalkForwardConfig, SWalkForwardReport, SWalkForwardWindow, SWalkForwardWindowResult};
+
+    fn get_report(equities: &[f64]) -> SPerformanceReport {
+        let time = Local.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+        SPerformanceReport::from_samples(SReportSample::from_equities(time, Duration::days(1), equities)).unwrap()
+    }
+
+    #[test]
+    pub fn test_windows() {
+        let date_from = Local.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+        let date_to = date_from + Duration::days(25);
+        let config = SWalkForwardConfig { in_sample: Duration::days(10), out_of_sample: Duration::days(10), anchored: false };
+        let windows = config.get_windows(date_from, date_to).unwrap();
+        assert_eq!(windows.len(), 2);
+        assert_eq!(windows[0], SWalkForwardWindow {
+            index: 1,
+            in_sample_from: date_from,
+            in_sample_to: date_from + Duration::days(10) - Duration::minutes(1),
+            out_of_sample_from: date_from + Duration::days(10),
+            out_of_sample_to: date_from + Duration::days(20) - Duration::minutes(1),
+        });
+        // 滚动窗口的样本内长度固定 最后一个样本外窗口截止到回测结束
+        assert_eq!(windows[1].in_sample_from, date_from + Duration::days(10));
+        assert_eq!(windows[1].out_of_sample_from, windows[0].out_of_sample_to + Duration::minutes(1));
+        assert_eq!(windows[1].out_of_sample_to, date_to - Duration::minutes(1));
+
+        let windows = SWalkForwardConfig { anchored: true, ..config.clone() }.get_windows(date_from, date_to).unwrap();
+        assert_eq!(windows[1].in_sample_from, date_from);
+
+        assert!(matches!(config.get_windows(date_from, date_from + Duration::days(10)), Err(ESweepError::WalkForwardWindowError(..))));
+        let config = SWalkForwardConfig { out_of_sample: Duration::zero(), ..config };
+        assert!(config.get_windows(date_from, date_to).is_err());
+    }
+
+    #[test]
+    pub fn test_diagnostics() {
+        let date = Local.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+        let get_result = |position_max: f64, in_sample: &[f64], out_of_sample: Option<&[f64]>| {
+            let in_sample = get_report(in_sample);
+            let out_of_sample = out_of_sample.map(get_report);
+            SWalkForwardWindowResult {
+                window: SWalkForwardWindow { index: 1, in_sample_from: date, in_sample_to: date, out_of_sample_from: date, out_of_sample_to: date },
+                param_set: BTreeMap::from([(String::from("position_max"), position_max)]),
+                params: (),
+                in_sample_score: Some(in_sample.strategy.total_return),
+                out_of_sample_score: out_of_sample.as_ref().map(|report| report.strategy.total_return),
+                in_sample,
+                out_of_sample,
+            }
+        };
+        let windows = vec![
+            get_result(0.9, &[100.0, 110.0, 120.0], Some(&[100.0, 101.0, 102.0])),
+            get_result(0.9, &[100.0, 105.0, 110.0], Some(&[100.0, 99.0, 98.0])),
+            get_result(0.8, &[100.0, 101.0, 102.0], None),
+        ];
+        let diagnostics = SOverfittingDiagnostics::from_windows(&windows);
+        assert!(diagnostics.mean_in_sample_annualized_return > diagnostics.mean_out_of_sample_annualized_return);
+        assert!(diagnostics.walk_forward_efficiency.unwrap() < 1.0);
+        assert!((diagnostics.mean_score_degradation.unwrap() - ((0.2 - 0.02) + (0.1 + 0.02)) / 2.0).abs() < 1e-9);
+        assert_eq!(diagnostics.degraded_window_ratio, 1.0);
+        assert!((diagnostics.out_of_sample_positive_ratio - 1.0 / 3.0).abs() < 1e-9);
+        assert_eq!(diagnostics.distinct_param_set_cnt, 2);
+
+        // 失败窗口只记录 不计入诊断
+        let report = SWalkForwardReport::new(ESweepMetric::TotalReturn, windows, None).with_failed_windows(vec![SFailedWindow {
+            date_from: date,
+            date_to: date + Duration::days(1),
+            error: String::from("回测数据加载失败"),
+        }]);
+        assert_eq!(report.diagnostics.distinct_param_set_cnt, 2);
+        assert_eq!(report.failed_windows.len(), 1);
+        assert!(report.to_string().contains("失败窗口"));
+    }
+}
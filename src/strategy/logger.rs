@@ -1,16 +1,46 @@
 use rust_decimal::Decimal;
+use crate::data_runtime::order::EOrderDirection;
+use crate::strategy::order::order::SStrategyOrder;
 
 /// 用于记录Strategy运行中的中间过程数据
 #[derive(Clone)]
 pub struct SStrategyLogger {
     /// 目标仓位占比
     pub target_position_ratio:Decimal,
+    /// 累计已平仓的策略订单对数量
+    pub closed_order_pair_cnt: i64,
+    /// 累计盈利的策略订单对数量（平仓价格优于开仓价格 不含手续费）
+    pub winning_order_pair_cnt: i64,
 }
 
 impl SStrategyLogger {
+    pub fn new(target_position_ratio: Decimal) -> Self {
+        Self {
+            target_position_ratio,
+            closed_order_pair_cnt: 0,
+            winning_order_pair_cnt: 0,
+        }
+    }
+
     /// 空数据
     /// 用于适配一些不支持特定数据的Strategy
     pub fn none() -> Self {
-        Self{ target_position_ratio: Decimal::from(-1) }
+        Self::new(Decimal::from(-1))
     }
-}
\ No newline at end of file
+
+    /// 记录已平仓的策略订单对
+    pub fn record_closed_order_pair(&mut self, strategy_order: &SStrategyOrder) {
+        let close_price = match strategy_order.get_close_price() {
+            None => { return; }
+            Some(close_price) => { close_price }
+        };
+        let profit = match strategy_order.get_direction() {
+            EOrderDirection::Long => { close_price - strategy_order.get_open_price() }
+            EOrderDirection::Short => { strategy_order.get_open_price() - close_price }
+        };
+        self.closed_order_pair_cnt += 1;
+        if profit > Decimal::from(0) {
+            self.winning_order_pair_cnt += 1;
+        }
+    }
+}
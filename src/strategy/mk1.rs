@@ -47,13 +47,13 @@ use crate::config::{SDebugConfig, trading_pair::btc_usdt::TRADDING_PAIR_BTC_USDT
 use crate::data_runtime::asset::asset::SAsset;
 use crate::data_runtime::asset::asset_map_v3::SAssetMapV3;
 use crate::data_runtime::asset::asset_union::EAssetUnion;
-use crate::data_runtime::order::EOrderAction;
+use crate::data_runtime::order::{EOrderAction, EOrderType};
 use crate::data_runtime::order::trading_pair_order_manager_map_v3::STradingPairOrderManagerMapV3;
 use crate::data_source::trading_pair::ETradingPairType;
 use crate::protocol::{ERunnerParseOrderResult, ERunnerSyncActionResult, EStrategyAction, SRunnerParseKlineResult};
 use crate::protocol::strategy_order::SStrategyOrderAdd;
 use crate::strategy::logger::SStrategyLogger;
-use crate::strategy::TStrategy;
+use crate::strategy::{TStrategy, TStrategyFromConfig};
 
 pub struct SStrategyMk1 {
     /// 目标仓位占比
@@ -85,6 +85,13 @@ impl Default for SStrategyMk1 {
     }
 }
 
+impl TStrategyFromConfig for SStrategyMk1 {
+    /// 策略参数与初始资金量无关
+    fn from_init_balance_usdt(_init_balance_usdt: f64) -> Self {
+        Self::default()
+    }
+}
+
 impl TStrategy for SStrategyMk1 {
     fn run(
         &mut self,
@@ -108,6 +115,19 @@ impl TStrategy for SStrategyMk1 {
                     // 删除已执行的订单
                     self.order_list.remove(&order.get_id());
                 }
+                ERunnerParseOrderResult::Liquidated(liquidation) => {
+                    // 强平时runner已取消该交易对的所有挂单 无需再撤回
+                    for order in liquidation.canceled_orders.iter() {
+                        self.order_list.remove(&order.get_id());
+                    }
+                }
+                ERunnerParseOrderResult::OrderCanceled(order) => {
+                    // 删除已取消的订单
+                    self.order_list.remove(&order.get_id());
+                }
+                ERunnerParseOrderResult::OrderPartiallyExecuted(..) => {
+                    // 部分成交的订单仍保留在挂单列表中 与其他剩余订单一同撤回
+                }
             }
         }
         //  2. 撤回所有剩余的订单
@@ -182,6 +202,7 @@ impl TStrategy for SStrategyMk1 {
                 price: tmp_price,
                 base_quantity: tmp_quantity,
                 margin_quantity: tmp_quantity * tmp_price,
+                order_type: EOrderType::Limit,
             }));
             // 重新计算仓位、资产
             tmp_position_ratio = tmp_base_quantity * tmp_price / (tmp_base_quantity * tmp_price + tmp_quote_quantity);
@@ -223,6 +244,7 @@ impl TStrategy for SStrategyMk1 {
                 price: tmp_price,
                 base_quantity: tmp_quantity,
                 margin_quantity: tmp_quantity * tmp_price,
+                order_type: EOrderType::Limit,
             }));
             // 重新计算仓位、资产
             tmp_position_ratio = tmp_base_quantity * tmp_price / (tmp_base_quantity * tmp_price + tmp_quote_quantity);
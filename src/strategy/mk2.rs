@@ -26,14 +26,16 @@ use crate::{
     strategy::{
         order::trading_pair_order_map::SStrategyTradingPairOrderMap,
         TStrategy,
+        TStrategyFromConfig,
     },
 };
 use crate::config::{fee::MAKER_ORDER_FEE, SDebugConfig};
 use crate::config::trading_pair::btc_usdt::TRADDING_PAIR_USDT_MIN_QUANTITY;
 use crate::data_runtime::asset::asset_map_v3::SAssetMapV3;
 use crate::data_runtime::asset::asset_union::EAssetUnion;
-use crate::data_runtime::order::{EOrderDirection, EOrderPosition};
+use crate::data_runtime::order::{EOrderDirection, EOrderPosition, EOrderType};
 use crate::data_runtime::order::trading_pair_order_manager_map_v3::STradingPairOrderManagerMapV3;
+use crate::strategy::order::order_result::{handle_canceled, handle_liquidation, handle_partially_executed};
 use crate::strategy::logger::SStrategyLogger;
 use crate::strategy::order::order::{EStrategyOrderState, SStrategyOrder};
 use crate::strategy::order::order_manager::SStrategyOrderManager;
@@ -262,6 +264,13 @@ impl SStrategyMk2 {
     }
 }
 
+impl TStrategyFromConfig for SStrategyMk2 {
+    /// 策略参数与初始资金量无关
+    fn from_init_balance_usdt(_init_balance_usdt: f64) -> Self {
+        Self::default()
+    }
+}
+
 impl TStrategy for SStrategyMk2 {
     fn run(
         &mut self,
@@ -320,6 +329,15 @@ impl TStrategy for SStrategyMk2 {
                     //     error!("order can not insert into strategy_order:{:?}", order);
                     // }
                 }
+                ERunnerParseOrderResult::OrderCanceled(order) => {
+                    handle_canceled(strategy_order_manager, &mut self.opening_and_closing_orders, &order);
+                }
+                ERunnerParseOrderResult::Liquidated(liquidation) => {
+                    handle_liquidation(strategy_order_manager, &mut self.opening_and_closing_orders, &liquidation);
+                }
+                ERunnerParseOrderResult::OrderPartiallyExecuted(order, _) => {
+                    handle_partially_executed(strategy_order_manager, &order);
+                }
             }
         }
 
@@ -422,6 +440,7 @@ impl TStrategy for SStrategyMk2 {
                         price: order_price,
                         base_quantity: order_quantity,
                         margin_quantity: order_quantity * order_price,
+                        order_type: EOrderType::Limit,
                     }));
                     // 更新数据
                     price = order_price;
@@ -471,6 +490,7 @@ impl TStrategy for SStrategyMk2 {
                         price: order_price,
                         base_quantity: order_quantity,
                         margin_quantity: order_quantity * order_price,
+                        order_type: EOrderType::Limit,
                     }));
                     // 更新数据
                     price = order_price;
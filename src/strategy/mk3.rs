@@ -28,6 +28,7 @@ use crate::{
     strategy::{
         order::trading_pair_order_map::SStrategyTradingPairOrderMap,
         TStrategy,
+        TStrategyFromConfig,
     },
 };
 use crate::config::{fee::MAKER_ORDER_FEE, SDebugConfig};
@@ -35,9 +36,10 @@ use crate::config::trading_pair::btc_usdt::TRADDING_PAIR_USDT_MIN_QUANTITY;
 use crate::config::user::INIT_BALANCE_USDT;
 use crate::data_runtime::asset::asset_map_v3::SAssetMapV3;
 use crate::data_runtime::asset::asset_union::EAssetUnion;
-use crate::data_runtime::order::{EOrderDirection, EOrderPosition};
+use crate::data_runtime::order::{EOrderDirection, EOrderPosition, EOrderType};
 use crate::data_runtime::order::trading_pair_order_manager_map_v3::STradingPairOrderManagerMapV3;
 use crate::protocol::strategy_order::SStrategyOrderAdd;
+use crate::strategy::order::order_result::{handle_canceled, handle_liquidation, handle_partially_executed};
 use crate::strategy::logger::SStrategyLogger;
 use crate::strategy::model::feedback_control::{SPidIntegral, SStrategyPidConfig};
 use crate::strategy::model::price_model_sin_test::SPriceModelSin;
@@ -90,8 +92,18 @@ struct SNextOrderFormat {
     pub quote_quantity: Decimal,
 }
 
-impl Default for SStrategyMk3<SPriceModelSin> {
+impl<M: TPriceModel> Default for SStrategyMk3<M>
+where
+    Self: TStrategyFromConfig,
+{
+    /// 使用config.rs中的默认初始资金量构建
     fn default() -> Self {
+        Self::from_init_balance_usdt(INIT_BALANCE_USDT)
+    }
+}
+
+impl TStrategyFromConfig for SStrategyMk3<SPriceModelSin> {
+    fn from_init_balance_usdt(init_balance_usdt: f64) -> Self {
         // 构建正弦波周期性价格模型
         // 周期2天
         let period = 60 * 60 * 24 * 2;
@@ -110,7 +122,7 @@ impl Default for SStrategyMk3<SPriceModelSin> {
         let maker_order_fee_percentage = MAKER_ORDER_FEE;
         let open_quantity_percentage = TRADDING_PAIR_USDT_MIN_QUANTITY;
         // 订单最小价格间距
-        let delta_price_min_percentage = TRADDING_PAIR_USDT_MIN_QUANTITY / INIT_BALANCE_USDT;
+        let delta_price_min_percentage = TRADDING_PAIR_USDT_MIN_QUANTITY / init_balance_usdt;
         // 订单最大价格间距(无用参数)
         let delta_price_max_percentage = 0.0002;
         // 死区大小（等于开单与平单的价差最小值）
@@ -144,8 +156,8 @@ impl Default for SStrategyMk3<SPriceModelSin> {
     }
 }
 
-impl Default for SStrategyMk3<SPriceModelStep> {
-    fn default() -> Self {
+impl TStrategyFromConfig for SStrategyMk3<SPriceModelStep> {
+    fn from_init_balance_usdt(init_balance_usdt: f64) -> Self {
         // 构建阶跃周期性价格模型
         // 周期2天
         let period = 60 * 60 * 24 * 2;
@@ -166,7 +178,7 @@ impl Default for SStrategyMk3<SPriceModelStep> {
         let maker_order_fee_percentage = MAKER_ORDER_FEE;
         let open_quantity_percentage = TRADDING_PAIR_USDT_MIN_QUANTITY;
         // 订单最小价格间距
-        let delta_price_min_percentage = TRADDING_PAIR_USDT_MIN_QUANTITY / INIT_BALANCE_USDT;
+        let delta_price_min_percentage = TRADDING_PAIR_USDT_MIN_QUANTITY / init_balance_usdt;
         // 订单最大价格间距(无用参数)
         let delta_price_max_percentage = 0.0002;
         // 死区大小（等于开单与平单的价差最小值）
@@ -220,7 +232,7 @@ impl<M: TPriceModel> SStrategyMk3<M> {
         strategy_order_map.inner.insert(ETradingPairType::BtcUsdtFuture, SStrategyOrderManager::new());
         strategy_order_map.inner.insert(ETradingPairType::BtcUsdCmFuture, SStrategyOrderManager::new());
         Self {
-            logger: SStrategyLogger { target_position_ratio: Decimal::from(0) },
+            logger: SStrategyLogger::new(Decimal::from(0)),
             price_model,
             opening_and_closing_orders: Default::default(),
             strategy_order_map,
@@ -523,7 +535,11 @@ impl<M: TPriceModel> TStrategy for SStrategyMk3<M> {
                                     match strategy_order_manager.closed_by_order_id(order_id) {
                                         Err(e) => { error!("SStrategyMk3::run():{:?}", e); }
                                         Ok(Err(e)) => { error!("SStrategyMk3::run():{:?}", e); }
-                                        Ok(Ok(_)) => {}
+                                        Ok(Ok(_)) => {
+                                            if let Ok(strategy_order) = strategy_order_manager.peek_by_order_id(order_id) {
+                                                self.logger.record_closed_order_pair(strategy_order);
+                                            }
+                                        }
                                     }
                                 }
                                 unexpected_state => {
@@ -537,6 +553,17 @@ impl<M: TPriceModel> TStrategy for SStrategyMk3<M> {
                     //     error!("order can not insert into strategy_order:{:?}", order);
                     // }
                 }
+                ERunnerParseOrderResult::OrderCanceled(order) => {
+                    handle_canceled(strategy_order_manager, &mut self.opening_and_closing_orders, &order);
+                }
+                ERunnerParseOrderResult::Liquidated(liquidation) => {
+                    handle_liquidation(strategy_order_manager, &mut self.opening_and_closing_orders, &liquidation);
+                }
+                ERunnerParseOrderResult::OrderPartiallyExecuted(order, _) => {
+                    if let Some(closed_strategy_order) = handle_partially_executed(strategy_order_manager, &order) {
+                        self.logger.record_closed_order_pair(&closed_strategy_order);
+                    }
+                }
             }
         }
 
@@ -684,6 +711,7 @@ impl<M: TPriceModel> TStrategy for SStrategyMk3<M> {
                         price: order_price,
                         base_quantity: order_quantity,
                         margin_quantity: order_quantity * order_price,
+                        order_type: EOrderType::Limit,
                     }));
                     // 更新数据
                     price = order_price;
@@ -760,6 +788,7 @@ impl<M: TPriceModel> TStrategy for SStrategyMk3<M> {
                         price: order_price,
                         base_quantity: order_quantity,
                         margin_quantity: order_quantity * order_price,
+                        order_type: EOrderType::Limit,
                     }));
                     // 更新数据
                     price = order_price;
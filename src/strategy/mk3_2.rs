@@ -27,6 +27,7 @@ use crate::{
     protocol::{ERunnerParseOrderResult, ERunnerSyncActionResult, EStrategyAction, SRunnerParseKlineResult},
     strategy::{
         TStrategy,
+        TStrategyFromConfig,
     },
 };
 use crate::config::{fee::MAKER_ORDER_FEE, SDebugConfig};
@@ -34,9 +35,10 @@ use crate::config::trading_pair::btc_usdt::TRADDING_PAIR_USDT_MIN_QUANTITY;
 use crate::config::user::INIT_BALANCE_USDT;
 use crate::data_runtime::asset::asset_map_v3::SAssetMapV3;
 use crate::data_runtime::asset::asset_union::EAssetUnion;
-use crate::data_runtime::order::{EOrderDirection, EOrderPosition};
+use crate::data_runtime::order::{EOrderDirection, EOrderPosition, EOrderType};
 use crate::data_runtime::order::trading_pair_order_manager_map_v3::STradingPairOrderManagerMapV3;
 use crate::protocol::strategy_order::SStrategyOrderAdd;
+use crate::strategy::order::order_result::{handle_canceled, handle_liquidation, handle_partially_executed};
 use crate::strategy::logger::SStrategyLogger;
 use crate::strategy::model::feedback_control::{SPidIntegral, SStrategyPidConfig};
 use crate::strategy::model::price_model_sin_test::SPriceModelSin;
@@ -93,8 +95,18 @@ struct SNextOrderFormat {
     pub quote_quantity: Decimal,
 }
 
-impl Default for SStrategyMk3_2<SPriceModelSin> {
-    fn default() -> Self { // 构建正弦波周期性价格模型
+impl<M: TPriceModel> Default for SStrategyMk3_2<M>
+where
+    Self: TStrategyFromConfig,
+{
+    /// 使用config.rs中的默认初始资金量构建
+    fn default() -> Self {
+        Self::from_init_balance_usdt(INIT_BALANCE_USDT)
+    }
+}
+
+impl TStrategyFromConfig for SStrategyMk3_2<SPriceModelSin> {
+    fn from_init_balance_usdt(init_balance_usdt: f64) -> Self { // 构建正弦波周期性价格模型
         // 周期7天
         let period = 60 * 60 * 24 * 7;
         // 振幅80%
@@ -116,7 +128,7 @@ impl Default for SStrategyMk3_2<SPriceModelSin> {
         let maker_order_fee_percentage = MAKER_ORDER_FEE;
         let open_quantity_percentage = TRADDING_PAIR_USDT_MIN_QUANTITY;
         // 订单最小价格间距
-        let delta_price_min_percentage = TRADDING_PAIR_USDT_MIN_QUANTITY / INIT_BALANCE_USDT;
+        let delta_price_min_percentage = TRADDING_PAIR_USDT_MIN_QUANTITY / init_balance_usdt;
         // 死区大小（等于开单与平单的价差最小值）
         let dead_zone_range_percentage = maker_order_fee_percentage * 2.0 + minimum_profit_percentage;
         // 活区大小
@@ -150,8 +162,8 @@ impl Default for SStrategyMk3_2<SPriceModelSin> {
     }
 }
 
-impl Default for SStrategyMk3_2<SPriceModelStep> {
-    fn default() -> Self {
+impl TStrategyFromConfig for SStrategyMk3_2<SPriceModelStep> {
+    fn from_init_balance_usdt(init_balance_usdt: f64) -> Self {
         // 构建阶跃周期性价格模型
         // 周期7天
         let period = 60 * 60 * 24 * 7;
@@ -176,7 +188,7 @@ impl Default for SStrategyMk3_2<SPriceModelStep> {
         let maker_order_fee_percentage = MAKER_ORDER_FEE;
         let open_quantity_percentage = TRADDING_PAIR_USDT_MIN_QUANTITY;
         // 订单最小价格间距
-        let delta_price_min_percentage = TRADDING_PAIR_USDT_MIN_QUANTITY / INIT_BALANCE_USDT;
+        let delta_price_min_percentage = TRADDING_PAIR_USDT_MIN_QUANTITY / init_balance_usdt;
         // 死区大小（等于开单与平单的价差最小值）
         let dead_zone_range_percentage = maker_order_fee_percentage * 2.0 + minimum_profit_percentage;
         // 活区大小
@@ -235,7 +247,7 @@ impl<M: TPriceModel> SStrategyMk3_2<M> {
         strategy_order_map.inner.insert(ETradingPairType::BtcUsdtFuture, manager.clone());
         strategy_order_map.inner.insert(ETradingPairType::BtcUsdCmFuture, manager.clone());
         Self {
-            logger: SStrategyLogger { target_position_ratio: Decimal::from(0) },
+            logger: SStrategyLogger::new(Decimal::from(0)),
             price_model,
             opening_and_closing_orders: Default::default(),
             strategy_order_map,
@@ -478,7 +490,11 @@ impl<M: TPriceModel> TStrategy for SStrategyMk3_2<M> {
                                     match strategy_order_manager.closed_by_order_id(order_id) {
                                         Err(e) => { error!("SStrategyMk3_2::run():{:?}", e); }
                                         Ok(Err(e)) => { error!("SStrategyMk3_2::run():{:?}", e); }
-                                        Ok(Ok(_)) => {}
+                                        Ok(Ok(_)) => {
+                                            if let Ok(strategy_order) = strategy_order_manager.peek_by_order_id(order_id) {
+                                                self.logger.record_closed_order_pair(strategy_order);
+                                            }
+                                        }
                                     }
                                 }
                                 unexpected_state => {
@@ -492,6 +508,17 @@ impl<M: TPriceModel> TStrategy for SStrategyMk3_2<M> {
                     //     error!("order can not insert into strategy_order:{:?}", order);
                     // }
                 }
+                ERunnerParseOrderResult::OrderCanceled(order) => {
+                    handle_canceled(strategy_order_manager, &mut self.opening_and_closing_orders, &order);
+                }
+                ERunnerParseOrderResult::Liquidated(liquidation) => {
+                    handle_liquidation(strategy_order_manager, &mut self.opening_and_closing_orders, &liquidation);
+                }
+                ERunnerParseOrderResult::OrderPartiallyExecuted(order, _) => {
+                    if let Some(closed_strategy_order) = handle_partially_executed(strategy_order_manager, &order) {
+                        self.logger.record_closed_order_pair(&closed_strategy_order);
+                    }
+                }
             }
         }
 
@@ -642,6 +669,7 @@ impl<M: TPriceModel> TStrategy for SStrategyMk3_2<M> {
                         price,
                         base_quantity: order_quantity,
                         margin_quantity: order_quantity * price,
+                        order_type: EOrderType::Limit,
                     }));
                     // 更新数据
                     base_quantity = new_base_quantity;
@@ -717,6 +745,7 @@ impl<M: TPriceModel> TStrategy for SStrategyMk3_2<M> {
                         price: order_price,
                         base_quantity: order_quantity,
                         margin_quantity: order_quantity * order_price,
+                        order_type: EOrderType::Limit,
                     }));
                     // 更新数据
                     price = order_price;
@@ -831,4 +860,105 @@ impl<M: TPriceModel> TStrategy for SStrategyMk3_2<M> {
     fn get_position(&self, _time: DateTime<Local>) -> Option<Decimal> {
         Some(Decimal::from(0))
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+    use chrono::{Local, TimeZone};
+    use rust_decimal::Decimal;
+    use crate::config::SDebugConfig;
+    use crate::data_runtime::asset::asset::SAsset;
+    use crate::data_runtime::asset::asset_map_v3::SAssetMapV3;
+    use crate::data_runtime::asset::asset_union::EAssetUnion;
+    use crate::data_runtime::order::order_v3::SOrderV3;
+    use crate::data_runtime::order::trading_pair_order_manager_map_v3::STradingPairOrderManagerMapV3;
+    use crate::data_runtime::asset::EAssetType;
+    use crate::data_source::kline::SKlineUnitData;
+    use crate::data_source::trading_pair::ETradingPairType;
+    use crate::protocol::{ERunnerParseOrderResult, ERunnerSyncActionResult, EStrategyAction, SRunnerParseKlineResult};
+    use crate::strategy::mk3_2::SStrategyMk3_2;
+    use crate::strategy::model::price_model_sin_test::SPriceModelSin;
+    use crate::strategy::order::order::EStrategyOrderState;
+    use crate::strategy::TStrategy;
+
+    fn get_parse_result(order_result: Vec<ERunnerParseOrderResult>) -> SRunnerParseKlineResult {
+        let time = Local.with_ymd_and_hms(2025, 1, 1, 0, 0, 0).unwrap();
+        let price = Decimal::from(100_000);
+        SRunnerParseKlineResult {
+            tp_type: ETradingPairType::BtcUsdt,
+            new_kline: SKlineUnitData {
+                open_time: time,
+                close_time: time,
+                open_price: price,
+                close_price: price,
+                high_price: price,
+                low_price: price,
+                volume: Decimal::from(1),
+            },
+            new_funding_rate: Decimal::from(0),
+            order_result,
+        }
+    }
+
+    /// 提交订单并模拟部分成交 返回(成交部分, 剩余部分)
+    fn split_order(mut order: SOrderV3, locked_asset: SAsset, executed_quantity: &str) -> (SOrderV3, SOrderV3) {
+        order.submit(locked_asset).unwrap();
+        let executed_order = order.split_partial(Decimal::from_str(executed_quantity).unwrap()).unwrap();
+        (executed_order, order)
+    }
+
+    #[test]
+    pub fn test_partially_executed() {
+        let debug_config = SDebugConfig { is_debug: false, is_info: false };
+        let tp_type = ETradingPairType::BtcUsdt;
+        let mut strategy = SStrategyMk3_2::<SPriceModelSin>::default();
+        let mut tp_order_map = STradingPairOrderManagerMapV3::default();
+        let mut available_assets = SAssetMapV3::new();
+        available_assets.merge_asset(EAssetUnion::from(SAsset { as_type: EAssetType::Usdt, balance: Decimal::from(100_000) }));
+
+        // 开单部分成交：已成交部分转为已开仓 剩余部分撤单后删除
+        let open_order = SOrderV3::new_buy_order(tp_type, Decimal::from(100_000), Decimal::from_str("0.5").unwrap());
+        strategy.verify(&tp_type, vec![ERunnerSyncActionResult::OrderPlaced(open_order.clone(), None)], &debug_config);
+        let (executed_order, remaining_order) = split_order(
+            open_order.clone(),
+            SAsset { as_type: EAssetType::Usdt, balance: Decimal::from(50_000) },
+            "0.2",
+        );
+        let actions = strategy.run(
+            &mut tp_order_map,
+            &mut available_assets,
+            get_parse_result(vec![ERunnerParseOrderResult::OrderPartiallyExecuted(executed_order, remaining_order.get_quantity())]),
+            &debug_config,
+        );
+        assert!(actions.iter().any(|action| matches!(action, EStrategyAction::CancelOrder(id) if *id == open_order.get_id())));
+        strategy.verify(&tp_type, vec![ERunnerSyncActionResult::OrderCanceled(remaining_order)], &debug_config);
+        let strategy_order_manager = strategy.strategy_order_map.get(&tp_type).unwrap();
+        assert_eq!(strategy_order_manager.strategy_orders.len(), 1);
+        let opened_order = strategy_order_manager.strategy_orders.values().next().unwrap().clone();
+        assert_eq!(opened_order.get_state(), EStrategyOrderState::Opened);
+        assert_eq!(opened_order.get_quantity(), Decimal::from_str("0.2").unwrap());
+        assert_eq!(strategy_order_manager.long_opened_orders.values().flatten().count(), 1);
+
+        // 平单部分成交：已成交部分记为已平仓 剩余部分撤单后恢复为已开仓
+        let close_order = SOrderV3::new_sell_order(tp_type, Decimal::from(101_000), Decimal::from_str("0.2").unwrap());
+        strategy.verify(&tp_type, vec![ERunnerSyncActionResult::OrderPlaced(close_order.clone(), Some(opened_order.get_id()))], &debug_config);
+        let (executed_order, remaining_order) = split_order(
+            close_order,
+            SAsset { as_type: EAssetType::Btc, balance: Decimal::from_str("0.21").unwrap() },
+            "0.05",
+        );
+        strategy.run(
+            &mut tp_order_map,
+            &mut available_assets,
+            get_parse_result(vec![ERunnerParseOrderResult::OrderPartiallyExecuted(executed_order, remaining_order.get_quantity())]),
+            &debug_config,
+        );
+        assert_eq!(strategy.logger.closed_order_pair_cnt, 1);
+        assert_eq!(strategy.logger.winning_order_pair_cnt, 1);
+        strategy.verify(&tp_type, vec![ERunnerSyncActionResult::OrderCanceled(remaining_order)], &debug_config);
+        let strategy_order = strategy.strategy_order_map.get(&tp_type).unwrap().peek_by_id(&opened_order.get_id()).unwrap();
+        assert_eq!(strategy_order.get_state(), EStrategyOrderState::Opened);
+        assert_eq!(strategy_order.get_quantity(), Decimal::from_str("0.15").unwrap());
+        assert!(strategy.opening_and_closing_orders.is_empty());
+    }
+}
@@ -14,7 +14,7 @@
 
 use std::cmp::{max, min};
 use std::collections::HashSet;
-use chrono::{DateTime, Local};
+use chrono::{DateTime, Local, TimeZone};
 use log::{debug, error};
 use rust_decimal::Decimal;
 use rust_decimal::prelude::FromPrimitive;
@@ -33,6 +33,7 @@ use crate::{
     protocol::{ERunnerParseOrderResult, ERunnerSyncActionResult, EStrategyAction, SRunnerParseKlineResult},
     strategy::{
         TStrategy,
+        TStrategyFromConfig,
     },
 };
 use crate::config::{SDebugConfig};
@@ -41,13 +42,16 @@ use crate::config::trading_pair::btc_usdt::TRADDING_PAIR_USDT_MIN_QUANTITY;
 use crate::config::user::INIT_BALANCE_USDT;
 use crate::data_runtime::asset::asset_map_v3::SAssetMapV3;
 use crate::data_runtime::asset::asset_union::EAssetUnion;
-use crate::data_runtime::order::EOrderDirection;
+use crate::data_runtime::order::{EOrderDirection, EOrderType};
 use crate::data_runtime::order::trading_pair_order_manager_map_v3::STradingPairOrderManagerMapV3;
 use crate::protocol::strategy_order::SStrategyOrderAdd;
+use crate::strategy::order::order_result::{handle_canceled, handle_liquidation, handle_partially_executed};
 use crate::strategy::logger::SStrategyLogger;
 use crate::strategy::model::feedback_control::{SPidIntegral, SStrategyPidConfig};
 use crate::strategy::model::position_model::SPositionModel;
 use crate::strategy::model::price_model_long_term_trend::SPriceModelLongTermTrend;
+use crate::strategy::model::price_model_sin_test::SPriceModelSin;
+use crate::strategy::model::price_model_step_test::SPriceModelStep;
 use crate::strategy::model::TPriceModel;
 use crate::strategy::order::order::{EStrategyOrderState, SStrategyOrder};
 use crate::strategy::order::order_manager_v2::SStrategyOrderManagerV2;
@@ -100,12 +104,58 @@ struct SNextOrderFormat {
     pub quote_quantity: Decimal,
 }
 
-impl Default for SStrategyMk4<SPriceModelLongTermTrend> {
+impl<M: TPriceModel> Default for SStrategyMk4<M>
+where
+    Self: TStrategyFromConfig,
+{
+    /// 使用config.rs中的默认初始资金量构建
     fn default() -> Self {
+        Self::from_init_balance_usdt(INIT_BALANCE_USDT)
+    }
+}
+
+impl TStrategyFromConfig for SStrategyMk4<SPriceModelLongTermTrend> {
+    fn from_init_balance_usdt(init_balance_usdt: f64) -> Self {
         // 构建长周期趋势模型
+        Self::with_price_model(SPriceModelLongTermTrend::default(), init_balance_usdt)
+    }
+}
+
+impl TStrategyFromConfig for SStrategyMk4<SPriceModelSin> {
+    fn from_init_balance_usdt(init_balance_usdt: f64) -> Self {
+        // 构建正弦波周期性价格模型
+        // 周期2天
+        let period = 60 * 60 * 24 * 2;
+        // 振幅10%
+        let amplitude = Decimal::from_f64(0.1 / 2.0).unwrap();
+        // 原点
+        let origin = Local.with_ymd_and_hms(2025, 1, 1, 0, 0, 0).unwrap();
+        // 期望均值仓位10%
+        let mean = Decimal::from_f64(0.1).unwrap();
+        Self::with_price_model(SPriceModelSin::new(period, amplitude, origin, mean), init_balance_usdt)
+    }
+}
+
+impl TStrategyFromConfig for SStrategyMk4<SPriceModelStep> {
+    fn from_init_balance_usdt(init_balance_usdt: f64) -> Self {
+        // 构建阶跃周期性价格模型
+        // 周期2天
+        let period = 60 * 60 * 24 * 2;
+        // 高点
+        let top = Decimal::from_f64(0.75).unwrap();
+        // 低点
+        let button = Decimal::from_f64(0.25).unwrap();
+        // 原点
+        let origin = Local.with_ymd_and_hms(2025, 1, 1, 0, 0, 0).unwrap();
+        Self::with_price_model(SPriceModelStep::new(period, top, button, origin), init_balance_usdt)
+    }
+}
+
+impl<M: TPriceModel> SStrategyMk4<M> {
+    /// 使用默认参数及给定的价格模型构建 订单最小价格间距按初始资金量（USDT计价）计算
+    pub fn with_price_model(price_model: M, init_balance_usdt: f64) -> Self {
         let position_max = 0.95;
         let position_min = 0.05;
-        let price_model = SPriceModelLongTermTrend::default();
 
         // 只在盘口价的±2.0%挂单
         let cut_off_price_percentage = 0.02;
@@ -118,7 +168,7 @@ impl Default for SStrategyMk4<SPriceModelLongTermTrend> {
         let maker_order_fee_percentage = MAKER_ORDER_FEE;
         let open_quantity_percentage = TRADDING_PAIR_USDT_MIN_QUANTITY;
         // 订单最小价格间距
-        let delta_price_min_percentage = TRADDING_PAIR_USDT_MIN_QUANTITY / INIT_BALANCE_USDT;
+        let delta_price_min_percentage = TRADDING_PAIR_USDT_MIN_QUANTITY / init_balance_usdt;
         // 死区大小（等于开单与平单的价差最小值）
         let dead_zone_range_percentage = maker_order_fee_percentage * 2.0 + minimum_profit_percentage;
         // 活区大小
@@ -152,69 +202,7 @@ impl Default for SStrategyMk4<SPriceModelLongTermTrend> {
             position_min,
         )
     }
-}
-
-// impl Default for SStrategyMk4<SPriceModelSin> {
-//     fn default() -> Self {
-//         // 构建正弦波周期性价格模型
-//         // 周期2天
-//         // let period = 60 * 60 * 24 * 2;
-//         // 周期2小时
-//         let period = 60 * 60 * 2;
-//         // 振幅10%
-//         let amplitude = Decimal::from_f64(0.1 / 2.0).unwrap();
-//         // 原点
-//         let origin = Local.ymd(2025, 1, 1).and_hms(0, 0, 0);
-//         // 期望均值仓位10%
-//         let mean = Decimal::from_f64(0.1).unwrap();
-//         let price_model = SPriceModelSin::new(period, amplitude, origin, mean);
-//
-//         // 只在盘口价的±2.0%挂单
-//         let cut_off_price_percentage = 0.02;
-//         // 每单的最小盈利0.06%
-//         let minimum_profit_percentage = 0.0006;
-//         // 每单的最大盈利5.0%
-//         let max_profit_percentage = 0.05;
-//         // 平仓价最小间距为开仓价格的0.1%
-//         let close_price_step_percentage = 0.001;
-//         let maker_order_fee_percentage = MAKER_ORDER_FEE;
-//         let open_quantity_percentage = TRADDING_PAIR_USDT_MIN_QUANTITY;
-//         // 订单最小价格间距
-//         let delta_price_min_percentage = TRADDING_PAIR_USDT_MIN_QUANTITY / INIT_BALANCE_USDT;
-//         // 死区大小（等于开单与平单的价差最小值）
-//         let dead_zone_range_percentage = maker_order_fee_percentage * 2.0 + minimum_profit_percentage;
-//         // 活区大小
-//         let live_zone_range_percentage = dead_zone_range_percentage;
-//         // pid参数
-//         // let pid_p_parameter = 0.005; // pid比例项参数
-//         let pid_p_parameter = 1.0; // pid比例项参数（1.0代表没有比例项）
-//         // let pid_i_parameter = 0.00025;  // pid积分项参数
-//         let pid_i_parameter = 0.0;  // pid积分项参数(0.0代表没有积分项)
-//         let pid_i_max_cumulative = 1.8; // pid积分项累计值最大值
-//         Self::new(
-//             price_model,
-//             Decimal::from_f64(cut_off_price_percentage).unwrap(),
-//             Decimal::from_f64(minimum_profit_percentage).unwrap(),
-//             Decimal::from_f64(max_profit_percentage).unwrap(),
-//             Decimal::from_f64(close_price_step_percentage).unwrap(),
-//             Decimal::from_f64(open_quantity_percentage).unwrap(),
-//             Decimal::from_f64(delta_price_min_percentage).unwrap(),
-//             Decimal::from_f64(maker_order_fee_percentage).unwrap(),
-//             SStrategyPidConfig {
-//                 proportional: Decimal::from_f64(pid_p_parameter).unwrap(),
-//                 integral: Some(SPidIntegral::new(
-//                     Decimal::from_f64(pid_i_parameter).unwrap(), // 积分项参数
-//                     Decimal::from_f64(pid_i_max_cumulative).unwrap(),
-//                 )),
-//                 derivative: None,
-//             },
-//             Decimal::from_f64(dead_zone_range_percentage).unwrap(),
-//             Decimal::from_f64(live_zone_range_percentage).unwrap(),
-//         )
-//     }
-// }
 
-impl<M: TPriceModel> SStrategyMk4<M> {
     pub fn new(
         price_model: M,
         cut_off_price_percentage: Decimal,
@@ -242,7 +230,7 @@ impl<M: TPriceModel> SStrategyMk4<M> {
         strategy_order_map.inner.insert(ETradingPairType::BtcUsdtFuture, manager.clone());
         strategy_order_map.inner.insert(ETradingPairType::BtcUsdCmFuture, manager.clone());
         Self {
-            logger: SStrategyLogger { target_position_ratio: Decimal::from(0) },
+            logger: SStrategyLogger::new(Decimal::from(0)),
             model,
             opening_and_closing_orders: Default::default(),
             strategy_order_map,
@@ -556,6 +544,7 @@ impl<M: TPriceModel> SStrategyMk4<M> {
                         price: order_price,
                         base_quantity: order_quantity,
                         margin_quantity: order_quantity * order_price,
+                        order_type: EOrderType::Limit,
                     }));
                     { // debug
                         // debug!("MK4::generate_open_orders: new order\ttp_type:{:?}\taction:{:?}\tprice:{:.2?}\tquantity:{:?}"
@@ -712,6 +701,7 @@ impl<M: TPriceModel> SStrategyMk4<M> {
                         price: tmp_price,
                         base_quantity: order_quantity,
                         margin_quantity: order_quantity * tmp_price,
+                        order_type: EOrderType::Limit,
                     }));
                     // 更新数据
                     tmp_base_quantity = new_base_quantity;
@@ -911,7 +901,11 @@ impl<M: TPriceModel> TStrategy for SStrategyMk4<M> {
                                     match strategy_order_manager.closed_by_order_id(order_id) {
                                         Err(e) => { error!("SStrategyMk4::run():{:?}", e); }
                                         Ok(Err(e)) => { error!("SStrategyMk4::run():{:?}", e); }
-                                        Ok(Ok(_)) => {}
+                                        Ok(Ok(_)) => {
+                                            if let Ok(strategy_order) = strategy_order_manager.peek_by_order_id(order_id) {
+                                                self.logger.record_closed_order_pair(strategy_order);
+                                            }
+                                        }
                                     }
                                 }
                                 unexpected_state => {
@@ -925,6 +919,17 @@ impl<M: TPriceModel> TStrategy for SStrategyMk4<M> {
                     //     error!("order can not insert into strategy_order:{:?}", order);
                     // }
                 }
+                ERunnerParseOrderResult::OrderCanceled(order) => {
+                    handle_canceled(strategy_order_manager, &mut self.opening_and_closing_orders, &order);
+                }
+                ERunnerParseOrderResult::Liquidated(liquidation) => {
+                    handle_liquidation(strategy_order_manager, &mut self.opening_and_closing_orders, &liquidation);
+                }
+                ERunnerParseOrderResult::OrderPartiallyExecuted(order, _) => {
+                    if let Some(closed_strategy_order) = handle_partially_executed(strategy_order_manager, &order) {
+                        self.logger.record_closed_order_pair(&closed_strategy_order);
+                    }
+                }
             }
         }
 
@@ -6,10 +6,11 @@
 
 use std::cmp::{max, min};
 use std::collections::HashSet;
-use chrono::{DateTime, Local};
+use chrono::{DateTime, Local, TimeZone};
 use log::{debug, error};
 use rust_decimal::Decimal;
 use rust_decimal::prelude::FromPrimitive;
+use serde::Serialize;
 use uuid::Uuid;
 
 use crate::{
@@ -25,6 +26,7 @@ use crate::{
     protocol::{ERunnerParseOrderResult, ERunnerSyncActionResult, EStrategyAction, SRunnerParseKlineResult},
     strategy::{
         TStrategy,
+        TStrategyFromConfig,
     },
 };
 use crate::config::{SDebugConfig};
@@ -33,13 +35,17 @@ use crate::config::trading_pair::btc_usdt::TRADDING_PAIR_USDT_MIN_QUANTITY;
 use crate::config::user::INIT_BALANCE_USDT;
 use crate::data_runtime::asset::asset_map_v3::SAssetMapV3;
 use crate::data_runtime::asset::asset_union::EAssetUnion;
-use crate::data_runtime::order::EOrderDirection;
+use crate::data_runtime::order::{EOrderDirection, EOrderType};
 use crate::data_runtime::order::trading_pair_order_manager_map_v3::STradingPairOrderManagerMapV3;
 use crate::protocol::strategy_order::SStrategyOrderAdd;
+use crate::script::sweep::{ESweepError, RSweepResult, TSweepParams, TSweepStrategy};
+use crate::strategy::order::order_result::{handle_canceled, handle_liquidation, handle_partially_executed};
 use crate::strategy::logger::SStrategyLogger;
 use crate::strategy::model::feedback_control::{SPidIntegral, SStrategyPidConfig};
 use crate::strategy::model::position_model::SPositionModel;
 use crate::strategy::model::price_model_long_term_trend::SPriceModelLongTermTrend;
+use crate::strategy::model::price_model_sin_test::SPriceModelSin;
+use crate::strategy::model::price_model_step_test::SPriceModelStep;
 use crate::strategy::model::TPriceModel;
 use crate::strategy::order::order::{EStrategyOrderState, SStrategyOrder};
 use crate::strategy::order::order_manager_v2::SStrategyOrderManagerV2;
@@ -92,61 +98,208 @@ struct SNextOrderFormat {
     pub quote_quantity: Decimal,
 }
 
-impl Default for SStrategyMk5<SPriceModelLongTermTrend> {
+/// StrategyMk5的数值参数 用于参数扫描
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SStrategyMk5Params {
+    /// 最大仓位
+    pub position_max: f64,
+    /// 最小仓位
+    pub position_min: f64,
+    /// 只在盘口价的±cut_off_price_percentage挂单
+    pub cut_off_price_percentage: f64,
+    /// 每单的最小盈利
+    pub minimum_profit_percentage: f64,
+    /// 每单的最大盈利
+    pub max_profit_percentage: f64,
+    /// 平仓价最小间距（相比于开仓价的百分比）
+    pub close_price_step_percentage: f64,
+    /// 挂单手续费
+    pub maker_order_fee_percentage: f64,
+    /// open订单固定下单量百分比
+    pub open_quantity_percentage: f64,
+    /// 订单最小价格间距
+    pub delta_price_min_percentage: f64,
+    /// 死区大小 为None时等于开单与平单的价差最小值（挂单手续费*2+最小盈利）
+    pub dead_zone_range_percentage: Option<f64>,
+    /// 活区大小 为None时等于死区大小
+    pub live_zone_range_percentage: Option<f64>,
+    /// pid比例项参数（1.0代表没有比例项）
+    pub pid_p_parameter: f64,
+    /// pid积分项参数（0.0代表没有积分项）
+    pub pid_i_parameter: f64,
+    /// pid积分项累计值最大值（需要大于0）
+    pub pid_i_max_cumulative: f64,
+}
+
+impl Default for SStrategyMk5Params {
+    /// 使用config.rs中的默认初始资金量计算订单最小价格间距
+    fn default() -> Self {
+        Self::from_init_balance_usdt(INIT_BALANCE_USDT)
+    }
+}
+
+impl TSweepParams for SStrategyMk5Params {
+    fn from_init_balance_usdt(init_balance_usdt: f64) -> Self {
+        Self {
+            position_max: 0.95,
+            position_min: 0.05,
+            // 只在盘口价的±2.0%挂单
+            cut_off_price_percentage: 0.02,
+            // 每单的最小盈利0.06%
+            minimum_profit_percentage: 0.0006,
+            // 每单的最大盈利5.0%
+            max_profit_percentage: 0.05,
+            // 平仓价最小间距为开仓价格的0.1%
+            close_price_step_percentage: 0.001,
+            maker_order_fee_percentage: MAKER_ORDER_FEE,
+            open_quantity_percentage: TRADDING_PAIR_USDT_MIN_QUANTITY,
+            delta_price_min_percentage: TRADDING_PAIR_USDT_MIN_QUANTITY / init_balance_usdt,
+            dead_zone_range_percentage: None,
+            live_zone_range_percentage: None,
+            pid_p_parameter: 1.0,
+            pid_i_parameter: 0.0,
+            pid_i_max_cumulative: 1.8,
+        }
+    }
+
+    fn set(&mut self, name: &str, value: f64) -> RSweepResult<()> {
+        match name {
+            "position_max" => { self.position_max = value; }
+            "position_min" => { self.position_min = value; }
+            "cut_off_price_percentage" => { self.cut_off_price_percentage = value; }
+            "minimum_profit_percentage" => { self.minimum_profit_percentage = value; }
+            "max_profit_percentage" => { self.max_profit_percentage = value; }
+            "close_price_step_percentage" => { self.close_price_step_percentage = value; }
+            "maker_order_fee_percentage" => { self.maker_order_fee_percentage = value; }
+            "open_quantity_percentage" => { self.open_quantity_percentage = value; }
+            "delta_price_min_percentage" => { self.delta_price_min_percentage = value; }
+            "dead_zone_range_percentage" => { self.dead_zone_range_percentage = Some(value); }
+            "live_zone_range_percentage" => { self.live_zone_range_percentage = Some(value); }
+            "pid_p_parameter" => { self.pid_p_parameter = value; }
+            "pid_i_parameter" => { self.pid_i_parameter = value; }
+            "pid_i_max_cumulative" => { self.pid_i_max_cumulative = value; }
+            _ => { return Err(ESweepError::UnknownParameterError(name.to_string())); }
+        }
+        Ok(())
+    }
+
+    /// 仓位在[0, 1]内且最小仓位不大于最大仓位 挂单范围在[0, 1)内 最小盈利不大于最大盈利
+    /// 下单量及积分项累计值最大值大于0 其余参数不小于0 所有取值需要能转换为Decimal（有限且不超出Decimal范围）
+    fn validate(&self) -> RSweepResult<()> {
+        let dead_zone_range_percentage = self.dead_zone_range_percentage.unwrap_or_default();
+        let live_zone_range_percentage = self.live_zone_range_percentage.unwrap_or_default();
+        let checks = [
+            ("position_max", self.position_max, (0.0..=1.0).contains(&self.position_max)),
+            ("position_min", self.position_min, (0.0..=self.position_max).contains(&self.position_min)),
+            ("cut_off_price_percentage", self.cut_off_price_percentage, (0.0..1.0).contains(&self.cut_off_price_percentage)),
+            ("minimum_profit_percentage", self.minimum_profit_percentage, self.minimum_profit_percentage >= 0.0),
+            ("max_profit_percentage", self.max_profit_percentage, self.max_profit_percentage >= self.minimum_profit_percentage),
+            ("close_price_step_percentage", self.close_price_step_percentage, self.close_price_step_percentage >= 0.0),
+            ("maker_order_fee_percentage", self.maker_order_fee_percentage, self.maker_order_fee_percentage >= 0.0),
+            ("open_quantity_percentage", self.open_quantity_percentage, self.open_quantity_percentage > 0.0),
+            ("delta_price_min_percentage", self.delta_price_min_percentage, self.delta_price_min_percentage >= 0.0),
+            ("dead_zone_range_percentage", dead_zone_range_percentage, dead_zone_range_percentage >= 0.0),
+            ("live_zone_range_percentage", live_zone_range_percentage, live_zone_range_percentage >= 0.0),
+            ("pid_p_parameter", self.pid_p_parameter, self.pid_p_parameter >= 0.0),
+            ("pid_i_parameter", self.pid_i_parameter, self.pid_i_parameter >= 0.0),
+            ("pid_i_max_cumulative", self.pid_i_max_cumulative, self.pid_i_max_cumulative > 0.0),
+        ];
+        for (name, value, is_valid) in checks {
+            if !is_valid || Decimal::from_f64(value).is_none() {
+                return Err(ESweepError::InvalidParameterError(name.to_string(), value));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl TSweepStrategy for SStrategyMk5<SPriceModelLongTermTrend> {
+    type Params = SStrategyMk5Params;
+
+    fn from_params(params: &Self::Params) -> Self {
+        Self::with_params(SPriceModelLongTermTrend::default(), params)
+    }
+}
+
+impl<M: TPriceModel> Default for SStrategyMk5<M>
+where
+    Self: TStrategyFromConfig,
+{
+    /// 使用config.rs中的默认初始资金量构建
     fn default() -> Self {
-        // 构建长周期趋势模型
-        let position_max = 0.95;
-        let position_min = 0.05;
-        let price_model = SPriceModelLongTermTrend::default();
-
-        // 只在盘口价的±2.0%挂单
-        let cut_off_price_percentage = 0.02;
-        // 每单的最小盈利0.06%
-        let minimum_profit_percentage = 0.0006;
-        // 每单的最大盈利5.0%
-        let max_profit_percentage = 0.05;
-        // 平仓价最小间距为开仓价格的0.1%
-        let close_price_step_percentage = 0.001;
-        let maker_order_fee_percentage = MAKER_ORDER_FEE;
-        let open_quantity_percentage = TRADDING_PAIR_USDT_MIN_QUANTITY;
-        // 订单最小价格间距
-        let delta_price_min_percentage = TRADDING_PAIR_USDT_MIN_QUANTITY / INIT_BALANCE_USDT;
-        // 死区大小（等于开单与平单的价差最小值）
-        let dead_zone_range_percentage = maker_order_fee_percentage * 2.0 + minimum_profit_percentage;
-        // 活区大小
-        let live_zone_range_percentage = dead_zone_range_percentage;
-        // pid参数
-        // let pid_p_parameter = 0.005; // pid比例项参数
-        let pid_p_parameter = 1.0; // pid比例项参数（1.0代表没有比例项）
-        // let pid_i_parameter = 0.025;  // pid积分项参数
-        let pid_i_parameter = 0.0;  // pid积分项参数(0.0代表没有积分项)
-        let pid_i_max_cumulative = 1.8; // pid积分项累计值最大值
+        Self::from_init_balance_usdt(INIT_BALANCE_USDT)
+    }
+}
+
+impl TStrategyFromConfig for SStrategyMk5<SPriceModelLongTermTrend> {
+    fn from_init_balance_usdt(init_balance_usdt: f64) -> Self {
+        Self::from_params(&SStrategyMk5Params::from_init_balance_usdt(init_balance_usdt))
+    }
+}
+
+impl TStrategyFromConfig for SStrategyMk5<SPriceModelSin> {
+    fn from_init_balance_usdt(init_balance_usdt: f64) -> Self {
+        // 构建正弦波周期性价格模型
+        // 周期2天
+        let period = 60 * 60 * 24 * 2;
+        // 振幅10%
+        let amplitude = Decimal::from_f64(0.1 / 2.0).unwrap();
+        // 原点
+        let origin = Local.with_ymd_and_hms(2025, 1, 1, 0, 0, 0).unwrap();
+        // 期望均值仓位10%
+        let mean = Decimal::from_f64(0.1).unwrap();
+        Self::with_params(SPriceModelSin::new(period, amplitude, origin, mean), &SStrategyMk5Params::from_init_balance_usdt(init_balance_usdt))
+    }
+}
+
+impl TStrategyFromConfig for SStrategyMk5<SPriceModelStep> {
+    fn from_init_balance_usdt(init_balance_usdt: f64) -> Self {
+        // 构建阶跃周期性价格模型
+        // 周期2天
+        let period = 60 * 60 * 24 * 2;
+        // 高点
+        let top = Decimal::from_f64(0.75).unwrap();
+        // 低点
+        let button = Decimal::from_f64(0.25).unwrap();
+        // 原点
+        let origin = Local.with_ymd_and_hms(2025, 1, 1, 0, 0, 0).unwrap();
+        Self::with_params(SPriceModelStep::new(period, top, button, origin), &SStrategyMk5Params::from_init_balance_usdt(init_balance_usdt))
+    }
+}
+
+impl<M: TPriceModel> SStrategyMk5<M> {
+    /// 使用给定的价格模型及参数构建
+    pub fn with_params(price_model: M, params: &SStrategyMk5Params) -> Self {
+        let to_decimal = |value: f64| Decimal::from_f64(value).unwrap();
+        // 死区大小（默认等于开单与平单的价差最小值）
+        let dead_zone_range_percentage = params.dead_zone_range_percentage
+            .unwrap_or(params.maker_order_fee_percentage * 2.0 + params.minimum_profit_percentage);
+        // 活区大小（默认等于死区大小）
+        let live_zone_range_percentage = params.live_zone_range_percentage.unwrap_or(dead_zone_range_percentage);
         Self::new(
             price_model,
-            Decimal::from_f64(cut_off_price_percentage).unwrap(),
-            Decimal::from_f64(minimum_profit_percentage).unwrap(),
-            Decimal::from_f64(max_profit_percentage).unwrap(),
-            Decimal::from_f64(close_price_step_percentage).unwrap(),
-            Decimal::from_f64(open_quantity_percentage).unwrap(),
-            Decimal::from_f64(delta_price_min_percentage).unwrap(),
-            Decimal::from_f64(maker_order_fee_percentage).unwrap(),
+            to_decimal(params.cut_off_price_percentage),
+            to_decimal(params.minimum_profit_percentage),
+            to_decimal(params.max_profit_percentage),
+            to_decimal(params.close_price_step_percentage),
+            to_decimal(params.open_quantity_percentage),
+            to_decimal(params.delta_price_min_percentage),
+            to_decimal(params.maker_order_fee_percentage),
             SStrategyPidConfig {
-                proportional: Decimal::from_f64(pid_p_parameter).unwrap(),
+                proportional: to_decimal(params.pid_p_parameter),
                 integral: Some(SPidIntegral::new(
-                    Decimal::from_f64(pid_i_parameter).unwrap(), // 积分项参数
-                    Decimal::from_f64(pid_i_max_cumulative).unwrap(),
+                    to_decimal(params.pid_i_parameter), // 积分项参数
+                    to_decimal(params.pid_i_max_cumulative),
                 )),
                 derivative: None,
             },
-            Decimal::from_f64(dead_zone_range_percentage).unwrap(),
-            Decimal::from_f64(live_zone_range_percentage).unwrap(),
-            position_max,
-            position_min,
+            to_decimal(dead_zone_range_percentage),
+            to_decimal(live_zone_range_percentage),
+            params.position_max,
+            params.position_min,
         )
     }
-}
 
-impl<M: TPriceModel> SStrategyMk5<M> {
     pub fn new(
         price_model: M,
         cut_off_price_percentage: Decimal,
@@ -174,7 +327,7 @@ impl<M: TPriceModel> SStrategyMk5<M> {
         strategy_order_map.inner.insert(ETradingPairType::BtcUsdtFuture, manager.clone());
         strategy_order_map.inner.insert(ETradingPairType::BtcUsdCmFuture, manager.clone());
         Self {
-            logger: SStrategyLogger { target_position_ratio: Decimal::from(0) },
+            logger: SStrategyLogger::new(Decimal::from(0)),
             model,
             opening_and_closing_orders: Default::default(),
             strategy_order_map,
@@ -488,6 +641,7 @@ impl<M: TPriceModel> SStrategyMk5<M> {
                         price: order_price,
                         base_quantity: order_quantity,
                         margin_quantity: order_quantity * order_price,
+                        order_type: EOrderType::Limit,
                     }));
                     { // debug
                         // debug!("Mk5::generate_open_orders: new order\ttp_type:{:?}\taction:{:?}\tprice:{:.2?}\tquantity:{:?}"
@@ -644,6 +798,7 @@ impl<M: TPriceModel> SStrategyMk5<M> {
                         price: tmp_price,
                         base_quantity: order_quantity,
                         margin_quantity: order_quantity * tmp_price,
+                        order_type: EOrderType::Limit,
                     }));
                     // 更新数据
                     tmp_base_quantity = new_base_quantity;
@@ -843,7 +998,11 @@ impl<M: TPriceModel> TStrategy for SStrategyMk5<M> {
                                     match strategy_order_manager.closed_by_order_id(order_id) {
                                         Err(e) => { error!("SStrategyMk5::run():{:?}", e); }
                                         Ok(Err(e)) => { error!("SStrategyMk5::run():{:?}", e); }
-                                        Ok(Ok(_)) => {}
+                                        Ok(Ok(_)) => {
+                                            if let Ok(strategy_order) = strategy_order_manager.peek_by_order_id(order_id) {
+                                                self.logger.record_closed_order_pair(strategy_order);
+                                            }
+                                        }
                                     }
                                 }
                                 unexpected_state => {
@@ -857,6 +1016,17 @@ impl<M: TPriceModel> TStrategy for SStrategyMk5<M> {
                     //     error!("order can not insert into strategy_order:{:?}", order);
                     // }
                 }
+                ERunnerParseOrderResult::OrderCanceled(order) => {
+                    handle_canceled(strategy_order_manager, &mut self.opening_and_closing_orders, &order);
+                }
+                ERunnerParseOrderResult::Liquidated(liquidation) => {
+                    handle_liquidation(strategy_order_manager, &mut self.opening_and_closing_orders, &liquidation);
+                }
+                ERunnerParseOrderResult::OrderPartiallyExecuted(order, _) => {
+                    if let Some(closed_strategy_order) = handle_partially_executed(strategy_order_manager, &order) {
+                        self.logger.record_closed_order_pair(&closed_strategy_order);
+                    }
+                }
             }
         }
 
@@ -6,13 +6,13 @@ use rust_decimal::Decimal;
 use uuid::Uuid;
 use crate::config::SDebugConfig;
 use crate::data_runtime::asset::asset_map_v3::SAssetMapV3;
-use crate::data_runtime::order::EOrderAction;
+use crate::data_runtime::order::{EOrderAction, EOrderType};
 use crate::protocol::{ERunnerSyncActionResult, EStrategyAction, SRunnerParseKlineResult};
 use crate::data_runtime::order::trading_pair_order_manager_map_v3::STradingPairOrderManagerMapV3;
 use crate::data_source::trading_pair::ETradingPairType;
 use crate::protocol::strategy_order::SStrategyOrderAdd;
 use crate::strategy::logger::SStrategyLogger;
-use crate::strategy::TStrategy;
+use crate::strategy::{TStrategy, TStrategyFromConfig};
 
 /// 测试用策略
 #[derive(Debug)]
@@ -26,6 +26,13 @@ impl Default for SStrategyMkTest {
     }
 }
 
+impl TStrategyFromConfig for SStrategyMkTest {
+    /// 策略参数与初始资金量无关
+    fn from_init_balance_usdt(_init_balance_usdt: f64) -> Self {
+        Self::default()
+    }
+}
+
 impl TStrategy for SStrategyMkTest {
     fn run(
         &mut self,
@@ -62,6 +69,7 @@ impl TStrategy for SStrategyMkTest {
             price,
             base_quantity,
             margin_quantity,
+            order_type: EOrderType::Limit,
         };
         let price = kline_unit.high_price;
         let margin_quantity = price * base_quantity;
@@ -72,6 +80,7 @@ impl TStrategy for SStrategyMkTest {
             price,
             base_quantity,
             margin_quantity,
+            order_type: EOrderType::Limit,
         };
         result.push(EStrategyAction::NewOrder(action_new_order1));
         result.push(EStrategyAction::NewOrder(action_new_order2));
@@ -12,7 +12,7 @@ use crate::data_runtime::order::trading_pair_order_manager_map_v3::STradingPairO
 use crate::data_source::trading_pair::ETradingPairType;
 use crate::protocol::strategy_order::SStrategyOrderAdd;
 use crate::strategy::logger::SStrategyLogger;
-use crate::strategy::TStrategy;
+use crate::strategy::{TStrategy, TStrategyFromConfig};
 
 /// 测试用策略-杠杆资产专用
 #[derive(Debug)]
@@ -26,6 +26,13 @@ impl Default for SStrategyMkTestLeveraged {
     }
 }
 
+impl TStrategyFromConfig for SStrategyMkTestLeveraged {
+    /// 策略参数与初始资金量无关
+    fn from_init_balance_usdt(_init_balance_usdt: f64) -> Self {
+        Self::default()
+    }
+}
+
 impl TStrategy for SStrategyMkTestLeveraged {
     fn run(
         &mut self,
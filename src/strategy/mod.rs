@@ -42,3 +42,9 @@ pub trait TStrategy {
     fn get_position(&self, time: DateTime<Local>) -> Option<Decimal>;
 }
 
+/// 按回测配置构建策略
+pub trait TStrategyFromConfig {
+    /// 按配置的初始资金量（USDT计价）构建策略
+    fn from_init_balance_usdt(init_balance_usdt: f64) -> Self;
+}
+
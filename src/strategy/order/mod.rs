@@ -2,4 +2,4 @@ pub mod order;
 pub mod order_manager;
 pub mod trading_pair_order_map;
 pub mod order_manager_v2;
-pub mod trading_pair_order_map_v2;
\ No newline at end of file
+pub mod trading_pair_order_map_v2;pub mod order_result;
@@ -18,6 +18,8 @@ pub enum EStrategyOrderError {
     StateTransferError(EStrategyOrderState, EStrategyOrderState, EStrategyOrderState),
     /// 开单和平单的quantity不一致（开单quantity 平单quantity）
     InconsistentQuantityBetweenOrderPair(Decimal, Decimal),
+    /// 拆分量必须与订单量同号且绝对值小于订单量（订单quantity 拆分quantity）
+    SplitQuantityError(Decimal, Decimal),
 }
 
 #[derive(Debug, Eq, PartialEq, Copy, Clone)]
@@ -117,6 +119,20 @@ impl SStrategyOrder {
         Ok(())
     }
 
+    /// 拆分订单 用于订单部分成交
+    /// 拆分出的订单使用新的id 状态与价格与原订单一致 原订单保留剩余的quantity
+    pub fn split(&mut self, quantity: Decimal) -> RStrategyOrderResult<Self> {
+        if quantity.is_zero() || quantity.is_sign_negative() != self.quantity.is_sign_negative() || quantity.abs() >= self.quantity.abs() {
+            return Err(EStrategyOrderError::SplitQuantityError(self.quantity, quantity));
+        }
+        self.quantity -= quantity;
+        Ok(Self {
+            id: Uuid::new_v4(),
+            quantity,
+            ..self.clone()
+        })
+    }
+
     // region --- getter and setter
     pub fn get_id(&self) -> Uuid {
         self.id
@@ -438,4 +454,18 @@ mod tests {
         assert_eq!(strategy_order.get_open_price(), Decimal::from(100_000));
         assert_eq!(strategy_order.get_close_price(), None);
     }
+
+    #[test]
+    pub fn test_split() {
+        let open_order = get_test_data_open_order();
+        let mut strategy_order = SStrategyOrder::new(&open_order);
+        assert!(matches!(strategy_order.split(Decimal::from_f64(0.5).unwrap()), Err(EStrategyOrderError::SplitQuantityError(..))));
+        assert!(matches!(strategy_order.split(Decimal::from_f64(-0.1).unwrap()), Err(EStrategyOrderError::SplitQuantityError(..))));
+        let split_order = strategy_order.split(Decimal::from_f64(0.2).unwrap()).unwrap();
+        assert_ne!(split_order.get_id(), strategy_order.get_id());
+        assert_eq!(split_order.get_open_order_id(), open_order.get_id());
+        assert_eq!(split_order.get_state(), EStrategyOrderState::Opening);
+        assert_eq!(split_order.get_quantity(), Decimal::from_f64(0.2).unwrap());
+        assert_eq!(strategy_order.get_quantity(), Decimal::from_f64(0.3).unwrap());
+    }
 }
@@ -6,7 +6,7 @@ use uuid::Uuid;
 
 use crate::data_runtime::order::EOrderDirection;
 use crate::data_runtime::order::order_v3::SOrderV3;
-use crate::strategy::order::order::{EStrategyOrderState, RStrategyOrderResult, SStrategyOrder};
+use crate::strategy::order::order::{EStrategyOrderError, EStrategyOrderState, RStrategyOrderResult, SStrategyOrder};
 
 pub type RStrategyOrderManagerResult<T> = Result<T, EStrategyOrderManagerError>;
 
@@ -99,12 +99,16 @@ impl SStrategyOrderManager {
             Some(order) => {
                 // 删除索引
 
-                // 删除open order的id索引
-                let _ = self.order_strategy_order_index.remove(&order.get_open_order_id());
+                // 删除open order的id索引（部分成交拆分出的策略订单与原策略订单共用open order 只删除指向自身的索引）
+                if self.order_strategy_order_index.get(&order.get_open_order_id()) == Some(uuid) {
+                    let _ = self.order_strategy_order_index.remove(&order.get_open_order_id());
+                }
 
                 // 删除close order的id索引
                 if let Some(close_order_id) = order.get_close_order_id() {
-                    let _ = self.order_strategy_order_index.remove(&close_order_id);
+                    if self.order_strategy_order_index.get(&close_order_id) == Some(uuid) {
+                        let _ = self.order_strategy_order_index.remove(&close_order_id);
+                    }
                 }
 
                 // 从 LongOrders/ShortOrders中删除对应元素
@@ -247,6 +251,39 @@ impl SStrategyOrderManager {
         Ok(strategy_order.closed())
     }
 
+    /// 订单部分成交 从订单对应的策略订单中拆分出已成交部分
+    /// 开单部分成交时 已成交部分作为新的已开仓策略订单写入opened_orders 不占用open order的索引
+    /// 平单部分成交时 返回已平仓的部分并在外部析构
+    /// 剩余部分保持原有状态和索引 撤单后按剩余的quantity处理
+    pub fn partially_executed_by_order_id(&mut self, order_id: &Uuid, executed_quantity: Decimal) -> RStrategyOrderManagerResult<RStrategyOrderResult<Option<SStrategyOrder>>> {
+        let strategy_order = self.peek_mut_by_order_id(order_id)?;
+        let state = strategy_order.get_state();
+        if state != EStrategyOrderState::Opening && state != EStrategyOrderState::Closing {
+            return Ok(Err(EStrategyOrderError::StateVerificationError(format!("Can not split strategy order in state:{:?}", state))));
+        }
+        let mut executed = match strategy_order.split(executed_quantity) {
+            Err(e) => { return Ok(Err(e)); }
+            Ok(executed) => { executed }
+        };
+        match state {
+            EStrategyOrderState::Opening => {
+                if let Err(e) = executed.opened() {
+                    return Ok(Err(e));
+                }
+                let executed_id = executed.get_id();
+                self.strategy_orders.insert(executed_id, executed);
+                self.push_into_opened_orders(&executed_id)?;
+                Ok(Ok(None))
+            }
+            _ => {
+                match executed.closed() {
+                    Err(e) => { Ok(Err(e)) }
+                    Ok(_) => { Ok(Ok(Some(executed))) }
+                }
+            }
+        }
+    }
+
     /// 取消平单
     /// 需要将订单写入opened_orders
     pub fn cancel_close_by_order_id(&mut self, order_id: &Uuid) -> RStrategyOrderManagerResult<RStrategyOrderResult<()>> {
@@ -0,0 +1,178 @@
+//! 各策略共用的订单结果处理（部分成交、强平）
+
+use std::collections::HashSet;
+use std::fmt::Debug;
+
+use log::error;
+use rust_decimal::Decimal;
+use uuid::Uuid;
+
+use crate::data_runtime::order::order_v3::SOrderV3;
+use crate::protocol::SRunnerLiquidation;
+use crate::strategy::order::order::{EStrategyOrderState, RStrategyOrderResult, SStrategyOrder};
+use crate::strategy::order::order_manager::{EStrategyOrderManagerError, SStrategyOrderManager};
+use crate::strategy::order::order_manager_v2::{EStrategyOrderManagerV2Error, SStrategyOrderManagerV2};
+
+/// 策略订单管理器的公共操作
+pub trait TStrategyOrderManager {
+    type Error: Debug;
+
+    /// 订单部分成交 拆分出已成交部分 平单部分成交时返回已平仓的部分
+    fn partially_executed_by_order_id(&mut self, order_id: &Uuid, executed_quantity: Decimal) -> Result<RStrategyOrderResult<Option<SStrategyOrder>>, Self::Error>;
+
+    /// 订单被取消 开单取消时删除策略订单 平单取消时策略订单恢复为已开仓
+    fn cancel_by_order_id(&mut self, order_id: &Uuid) -> Result<RStrategyOrderResult<()>, Self::Error>;
+
+    /// 清空所有策略订单
+    fn clear(&mut self);
+}
+
+impl TStrategyOrderManager for SStrategyOrderManager {
+    type Error = EStrategyOrderManagerError;
+
+    fn partially_executed_by_order_id(&mut self, order_id: &Uuid, executed_quantity: Decimal) -> Result<RStrategyOrderResult<Option<SStrategyOrder>>, Self::Error> {
+        SStrategyOrderManager::partially_executed_by_order_id(self, order_id, executed_quantity)
+    }
+
+    fn cancel_by_order_id(&mut self, order_id: &Uuid) -> Result<RStrategyOrderResult<()>, Self::Error> {
+        match self.peek_by_order_id(order_id)?.get_state() {
+            EStrategyOrderState::Closing => { self.cancel_close_by_order_id(order_id) }
+            _ => { Ok(self.cancel_open_by_order_id(order_id)?.map(|_| ())) }
+        }
+    }
+
+    fn clear(&mut self) {
+        self.strategy_orders.clear();
+        self.order_strategy_order_index.clear();
+        self.long_opened_orders.clear();
+        self.short_opened_orders.clear();
+    }
+}
+
+impl TStrategyOrderManager for SStrategyOrderManagerV2 {
+    type Error = EStrategyOrderManagerV2Error;
+
+    fn partially_executed_by_order_id(&mut self, order_id: &Uuid, executed_quantity: Decimal) -> Result<RStrategyOrderResult<Option<SStrategyOrder>>, Self::Error> {
+        SStrategyOrderManagerV2::partially_executed_by_order_id(self, order_id, executed_quantity)
+    }
+
+    fn cancel_by_order_id(&mut self, order_id: &Uuid) -> Result<RStrategyOrderResult<()>, Self::Error> {
+        match self.peek_by_order_id(order_id)?.get_state() {
+            EStrategyOrderState::Closing => { Ok(self.cancel_close_by_order_id(order_id)?.map(|_| ())) }
+            _ => { Ok(self.cancel_open_by_order_id(order_id)?.map(|_| ())) }
+        }
+    }
+
+    fn clear(&mut self) {
+        self.strategy_orders.clear();
+        self.order_strategy_order_index.clear();
+        self.long_opened_orders.clear();
+        self.short_opened_orders.clear();
+    }
+}
+
+/// 处理部分成交的订单
+/// 已成交部分从策略订单中拆分出来（开单->已开仓 平单->已平仓） 剩余部分保持挂单状态 随后与其他挂单一同撤回
+/// 返回已平仓的部分 用于记录平仓订单对
+pub fn handle_partially_executed<M: TStrategyOrderManager>(
+    strategy_order_manager: &mut M,
+    executed_order: &SOrderV3,
+) -> Option<SStrategyOrder>
+{
+    match strategy_order_manager.partially_executed_by_order_id(&executed_order.get_id(), executed_order.get_quantity()) {
+        Err(e) => {
+            error!("handle_partially_executed():{:?}", e);
+            None
+        }
+        Ok(Err(e)) => {
+            error!("handle_partially_executed():{:?}", e);
+            None
+        }
+        Ok(Ok(closed_strategy_order)) => { closed_strategy_order }
+    }
+}
+
+/// 处理被runner取消的订单（条件单触发时可用资产不足）
+pub fn handle_canceled<M: TStrategyOrderManager>(
+    strategy_order_manager: &mut M,
+    opening_and_closing_orders: &mut HashSet<Uuid>,
+    canceled_order: &SOrderV3,
+)
+{
+    opening_and_closing_orders.remove(&canceled_order.get_id());
+    match strategy_order_manager.cancel_by_order_id(&canceled_order.get_id()) {
+        Err(e) => { error!("handle_canceled():{:?}", e); }
+        Ok(Err(e)) => { error!("handle_canceled():{:?}", e); }
+        Ok(Ok(())) => {}
+    }
+}
+
+/// 处理强平
+/// 强平后该交易对的仓位已不存在 被取消的挂单不再需要撤回 清空该交易对的全部策略订单
+pub fn handle_liquidation<M: TStrategyOrderManager>(
+    strategy_order_manager: &mut M,
+    opening_and_closing_orders: &mut HashSet<Uuid>,
+    liquidation: &SRunnerLiquidation,
+)
+{
+    for order in liquidation.canceled_orders.iter() {
+        opening_and_closing_orders.remove(&order.get_id());
+    }
+    strategy_order_manager.clear();
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+    use rust_decimal::Decimal;
+    use uuid::Uuid;
+    use crate::data_runtime::asset::asset::SAsset;
+    use crate::data_runtime::asset::EAssetType;
+    use crate::data_runtime::order::order_v3::SOrderV3;
+    use crate::data_source::trading_pair::ETradingPairType;
+    use crate::protocol::SRunnerLiquidation;
+    use crate::strategy::order::order_manager_v2::SStrategyOrderManagerV2;
+    use crate::strategy::order::order_result::{handle_canceled, handle_liquidation};
+
+    #[test]
+    pub fn test_canceled() {
+        let tp_type = ETradingPairType::BtcUsdt;
+        let mut strategy_order_manager = SStrategyOrderManagerV2::default();
+        // 待开仓的条件单触发时被runner取消 删除对应的策略订单
+        let opening_order = SOrderV3::new_buy_order(tp_type, Decimal::from(100_000), Decimal::from(1));
+        strategy_order_manager.add_with_order(&opening_order);
+        let mut opening_and_closing_orders = HashSet::from([opening_order.get_id()]);
+        handle_canceled(&mut strategy_order_manager, &mut opening_and_closing_orders, &opening_order);
+        assert!(strategy_order_manager.strategy_orders.is_empty());
+        assert!(opening_and_closing_orders.is_empty());
+    }
+
+    #[test]
+    pub fn test_liquidation() {
+        let tp_type = ETradingPairType::BtcUsdtFuture;
+        let mut strategy_order_manager = SStrategyOrderManagerV2::default();
+        // 已开仓的策略订单
+        let opened_order = SOrderV3::new_buy_order(tp_type, Decimal::from(100_000), Decimal::from(1));
+        strategy_order_manager.add_with_order(&opened_order);
+        strategy_order_manager.opened_by_order_id(&opened_order.get_id()).unwrap().unwrap();
+        // 待开仓的策略订单 强平时被runner取消
+        let opening_order = SOrderV3::new_buy_order(tp_type, Decimal::from(99_000), Decimal::from(1));
+        strategy_order_manager.add_with_order(&opening_order);
+        let other_order_id = Uuid::new_v4();
+        let mut opening_and_closing_orders = HashSet::from([opening_order.get_id(), other_order_id]);
+
+        let liquidation = SRunnerLiquidation {
+            tp_type,
+            liquidation_price: Decimal::from(90_000),
+            base_quantity: Decimal::from(1),
+            returned_margin: SAsset { as_type: EAssetType::Usdt, balance: Decimal::from(0) },
+            liquidation_fee: SAsset { as_type: EAssetType::Usdt, balance: Decimal::from(0) },
+            canceled_orders: vec![opening_order],
+        };
+        handle_liquidation(&mut strategy_order_manager, &mut opening_and_closing_orders, &liquidation);
+        assert!(strategy_order_manager.strategy_orders.is_empty());
+        assert!(strategy_order_manager.order_strategy_order_index.is_empty());
+        assert!(strategy_order_manager.long_opened_orders.is_empty());
+        assert_eq!(opening_and_closing_orders, HashSet::from([other_order_id]));
+    }
+}
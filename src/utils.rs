@@ -17,6 +17,25 @@ pub mod date_time {
     }
 }
 
+/// 按交易对报价换算资产量 资产量为0时不需要报价
+/// 报价缺失时记录错误并按0计价
+fn denominate_by_price(
+    balance: Decimal,
+    tp_type: ETradingPairType,
+    trading_pair_prices: &HashMap<ETradingPairType, Decimal>,
+) -> Decimal {
+    if balance.is_zero() {
+        return Decimal::from(0);
+    }
+    match trading_pair_prices.get(&tp_type) {
+        None => {
+            error!("trading pair price not found:\t{:?}", tp_type);
+            Decimal::from(0)
+        }
+        Some(price) => { balance * price }
+    }
+}
+
 /// 将对应资产以USDT进行计价
 pub fn assets_denominate_usdt(
     asset: &EAssetUnion,
@@ -27,35 +46,32 @@ pub fn assets_denominate_usdt(
     let asset = match asset {
         EAssetUnion::Usdt(asset) | EAssetUnion::Btc(asset) => {asset.clone()}
         EAssetUnion::BtcUsdtFuture(asset_leveraged) => {
-            let price = trading_pair_prices.get(&ETradingPairType::BtcUsdtFuture).unwrap();
             let base_balance = asset_leveraged.get_base().get_balance();
             let quote_balance = asset_leveraged.get_quote().get_balance();
             let margin_balance = asset_leveraged.get_margin().get_balance();
             SAsset {
                 as_type: EAssetType::Usdt,
-                balance: base_balance * price + margin_balance + quote_balance,
+                balance: denominate_by_price(base_balance, ETradingPairType::BtcUsdtFuture, trading_pair_prices) + margin_balance + quote_balance,
             }
         }
         EAssetUnion::BtcUsdCmFuture(asset_leveraged) => {
-            let price = trading_pair_prices.get(&ETradingPairType::BtcUsdCmFuture).unwrap();
             let base_balance = asset_leveraged.get_base().get_balance();
             let quote_balance = asset_leveraged.get_quote().get_balance();
             let margin_balance = asset_leveraged.get_margin().get_balance();
             SAsset {
                 as_type: EAssetType::Btc,
-                balance: base_balance * price + margin_balance + quote_balance,
+                balance: denominate_by_price(base_balance, ETradingPairType::BtcUsdCmFuture, trading_pair_prices) + margin_balance + quote_balance,
             }
         }
     };
 
     // 再根据target_as_type进行转换
-    let price = trading_pair_prices.get(&ETradingPairType::BtcUsdt).unwrap();
     match asset.get_type() {
         EAssetType::Usdt => {
             asset.get_balance()
         }
         EAssetType::Btc => {
-            asset.get_balance() * price
+            denominate_by_price(asset.get_balance(), ETradingPairType::BtcUsdt, trading_pair_prices)
         }
         _ => {
             error!("pub fn assets_denominate_usdt:\t{:?}", asset);
@@ -74,18 +90,14 @@ pub fn assets_denominate_usdt_old(
     let new_asset = match asset.as_type {
         EAssetType::Usdt | EAssetType::Btc => { asset.clone() }
         EAssetType::BtcUsdtFuture => {
-            let price = trading_pair_prices.get(&ETradingPairType::BtcUsdtFuture).unwrap();
-            let btc_usdt_future_balance = asset.balance;
-            let usdt_balance = btc_usdt_future_balance * price;
+            let usdt_balance = denominate_by_price(asset.balance, ETradingPairType::BtcUsdtFuture, trading_pair_prices);
             SAsset {
                 as_type: EAssetType::Usdt,
                 balance: usdt_balance,
             }
         }
         EAssetType::BtcUsdCmFuture => {
-            let price = trading_pair_prices.get(&ETradingPairType::BtcUsdCmFuture).unwrap();
-            let btc_usd_cm_future_balance = asset.balance;
-            let btc_balance = btc_usd_cm_future_balance * price;
+            let btc_balance = denominate_by_price(asset.balance, ETradingPairType::BtcUsdCmFuture, trading_pair_prices);
             SAsset {
                 as_type: EAssetType::Btc,
                 balance: btc_balance,
@@ -94,13 +106,12 @@ pub fn assets_denominate_usdt_old(
     };
 
     // 再根据target_as_type进行转换
-    let price = trading_pair_prices.get(&ETradingPairType::BtcUsdt).unwrap();
     match new_asset.as_type {
         EAssetType::Usdt => {
             new_asset.balance
         }
         EAssetType::Btc => {
-            new_asset.balance * price
+            denominate_by_price(new_asset.balance, ETradingPairType::BtcUsdt, trading_pair_prices)
         }
         _ => Decimal::from(0)
     }